@@ -1,25 +1,55 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
 use uuid::Uuid;
 
 use crate::{
+    config::{BackupPolicy, RecommendationRetention},
+    ingredients::{
+        catalog::{catalog, CatalogEntry},
+        nutrition::{self, NutritionLint, NutritionTolerance, RecipeNutrition},
+        scaling::{self, ScaledRecipe},
+        units::{self, UnitSystem},
+    },
+    interchange::{
+        export::{self, ExportFilter},
+        ImportReport,
+    },
+    recommendation::{
+        config::{self as recommendation_config, RecommendationConfig},
+        engine::{self, ExcludedRecipe, PortionSuggestion, RecommendationEngine},
+        group::{FairnessStrategy, GroupMember},
+        planner::{DailyPlan, PlanRequest, PlannedDay},
+        targets::{MealShares, NutritionTargets, TargetGoal},
+    },
+    shopping,
     storage::{
-        models::{DietHistory, DietRecommendation, HealthProfile, Recipe},
+        models::{
+            CheckedConstraints, DietHistory, DietRecommendation, HealthProfile, MealPlan,
+            MealPlanEntry, NutritionalInfo, PantryItem, Profile, Recipe, RecipeIngredient, RecipeOrigin, RecipeSearchFilter, ScoreComponent,
+            ShoppingList, ShoppingListItem, TagCount, default_servings,
+        },
+        backup::{self, SnapshotInfo},
         Database,
     },
     AppError, AppResult,
 };
 
+/// Recommendation engine shared between commands; reload it after the recipes table changes.
+pub type SharedEngine = Arc<RwLock<RecommendationEngine>>;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct HealthProfileDto {
     pub id: Option<String>,
-    pub user_id: String,
+    #[serde(default)]
+    pub user_id: String, // empty means the active profile
     pub age: u32,
     pub gender: String, // 'male' | 'female' | 'other' | 'prefer_not_to_say'
     pub weight: f64,    // in kg
     pub height: f64,    // in cm
-    pub activity_level: String, // 'sedentary' | 'light' | 'moderate' | 'active' | 'very_active'
+    pub activity_level: String, // 'sedentary' | 'lightly_active' | 'moderately_active' | 'very_active' | 'extremely_active'
     pub health_goals: Vec<String>, // e.g., ['weight_loss', 'muscle_gain']
     pub dietary_preferences: Vec<String>, // e.g., ['vegetarian', 'low_carb']
     pub dietary_restrictions: Vec<String>, // specific foods/ingredients to avoid
@@ -43,6 +73,159 @@ pub struct RecommendationItemDto {
     pub created_at: String, // ISO date string
     pub is_personalized: bool,
     pub relevance_score: f64, // 0.0 to 1.0
+    pub recipe_id: Option<String>, // recipe the recommendation was generated from
+    pub score_breakdown: Vec<ScoreComponent>, // contributing rules: rule, delta and reason
+    pub checked_constraints: CheckedConstraints, // allergies and restrictions that were checked
+}
+
+impl From<DietRecommendation> for RecommendationItemDto {
+    fn from(rec: DietRecommendation) -> Self {
+        RecommendationItemDto {
+            id: rec.id.to_string(),
+            user_id: rec.user_id,
+            title: rec.title,
+            description: rec.description,
+            ingredients: rec
+                .ingredients
+                .into_iter()
+                .map(|ing| IngredientDto {
+                    name: ing.name,
+                    amount: ing.amount,
+                    unit: ing.unit,
+                })
+                .collect(),
+            nutritional_info: NutritionalInfoDto {
+                calories: rec.nutritional_info.calories,
+                protein: rec.nutritional_info.protein,
+                carbs: rec.nutritional_info.carbs,
+                fat: rec.nutritional_info.fat,
+                fiber: rec.nutritional_info.fiber,
+            },
+            preparation_time: rec.preparation_time,
+            difficulty_level: rec.difficulty_level,
+            meal_type: rec.meal_type,
+            recipe_instructions: rec.recipe_instructions,
+            created_at: rec.created_at.to_rfc3339(),
+            is_personalized: rec.is_personalized,
+            relevance_score: rec.relevance_score,
+            recipe_id: rec.recipe_id,
+            score_breakdown: rec.score_breakdown,
+            checked_constraints: rec.checked_constraints,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GroupRecommendationDto {
+    pub recipe_id: String,
+    pub title: String,
+    pub description: String,
+    pub ingredients: Vec<IngredientDto>,
+    pub nutritional_info: NutritionalInfoDto,
+    pub preparation_time: u32,    // in minutes
+    pub difficulty_level: String, // 'easy' | 'medium' | 'hard'
+    pub meal_type: String,        // 'breakfast' | 'lunch' | 'dinner' | 'snack'
+    pub relevance_score: f64,     // 0.0 to 1.0, combined with the fairness strategy
+    pub strategy: FairnessStrategy,
+    pub member_scores: Vec<GroupMemberScoreDto>,
+    pub best_suited_for: String, // user_id of the member the recipe suits best
+    pub explanation: String,
+    pub checked_constraints: CheckedConstraints, // every member's allergies and restrictions
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GroupMemberScoreDto {
+    pub user_id: String,
+    pub display_name: String,
+    pub score: f64,
+    pub score_breakdown: Vec<ScoreComponent>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NutritionTargetsDto {
+    pub user_id: String,
+    pub bmr: f64,  // kcal/day
+    pub tdee: f64, // kcal/day
+    pub activity_multiplier: f64,
+    pub goal: TargetGoal,
+    pub daily: NutritionalInfoDto,
+    pub meals: Vec<MealTargetDto>, // breakfast, lunch, dinner, snack
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MealTargetDto {
+    pub meal_type: String,
+    pub share: f64,
+    pub target: NutritionalInfoDto,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DailyPlanDto {
+    pub user_id: String,
+    pub meals: Vec<PlannedMealDto>,    // breakfast, lunch, dinner, snack
+    pub unfilled_slots: Vec<String>,   // slots no suitable recipe could fill
+    pub relaxed_slots: Vec<String>,    // slots where a variety rule had to be broken
+    pub totals: NutritionalInfoDto,
+    pub targets: NutritionalInfoDto,   // daily targets for the filled slots
+    pub deviation: NutritionalInfoDto, // totals - targets
+    pub tolerance: f64,
+    pub within_tolerance: bool,
+    pub total_relevance: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PlannedMealDto {
+    pub meal_type: String,
+    pub recipe_id: String,
+    pub title: String,
+    pub description: String,
+    pub ingredients: Vec<IngredientDto>,
+    pub nutritional_info: NutritionalInfoDto,
+    pub preparation_time: u32,    // in minutes
+    pub difficulty_level: String, // 'easy' | 'medium' | 'hard'
+    pub relevance_score: f64,
+    pub score_breakdown: Vec<ScoreComponent>,
+}
+
+impl From<DailyPlan> for DailyPlanDto {
+    fn from(plan: DailyPlan) -> Self {
+        DailyPlanDto {
+            user_id: plan.user_id,
+            meals: plan
+                .meals
+                .into_iter()
+                .map(|meal| PlannedMealDto {
+                    meal_type: meal.meal_type,
+                    nutritional_info: nutrition_dto(&meal.recipe.nutritional_info_per_serving),
+                    recipe_id: meal.recipe.id,
+                    title: meal.recipe.title,
+                    description: meal.recipe.description,
+                    ingredients: meal
+                        .recipe
+                        .ingredients
+                        .into_iter()
+                        .map(|i| IngredientDto {
+                            name: i.name,
+                            amount: i.amount,
+                            unit: i.unit,
+                        })
+                        .collect(),
+                    preparation_time: meal.recipe.preparation_time,
+                    difficulty_level: meal.recipe.difficulty_level,
+                    relevance_score: meal.relevance_score,
+                    score_breakdown: meal.score_breakdown,
+                })
+                .collect(),
+            unfilled_slots: plan.unfilled_slots,
+            relaxed_slots: plan.relaxed_slots,
+            totals: nutrition_dto(&plan.totals),
+            targets: nutrition_dto(&plan.targets),
+            deviation: nutrition_dto(&plan.deviation),
+            tolerance: plan.tolerance,
+            within_tolerance: plan.within_tolerance,
+            total_relevance: plan.total_relevance,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -64,7 +247,8 @@ pub struct NutritionalInfoDto {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct DietEntryDto {
     pub id: Option<String>,
-    pub user_id: String,
+    #[serde(default)]
+    pub user_id: String, // empty means the active profile
     pub diet_item_id: String,   // references recommendation or custom entry
     pub date_attempted: String, // ISO date string
     pub rating: Option<u8>,     // 1-5 star rating
@@ -77,7 +261,8 @@ pub struct DietEntryDto {
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct GetHistoryParamsDto {
-    pub user_id: String,
+    #[serde(default)]
+    pub user_id: String, // empty means the active profile
     pub start_date: Option<String>, // ISO date string
     pub end_date: Option<String>,   // ISO date string
     pub limit: Option<u32>,
@@ -100,6 +285,7 @@ pub struct RecipeDto {
     pub description: String,
     pub ingredients: Vec<IngredientWithOptionalDto>,
     pub nutritional_info_per_serving: NutritionalInfoDto,
+    pub servings: u32,            // servings the ingredient amounts make
     pub preparation_time: u32,    // in minutes
     pub difficulty_level: String, // 'easy' | 'medium' | 'hard'
     pub meal_type: String,        // 'breakfast' | 'lunch' | 'dinner' | 'snack'
@@ -107,10 +293,126 @@ pub struct RecipeDto {
     pub cuisine_type: Option<String>,
     pub seasonal: bool,
     pub tags: Vec<String>,
+    pub user_id: Option<String>, // owner, absent for bundled recipes
+    pub origin: String,          // 'bundled' | 'imported' | 'user'
     pub created_at: String, // ISO date string
     pub updated_at: String, // ISO date string
 }
 
+impl From<Recipe> for RecipeDto {
+    fn from(recipe: Recipe) -> Self {
+        RecipeDto {
+            id: recipe.id.to_string(),
+            title: recipe.title,
+            description: recipe.description,
+            ingredients: recipe
+                .ingredients
+                .into_iter()
+                .map(|ing| IngredientWithOptionalDto {
+                    name: ing.name,
+                    amount: ing.amount,
+                    unit: ing.unit,
+                    optional: ing.optional,
+                })
+                .collect(),
+            nutritional_info_per_serving: NutritionalInfoDto {
+                calories: recipe.nutritional_info_per_serving.calories,
+                protein: recipe.nutritional_info_per_serving.protein,
+                carbs: recipe.nutritional_info_per_serving.carbs,
+                fat: recipe.nutritional_info_per_serving.fat,
+                fiber: recipe.nutritional_info_per_serving.fiber,
+            },
+            servings: recipe.servings,
+            preparation_time: recipe.preparation_time,
+            difficulty_level: recipe.difficulty_level,
+            meal_type: recipe.meal_type,
+            recipe_instructions: recipe.recipe_instructions,
+            cuisine_type: recipe.cuisine_type,
+            seasonal: recipe.seasonal,
+            tags: recipe.tags,
+            user_id: recipe.user_id,
+            origin: recipe.origin.as_str().to_string(),
+            created_at: recipe.created_at.to_rfc3339(),
+            updated_at: recipe.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Editable fields of a user recipe, as sent by the recipe editor.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RecipeInputDto {
+    pub title: String,
+    pub description: String,
+    pub ingredients: Vec<IngredientWithOptionalDto>,
+    pub nutritional_info_per_serving: NutritionalInfoDto,
+    #[serde(default)]
+    pub servings: Option<u32>, // defaults to one serving
+    pub preparation_time: u32,    // in minutes
+    pub difficulty_level: String, // 'easy' | 'medium' | 'hard'
+    pub meal_type: String,        // 'breakfast' | 'lunch' | 'dinner' | 'snack'
+    pub recipe_instructions: String,
+    pub cuisine_type: Option<String>,
+    pub seasonal: bool,
+    pub tags: Vec<String>,
+}
+
+impl RecipeInputDto {
+    /// Builds a user recipe owned by `user_id`, trimming text and dropping blank tags.
+    fn into_user_recipe(self, id: Uuid, user_id: String, created_at: DateTime<Utc>) -> Recipe {
+        Recipe {
+            id,
+            title: self.title.trim().to_string(),
+            description: self.description.trim().to_string(),
+            ingredients: self
+                .ingredients
+                .into_iter()
+                .map(|ing| RecipeIngredient {
+                    name: ing.name.trim().to_string(),
+                    amount: ing.amount,
+                    unit: ing.unit.trim().to_string(),
+                    optional: ing.optional,
+                })
+                .collect(),
+            nutritional_info_per_serving: NutritionalInfo {
+                calories: self.nutritional_info_per_serving.calories,
+                protein: self.nutritional_info_per_serving.protein,
+                carbs: self.nutritional_info_per_serving.carbs,
+                fat: self.nutritional_info_per_serving.fat,
+                fiber: self.nutritional_info_per_serving.fiber,
+            },
+            servings: self.servings.unwrap_or_else(default_servings),
+            preparation_time: self.preparation_time,
+            difficulty_level: self.difficulty_level,
+            meal_type: self.meal_type,
+            recipe_instructions: self.recipe_instructions.trim().to_string(),
+            cuisine_type: self
+                .cuisine_type
+                .map(|c| c.trim().to_string())
+                .filter(|c| !c.is_empty()),
+            seasonal: self.seasonal,
+            tags: self
+                .tags
+                .into_iter()
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty())
+                .collect(),
+            user_id: Some(user_id),
+            origin: RecipeOrigin::User,
+            created_at,
+            updated_at: Utc::now(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RecipeSearchResultDto {
+    #[serde(flatten)]
+    pub recipe: RecipeDto,
+    pub score: Option<f64>,              // higher is a better match
+    pub title_highlight: Option<String>, // matches wrapped in <mark></mark>
+    pub snippet: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct IngredientWithOptionalDto {
     pub name: String,
@@ -122,11 +424,14 @@ pub struct IngredientWithOptionalDto {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct SearchRecipesParamsDto {
     pub query: Option<String>,                    // search term
-    pub tags: Option<Vec<String>>,                // recipe tags to match
+    pub tags: Option<Vec<String>>,                // recipe must have all of these tags
+    pub any_tags: Option<Vec<String>>,            // recipe must have at least one of these tags
+    pub include_ingredients: Option<Vec<String>>, // ingredients that must be used
     pub exclude_ingredients: Option<Vec<String>>, // ingredients to exclude
     pub max_preparation_time: Option<u32>,
     pub difficulty_level: Option<String>,
     pub meal_type: Option<String>,
+    pub user_id: Option<String>, // when set, other users' recipes are left out
     pub limit: Option<u32>,
     pub offset: Option<u32>,
 }
@@ -137,14 +442,57 @@ pub struct AppConfigDto {
     pub storage_path: String,
     pub privacy_mode: bool,
     pub theme: String, // 'light' | 'dark' | 'system'
+    #[serde(default)]
+    pub recommendation_retention: Option<RecommendationRetention>,
+    #[serde(default)]
+    pub backup: Option<BackupPolicy>,
+    #[serde(default)]
+    pub active_profile: Option<String>, // changed with set_active_profile
+    #[serde(default)]
+    pub database_locked: bool, // privacy mode is on and the passphrase has not been entered
+    #[serde(default)]
+    pub unit_system: Option<UnitSystem>, // metric or imperial amounts on shopping lists
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ProfileDto {
+    pub user_id: String,
+    pub display_name: String,
+    pub is_active: bool,
+    pub created_at: String, // ISO date string
+    pub updated_at: String, // ISO date string
+}
+
+impl ProfileDto {
+    fn new(profile: Profile, active_profile: Option<&str>) -> Self {
+        ProfileDto {
+            is_active: active_profile == Some(profile.user_id.as_str()),
+            user_id: profile.user_id,
+            display_name: profile.display_name,
+            created_at: profile.created_at.to_rfc3339(),
+            updated_at: profile.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+/// The given user id, or the active profile's when it is omitted or blank.
+fn resolve_user_id(user_id: Option<String>) -> Result<String, String> {
+    if let Some(user_id) = user_id.filter(|id| !id.trim().is_empty()) {
+        return Ok(user_id);
+    }
+    crate::config::get_app_config()
+        .map_err(|e| e.to_string())?
+        .active_profile
+        .ok_or_else(|| "No user_id given and no active profile is set".to_string())
 }
 
 // Tauri command implementations will go here
 #[tauri::command]
 pub fn save_health_profile(
-    profile: HealthProfileDto,
+    mut profile: HealthProfileDto,
     db: tauri::State<'_, Arc<Database>>,
 ) -> Result<String, String> {
+    profile.user_id = resolve_user_id(Some(profile.user_id))?;
     log::info!("Saving health profile for user: {}", profile.user_id);
 
     // Convert DTO to domain model
@@ -200,9 +548,10 @@ pub fn save_health_profile(
 
 #[tauri::command]
 pub fn get_health_profile(
-    user_id: String,
+    user_id: Option<String>,
     db: tauri::State<'_, Arc<Database>>,
 ) -> Result<Option<HealthProfileDto>, String> {
+    let user_id = resolve_user_id(user_id)?;
     match db.get_health_profile(&user_id).map_err(|e| e.to_string())? {
         Some(profile) => Ok(Some(HealthProfileDto {
             id: Some(profile.id.to_string()),
@@ -225,9 +574,10 @@ pub fn get_health_profile(
 
 #[tauri::command]
 pub fn delete_health_profile(
-    user_id: String,
+    user_id: Option<String>,
     db: tauri::State<'_, Arc<Database>>,
 ) -> Result<bool, String> {
+    let user_id = resolve_user_id(user_id)?;
     db.delete_health_profile(&user_id)
         .map_err(|e| e.to_string())
         .map(|_| true)
@@ -235,9 +585,11 @@ pub fn delete_health_profile(
 
 #[tauri::command]
 pub async fn get_recommendations(
-    user_id: String,
+    user_id: Option<String>,
     db: tauri::State<'_, Arc<Database>>,
+    engine: tauri::State<'_, SharedEngine>,
 ) -> Result<Vec<RecommendationItemDto>, String> {
+    let user_id = resolve_user_id(user_id)?;
     log::info!("Generating recommendations for user: {}", user_id);
     let timer = crate::utils::performance::PerformanceTimer::start("get_recommendations");
 
@@ -264,20 +616,13 @@ pub async fn get_recommendations(
         }
     };
 
-    // Load sample recipes or get from database
-    let sample_recipes = crate::utils::load_sample_recipes().map_err(|e| {
-        log::error!("Failed to load sample recipes: {}", e);
-        e
-    })?;
-
-    // Create a recommendation engine and add the recipes
-    let mut engine = crate::recommendation::engine::RecommendationEngine::new();
-    for recipe in sample_recipes {
-        engine.add_recipe(recipe);
-    }
+    // The engine shares the recipe corpus loaded from the database at startup
+    let engine = engine
+        .read()
+        .map_err(|_| "Recommendation engine lock poisoned".to_string())?;
 
     // Generate recommendations based on the user's profile (or default if no profile)
-    let recommendations = match profile {
+    let mut recommendations = match profile {
         Some(ref user_profile) => {
             // Generate personalized recommendations
             engine.get_recommendations(user_profile)
@@ -287,39 +632,28 @@ pub async fn get_recommendations(
             engine.get_default_recommendations(&user_id)
         }
     };
+    drop(engine);
+
+    // Persist the batch so the ids handed to the UI resolve via get_recommendation_by_id
+    db.save_recommendation_batch(&user_id, &mut recommendations)
+        .map_err(|e| {
+            log::error!("Failed to save recommendations for user {}: {}", user_id, e);
+            e.to_string()
+        })?;
+
+    let retention = crate::config::get_app_config()
+        .map(|config| config.recommendation_retention)
+        .unwrap_or_default();
+    if let Err(e) =
+        db.purge_expired_recommendations(&user_id, retention.max_age(), retention.keep_batches)
+    {
+        log::warn!("Failed to purge expired recommendations for user {}: {}", user_id, e);
+    }
 
     let count = recommendations.len();
     let dtos = recommendations
         .into_iter()
-        .map(|rec| RecommendationItemDto {
-            id: rec.id.to_string(),
-            user_id: rec.user_id,
-            title: rec.title,
-            description: rec.description,
-            ingredients: rec
-                .ingredients
-                .into_iter()
-                .map(|ing| IngredientDto {
-                    name: ing.name,
-                    amount: ing.amount,
-                    unit: ing.unit,
-                })
-                .collect(),
-            nutritional_info: NutritionalInfoDto {
-                calories: rec.nutritional_info.calories,
-                protein: rec.nutritional_info.protein,
-                carbs: rec.nutritional_info.carbs,
-                fat: rec.nutritional_info.fat,
-                fiber: rec.nutritional_info.fiber,
-            },
-            preparation_time: rec.preparation_time,
-            difficulty_level: rec.difficulty_level,
-            meal_type: rec.meal_type,
-            recipe_instructions: rec.recipe_instructions,
-            created_at: rec.created_at.to_rfc3339(),
-            is_personalized: rec.is_personalized,
-            relevance_score: rec.relevance_score,
-        })
+        .map(RecommendationItemDto::from)
         .collect();
 
     let elapsed = timer.stop();
@@ -340,7 +674,654 @@ pub async fn get_recommendations(
         );
     }
 
-    Ok(dtos)
+    Ok(dtos)
+}
+
+/// Recommends recipes the given household members can all eat. With no user ids, every
+/// profile that has a health profile is included. `weights` maps user ids to weights for
+/// the weighted strategy; members left out weigh 1.
+#[tauri::command]
+pub async fn get_group_recommendations(
+    user_ids: Option<Vec<String>>,
+    strategy: Option<FairnessStrategy>,
+    weights: Option<HashMap<String, f64>>,
+    db: tauri::State<'_, Arc<Database>>,
+    engine: tauri::State<'_, SharedEngine>,
+) -> Result<Vec<GroupRecommendationDto>, String> {
+    let strategy = strategy.unwrap_or_default();
+    let weights = weights.unwrap_or_default();
+    let names: HashMap<String, String> = db
+        .list_profiles()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|p| (p.user_id, p.display_name))
+        .collect();
+
+    let members = match user_ids.filter(|ids| !ids.is_empty()) {
+        Some(ids) => ids
+            .into_iter()
+            .map(|user_id| {
+                db.get_health_profile(&user_id)
+                    .map_err(|e| e.to_string())?
+                    .ok_or_else(|| format!("No health profile for {}", user_id))
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        None => db.get_all_health_profiles().map_err(|e| e.to_string())?,
+    };
+    if members.is_empty() {
+        return Err("No household members with a health profile".to_string());
+    }
+
+    let members: Vec<GroupMember> = members
+        .into_iter()
+        .map(|profile| GroupMember {
+            weight: weights.get(&profile.user_id).copied().unwrap_or(1.0),
+            profile,
+        })
+        .collect();
+    log::info!(
+        "Generating group recommendations for {} members ({:?})",
+        members.len(),
+        strategy
+    );
+
+    let engine = engine
+        .read()
+        .map_err(|_| "Recommendation engine lock poisoned".to_string())?;
+    let recommendations = engine.get_group_recommendations(&members, strategy);
+    drop(engine);
+
+    let display_name = |user_id: &str| {
+        names
+            .get(user_id)
+            .cloned()
+            .unwrap_or_else(|| user_id.to_string())
+    };
+    Ok(recommendations
+        .into_iter()
+        .map(|rec| {
+            let explanation = match rec.best_member() {
+                Some(best) => format!(
+                    "最适合{}（匹配度{:.0}%），全家综合匹配度{:.0}%",
+                    display_name(&best.user_id),
+                    best.score * 100.0,
+                    rec.relevance_score * 100.0
+                ),
+                None => String::new(),
+            };
+            let recipe = rec.recipe;
+            GroupRecommendationDto {
+                recipe_id: recipe.id,
+                title: recipe.title,
+                description: recipe.description,
+                ingredients: recipe
+                    .ingredients
+                    .into_iter()
+                    .map(|ing| IngredientDto {
+                        name: ing.name,
+                        amount: ing.amount,
+                        unit: ing.unit,
+                    })
+                    .collect(),
+                nutritional_info: NutritionalInfoDto {
+                    calories: recipe.nutritional_info_per_serving.calories,
+                    protein: recipe.nutritional_info_per_serving.protein,
+                    carbs: recipe.nutritional_info_per_serving.carbs,
+                    fat: recipe.nutritional_info_per_serving.fat,
+                    fiber: recipe.nutritional_info_per_serving.fiber,
+                },
+                preparation_time: recipe.preparation_time,
+                difficulty_level: recipe.difficulty_level,
+                meal_type: recipe.meal_type,
+                relevance_score: rec.relevance_score,
+                strategy: rec.strategy,
+                member_scores: rec
+                    .member_scores
+                    .into_iter()
+                    .map(|m| GroupMemberScoreDto {
+                        display_name: display_name(&m.user_id),
+                        user_id: m.user_id,
+                        score: m.score,
+                        score_breakdown: m.score_breakdown,
+                    })
+                    .collect(),
+                best_suited_for: rec.best_suited_for,
+                explanation,
+                checked_constraints: rec.checked_constraints,
+            }
+        })
+        .collect())
+}
+
+/// Recipes left out because of allergies or dietary restrictions, with the entry, the
+/// ingredient and the matching rule behind each exclusion. Several `user_ids` check the
+/// household as a group; without any, the active profile is used.
+#[tauri::command]
+pub fn get_excluded_recipes(
+    user_ids: Option<Vec<String>>,
+    db: tauri::State<'_, Arc<Database>>,
+    engine: tauri::State<'_, SharedEngine>,
+) -> Result<Vec<ExcludedRecipe>, String> {
+    let user_ids = match user_ids.filter(|ids| !ids.is_empty()) {
+        Some(ids) => ids,
+        None => vec![resolve_user_id(None)?],
+    };
+    let profiles = user_ids
+        .iter()
+        .map(|user_id| require_health_profile(&db, user_id))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(engine
+        .read()
+        .map_err(|_| "Recommendation engine lock poisoned".to_string())?
+        .get_excluded_recipes(&profiles))
+}
+
+/// Daily calorie and macro targets for a profile, and how they split across meals. Fails
+/// when the profile has no age, weight or height yet.
+#[tauri::command]
+pub fn get_nutrition_targets(
+    user_id: Option<String>,
+    db: tauri::State<'_, Arc<Database>>,
+    engine: tauri::State<'_, SharedEngine>,
+) -> Result<NutritionTargetsDto, String> {
+    let user_id = resolve_user_id(user_id)?;
+    let profile = require_health_profile(&db, &user_id)?;
+    let targets = NutritionTargets::for_profile(&profile).ok_or_else(|| {
+        AppError::Validation(
+            "Age, weight and height are needed to calculate nutrition targets".to_string(),
+        )
+    })?;
+
+    let shares = engine
+        .read()
+        .map_err(|_| "Recommendation engine lock poisoned".to_string())?
+        .meal_targets()
+        .shares
+        .clone();
+
+    Ok(NutritionTargetsDto {
+        user_id,
+        bmr: targets.bmr,
+        tdee: targets.tdee,
+        activity_multiplier: targets.activity_multiplier,
+        goal: targets.goal,
+        daily: nutrition_dto(&targets.portion(1.0)),
+        meals: MealShares::MEAL_TYPES
+            .iter()
+            .filter_map(|meal_type| {
+                let share = shares.share(meal_type)?;
+                Some(MealTargetDto {
+                    meal_type: meal_type.to_string(),
+                    share,
+                    target: nutrition_dto(&targets.portion(share)),
+                })
+            })
+            .collect(),
+    })
+}
+
+/// Picks one recipe per meal slot so the day lands near the profile's nutrition targets.
+/// `fixed` keeps chosen recipes by meal type; `excluded` recipes are not picked.
+#[tauri::command]
+pub fn generate_daily_plan(
+    user_id: Option<String>,
+    fixed: Option<BTreeMap<String, String>>,
+    excluded: Option<Vec<String>>,
+    db: tauri::State<'_, Arc<Database>>,
+    engine: tauri::State<'_, SharedEngine>,
+) -> Result<DailyPlanDto, String> {
+    let user_id = resolve_user_id(user_id)?;
+    let profile = require_health_profile(&db, &user_id)?;
+    let request = PlanRequest {
+        fixed: fixed.unwrap_or_default(),
+        excluded: excluded.unwrap_or_default(),
+    };
+
+    let plan = engine
+        .read()
+        .map_err(|_| "Recommendation engine lock poisoned".to_string())?
+        .generate_daily_plan(&profile, &request)?;
+    Ok(plan.into())
+}
+
+/// Picks a different recipe for `meal_type` and keeps the rest of `current` (recipe ids by
+/// meal type). The replaced recipe and `excluded` are not picked again.
+#[tauri::command]
+pub fn regenerate_plan_slot(
+    user_id: Option<String>,
+    current: BTreeMap<String, String>,
+    meal_type: String,
+    excluded: Option<Vec<String>>,
+    db: tauri::State<'_, Arc<Database>>,
+    engine: tauri::State<'_, SharedEngine>,
+) -> Result<DailyPlanDto, String> {
+    let user_id = resolve_user_id(user_id)?;
+    let profile = require_health_profile(&db, &user_id)?;
+
+    let plan = engine
+        .read()
+        .map_err(|_| "Recommendation engine lock poisoned".to_string())?
+        .regenerate_slot(
+            &profile,
+            &current,
+            &meal_type,
+            &excluded.unwrap_or_default(),
+        )?;
+    Ok(plan.into())
+}
+
+/// Plans `days` days (7 by default) from `start_date` (today by default) under the
+/// configured variety rules and stores the plan.
+#[tauri::command]
+pub fn create_meal_plan(
+    user_id: Option<String>,
+    name: Option<String>,
+    start_date: Option<String>,
+    days: Option<u32>,
+    db: tauri::State<'_, Arc<Database>>,
+    engine: tauri::State<'_, SharedEngine>,
+) -> Result<MealPlan, String> {
+    let user_id = resolve_user_id(user_id)?;
+    let profile = require_health_profile(&db, &user_id)?;
+    let start_date = match start_date {
+        Some(date) => date
+            .parse::<chrono::NaiveDate>()
+            .map_err(|_| "Invalid date format. Expected YYYY-MM-DD".to_string())?,
+        None => chrono::Local::now().date_naive(),
+    };
+    let days = days.unwrap_or(7);
+
+    let planned = engine
+        .read()
+        .map_err(|_| "Recommendation engine lock poisoned".to_string())?
+        .generate_weekly_plan(&profile, start_date, days)?;
+    let name = name.unwrap_or_else(|| format!("{}起{}日食谱", start_date, days));
+    let plan = meal_plan_from_days(&user_id, &name, start_date, planned)?;
+
+    db.save_meal_plan(&plan).map_err(|e| {
+        log::error!("Failed to save meal plan for user {}: {}", user_id, e);
+        e.to_string()
+    })?;
+    log::info!("Created meal plan {} for user {}", plan.id, user_id);
+    db.get_meal_plan(&plan.id.to_string())?
+        .ok_or_else(|| AppError::NotFound(format!("Meal plan {} not found", plan.id)).into())
+}
+
+#[tauri::command]
+pub fn get_meal_plan(id: String, db: tauri::State<'_, Arc<Database>>) -> Result<MealPlan, String> {
+    db.get_meal_plan(&id)?
+        .ok_or_else(|| AppError::NotFound(format!("Meal plan {} not found", id)).into())
+}
+
+#[tauri::command]
+pub fn list_meal_plans(
+    user_id: Option<String>,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<Vec<MealPlan>, String> {
+    let user_id = resolve_user_id(user_id)?;
+    Ok(db.list_meal_plans(&user_id)?)
+}
+
+#[tauri::command]
+pub fn rename_meal_plan(
+    id: String,
+    name: String,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<MealPlan, String> {
+    Ok(db.rename_meal_plan(&id, &name)?)
+}
+
+/// Swaps the recipe of one planned meal, e.g. after `regenerate_plan_slot`.
+#[tauri::command]
+pub fn update_meal_plan_entry(
+    entry_id: String,
+    recipe_id: String,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<MealPlanEntry, String> {
+    Ok(db.update_meal_plan_entry(&entry_id, &recipe_id)?)
+}
+
+#[tauri::command]
+pub fn delete_meal_plan(id: String, db: tauri::State<'_, Arc<Database>>) -> Result<bool, String> {
+    Ok(db.delete_meal_plan(&id)?)
+}
+
+/// Logs a planned meal as eaten; returns the id of the new diet history entry.
+#[tauri::command]
+pub fn mark_meal_plan_entry_eaten(
+    entry_id: String,
+    rating: Option<u8>,
+    notes: Option<String>,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<String, String> {
+    let history = db
+        .mark_meal_plan_entry_eaten(&entry_id, rating, notes)
+        .map_err(|e| {
+            log::error!("Failed to mark meal plan entry {} as eaten: {}", entry_id, e);
+            e.to_string()
+        })?;
+    Ok(history.id.to_string())
+}
+
+/// Builds and stores a shopping list for the meals of a plan that have not been eaten yet,
+/// one serving each, or for the whole recipes in `recipe_ids` (a recipe listed twice is
+/// bought for twice). Whatever the pantry already covers is left off unless `use_pantry`
+/// is false.
+#[tauri::command]
+pub fn create_shopping_list(
+    user_id: Option<String>,
+    meal_plan_id: Option<String>,
+    recipe_ids: Option<Vec<String>>,
+    name: Option<String>,
+    use_pantry: Option<bool>,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<ShoppingList, String> {
+    let (user_id, plan, recipe_ids) = match (meal_plan_id, recipe_ids) {
+        (Some(plan_id), None) => {
+            let plan = db
+                .get_meal_plan(&plan_id)?
+                .ok_or_else(|| AppError::NotFound(format!("Meal plan {} not found", plan_id)))?;
+            let recipe_ids = plan
+                .entries
+                .iter()
+                .filter(|entry| !entry.is_eaten())
+                .map(|entry| entry.recipe_id.to_string())
+                .collect();
+            (plan.user_id.clone(), Some(plan), recipe_ids)
+        }
+        (None, Some(recipe_ids)) if !recipe_ids.is_empty() => {
+            (resolve_user_id(user_id)?, None, recipe_ids)
+        }
+        _ => {
+            return Err(AppError::Validation(
+                "Give either a meal_plan_id or a non-empty list of recipe_ids".to_string(),
+            )
+            .into())
+        }
+    };
+
+    let mut loaded: HashMap<String, Recipe> = HashMap::new();
+    let mut recipes = Vec::with_capacity(recipe_ids.len());
+    for recipe_id in recipe_ids {
+        if !loaded.contains_key(&recipe_id) {
+            let recipe = db
+                .get_recipe_by_id(&recipe_id)?
+                .ok_or_else(|| AppError::NotFound(format!("Recipe {} not found", recipe_id)))?;
+            // A planned meal is one serving of its recipe
+            let recipe = match plan {
+                Some(_) => shopping::single_serving(&recipe),
+                None => recipe,
+            };
+            loaded.insert(recipe_id.clone(), recipe);
+        }
+        recipes.push(loaded[&recipe_id].clone());
+    }
+    let pantry = if use_pantry.unwrap_or(true) {
+        db.get_pantry(&user_id)?
+    } else {
+        Vec::new()
+    };
+    let unit_system = crate::config::get_app_config()
+        .map_err(|e| e.to_string())?
+        .unit_system;
+
+    let id = Uuid::new_v4();
+    let today = chrono::Local::now().date_naive();
+    let name = name.unwrap_or_else(|| match plan {
+        Some(ref plan) => format!("{}至{}购物清单", plan.start_date, plan.end_date),
+        None => format!("{}购物清单", today),
+    });
+    let now = Utc::now();
+    let list = ShoppingList {
+        id,
+        user_id: user_id.clone(),
+        name,
+        meal_plan_id: plan.map(|plan| plan.id),
+        items: shopping::consolidate(&recipes, &pantry, id, unit_system),
+        created_at: now,
+        updated_at: now,
+    };
+
+    db.save_shopping_list(&list).map_err(|e| {
+        log::error!("Failed to save shopping list for user {}: {}", user_id, e);
+        e.to_string()
+    })?;
+    log::info!("Created shopping list {} with {} items for user {}", id, list.items.len(), user_id);
+    db.get_shopping_list(&id.to_string())?
+        .ok_or_else(|| AppError::NotFound(format!("Shopping list {} not found", id)).into())
+}
+
+#[tauri::command]
+pub fn get_shopping_list(
+    id: String,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<ShoppingList, String> {
+    db.get_shopping_list(&id)?
+        .ok_or_else(|| AppError::NotFound(format!("Shopping list {} not found", id)).into())
+}
+
+#[tauri::command]
+pub fn list_shopping_lists(
+    user_id: Option<String>,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<Vec<ShoppingList>, String> {
+    let user_id = resolve_user_id(user_id)?;
+    Ok(db.list_shopping_lists(&user_id)?)
+}
+
+#[tauri::command]
+pub fn set_shopping_list_item_checked(
+    item_id: String,
+    checked: bool,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<ShoppingListItem, String> {
+    Ok(db.set_shopping_list_item_checked(&item_id, checked)?)
+}
+
+#[tauri::command]
+pub fn delete_shopping_list(id: String, db: tauri::State<'_, Arc<Database>>) -> Result<bool, String> {
+    Ok(db.delete_shopping_list(&id)?)
+}
+
+#[tauri::command]
+pub fn get_pantry(
+    user_id: Option<String>,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<Vec<PantryItem>, String> {
+    let user_id = resolve_user_id(user_id)?;
+    Ok(db.get_pantry(&user_id)?)
+}
+
+/// Records how much of an ingredient is at home; saving a name again replaces its amount.
+#[tauri::command]
+pub fn save_pantry_item(
+    user_id: Option<String>,
+    name: String,
+    amount: f64,
+    unit: String,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<PantryItem, String> {
+    let user_id = resolve_user_id(user_id)?;
+    let item = PantryItem {
+        id: Uuid::new_v4(),
+        user_id,
+        name,
+        amount,
+        unit,
+        updated_at: Utc::now(),
+    };
+    Ok(db.save_pantry_item(&item)?)
+}
+
+#[tauri::command]
+pub fn delete_pantry_item(id: String, db: tauri::State<'_, Arc<Database>>) -> Result<bool, String> {
+    Ok(db.delete_pantry_item(&id)?)
+}
+
+/// Every ingredient of the bundled catalog, with its aliases, category and default unit.
+#[tauri::command]
+pub fn get_ingredient_catalog() -> Result<Vec<CatalogEntry>, String> {
+    Ok(catalog().entries().to_vec())
+}
+
+/// The catalog entry each free-text name maps onto, in order; `None` for names the
+/// catalog does not know.
+#[tauri::command]
+pub fn normalize_ingredients(names: Vec<String>) -> Result<Vec<Option<CatalogEntry>>, String> {
+    Ok(names
+        .iter()
+        .map(|name| catalog().normalize(name).cloned())
+        .collect())
+}
+
+/// `amount` of an ingredient converted from one unit to another, weighing it with the
+/// ingredient's density or piece weight when the units measure different things.
+#[tauri::command]
+pub fn convert_ingredient_amount(
+    name: String,
+    amount: f64,
+    from_unit: String,
+    to_unit: String,
+) -> Result<f64, String> {
+    units::convert(catalog().normalize(&name), amount, &from_unit, &to_unit).ok_or_else(|| {
+        AppError::Validation(format!(
+            "Cannot convert {} from {} to {}",
+            name, from_unit, to_unit
+        ))
+        .to_string()
+    })
+}
+
+/// Nutrition of a recipe computed from its ingredients and the bundled food-composition
+/// table, per ingredient and in total.
+#[tauri::command]
+pub fn calculate_recipe_nutrition(
+    recipe_id: String,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<RecipeNutrition, String> {
+    let recipe = db
+        .get_recipe_by_id(&recipe_id)?
+        .ok_or_else(|| AppError::NotFound(format!("Recipe {} not found", recipe_id)))?;
+    Ok(nutrition::calculate(
+        &recipe.ingredients,
+        recipe.servings as f64,
+    ))
+}
+
+/// A recipe's ingredient amounts and nutrition rescaled to `servings`, with amounts
+/// rounded to steps that suit their units.
+#[tauri::command]
+pub fn scale_recipe(
+    recipe_id: String,
+    servings: f64,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<ScaledRecipe, String> {
+    let recipe = db
+        .get_recipe_by_id(&recipe_id)?
+        .ok_or_else(|| AppError::NotFound(format!("Recipe {} not found", recipe_id)))?;
+    Ok(scaling::scale_recipe(&recipe, servings)?)
+}
+
+/// How many servings of a recipe bring a meal closest to the user's calorie target for
+/// it. `meal_type` defaults to the recipe's own meal type.
+#[tauri::command]
+pub fn suggest_portion(
+    recipe_id: String,
+    user_id: Option<String>,
+    meal_type: Option<String>,
+    db: tauri::State<'_, Arc<Database>>,
+    engine: tauri::State<'_, SharedEngine>,
+) -> Result<PortionSuggestion, String> {
+    let user_id = resolve_user_id(user_id)?;
+    let profile = require_health_profile(&db, &user_id)?;
+    let recipe = db
+        .get_recipe_by_id(&recipe_id)?
+        .ok_or_else(|| AppError::NotFound(format!("Recipe {} not found", recipe_id)))?;
+
+    Ok(engine
+        .read()
+        .map_err(|_| "Recommendation engine lock poisoned".to_string())?
+        .suggest_portion(&engine::Recipe::from(&recipe), &profile, meal_type.as_deref())
+        .ok_or_else(|| {
+            AppError::Validation(
+                "Age, weight and height and a known meal type are needed to suggest a portion"
+                    .to_string(),
+            )
+        })?)
+}
+
+/// Recipes whose declared nutrition differs from what their ingredients add up to by more
+/// than the tolerance. `relative_tolerance` overrides the default share (0.25).
+#[tauri::command]
+pub fn lint_recipe_nutrition(
+    relative_tolerance: Option<f64>,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<Vec<NutritionLint>, String> {
+    let mut tolerance = NutritionTolerance::default();
+    if let Some(relative) = relative_tolerance {
+        if !relative.is_finite() || relative < 0.0 {
+            return Err("Tolerance must be a non-negative number".to_string());
+        }
+        tolerance.relative = relative;
+    }
+    Ok(db
+        .get_all_recipes()?
+        .iter()
+        .filter_map(|recipe| nutrition::lint_recipe(recipe, &tolerance))
+        .collect())
+}
+
+fn meal_plan_from_days(
+    user_id: &str,
+    name: &str,
+    start_date: chrono::NaiveDate,
+    days: Vec<PlannedDay>,
+) -> AppResult<MealPlan> {
+    let id = Uuid::new_v4();
+    let end_date = days.last().map(|day| day.date).unwrap_or(start_date);
+    let mut entries = Vec::new();
+    for day in days {
+        for meal in day.plan.meals {
+            entries.push(MealPlanEntry {
+                id: Uuid::new_v4(),
+                plan_id: id,
+                date: day.date,
+                meal_type: meal.meal_type,
+                recipe_id: Uuid::parse_str(&meal.recipe.id)?,
+                recipe_title: meal.recipe.title,
+                diet_history_id: None,
+            });
+        }
+    }
+
+    let now = Utc::now();
+    Ok(MealPlan {
+        id,
+        user_id: user_id.to_string(),
+        name: name.to_string(),
+        start_date,
+        end_date,
+        entries,
+        created_at: now,
+        updated_at: now,
+    })
+}
+
+fn require_health_profile(db: &Database, user_id: &str) -> Result<HealthProfile, String> {
+    db.get_health_profile(user_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| AppError::NotFound(format!("Health profile for user {}", user_id)).into())
+}
+
+fn nutrition_dto(nutrition: &engine::NutritionalInfo) -> NutritionalInfoDto {
+    NutritionalInfoDto {
+        calories: nutrition.calories,
+        protein: nutrition.protein,
+        carbs: nutrition.carbs,
+        fat: nutrition.fat,
+        fiber: nutrition.fiber,
+    }
 }
 
 #[tauri::command]
@@ -353,45 +1334,28 @@ pub async fn get_recommendation_by_id(
         .get_recommendation_by_id(&id)
         .map_err(|e| e.to_string())?;
 
-    match rec {
-        Some(rec) => Ok(Some(RecommendationItemDto {
-            id: rec.id.to_string(),
-            user_id: rec.user_id,
-            title: rec.title,
-            description: rec.description,
-            ingredients: rec
-                .ingredients
-                .into_iter()
-                .map(|ing| IngredientDto {
-                    name: ing.name,
-                    amount: ing.amount,
-                    unit: ing.unit,
-                })
-                .collect(),
-            nutritional_info: NutritionalInfoDto {
-                calories: rec.nutritional_info.calories,
-                protein: rec.nutritional_info.protein,
-                carbs: rec.nutritional_info.carbs,
-                fat: rec.nutritional_info.fat,
-                fiber: rec.nutritional_info.fiber,
-            },
-            preparation_time: rec.preparation_time,
-            difficulty_level: rec.difficulty_level,
-            meal_type: rec.meal_type,
-            recipe_instructions: rec.recipe_instructions,
-            created_at: rec.created_at.to_rfc3339(),
-            is_personalized: rec.is_personalized,
-            relevance_score: rec.relevance_score,
-        })),
-        None => Ok(None),
-    }
+    Ok(rec.map(RecommendationItemDto::from))
+}
+
+/// Every recommendation of one batch as it was generated, for browsing past batches.
+#[tauri::command]
+pub fn get_recommendation_batch(
+    batch_id: String,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<Vec<RecommendationItemDto>, String> {
+    Ok(db
+        .get_recommendation_batch(&batch_id)?
+        .into_iter()
+        .map(RecommendationItemDto::from)
+        .collect())
 }
 
 #[tauri::command]
 pub fn log_diet_entry(
-    entry: DietEntryDto,
+    mut entry: DietEntryDto,
     db: tauri::State<'_, Arc<Database>>,
 ) -> Result<String, String> {
+    entry.user_id = resolve_user_id(Some(entry.user_id))?;
     log::info!(
         "Logging diet entry for user: {}, item: {}",
         entry.user_id,
@@ -458,9 +1422,10 @@ pub fn log_diet_entry(
 
 #[tauri::command]
 pub fn get_diet_history(
-    params: GetHistoryParamsDto,
+    mut params: GetHistoryParamsDto,
     db: tauri::State<'_, Arc<Database>>,
 ) -> Result<Vec<DietEntryDto>, String> {
+    params.user_id = resolve_user_id(Some(params.user_id))?;
     let history = db
         .get_diet_history(
             &params.user_id,
@@ -493,9 +1458,10 @@ pub fn get_diet_history(
 
 #[tauri::command]
 pub fn get_diet_history_count(
-    params: GetHistoryParamsDto,
+    mut params: GetHistoryParamsDto,
     db: tauri::State<'_, Arc<Database>>,
 ) -> Result<u32, String> {
+    params.user_id = resolve_user_id(Some(params.user_id))?;
     let count = db
         .get_diet_history_count(
             &params.user_id,
@@ -735,98 +1701,39 @@ pub fn get_recipe_by_id(
     id: String,
     db: tauri::State<'_, Arc<Database>>,
 ) -> Result<Option<RecipeDto>, String> {
-    match db.get_recipe_by_id(&id).map_err(|e| e.to_string())? {
-        Some(recipe) => Ok(Some(RecipeDto {
-            id: recipe.id.to_string(),
-            title: recipe.title,
-            description: recipe.description,
-            ingredients: recipe
-                .ingredients
-                .into_iter()
-                .map(|ing| IngredientWithOptionalDto {
-                    name: ing.name,
-                    amount: ing.amount,
-                    unit: ing.unit,
-                    optional: ing.optional,
-                })
-                .collect(),
-            nutritional_info_per_serving: NutritionalInfoDto {
-                calories: recipe.nutritional_info_per_serving.calories,
-                protein: recipe.nutritional_info_per_serving.protein,
-                carbs: recipe.nutritional_info_per_serving.carbs,
-                fat: recipe.nutritional_info_per_serving.fat,
-                fiber: recipe.nutritional_info_per_serving.fiber,
-            },
-            preparation_time: recipe.preparation_time,
-            difficulty_level: recipe.difficulty_level,
-            meal_type: recipe.meal_type,
-            recipe_instructions: recipe.recipe_instructions,
-            cuisine_type: recipe.cuisine_type,
-            seasonal: recipe.seasonal,
-            tags: recipe.tags,
-            created_at: recipe.created_at.to_rfc3339(),
-            updated_at: recipe.updated_at.to_rfc3339(),
-        })),
-        None => Ok(None),
-    }
+    db.get_recipe_by_id(&id)
+        .map(|recipe| recipe.map(RecipeDto::from))
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub fn search_recipes(
     params: SearchRecipesParamsDto,
     db: tauri::State<'_, Arc<Database>>,
-) -> Result<Vec<RecipeDto>, String> {
-    let recipes = db
-        .search_recipes(
-            params.query.as_deref(),
-            params
-                .tags
-                .as_ref()
-                .map(|v| v.iter().map(|s| s.as_str()).collect()),
-            params
-                .exclude_ingredients
-                .as_ref()
-                .map(|v| v.iter().map(|s| s.as_str()).collect()),
-            params.max_preparation_time,
-            params.difficulty_level.as_deref(),
-            params.meal_type.as_deref(),
-            params.limit,
-            params.offset,
-        )
-        .map_err(|e| e.to_string())?;
+) -> Result<Vec<RecipeSearchResultDto>, String> {
+    let filter = RecipeSearchFilter {
+        query: params.query,
+        all_tags: params.tags.unwrap_or_default(),
+        any_tags: params.any_tags.unwrap_or_default(),
+        include_ingredients: params.include_ingredients.unwrap_or_default(),
+        exclude_ingredients: params.exclude_ingredients.unwrap_or_default(),
+        max_preparation_time: params.max_preparation_time,
+        difficulty_level: params.difficulty_level,
+        meal_type: params.meal_type,
+        visible_to: params.user_id,
+        limit: params.limit,
+        offset: params.offset,
+    };
+
+    let recipes = db.search_recipes(&filter).map_err(|e| e.to_string())?;
 
     let dtos = recipes
         .into_iter()
-        .map(|r| RecipeDto {
-            id: r.id.to_string(),
-            title: r.title,
-            description: r.description,
-            ingredients: r
-                .ingredients
-                .into_iter()
-                .map(|ing| IngredientWithOptionalDto {
-                    name: ing.name,
-                    amount: ing.amount,
-                    unit: ing.unit,
-                    optional: ing.optional,
-                })
-                .collect(),
-            nutritional_info_per_serving: NutritionalInfoDto {
-                calories: r.nutritional_info_per_serving.calories,
-                protein: r.nutritional_info_per_serving.protein,
-                carbs: r.nutritional_info_per_serving.carbs,
-                fat: r.nutritional_info_per_serving.fat,
-                fiber: r.nutritional_info_per_serving.fiber,
-            },
-            preparation_time: r.preparation_time,
-            difficulty_level: r.difficulty_level,
-            meal_type: r.meal_type,
-            recipe_instructions: r.recipe_instructions,
-            cuisine_type: r.cuisine_type,
-            seasonal: r.seasonal,
-            tags: r.tags,
-            created_at: r.created_at.to_rfc3339(),
-            updated_at: r.updated_at.to_rfc3339(),
+        .map(|hit| RecipeSearchResultDto {
+            recipe: RecipeDto::from(hit.recipe),
+            score: hit.score,
+            title_highlight: hit.title_highlight,
+            snippet: hit.snippet,
         })
         .collect();
 
@@ -834,7 +1741,235 @@ pub fn search_recipes(
 }
 
 #[tauri::command]
-pub fn get_config() -> Result<AppConfigDto, String> {
+pub fn get_recipe_tag_counts(db: tauri::State<'_, Arc<Database>>) -> Result<Vec<TagCount>, String> {
+    db.get_tag_counts().map_err(|e| e.to_string())
+}
+
+/// Loads a recipe that `user_id` is allowed to modify: it must exist, be user-authored
+/// and belong to them.
+fn load_owned_recipe(db: &Database, user_id: &str, id: &str) -> AppResult<Recipe> {
+    let recipe = db
+        .get_recipe_by_id(id)?
+        .ok_or_else(|| AppError::NotFound(format!("Recipe {} not found", id)))?;
+
+    if recipe.origin != RecipeOrigin::User || recipe.user_id.as_deref() != Some(user_id) {
+        return Err(AppError::Validation(format!(
+            "Recipe {} is not a recipe of user {}",
+            id, user_id
+        )));
+    }
+
+    Ok(recipe)
+}
+
+/// Reloads the shared engine so recommendations see recipe changes immediately.
+fn reload_engine(engine: &SharedEngine, db: &Database) -> Result<(), String> {
+    engine
+        .write()
+        .map_err(|_| "Recommendation engine lock poisoned".to_string())?
+        .reload(db)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn create_recipe(
+    user_id: Option<String>,
+    recipe: RecipeInputDto,
+    db: tauri::State<'_, Arc<Database>>,
+    engine: tauri::State<'_, SharedEngine>,
+) -> Result<RecipeDto, String> {
+    let user_id = resolve_user_id(user_id)?;
+    let new_recipe = recipe.into_user_recipe(Uuid::new_v4(), user_id, Utc::now());
+
+    new_recipe.validate().map_err(|e| e.to_string())?;
+    db.save_recipe(&new_recipe).map_err(|e| e.to_string())?;
+    reload_engine(&engine, &db)?;
+
+    log::info!("Created recipe {} for user {:?}", new_recipe.id, new_recipe.user_id);
+    Ok(RecipeDto::from(new_recipe))
+}
+
+#[tauri::command]
+pub fn update_recipe(
+    user_id: Option<String>,
+    id: String,
+    recipe: RecipeInputDto,
+    db: tauri::State<'_, Arc<Database>>,
+    engine: tauri::State<'_, SharedEngine>,
+) -> Result<RecipeDto, String> {
+    let user_id = resolve_user_id(user_id)?;
+    let existing = load_owned_recipe(&db, &user_id, &id).map_err(|e| e.to_string())?;
+    let updated = recipe.into_user_recipe(existing.id, user_id, existing.created_at);
+
+    updated.validate().map_err(|e| e.to_string())?;
+    db.update_recipe(&updated).map_err(|e| e.to_string())?;
+    reload_engine(&engine, &db)?;
+
+    Ok(RecipeDto::from(updated))
+}
+
+#[tauri::command]
+pub fn delete_recipe(
+    user_id: Option<String>,
+    id: String,
+    db: tauri::State<'_, Arc<Database>>,
+    engine: tauri::State<'_, SharedEngine>,
+) -> Result<bool, String> {
+    let user_id = resolve_user_id(user_id)?;
+    load_owned_recipe(&db, &user_id, &id).map_err(|e| e.to_string())?;
+    let deleted = db.delete_recipe(&id).map_err(|e| e.to_string())?;
+    reload_engine(&engine, &db)?;
+    Ok(deleted)
+}
+
+/// Copies any recipe the user can see into a new recipe they own, e.g. to tweak a bundled one.
+#[tauri::command]
+pub fn duplicate_recipe(
+    user_id: Option<String>,
+    id: String,
+    title: Option<String>,
+    db: tauri::State<'_, Arc<Database>>,
+    engine: tauri::State<'_, SharedEngine>,
+) -> Result<RecipeDto, String> {
+    let user_id = resolve_user_id(user_id)?;
+    let source = db
+        .get_recipe_by_id(&id)
+        .map_err(|e| e.to_string())?
+        .filter(|r| r.user_id.as_deref().is_none_or(|owner| owner == user_id))
+        .ok_or_else(|| AppError::NotFound(format!("Recipe {} not found", id)).to_string())?;
+
+    let now = Utc::now();
+    let copy = Recipe {
+        id: Uuid::new_v4(),
+        title: title
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .unwrap_or_else(|| format!("{}（副本）", source.title)),
+        user_id: Some(user_id),
+        origin: RecipeOrigin::User,
+        created_at: now,
+        updated_at: now,
+        ..source
+    };
+
+    copy.validate().map_err(|e| e.to_string())?;
+    db.save_recipe(&copy).map_err(|e| e.to_string())?;
+    reload_engine(&engine, &db)?;
+
+    Ok(RecipeDto::from(copy))
+}
+
+/// Imports schema.org Recipe JSON-LD from files or directories. Imported recipes are
+/// owned by `user_id` when given, otherwise shared.
+#[tauri::command]
+pub async fn import_recipes(
+    paths: Vec<String>,
+    user_id: Option<String>,
+    db: tauri::State<'_, Arc<Database>>,
+    engine: tauri::State<'_, SharedEngine>,
+) -> Result<ImportReport, String> {
+    let paths: Vec<PathBuf> = paths.iter().map(PathBuf::from).collect();
+    let report = crate::interchange::import_paths(&db, &paths, user_id.as_deref())
+        .map_err(|e| {
+            log::error!("Recipe import failed: {}", e);
+            e.to_string()
+        })?;
+
+    if report.imported > 0 {
+        reload_engine(&engine, &db)?;
+    }
+    Ok(report)
+}
+
+fn export_filter(
+    user_id: Option<String>,
+    start_date: Option<String>,
+    end_date: Option<String>,
+) -> Result<ExportFilter, String> {
+    let parse = |date: Option<String>| {
+        date.map(|d| {
+            d.parse::<chrono::NaiveDate>()
+                .map_err(|_| format!("Invalid date '{}'. Expected YYYY-MM-DD", d))
+        })
+        .transpose()
+    };
+    Ok(ExportFilter {
+        user_id,
+        start_date: parse(start_date)?,
+        end_date: parse(end_date)?,
+    })
+}
+
+/// Exports recipes as "jsonld" or "markdown". Returns the number of recipes written.
+#[tauri::command]
+pub fn export_recipes(
+    format: String,
+    output_path: String,
+    user_id: Option<String>,
+    start_date: Option<String>,
+    end_date: Option<String>,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<usize, String> {
+    let filter = export_filter(user_id, start_date, end_date)?;
+    let recipes = export::select_recipes(&db, &filter).map_err(|e| e.to_string())?;
+
+    let contents = match format.as_str() {
+        "jsonld" => export::recipes_to_json_ld(&recipes).map_err(|e| e.to_string())?,
+        "markdown" => export::recipes_to_markdown(&recipes),
+        other => return Err(format!("Unsupported recipe export format: {}", other)),
+    };
+    export::write_export(&PathBuf::from(output_path), &contents).map_err(|e| {
+        log::error!("Recipe export failed: {}", e);
+        e.to_string()
+    })?;
+    Ok(recipes.len())
+}
+
+/// Exports diet history as "csv" or "jsonl". Returns the number of entries written.
+#[tauri::command]
+pub fn export_diet_history(
+    format: String,
+    output_path: String,
+    user_id: Option<String>,
+    start_date: Option<String>,
+    end_date: Option<String>,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<usize, String> {
+    let filter = export_filter(user_id, start_date, end_date)?;
+    let rows = export::select_history(&db, &filter).map_err(|e| e.to_string())?;
+
+    let contents = match format.as_str() {
+        "csv" => export::history_to_csv(&rows),
+        "jsonl" => export::history_to_jsonl(&rows).map_err(|e| e.to_string())?,
+        other => return Err(format!("Unsupported history export format: {}", other)),
+    };
+    export::write_export(&PathBuf::from(output_path), &contents).map_err(|e| {
+        log::error!("Diet history export failed: {}", e);
+        e.to_string()
+    })?;
+    Ok(rows.len())
+}
+
+/// Writes the user's profile, history, own recipes, meal plans, shopping lists and pantry to
+/// one archive; `import_recipes` restores it.
+#[tauri::command]
+pub fn export_account(
+    user_id: Option<String>,
+    output_path: String,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<(), String> {
+    let user_id = resolve_user_id(user_id)?;
+    log::info!("Exporting account archive for user: {}", user_id);
+    let archive = export::build_account_archive(&db, &user_id).map_err(|e| e.to_string())?;
+    let contents = serde_json::to_string_pretty(&archive).map_err(|e| e.to_string())?;
+    export::write_export(&PathBuf::from(output_path), &contents).map_err(|e| {
+        log::error!("Account export failed for user {}: {}", user_id, e);
+        e.to_string()
+    })
+}
+
+#[tauri::command]
+pub fn get_config(db: tauri::State<'_, Arc<Database>>) -> Result<AppConfigDto, String> {
     let config = crate::config::get_app_config().map_err(|e| e.to_string())?;
 
     Ok(AppConfigDto {
@@ -842,21 +1977,228 @@ pub fn get_config() -> Result<AppConfigDto, String> {
         storage_path: config.storage_path.to_string_lossy().to_string(),
         privacy_mode: config.privacy_mode,
         theme: config.theme,
+        recommendation_retention: Some(config.recommendation_retention),
+        backup: Some(config.backup),
+        active_profile: config.active_profile,
+        database_locked: db.is_locked(),
+        unit_system: Some(config.unit_system),
     })
 }
 
+/// Re-reads `recommendation.toml` right away instead of waiting for the file watcher, and
+/// returns the thresholds and weights now in effect.
+#[tauri::command]
+pub fn reload_recommendation_config(
+    engine: tauri::State<'_, SharedEngine>,
+) -> Result<RecommendationConfig, String> {
+    let path = crate::config::get_app_config()
+        .map_err(|e| e.to_string())?
+        .get_recommendation_config_path();
+    let config = recommendation_config::load_config(&path).map_err(|e| e.to_string())?;
+    engine
+        .write()
+        .map_err(|_| "Recommendation engine lock poisoned".to_string())?
+        .apply_config(&config);
+    Ok(config)
+}
+
 #[tauri::command]
 pub fn set_config(config: AppConfigDto) -> Result<bool, String> {
     log::info!("Updating application configuration");
 
+    // Privacy mode encrypts or decrypts the database, which needs the passphrase
+    let current = crate::config::get_app_config().map_err(|e| e.to_string())?;
+    if config.privacy_mode != current.privacy_mode {
+        return Err("Privacy mode must be changed with set_privacy_mode".to_string());
+    }
+
     // Update the configuration
-    crate::config::update_app_config(Some(config.privacy_mode), Some(config.theme)).map_err(
-        |e| {
-            log::error!("Failed to update application configuration: {}", e);
-            e.to_string()
-        },
-    )?;
+    crate::config::update_app_config(
+        None,
+        Some(config.theme),
+        config.recommendation_retention,
+        config.backup,
+        config.unit_system,
+    )
+    .map_err(|e| {
+        log::error!("Failed to update application configuration: {}", e);
+        e.to_string()
+    })?;
 
     log::info!("Successfully updated application configuration");
     Ok(true)
 }
+
+/// Turns privacy mode on or off. Turning it on encrypts health profiles and diet history
+/// notes with a key derived from `passphrase`; turning it off needs the same passphrase
+/// and writes them back in plaintext.
+#[tauri::command]
+pub fn set_privacy_mode(
+    enabled: bool,
+    passphrase: String,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<bool, String> {
+    log::info!("Setting privacy mode to {}", enabled);
+
+    let result = if enabled {
+        db.enable_encryption(&passphrase)
+    } else {
+        db.disable_encryption(&passphrase)
+    };
+    result.map_err(|e| {
+        log::error!("Failed to change privacy mode: {}", e);
+        e.to_string()
+    })?;
+
+    crate::config::update_app_config(Some(enabled), None, None, None, None).map_err(|e| {
+        log::error!("Failed to save privacy mode: {}", e);
+        e.to_string()
+    })?;
+    Ok(true)
+}
+
+/// Loads the privacy mode key so encrypted data can be read and written this session.
+#[tauri::command]
+pub fn unlock_database(
+    passphrase: String,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<bool, String> {
+    db.unlock(&passphrase).map_err(|e| {
+        log::warn!("Failed to unlock database: {}", e);
+        e.to_string()
+    })?;
+    Ok(true)
+}
+
+/// Takes a snapshot of the database now, regardless of the backup schedule.
+#[tauri::command]
+pub fn backup_data(db: tauri::State<'_, Arc<Database>>) -> Result<SnapshotInfo, String> {
+    let config = crate::config::get_app_config().map_err(|e| e.to_string())?;
+    backup::create_snapshot(&db, &config.get_backup_dir(), &config.backup).map_err(|e| {
+        log::error!("Backup failed: {}", e);
+        e.to_string()
+    })
+}
+
+#[tauri::command]
+pub fn list_backups() -> Result<Vec<SnapshotInfo>, String> {
+    let config = crate::config::get_app_config().map_err(|e| e.to_string())?;
+    backup::list_snapshots(&config.get_backup_dir()).map_err(|e| e.to_string())
+}
+
+/// Replaces the live database with the backup at `path`. The current data is snapshotted
+/// first; that snapshot is returned so the restore can be undone.
+#[tauri::command]
+pub fn restore_data(
+    path: String,
+    db: tauri::State<'_, Arc<Database>>,
+    engine: tauri::State<'_, SharedEngine>,
+) -> Result<SnapshotInfo, String> {
+    log::info!("Restoring database from {}", path);
+    let config = crate::config::get_app_config().map_err(|e| e.to_string())?;
+
+    let safety = backup::restore_snapshot(
+        &db,
+        &PathBuf::from(&path),
+        &config.get_backup_dir(),
+        &config.backup,
+    )
+    .map_err(|e| {
+        log::error!("Restore from {} failed: {}", path, e);
+        e.to_string()
+    })?;
+
+    if db.is_encrypted() != config.privacy_mode {
+        crate::config::update_app_config(Some(db.is_encrypted()), None, None, None, None)
+            .map_err(|e| e.to_string())?;
+    }
+    reload_engine(&engine, &db)?;
+    Ok(safety)
+}
+
+#[tauri::command]
+pub fn list_profiles(db: tauri::State<'_, Arc<Database>>) -> Result<Vec<ProfileDto>, String> {
+    let config = crate::config::get_app_config().map_err(|e| e.to_string())?;
+    let profiles = db.list_profiles().map_err(|e| e.to_string())?;
+    Ok(profiles
+        .into_iter()
+        .map(|p| ProfileDto::new(p, config.active_profile.as_deref()))
+        .collect())
+}
+
+/// Creates a household profile. The first profile created becomes the active one.
+#[tauri::command]
+pub fn create_profile(
+    display_name: String,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<ProfileDto, String> {
+    let profile = db.create_profile(&display_name).map_err(|e| e.to_string())?;
+    log::info!("Created profile {} ({})", profile.display_name, profile.user_id);
+
+    let mut active = crate::config::get_app_config()
+        .map_err(|e| e.to_string())?
+        .active_profile;
+    if active.is_none() {
+        crate::config::set_active_profile(Some(profile.user_id.clone()))
+            .map_err(|e| e.to_string())?;
+        active = Some(profile.user_id.clone());
+    }
+    Ok(ProfileDto::new(profile, active.as_deref()))
+}
+
+#[tauri::command]
+pub fn rename_profile(
+    user_id: String,
+    display_name: String,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<ProfileDto, String> {
+    let profile = db
+        .rename_profile(&user_id, &display_name)
+        .map_err(|e| e.to_string())?;
+    let config = crate::config::get_app_config().map_err(|e| e.to_string())?;
+    Ok(ProfileDto::new(profile, config.active_profile.as_deref()))
+}
+
+/// Deletes a profile and all of its data. If it was the active profile, the oldest
+/// remaining profile becomes active.
+#[tauri::command]
+pub fn delete_profile(
+    user_id: String,
+    db: tauri::State<'_, Arc<Database>>,
+    engine: tauri::State<'_, SharedEngine>,
+) -> Result<bool, String> {
+    log::info!("Deleting profile {} and all of its data", user_id);
+    let deleted = db.delete_profile(&user_id).map_err(|e| {
+        log::error!("Failed to delete profile {}: {}", user_id, e);
+        e.to_string()
+    })?;
+
+    let config = crate::config::get_app_config().map_err(|e| e.to_string())?;
+    if config.active_profile.as_deref() == Some(user_id.as_str()) {
+        let next = db
+            .list_profiles()
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .next()
+            .map(|p| p.user_id);
+        crate::config::set_active_profile(next).map_err(|e| e.to_string())?;
+    }
+
+    // The profile's own recipes are gone from the corpus
+    reload_engine(&engine, &db)?;
+    Ok(deleted)
+}
+
+#[tauri::command]
+pub fn set_active_profile(
+    user_id: String,
+    db: tauri::State<'_, Arc<Database>>,
+) -> Result<ProfileDto, String> {
+    let profile = db
+        .get_profile(&user_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| AppError::NotFound(format!("Profile {} not found", user_id)).to_string())?;
+    crate::config::set_active_profile(Some(user_id.clone())).map_err(|e| e.to_string())?;
+    log::info!("Active profile is now {}", profile.display_name);
+    Ok(ProfileDto::new(profile, Some(&user_id)))
+}
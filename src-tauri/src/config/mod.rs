@@ -3,6 +3,8 @@ use std::path::PathBuf;
 use std::fs;
 use serde::{Deserialize, Serialize};
 
+use crate::ingredients::units::UnitSystem;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
     pub version: String,
@@ -10,12 +12,72 @@ pub struct AppConfig {
     pub storage_path: PathBuf,
     pub privacy_mode: bool,
     pub theme: String,
+    pub recommendation_retention: RecommendationRetention,
+    pub backup: BackupPolicy,
+    pub active_profile: Option<String>, // user_id used when a command omits it
+    pub unit_system: UnitSystem,        // how shopping list amounts are shown
+}
+
+/// How long generated recommendation batches are kept. A batch is dropped once it is
+/// older than `max_age_days` or is not among the newest `keep_batches`; the newest batch
+/// and recommendations referenced from diet history are always kept.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RecommendationRetention {
+    pub max_age_days: u32,
+    pub keep_batches: u32,
+}
+
+impl Default for RecommendationRetention {
+    fn default() -> Self {
+        RecommendationRetention {
+            max_age_days: 7,
+            keep_batches: 5,
+        }
+    }
+}
+
+impl RecommendationRetention {
+    pub fn max_age(&self) -> chrono::Duration {
+        chrono::Duration::days(self.max_age_days as i64)
+    }
+}
+
+/// How many database snapshots are kept under `backups/`, and how often one is taken
+/// automatically. `interval_hours: None` disables scheduled backups.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BackupPolicy {
+    pub keep_snapshots: u32,
+    pub interval_hours: Option<u32>,
+}
+
+impl Default for BackupPolicy {
+    fn default() -> Self {
+        BackupPolicy {
+            keep_snapshots: 10,
+            interval_hours: None,
+        }
+    }
+}
+
+impl BackupPolicy {
+    pub fn interval(&self) -> Option<chrono::Duration> {
+        self.interval_hours
+            .map(|hours| chrono::Duration::hours(hours as i64))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct ConfigFile {
     pub privacy_mode: bool,
     pub theme: String,
+    #[serde(default)]
+    pub recommendation_retention: RecommendationRetention,
+    #[serde(default)]
+    pub backup: BackupPolicy,
+    #[serde(default)]
+    pub active_profile: Option<String>,
+    #[serde(default)]
+    pub unit_system: UnitSystem,
 }
 
 impl AppConfig {
@@ -37,12 +99,20 @@ impl AppConfig {
             storage_path,
             privacy_mode: false,
             theme: "system".to_string(),
+            recommendation_retention: RecommendationRetention::default(),
+            backup: BackupPolicy::default(),
+            active_profile: None,
+            unit_system: UnitSystem::default(),
         };
 
         // Try to load existing configuration
         if let Ok(loaded_config) = config.load_from_file() {
             config.privacy_mode = loaded_config.privacy_mode;
             config.theme = loaded_config.theme;
+            config.recommendation_retention = loaded_config.recommendation_retention;
+            config.backup = loaded_config.backup;
+            config.active_profile = loaded_config.active_profile;
+            config.unit_system = loaded_config.unit_system;
         }
 
         Ok(config)
@@ -54,6 +124,10 @@ impl AppConfig {
             return Ok(ConfigFile {
                 privacy_mode: false,
                 theme: "system".to_string(),
+                recommendation_retention: RecommendationRetention::default(),
+                backup: BackupPolicy::default(),
+                active_profile: None,
+                unit_system: UnitSystem::default(),
             });
         }
 
@@ -66,6 +140,10 @@ impl AppConfig {
         let config_file = ConfigFile {
             privacy_mode: self.privacy_mode,
             theme: self.theme.clone(),
+            recommendation_retention: self.recommendation_retention.clone(),
+            backup: self.backup.clone(),
+            active_profile: self.active_profile.clone(),
+            unit_system: self.unit_system,
         };
 
         let config_path = self.get_config_file_path();
@@ -74,13 +152,46 @@ impl AppConfig {
         Ok(())
     }
 
-    pub fn update(&mut self, privacy_mode: Option<bool>, theme: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn update(
+        &mut self,
+        privacy_mode: Option<bool>,
+        theme: Option<String>,
+        recommendation_retention: Option<RecommendationRetention>,
+        backup: Option<BackupPolicy>,
+        unit_system: Option<UnitSystem>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         if let Some(privacy) = privacy_mode {
             self.privacy_mode = privacy;
         }
         if let Some(new_theme) = theme {
             self.theme = new_theme;
         }
+        if let Some(retention) = recommendation_retention {
+            if retention.keep_batches == 0 {
+                return Err("recommendation_retention.keep_batches must be at least 1".into());
+            }
+            self.recommendation_retention = retention;
+        }
+        if let Some(backup) = backup {
+            if backup.keep_snapshots == 0 {
+                return Err("backup.keep_snapshots must be at least 1".into());
+            }
+            if backup.interval_hours == Some(0) {
+                return Err("backup.interval_hours must be at least 1".into());
+            }
+            self.backup = backup;
+        }
+        if let Some(unit_system) = unit_system {
+            self.unit_system = unit_system;
+        }
+        self.save_to_file()
+    }
+
+    pub fn set_active_profile(
+        &mut self,
+        user_id: Option<String>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.active_profile = user_id;
         self.save_to_file()
     }
 
@@ -119,6 +230,15 @@ impl AppConfig {
     pub fn get_cache_dir(&self) -> PathBuf {
         self.storage_path.join("cache")
     }
+
+    pub fn get_backup_dir(&self) -> PathBuf {
+        self.storage_path.join("backups")
+    }
+
+    pub fn get_recommendation_config_path(&self) -> PathBuf {
+        self.storage_path
+            .join(crate::recommendation::config::CONFIG_FILE_NAME)
+    }
 }
 
 use std::sync::Mutex;
@@ -133,15 +253,34 @@ pub fn get_app_config() -> Result<AppConfig, Box<dyn std::error::Error>> {
     Ok(config_guard.as_ref().unwrap().clone())
 }
 
-pub fn update_app_config(privacy_mode: Option<bool>, theme: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
+pub fn update_app_config(
+    privacy_mode: Option<bool>,
+    theme: Option<String>,
+    recommendation_retention: Option<RecommendationRetention>,
+    backup: Option<BackupPolicy>,
+    unit_system: Option<UnitSystem>,
+) -> Result<(), Box<dyn std::error::Error>> {
     let mut config_guard = CONFIG.lock().unwrap();
     if config_guard.is_none() {
         *config_guard = Some(AppConfig::new()?);
     }
     
     if let Some(ref mut config) = config_guard.as_mut() {
-        config.update(privacy_mode, theme)?;
+        config.update(privacy_mode, theme, recommendation_retention, backup, unit_system)?;
     }
     
     Ok(())
 }
+
+pub fn set_active_profile(user_id: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut config_guard = CONFIG.lock().unwrap();
+    if config_guard.is_none() {
+        *config_guard = Some(AppConfig::new()?);
+    }
+
+    if let Some(ref mut config) = config_guard.as_mut() {
+        config.set_active_profile(user_id)?;
+    }
+
+    Ok(())
+}
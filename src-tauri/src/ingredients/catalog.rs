@@ -0,0 +1,436 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::storage::models::{normalize_name, StoreSection};
+use crate::{AppError, AppResult};
+
+/// The catalog shipped with the app.
+const BUNDLED_CATALOG: &str = include_str!("../../ingredient_catalog.json");
+
+static CATALOG: Lazy<Catalog> = Lazy::new(|| {
+    Catalog::parse(BUNDLED_CATALOG).expect("bundled ingredient_catalog.json is invalid")
+});
+
+/// The bundled ingredient catalog.
+pub fn catalog() -> &'static Catalog {
+    &CATALOG
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IngredientCategory {
+    Vegetable,
+    Fruit,
+    Meat,
+    Seafood,
+    Egg,
+    Dairy,
+    Soy,
+    Grain,
+    Oil,
+    Nut,
+    Condiment,
+}
+
+impl IngredientCategory {
+    pub const ALL: [IngredientCategory; 11] = [
+        IngredientCategory::Vegetable,
+        IngredientCategory::Fruit,
+        IngredientCategory::Meat,
+        IngredientCategory::Seafood,
+        IngredientCategory::Egg,
+        IngredientCategory::Dairy,
+        IngredientCategory::Soy,
+        IngredientCategory::Grain,
+        IngredientCategory::Oil,
+        IngredientCategory::Nut,
+        IngredientCategory::Condiment,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            IngredientCategory::Vegetable => "蔬菜",
+            IngredientCategory::Fruit => "水果",
+            IngredientCategory::Meat => "肉类",
+            IngredientCategory::Seafood => "海鲜水产",
+            IngredientCategory::Egg => "蛋类",
+            IngredientCategory::Dairy => "乳制品",
+            IngredientCategory::Soy => "豆制品",
+            IngredientCategory::Grain => "米面杂粮",
+            IngredientCategory::Oil => "食用油",
+            IngredientCategory::Nut => "坚果",
+            IngredientCategory::Condiment => "调味品",
+        }
+    }
+
+    /// Words that name the whole category, as written in dietary restrictions ("肉") or
+    /// in what a store stocks ("调料"). None of them is also an ingredient alias.
+    #[rustfmt::skip]
+    fn words(&self) -> &'static [&'static str] {
+        match self {
+            IngredientCategory::Vegetable => &["蔬菜", "青菜", "vegetable", "vegetables", "veggies"],
+            IngredientCategory::Fruit => &["水果", "fruit", "fruits"],
+            IngredientCategory::Meat => &["肉", "肉类", "荤菜", "meat"],
+            IngredientCategory::Seafood => &["海鲜", "水产", "海产品", "seafood"],
+            IngredientCategory::Egg => &["蛋类", "禽蛋"],
+            IngredientCategory::Dairy => &["乳制品", "奶制品", "dairy"],
+            IngredientCategory::Soy => &["豆制品", "soy products"],
+            IngredientCategory::Grain => &["米面", "主食", "谷物", "粮食", "杂粮", "grains", "staples"],
+            IngredientCategory::Oil => &["油", "油类", "oils"],
+            IngredientCategory::Nut => &["坚果", "nuts"],
+            IngredientCategory::Condiment => &["调料", "调味品", "调味料", "佐料", "condiments", "seasoning", "spices"],
+        }
+    }
+
+    pub fn store_section(&self) -> StoreSection {
+        match self {
+            IngredientCategory::Vegetable | IngredientCategory::Fruit => StoreSection::Produce,
+            IngredientCategory::Meat => StoreSection::Meat,
+            IngredientCategory::Seafood => StoreSection::Seafood,
+            IngredientCategory::Egg | IngredientCategory::Dairy => StoreSection::DairyAndEggs,
+            IngredientCategory::Soy => StoreSection::Soy,
+            IngredientCategory::Grain | IngredientCategory::Oil | IngredientCategory::Nut => {
+                StoreSection::Staples
+            }
+            IngredientCategory::Condiment => StoreSection::Condiments,
+        }
+    }
+}
+
+/// One canonical ingredient. `parent` names a broader entry it is a kind of, so avoiding
+/// or stocking 鸡肉 covers 鸡胸肉 too. `density` and `piece_grams` let amounts in volume
+/// or in pieces be weighed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CatalogEntry {
+    pub id: String,
+    pub name: String,
+    pub category: IngredientCategory,
+    pub default_unit: String,
+    #[serde(default)]
+    pub parent: Option<String>,
+    #[serde(default)]
+    pub density: Option<f64>, // grams per ml, water when absent
+    #[serde(default)]
+    pub piece_grams: Option<f64>, // weight of one 个, or of one `default_unit` such as 根
+    #[serde(default)]
+    pub aliases: Vec<String>,
+}
+
+/// What a free-text name stands for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resolved<'a> {
+    Ingredient(&'a CatalogEntry),
+    /// A word for a whole category, e.g. 海鲜 or 进口蔬菜.
+    Category(IngredientCategory),
+    Unknown,
+}
+
+/// Qualifiers in front of a name that do not change the ingredient.
+const DESCRIPTORS: &[&str] = &[
+    "新鲜", "有机", "进口", "精品", "冷冻", "冰鲜", "散装", "国产", "野生", "去皮", "fresh",
+    "organic", "frozen", "imported", "chopped", "diced", "sliced", "minced",
+];
+
+/// How an ingredient is cut, at the end of a name: 猪肉末, 土豆丝, 姜片.
+const CUTS: &[&str] = &["末", "丁", "丝", "片", "块", "碎", "段", "泥", "粒", "条"];
+
+/// Shortest alias that may match at the end of a longer name, so 红甜椒 is 甜椒 but 蛋
+/// alone does not turn 皮蛋 into 鸡蛋.
+const MIN_SUFFIX_CHARS: usize = 2;
+
+pub struct Catalog {
+    entries: Vec<CatalogEntry>,
+    by_id: HashMap<String, usize>,
+    by_name: HashMap<String, usize>, // normalized ids, names and aliases
+    categories: HashMap<&'static str, IngredientCategory>,
+}
+
+impl Catalog {
+    /// Parses and checks a catalog: ids are unique, no name or alias belongs to two
+    /// entries or to a category, and every parent exists without forming a loop.
+    pub fn parse(json: &str) -> AppResult<Self> {
+        let entries: Vec<CatalogEntry> = serde_json::from_str(json)
+            .map_err(|e| AppError::Validation(format!("Invalid ingredient catalog: {}", e)))?;
+
+        let categories: HashMap<&'static str, IngredientCategory> = IngredientCategory::ALL
+            .iter()
+            .flat_map(|category| category.words().iter().map(move |word| (*word, *category)))
+            .collect();
+
+        let mut by_id = HashMap::new();
+        let mut by_name = HashMap::new();
+        for (index, entry) in entries.iter().enumerate() {
+            if entry.id.trim().is_empty() || entry.name.trim().is_empty() {
+                return Err(AppError::Validation(
+                    "Ingredient catalog entries need an id and a name".to_string(),
+                ));
+            }
+            if by_id.insert(entry.id.clone(), index).is_some() {
+                return Err(AppError::Validation(format!(
+                    "Duplicate ingredient id '{}'",
+                    entry.id
+                )));
+            }
+            if [entry.density, entry.piece_grams]
+                .iter()
+                .flatten()
+                .any(|value| !value.is_finite() || *value <= 0.0)
+            {
+                return Err(AppError::Validation(format!(
+                    "Ingredient '{}' needs a positive density and piece weight",
+                    entry.id
+                )));
+            }
+            let names = [&entry.id, &entry.name].into_iter().chain(&entry.aliases);
+            for name in names {
+                let key = normalize_name(name);
+                if categories.contains_key(key.as_str()) {
+                    return Err(AppError::Validation(format!(
+                        "Ingredient name '{}' is also a category word",
+                        name
+                    )));
+                }
+                match by_name.insert(key, index) {
+                    Some(other) if other != index => {
+                        return Err(AppError::Validation(format!(
+                            "Ingredient name '{}' is used by both '{}' and '{}'",
+                            name, entries[other].id, entry.id
+                        )));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let catalog = Catalog {
+            entries,
+            by_id,
+            by_name,
+            categories,
+        };
+        for entry in &catalog.entries {
+            let mut seen = vec![entry.id.as_str()];
+            let mut current = entry;
+            while let Some(parent) = &current.parent {
+                current = catalog.get(parent).ok_or_else(|| {
+                    AppError::Validation(format!(
+                        "Ingredient '{}' has unknown parent '{}'",
+                        entry.id, parent
+                    ))
+                })?;
+                if seen.contains(&current.id.as_str()) {
+                    return Err(AppError::Validation(format!(
+                        "Ingredient '{}' is its own ancestor",
+                        entry.id
+                    )));
+                }
+                seen.push(&current.id);
+            }
+        }
+        Ok(catalog)
+    }
+
+    pub fn entries(&self) -> &[CatalogEntry] {
+        &self.entries
+    }
+
+    pub fn get(&self, id: &str) -> Option<&CatalogEntry> {
+        self.by_id.get(id).map(|index| &self.entries[*index])
+    }
+
+    /// Catalog entry for a free-text name, if it names a single ingredient.
+    pub fn normalize(&self, name: &str) -> Option<&CatalogEntry> {
+        match self.resolve(name) {
+            Resolved::Ingredient(entry) => Some(entry),
+            _ => None,
+        }
+    }
+
+    /// Maps a free-text name onto the catalog. Exact names and aliases win, then the name
+    /// without qualifiers and cut (有机胡萝卜丁 is 胡萝卜), then the longest alias it ends
+    /// with (红甜椒 is 甜椒, red bell pepper is bell pepper).
+    pub fn resolve(&self, name: &str) -> Resolved<'_> {
+        let name = normalize_name(name);
+        if name.is_empty() {
+            return Resolved::Unknown;
+        }
+        if let Some(found) = self.lookup(&name) {
+            return found;
+        }
+        let stripped = strip_qualifiers(&name);
+        if let Some(found) = self.lookup(stripped) {
+            return found;
+        }
+        self.by_name
+            .iter()
+            .filter(|(alias, _)| {
+                alias.chars().count() >= MIN_SUFFIX_CHARS && ends_with_word(stripped, alias)
+            })
+            .max_by_key(|(alias, _)| alias.len())
+            .map(|(_, index)| Resolved::Ingredient(&self.entries[*index]))
+            .unwrap_or(Resolved::Unknown)
+    }
+
+    fn lookup(&self, name: &str) -> Option<Resolved<'_>> {
+        if let Some(index) = self.by_name.get(name) {
+            return Some(Resolved::Ingredient(&self.entries[*index]));
+        }
+        self.categories
+            .get(name)
+            .map(|category| Resolved::Category(*category))
+    }
+
+    /// Whether `entry` is the ingredient `ancestor_id` or a kind of it.
+    pub fn is_a(&self, entry: &CatalogEntry, ancestor_id: &str) -> bool {
+        let mut current = Some(entry);
+        while let Some(entry) = current {
+            if entry.id == ancestor_id {
+                return true;
+            }
+            current = entry.parent.as_deref().and_then(|parent| self.get(parent));
+        }
+        false
+    }
+
+    /// Key under which amounts of the same ingredient are added up: the catalog id, or
+    /// the normalized name for ingredients the catalog does not know.
+    pub fn canonical_key(&self, name: &str) -> String {
+        match self.normalize(name) {
+            Some(entry) => entry.id.clone(),
+            None => normalize_name(name),
+        }
+    }
+
+    /// Whether something listed as `offered` (a store's stock, a pantry shelf) provides
+    /// `wanted`. A category covers its ingredients and an ingredient covers its kinds;
+    /// names outside the catalog must be equal.
+    pub fn covers(&self, offered: &str, wanted: &str) -> bool {
+        match (self.resolve(offered), self.resolve(wanted)) {
+            (Resolved::Ingredient(offered), Resolved::Ingredient(wanted)) => {
+                self.is_a(wanted, &offered.id)
+            }
+            (Resolved::Category(offered), Resolved::Ingredient(wanted)) => {
+                wanted.category == offered
+            }
+            (Resolved::Ingredient(offered), Resolved::Category(wanted)) => {
+                offered.category == wanted
+            }
+            (Resolved::Category(offered), Resolved::Category(wanted)) => offered == wanted,
+            (Resolved::Unknown, Resolved::Unknown) => {
+                let offered = normalize_name(offered);
+                !offered.is_empty() && offered == normalize_name(wanted)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// `name` without leading qualifiers and a trailing cut.
+fn strip_qualifiers(name: &str) -> &str {
+    let mut name = name;
+    while let Some(rest) = DESCRIPTORS
+        .iter()
+        .find_map(|word| name.strip_prefix(word).map(str::trim_start))
+        .filter(|rest| !rest.is_empty())
+    {
+        name = rest;
+    }
+    CUTS.iter()
+        .find_map(|cut| name.strip_suffix(cut))
+        .filter(|rest| !rest.is_empty())
+        .unwrap_or(name)
+}
+
+/// Whether `name` ends with `alias`, not in the middle of an English word ("ham" is not
+/// the end of "graham").
+fn ends_with_word(name: &str, alias: &str) -> bool {
+    match name.strip_suffix(alias) {
+        Some(rest) => {
+            !(rest.ends_with(|c: char| c.is_ascii_alphanumeric())
+                && alias.starts_with(|c: char| c.is_ascii_alphanumeric()))
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(name: &str) -> Option<&'static str> {
+        catalog().normalize(name).map(|entry| entry.id.as_str())
+    }
+
+    #[test]
+    fn test_bundled_catalog_covers_sample_recipes() {
+        let recipes = crate::utils::load_sample_recipes().unwrap();
+        for recipe in &recipes {
+            for ingredient in &recipe.ingredients {
+                assert!(
+                    catalog().normalize(&ingredient.name).is_some(),
+                    "{} is not in the catalog",
+                    ingredient.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_normalize_free_text() {
+        assert_eq!(id("西红柿"), Some("tomato"));
+        assert_eq!(id(" Cherry  Tomatoes "), Some("cherry_tomato"));
+        assert_eq!(id("有机胡萝卜丁"), Some("carrot"));
+        assert_eq!(id("猪肉末"), Some("pork"));
+        assert_eq!(id("红甜椒"), Some("bell_pepper"));
+        assert_eq!(id("red bell pepper"), Some("bell_pepper"));
+        assert_eq!(id("马苏里拉奶酪"), Some("cheese"));
+        assert_eq!(id("牛油果"), Some("avocado"));
+        assert_eq!(id("graham"), None);
+        assert_eq!(id("皮蛋"), None);
+        assert_eq!(
+            catalog().resolve("进口蔬菜"),
+            Resolved::Category(IngredientCategory::Vegetable)
+        );
+        assert_eq!(
+            catalog().resolve("油"),
+            Resolved::Category(IngredientCategory::Oil)
+        );
+        assert_eq!(
+            catalog().canonical_key("西红柿"),
+            catalog().canonical_key("番茄")
+        );
+    }
+
+    #[test]
+    fn test_covers_compares_ids_not_substrings() {
+        let catalog = catalog();
+        assert!(!catalog.covers("油", "酱油"));
+        assert!(catalog.covers("油", "橄榄油"));
+        assert!(catalog.covers("鸡肉", "鸡胸肉"));
+        assert!(!catalog.covers("鸡胸肉", "鸡肉"));
+        assert!(!catalog.covers("鸡肉", "鸡蛋"));
+        assert!(catalog.covers("新鲜蔬菜", "土豆"));
+        assert!(catalog.covers("猪肉", "肉类"));
+        assert!(!catalog.covers("牛肉", "牛油果"));
+        assert!(catalog.covers("零食", "零食"));
+    }
+
+    #[test]
+    fn test_parse_rejects_clashing_names() {
+        let clash = r#"[
+            {"id": "a", "name": "甲", "category": "meat", "default_unit": "g", "aliases": ["同名"]},
+            {"id": "b", "name": "乙", "category": "meat", "default_unit": "g", "aliases": ["同名"]}
+        ]"#;
+        assert!(Catalog::parse(clash).is_err());
+        let category_word = r#"[
+            {"id": "a", "name": "肉", "category": "meat", "default_unit": "g"}
+        ]"#;
+        assert!(Catalog::parse(category_word).is_err());
+        let orphan = r#"[
+            {"id": "a", "name": "甲", "category": "meat", "default_unit": "g", "parent": "b"}
+        ]"#;
+        assert!(Catalog::parse(orphan).is_err());
+    }
+}
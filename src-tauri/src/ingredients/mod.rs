@@ -0,0 +1,4 @@
+pub mod catalog;
+pub mod nutrition;
+pub mod scaling;
+pub mod units;
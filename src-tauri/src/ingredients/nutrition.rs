@@ -0,0 +1,453 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::ingredients::catalog::catalog;
+use crate::ingredients::units;
+use crate::storage::models::{NutritionalInfo, Recipe, RecipeIngredient};
+use crate::{AppError, AppResult};
+
+/// Per-100 g values shipped with the app, one row per catalog ingredient.
+const BUNDLED_TABLE: &str = include_str!("../../food_composition.json");
+
+static TABLE: Lazy<CompositionTable> = Lazy::new(|| {
+    CompositionTable::parse(BUNDLED_TABLE).expect("bundled food_composition.json is invalid")
+});
+
+/// The bundled food-composition table.
+pub fn composition_table() -> &'static CompositionTable {
+    &TABLE
+}
+
+/// Nutrients in some amount of food: energy in kcal, sodium in mg, the rest in grams.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Nutrients {
+    pub calories: f64,
+    pub protein: f64,
+    pub carbs: f64,
+    pub fat: f64,
+    pub fiber: f64,
+    pub sugar: f64,
+    pub sodium: f64,
+}
+
+impl Nutrients {
+    fn values(&self) -> [f64; 7] {
+        [
+            self.calories,
+            self.protein,
+            self.carbs,
+            self.fat,
+            self.fiber,
+            self.sugar,
+            self.sodium,
+        ]
+    }
+
+    pub fn scaled(&self, factor: f64) -> Self {
+        Nutrients {
+            calories: self.calories * factor,
+            protein: self.protein * factor,
+            carbs: self.carbs * factor,
+            fat: self.fat * factor,
+            fiber: self.fiber * factor,
+            sugar: self.sugar * factor,
+            sodium: self.sodium * factor,
+        }
+    }
+
+    pub fn add(&mut self, other: &Nutrients) {
+        self.calories += other.calories;
+        self.protein += other.protein;
+        self.carbs += other.carbs;
+        self.fat += other.fat;
+        self.fiber += other.fiber;
+        self.sugar += other.sugar;
+        self.sodium += other.sodium;
+    }
+
+    /// The part recipes declare, rounded to one decimal.
+    pub fn to_nutritional_info(self) -> NutritionalInfo {
+        let round = |value: f64| (value * 10.0).round() / 10.0;
+        NutritionalInfo {
+            calories: round(self.calories),
+            protein: round(self.protein),
+            carbs: round(self.carbs),
+            fat: round(self.fat),
+            fiber: round(self.fiber),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CompositionRow {
+    id: String,
+    calories: f64,
+    protein: f64,
+    carbs: f64,
+    fat: f64,
+    fiber: f64,
+    sugar: f64,
+    sodium: f64,
+}
+
+/// Nutrients per 100 g of each catalog ingredient.
+pub struct CompositionTable {
+    per_100g: HashMap<String, Nutrients>,
+}
+
+impl CompositionTable {
+    /// Parses a table and checks that every row is a known catalog id, listed once, with
+    /// finite non-negative values.
+    pub fn parse(json: &str) -> AppResult<Self> {
+        let rows: Vec<CompositionRow> = serde_json::from_str(json)
+            .map_err(|e| AppError::Validation(format!("Invalid food composition table: {}", e)))?;
+
+        let mut per_100g = HashMap::new();
+        for row in rows {
+            if catalog().get(&row.id).is_none() {
+                return Err(AppError::Validation(format!(
+                    "Food composition row '{}' is not a catalog ingredient",
+                    row.id
+                )));
+            }
+            let nutrients = Nutrients {
+                calories: row.calories,
+                protein: row.protein,
+                carbs: row.carbs,
+                fat: row.fat,
+                fiber: row.fiber,
+                sugar: row.sugar,
+                sodium: row.sodium,
+            };
+            if nutrients
+                .values()
+                .iter()
+                .any(|value| !value.is_finite() || *value < 0.0)
+            {
+                return Err(AppError::Validation(format!(
+                    "Food composition row '{}' has a negative or invalid value",
+                    row.id
+                )));
+            }
+            if per_100g.insert(row.id.clone(), nutrients).is_some() {
+                return Err(AppError::Validation(format!(
+                    "Duplicate food composition row '{}'",
+                    row.id
+                )));
+            }
+        }
+        Ok(CompositionTable { per_100g })
+    }
+
+    pub fn per_100g(&self, ingredient_id: &str) -> Option<&Nutrients> {
+        self.per_100g.get(ingredient_id)
+    }
+}
+
+/// Why an ingredient could not be counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NutritionGap {
+    NotInCatalog,
+    NoCompositionData,
+    /// Neither the unit nor the ingredient's piece weight says what it weighs (一把).
+    UnknownWeight,
+}
+
+/// What one ingredient line contributes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IngredientNutrition {
+    pub name: String,
+    pub ingredient_id: Option<String>, // catalog id
+    pub grams: Option<f64>,
+    pub nutrients: Nutrients,
+    pub gap: Option<NutritionGap>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecipeNutrition {
+    pub servings: f64,
+    pub total: Nutrients,
+    pub per_serving: Nutrients,
+    pub ingredients: Vec<IngredientNutrition>,
+    /// False when some ingredient could not be counted, making the totals a lower bound.
+    pub complete: bool,
+}
+
+/// Adds up the nutrients of `ingredients` from the composition table. Optional
+/// ingredients are left out and "适量" ones count as nothing.
+pub fn calculate(ingredients: &[RecipeIngredient], servings: f64) -> RecipeNutrition {
+    let mut total = Nutrients::default();
+    let mut lines = Vec::new();
+    for ingredient in ingredients.iter().filter(|ingredient| !ingredient.optional) {
+        let line = ingredient_nutrition(ingredient);
+        total.add(&line.nutrients);
+        lines.push(line);
+    }
+    let servings = if servings > 0.0 { servings } else { 1.0 };
+    RecipeNutrition {
+        servings,
+        per_serving: total.scaled(1.0 / servings),
+        total,
+        complete: lines.iter().all(|line| line.gap.is_none()),
+        ingredients: lines,
+    }
+}
+
+fn ingredient_nutrition(ingredient: &RecipeIngredient) -> IngredientNutrition {
+    let entry = catalog().normalize(&ingredient.name);
+    let grams = if units::to_base(&ingredient.unit).0.is_to_taste() {
+        Some(0.0)
+    } else {
+        units::to_grams(entry, ingredient.amount, &ingredient.unit)
+    };
+    let per_100g = entry.and_then(|entry| composition_table().per_100g(&entry.id));
+    let gap = match (entry, per_100g, grams) {
+        (None, _, _) => Some(NutritionGap::NotInCatalog),
+        (Some(_), None, _) => Some(NutritionGap::NoCompositionData),
+        (Some(_), Some(_), None) => Some(NutritionGap::UnknownWeight),
+        _ => None,
+    };
+    let nutrients = match (per_100g, grams) {
+        (Some(per_100g), Some(grams)) => per_100g.scaled(grams / 100.0),
+        _ => Nutrients::default(),
+    };
+    IngredientNutrition {
+        name: ingredient.name.clone(),
+        ingredient_id: entry.map(|entry| entry.id.clone()),
+        grams,
+        nutrients,
+        gap,
+    }
+}
+
+/// How far declared nutrition may be from the computed value: the larger of `relative`
+/// of the computed value and an absolute allowance, so small values are not flagged over
+/// a gram or two.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct NutritionTolerance {
+    pub relative: f64,
+    pub calories: f64, // kcal
+    pub grams: f64,    // for protein, carbs, fat and fiber
+}
+
+impl Default for NutritionTolerance {
+    fn default() -> Self {
+        NutritionTolerance {
+            relative: 0.25,
+            calories: 50.0,
+            grams: 5.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NutritionDeviation {
+    pub nutrient: String, // calories, protein, carbs, fat or fiber
+    pub declared: f64,
+    pub computed: f64,
+}
+
+/// A recipe whose declared nutrition per serving does not fit its ingredients.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NutritionLint {
+    pub recipe_id: Uuid,
+    pub title: String,
+    pub declared: NutritionalInfo,
+    pub computed: NutritionalInfo,
+    pub deviations: Vec<NutritionDeviation>,
+    /// Ingredients that could not be counted; while any are left, only declared values
+    /// below the partial sum are flagged.
+    pub uncounted: Vec<String>,
+}
+
+/// Compares `recipe`'s declared nutrition with what its ingredients add up to. Returns
+/// `None` when everything is within `tolerance`.
+pub fn lint_recipe(recipe: &Recipe, tolerance: &NutritionTolerance) -> Option<NutritionLint> {
+    let nutrition = calculate(&recipe.ingredients, recipe.servings as f64);
+    let declared = &recipe.nutritional_info_per_serving;
+    let computed = nutrition.per_serving.to_nutritional_info();
+
+    let pairs = [
+        (
+            "calories",
+            declared.calories,
+            computed.calories,
+            tolerance.calories,
+        ),
+        (
+            "protein",
+            declared.protein,
+            computed.protein,
+            tolerance.grams,
+        ),
+        ("carbs", declared.carbs, computed.carbs, tolerance.grams),
+        ("fat", declared.fat, computed.fat, tolerance.grams),
+        ("fiber", declared.fiber, computed.fiber, tolerance.grams),
+    ];
+    let deviations: Vec<NutritionDeviation> = pairs
+        .iter()
+        .filter(|(_, declared, computed, absolute)| {
+            let allowed = (computed * tolerance.relative).max(*absolute);
+            if nutrition.complete {
+                (declared - computed).abs() > allowed
+            } else {
+                computed - declared > allowed
+            }
+        })
+        .map(|(nutrient, declared, computed, _)| NutritionDeviation {
+            nutrient: nutrient.to_string(),
+            declared: *declared,
+            computed: *computed,
+        })
+        .collect();
+
+    if deviations.is_empty() {
+        return None;
+    }
+    Some(NutritionLint {
+        recipe_id: recipe.id,
+        title: recipe.title.clone(),
+        declared: declared.clone(),
+        computed,
+        deviations,
+        uncounted: nutrition
+            .ingredients
+            .iter()
+            .filter(|line| line.gap.is_some())
+            .map(|line| line.name.clone())
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::models::RecipeOrigin;
+
+    fn ingredient(name: &str, amount: f64, unit: &str) -> RecipeIngredient {
+        RecipeIngredient {
+            name: name.to_string(),
+            amount,
+            unit: unit.to_string(),
+            optional: false,
+        }
+    }
+
+    fn recipe(declared_calories: f64, ingredients: Vec<RecipeIngredient>) -> Recipe {
+        Recipe {
+            id: Uuid::new_v4(),
+            title: "测试".to_string(),
+            description: String::new(),
+            ingredients,
+            nutritional_info_per_serving: NutritionalInfo {
+                calories: declared_calories,
+                protein: 45.0,
+                carbs: 0.0,
+                fat: 4.0,
+                fiber: 0.0,
+            },
+            servings: 1,
+            preparation_time: 20,
+            difficulty_level: "easy".to_string(),
+            meal_type: "lunch".to_string(),
+            recipe_instructions: "做。".to_string(),
+            cuisine_type: None,
+            seasonal: false,
+            tags: vec![],
+            user_id: None,
+            origin: RecipeOrigin::Bundled,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_bundled_table_covers_the_catalog() {
+        for entry in catalog().entries() {
+            assert!(
+                composition_table().per_100g(&entry.id).is_some(),
+                "{} has no composition data",
+                entry.id
+            );
+        }
+    }
+
+    #[test]
+    fn test_calculate_from_amounts_and_units() {
+        let mut garnish = ingredient("香菜", 10.0, "g");
+        garnish.optional = true;
+        let nutrition = calculate(
+            &[
+                ingredient("鸡胸肉", 0.2, "kg"),
+                ingredient("牛奶", 100.0, "ml"),
+                ingredient("鸡蛋", 1.0, "个"),
+                ingredient("盐", 1.0, "适量"),
+                garnish,
+            ],
+            2.0,
+        );
+        assert!(nutrition.complete);
+        assert_eq!(nutrition.ingredients.len(), 4);
+        // 200 g chicken breast, 103 g milk (100 ml) and 50 g egg
+        let calories = 240.0 + 61.0 * 1.03 + 143.0 * 0.5;
+        assert!((nutrition.total.calories - calories).abs() < 1e-6);
+        let protein = 45.0 + 3.2 * 1.03 + 12.6 * 0.5;
+        assert!((nutrition.per_serving.protein - protein / 2.0).abs() < 1e-6);
+
+        let nutrition = calculate(
+            &[
+                ingredient("胡萝卜", 2.0, "把"),
+                ingredient("神秘食材", 1.0, "g"),
+            ],
+            1.0,
+        );
+        assert!(!nutrition.complete);
+        let gaps: Vec<Option<NutritionGap>> =
+            nutrition.ingredients.iter().map(|line| line.gap).collect();
+        assert_eq!(
+            gaps,
+            vec![
+                Some(NutritionGap::UnknownWeight),
+                Some(NutritionGap::NotInCatalog)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_flags_declared_values_beyond_tolerance() {
+        let tolerance = NutritionTolerance::default();
+        // 200 g chicken breast: 240 kcal, 45 g protein, 5.2 g fat
+        let chicken = || vec![ingredient("鸡胸肉", 200.0, "g")];
+        assert_eq!(lint_recipe(&recipe(250.0, chicken()), &tolerance), None);
+
+        let lint = lint_recipe(&recipe(500.0, chicken()), &tolerance).unwrap();
+        let flagged: Vec<&str> = lint
+            .deviations
+            .iter()
+            .map(|d| d.nutrient.as_str())
+            .collect();
+        assert_eq!(flagged, vec!["calories"]);
+        assert_eq!(lint.computed.calories, 240.0);
+
+        // Declared values are per serving, so a recipe for two carries twice the chicken
+        let mut for_two = recipe(250.0, vec![ingredient("鸡胸肉", 400.0, "g")]);
+        assert!(lint_recipe(&for_two, &tolerance).is_some());
+        for_two.servings = 2;
+        assert_eq!(lint_recipe(&for_two, &tolerance), None);
+
+        // With an uncounted ingredient, declaring more than the partial sum is fine
+        let mut ingredients = chicken();
+        ingredients.push(ingredient("胡萝卜", 1.0, "把"));
+        assert_eq!(
+            lint_recipe(&recipe(500.0, ingredients.clone()), &tolerance),
+            None
+        );
+        let lint = lint_recipe(&recipe(100.0, ingredients), &tolerance).unwrap();
+        assert_eq!(lint.uncounted, vec!["胡萝卜"]);
+    }
+}
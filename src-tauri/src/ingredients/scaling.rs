@@ -0,0 +1,150 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::ingredients::nutrition::{self, RecipeNutrition};
+use crate::ingredients::units;
+use crate::storage::models::{NutritionalInfo, Recipe, RecipeIngredient};
+use crate::{AppError, AppResult};
+
+/// A recipe rewritten for a different number of servings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScaledRecipe {
+    pub recipe_id: Uuid,
+    pub title: String,
+    pub servings: f64,
+    /// What the recipe's own amounts were multiplied by.
+    pub factor: f64,
+    /// Amounts rounded to a step that suits each unit.
+    pub ingredients: Vec<RecipeIngredient>,
+    pub nutrition_per_serving: NutritionalInfo,
+    /// The declared per-serving values times `servings`.
+    pub total_nutrition: NutritionalInfo,
+    /// Nutrition added up from the scaled ingredients.
+    pub computed: RecipeNutrition,
+}
+
+/// Rescales `recipe` from the servings it declares to `servings`, which may be
+/// fractional (1.5 portions). A serving stays the same size, so the per-serving
+/// nutrition is unchanged and the total follows the servings.
+pub fn scale_recipe(recipe: &Recipe, servings: f64) -> AppResult<ScaledRecipe> {
+    if !servings.is_finite() || servings <= 0.0 || servings > Recipe::MAX_SERVINGS as f64 {
+        return Err(AppError::Validation(format!(
+            "Servings must be greater than 0 and at most {}",
+            Recipe::MAX_SERVINGS
+        )));
+    }
+
+    let factor = servings / recipe.servings.max(1) as f64;
+    let ingredients: Vec<RecipeIngredient> = recipe
+        .ingredients
+        .iter()
+        .map(|ingredient| {
+            // 适量 is to taste however many are eating
+            let amount = if units::to_base(&ingredient.unit).0.is_to_taste() {
+                ingredient.amount
+            } else {
+                units::round_for_unit(ingredient.amount * factor, &ingredient.unit)
+            };
+            RecipeIngredient {
+                amount,
+                ..ingredient.clone()
+            }
+        })
+        .collect();
+    let declared = &recipe.nutritional_info_per_serving;
+    let round = |value: f64| (value * 10.0).round() / 10.0;
+
+    Ok(ScaledRecipe {
+        recipe_id: recipe.id,
+        title: recipe.title.clone(),
+        servings,
+        factor,
+        computed: nutrition::calculate(&ingredients, servings),
+        ingredients,
+        nutrition_per_serving: declared.clone(),
+        total_nutrition: NutritionalInfo {
+            calories: round(declared.calories * servings),
+            protein: round(declared.protein * servings),
+            carbs: round(declared.carbs * servings),
+            fat: round(declared.fat * servings),
+            fiber: round(declared.fiber * servings),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::models::RecipeOrigin;
+
+    fn ingredient(name: &str, amount: f64, unit: &str) -> RecipeIngredient {
+        RecipeIngredient {
+            name: name.to_string(),
+            amount,
+            unit: unit.to_string(),
+            optional: false,
+        }
+    }
+
+    fn soup() -> Recipe {
+        Recipe {
+            id: Uuid::new_v4(),
+            title: "扁豆汤".to_string(),
+            description: String::new(),
+            ingredients: vec![
+                ingredient("扁豆", 200.0, "g"),
+                ingredient("胡萝卜", 2.0, "根"),
+                ingredient("橄榄油", 1.0, "汤匙"),
+                ingredient("盐", 1.0, "适量"),
+            ],
+            nutritional_info_per_serving: NutritionalInfo {
+                calories: 300.0,
+                protein: 18.0,
+                carbs: 45.0,
+                fat: 6.0,
+                fiber: 15.0,
+            },
+            servings: 3,
+            preparation_time: 40,
+            difficulty_level: "easy".to_string(),
+            meal_type: "dinner".to_string(),
+            recipe_instructions: "煮。".to_string(),
+            cuisine_type: None,
+            seasonal: false,
+            tags: vec![],
+            user_id: None,
+            origin: RecipeOrigin::Bundled,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_scale_rounds_amounts_per_unit() {
+        let scaled = scale_recipe(&soup(), 2.0).unwrap();
+        assert!((scaled.factor - 2.0 / 3.0).abs() < 1e-9);
+        let amounts: Vec<(f64, &str)> = scaled
+            .ingredients
+            .iter()
+            .map(|i| (i.amount, i.unit.as_str()))
+            .collect();
+        assert_eq!(
+            amounts,
+            vec![(135.0, "g"), (1.5, "根"), (0.75, "汤匙"), (1.0, "适量")]
+        );
+        assert_eq!(scaled.nutrition_per_serving.calories, 300.0);
+        assert_eq!(scaled.total_nutrition.calories, 600.0);
+        assert_eq!(scaled.computed.servings, 2.0);
+
+        let scaled = scale_recipe(&soup(), 3.0).unwrap();
+        assert_eq!(scaled.factor, 1.0);
+        assert_eq!(scaled.ingredients, soup().ingredients);
+    }
+
+    #[test]
+    fn test_scale_rejects_invalid_servings() {
+        for servings in [0.0, -1.0, f64::NAN, 101.0] {
+            assert!(scale_recipe(&soup(), servings).is_err(), "{}", servings);
+        }
+    }
+}
@@ -0,0 +1,322 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+use crate::ingredients::catalog::CatalogEntry;
+use crate::interchange::ingredient_line::TO_TASTE_UNIT;
+
+/// What amounts are summed in. Units with no conversion (根, 片, 把, ...) are their own base.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BaseUnit {
+    Mass,   // g
+    Volume, // ml
+    Count,  // 个
+    ToTaste,
+    Other(String),
+}
+
+impl BaseUnit {
+    pub fn name(&self) -> &str {
+        match self {
+            BaseUnit::Mass => "g",
+            BaseUnit::Volume => "ml",
+            BaseUnit::Count => "个",
+            BaseUnit::ToTaste => TO_TASTE_UNIT,
+            BaseUnit::Other(unit) => unit,
+        }
+    }
+
+    pub fn is_to_taste(&self) -> bool {
+        *self == BaseUnit::ToTaste
+    }
+}
+
+/// How amounts are shown to the user. Chinese kitchen units (汤匙, 斤, 根, ...) a recipe
+/// is written in are kept in either.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnitSystem {
+    #[default]
+    Metric,
+    Imperial,
+}
+
+const ML_PER_TEASPOON: f64 = 5.0;
+const ML_PER_TABLESPOON: f64 = 15.0;
+const ML_PER_CUP: f64 = 240.0;
+const GRAMS_PER_OUNCE: f64 = 28.35;
+const GRAMS_PER_POUND: f64 = 453.6;
+
+/// Grams per ml of ingredients without a density of their own.
+const WATER_GRAMS_PER_ML: f64 = 1.0;
+
+/// Units that convert, with their factor to the base unit and the system they belong to.
+/// Spellings follow what `parse_ingredient_line` stores.
+#[rustfmt::skip]
+const CONVERSIONS: &[(&str, BaseUnit, f64, Option<UnitSystem>)] = &[
+    ("g", BaseUnit::Mass, 1.0, Some(UnitSystem::Metric)),
+    ("kg", BaseUnit::Mass, 1000.0, Some(UnitSystem::Metric)),
+    ("mg", BaseUnit::Mass, 0.001, Some(UnitSystem::Metric)),
+    ("斤", BaseUnit::Mass, 500.0, None),
+    ("两", BaseUnit::Mass, 50.0, None),
+    ("oz", BaseUnit::Mass, GRAMS_PER_OUNCE, Some(UnitSystem::Imperial)),
+    ("lb", BaseUnit::Mass, GRAMS_PER_POUND, Some(UnitSystem::Imperial)),
+    ("ml", BaseUnit::Volume, 1.0, Some(UnitSystem::Metric)),
+    ("l", BaseUnit::Volume, 1000.0, Some(UnitSystem::Metric)),
+    ("汤匙", BaseUnit::Volume, ML_PER_TABLESPOON, None),
+    ("勺", BaseUnit::Volume, ML_PER_TABLESPOON, None),
+    ("茶匙", BaseUnit::Volume, ML_PER_TEASPOON, None),
+    ("杯", BaseUnit::Volume, ML_PER_CUP, None),
+    ("tbsp", BaseUnit::Volume, ML_PER_TABLESPOON, Some(UnitSystem::Imperial)),
+    ("tsp", BaseUnit::Volume, ML_PER_TEASPOON, Some(UnitSystem::Imperial)),
+    ("cup", BaseUnit::Volume, ML_PER_CUP, Some(UnitSystem::Imperial)),
+    ("个", BaseUnit::Count, 1.0, None),
+    ("只", BaseUnit::Count, 1.0, None),
+    ("颗", BaseUnit::Count, 1.0, None),
+    ("枚", BaseUnit::Count, 1.0, None),
+    ("piece", BaseUnit::Count, 1.0, None),
+];
+
+/// Spoon units; a total made only of these is shown in 汤匙 rather than ml.
+const SPOONS: &[&str] = &["汤匙", "tbsp", "勺", "茶匙", "tsp"];
+
+/// `unit` trimmed and lowercased, so "G" and " g" are the same unit.
+pub fn canonical(unit: &str) -> String {
+    unit.trim().to_lowercase()
+}
+
+/// Base unit of `unit` and the factor an amount in `unit` is multiplied by to get there.
+pub fn to_base(unit: &str) -> (BaseUnit, f64) {
+    let unit = canonical(unit);
+    if unit.is_empty() {
+        return (BaseUnit::Count, 1.0);
+    }
+    if unit == TO_TASTE_UNIT {
+        return (BaseUnit::ToTaste, 1.0);
+    }
+    CONVERSIONS
+        .iter()
+        .find(|(name, _, _, _)| *name == unit)
+        .map(|(_, base, factor, _)| (base.clone(), *factor))
+        .unwrap_or((BaseUnit::Other(unit), 1.0))
+}
+
+fn system_of(unit: &str) -> Option<UnitSystem> {
+    let unit = canonical(unit);
+    CONVERSIONS
+        .iter()
+        .find(|(name, _, _, _)| *name == unit)
+        .and_then(|(_, _, _, system)| *system)
+}
+
+/// Grams in one `base` unit of an ingredient: volumes by its density (water when it has
+/// none), pieces by its piece weight. A unit other than 个 counts as a piece only when it
+/// is the ingredient's default unit, like 根 for 胡萝卜.
+fn grams_per_base(entry: Option<&CatalogEntry>, base: &BaseUnit) -> Option<f64> {
+    match base {
+        BaseUnit::Mass => Some(1.0),
+        BaseUnit::Volume => Some(
+            entry
+                .and_then(|entry| entry.density)
+                .unwrap_or(WATER_GRAMS_PER_ML),
+        ),
+        BaseUnit::Count => entry.and_then(|entry| entry.piece_grams),
+        BaseUnit::Other(unit) => entry
+            .filter(|entry| canonical(&entry.default_unit) == *unit)
+            .and_then(|entry| entry.piece_grams),
+        BaseUnit::ToTaste => None,
+    }
+}
+
+/// Weight in grams of `amount` `unit` of an ingredient (`entry`, when it is in the
+/// catalog). `None` when nothing says what that weighs, e.g. 一把 or 适量.
+pub fn to_grams(entry: Option<&CatalogEntry>, amount: f64, unit: &str) -> Option<f64> {
+    let (base, factor) = to_base(unit);
+    grams_per_base(entry, &base).map(|grams| amount * factor * grams)
+}
+
+/// `amount` in `from` expressed in `to`. Units measuring the same thing convert directly;
+/// otherwise the amount is weighed with the ingredient's density or piece weight, so
+/// 2 个 鸡蛋 is 100 g.
+pub fn convert(entry: Option<&CatalogEntry>, amount: f64, from: &str, to: &str) -> Option<f64> {
+    let (from_base, from_factor) = to_base(from);
+    let (to_base, to_factor) = to_base(to);
+    if from_base.is_to_taste() || to_base.is_to_taste() {
+        return None;
+    }
+    let in_base = amount * from_factor;
+    if from_base == to_base {
+        return Some(in_base / to_factor);
+    }
+    let grams = in_base * grams_per_base(entry, &from_base)?;
+    Some(grams / grams_per_base(entry, &to_base)? / to_factor)
+}
+
+/// Amount and unit to show for `amount` in `base`, rounded to two decimals. A total
+/// written in a single unit is shown in that unit unless the unit belongs to the other
+/// system; anything else uses g/kg, ml/l or 汤匙 (metric) or oz/lb, tsp/tbsp/cup
+/// (imperial).
+pub fn display(
+    base: &BaseUnit,
+    amount: f64,
+    units: &BTreeSet<String>,
+    system: UnitSystem,
+) -> (f64, String) {
+    let (amount, unit) = match units.iter().next() {
+        Some(unit) if units.len() == 1 && system_of(unit).is_none_or(|own| own == system) => {
+            (amount / to_base(unit).1, unit.clone())
+        }
+        _ => match system {
+            UnitSystem::Metric => metric(base, amount, units),
+            UnitSystem::Imperial => imperial(base, amount),
+        },
+    };
+    ((amount * 100.0).round() / 100.0, unit)
+}
+
+/// `amount` of `unit` rounded to a step a cook can measure: grams and ml to 5 above 100
+/// and to 1 above 10, spoons and cups to quarters, pieces to halves. A positive amount
+/// never rounds down to zero, and 适量 is left as it is.
+pub fn round_for_unit(amount: f64, unit: &str) -> f64 {
+    let Some(step) = rounding_step(amount, unit) else {
+        return amount;
+    };
+    let rounded = ((amount / step).round() * step).max(if amount > 0.0 { step } else { 0.0 });
+    (rounded * 100.0).round() / 100.0
+}
+
+fn rounding_step(amount: f64, unit: &str) -> Option<f64> {
+    let step = match canonical(unit).as_str() {
+        TO_TASTE_UNIT => return None,
+        "g" | "ml" if amount >= 100.0 => 5.0,
+        "g" | "ml" if amount >= 10.0 => 1.0,
+        "g" | "ml" => 0.5,
+        "mg" => 1.0,
+        "kg" | "l" | "lb" => 0.05,
+        "斤" => 0.1,
+        "两" => 0.5,
+        "oz" | "杯" | "cup" => 0.25,
+        spoon if SPOONS.contains(&spoon) => 0.25,
+        _ => 0.5,
+    };
+    Some(step)
+}
+
+fn metric(base: &BaseUnit, amount: f64, units: &BTreeSet<String>) -> (f64, String) {
+    match base {
+        BaseUnit::Mass if amount >= 1000.0 => (amount / 1000.0, "kg".to_string()),
+        BaseUnit::Volume if units.iter().all(|unit| SPOONS.contains(&unit.as_str())) => {
+            (amount / ML_PER_TABLESPOON, "汤匙".to_string())
+        }
+        BaseUnit::Volume if amount >= 1000.0 => (amount / 1000.0, "l".to_string()),
+        base => (amount, base.name().to_string()),
+    }
+}
+
+fn imperial(base: &BaseUnit, amount: f64) -> (f64, String) {
+    match base {
+        BaseUnit::Mass if amount >= GRAMS_PER_POUND => (amount / GRAMS_PER_POUND, "lb".to_string()),
+        BaseUnit::Mass => (amount / GRAMS_PER_OUNCE, "oz".to_string()),
+        BaseUnit::Volume if amount < ML_PER_TABLESPOON => {
+            (amount / ML_PER_TEASPOON, "tsp".to_string())
+        }
+        BaseUnit::Volume if amount < ML_PER_CUP / 4.0 => {
+            (amount / ML_PER_TABLESPOON, "tbsp".to_string())
+        }
+        BaseUnit::Volume => (amount / ML_PER_CUP, "cup".to_string()),
+        base => (amount, base.name().to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingredients::catalog::catalog;
+
+    fn units(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_display_prefers_the_recipe_unit() {
+        let metric = UnitSystem::Metric;
+        assert_eq!(
+            display(&BaseUnit::Mass, 1500.0, &units(&["kg"]), metric),
+            (1.5, "kg".to_string())
+        );
+        assert_eq!(
+            display(&BaseUnit::Mass, 1500.0, &units(&["g", "kg"]), metric),
+            (1.5, "kg".to_string())
+        );
+        assert_eq!(
+            display(&BaseUnit::Mass, 600.0, &units(&["g", "kg"]), metric),
+            (600.0, "g".to_string())
+        );
+        assert_eq!(
+            display(&BaseUnit::Volume, 45.0, &units(&["汤匙", "茶匙"]), metric),
+            (3.0, "汤匙".to_string())
+        );
+        assert_eq!(
+            display(&BaseUnit::Volume, 215.0, &units(&["ml", "汤匙"]), metric),
+            (215.0, "ml".to_string())
+        );
+        assert_eq!(to_base(" KG ").1, 1000.0);
+        assert_eq!(to_base("根").0, BaseUnit::Other("根".to_string()));
+    }
+
+    #[test]
+    fn test_display_in_imperial() {
+        let imperial = UnitSystem::Imperial;
+        assert_eq!(
+            display(&BaseUnit::Mass, 1100.0, &units(&["g", "kg"]), imperial),
+            (2.43, "lb".to_string())
+        );
+        assert_eq!(
+            display(&BaseUnit::Mass, 200.0, &units(&["g"]), imperial),
+            (7.05, "oz".to_string())
+        );
+        assert_eq!(
+            display(&BaseUnit::Volume, 480.0, &units(&["ml"]), imperial),
+            (2.0, "cup".to_string())
+        );
+        // Kitchen units stay as written, and imperial ones are converted back for metric
+        assert_eq!(
+            display(&BaseUnit::Volume, 30.0, &units(&["汤匙"]), imperial),
+            (2.0, "汤匙".to_string())
+        );
+        assert_eq!(
+            display(&BaseUnit::Mass, 453.6, &units(&["lb"]), UnitSystem::Metric),
+            (453.6, "g".to_string())
+        );
+    }
+
+    #[test]
+    fn test_round_for_unit() {
+        assert_eq!(round_for_unit(333.33, "g"), 335.0);
+        assert_eq!(round_for_unit(66.67, "ml"), 67.0);
+        assert_eq!(round_for_unit(3.3, "g"), 3.5);
+        assert_eq!(round_for_unit(0.6, "KG"), 0.6);
+        assert_eq!(round_for_unit(1.33, "汤匙"), 1.25);
+        assert_eq!(round_for_unit(0.1, "tsp"), 0.25);
+        assert_eq!(round_for_unit(2.67, "个"), 2.5);
+        assert_eq!(round_for_unit(0.2, "根"), 0.5);
+        assert_eq!(round_for_unit(1.0, "适量"), 1.0);
+    }
+
+    #[test]
+    fn test_convert_with_density_and_piece_weight() {
+        let egg = catalog().normalize("鸡蛋");
+        let carrot = catalog().normalize("胡萝卜");
+        let oil = catalog().normalize("橄榄油");
+
+        assert_eq!(convert(None, 2.0, "斤", "kg"), Some(1.0));
+        assert_eq!(convert(egg, 3.0, "个", "g"), Some(150.0));
+        assert_eq!(convert(egg, 100.0, "g", "只"), Some(2.0));
+        assert_eq!(to_grams(carrot, 2.0, "根"), Some(160.0));
+        assert_eq!(to_grams(carrot, 2.0, "把"), None);
+        assert!((to_grams(oil, 1.0, "汤匙").unwrap() - 13.65).abs() < 1e-9);
+        // Water when the density is unknown, nothing without a piece weight
+        assert_eq!(to_grams(None, 250.0, "ml"), Some(250.0));
+        assert_eq!(to_grams(None, 1.0, "个"), None);
+        assert_eq!(convert(egg, 1.0, "适量", "g"), None);
+    }
+}
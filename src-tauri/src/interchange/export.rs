@@ -0,0 +1,413 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::interchange::ingredient_line::TO_TASTE_UNIT;
+use crate::interchange::jsonld::{format_amount, recipe_to_json_ld};
+use crate::storage::models::{
+    DietHistory, DietRecommendation, HealthProfile, MealPlan, PantryItem, Recipe, ShoppingList,
+};
+use crate::storage::Database;
+use crate::AppResult;
+
+/// Identifies a full-account archive among the JSON files the importer reads.
+pub const ARCHIVE_FORMAT: &str = "scx-recipes-account";
+
+/// Bump when the archive layout changes; the importer rejects newer versions.
+pub const ARCHIVE_VERSION: u32 = 1;
+
+/// Limits an export to one user and/or an inclusive date range. Recipes are matched on
+/// their creation date and owner, history entries on the date the meal was attempted.
+#[derive(Debug, Clone, Default)]
+pub struct ExportFilter {
+    pub user_id: Option<String>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+}
+
+impl ExportFilter {
+    fn includes_date(&self, date: NaiveDate) -> bool {
+        self.start_date.is_none_or(|start| date >= start)
+            && self.end_date.is_none_or(|end| date <= end)
+    }
+}
+
+/// A diet history entry with the title of the meal it refers to, when it can be resolved.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryExportRow {
+    #[serde(flatten)]
+    pub entry: DietHistory,
+    pub title: Option<String>,
+}
+
+/// Everything that belongs to one user, in a single versioned JSON document.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountArchive {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub user_id: String,
+    pub health_profile: Option<HealthProfile>,
+    pub recipes: Vec<Recipe>,
+    pub recommendations: Vec<DietRecommendation>, // the ones diet_history refers to
+    pub diet_history: Vec<DietHistory>,
+    #[serde(default)]
+    pub meal_plans: Vec<MealPlan>,
+    #[serde(default)]
+    pub shopping_lists: Vec<ShoppingList>,
+    #[serde(default)]
+    pub pantry: Vec<PantryItem>,
+}
+
+/// Recipes matching the filter. With a user, only recipes that user owns are exported.
+pub fn select_recipes(db: &Database, filter: &ExportFilter) -> AppResult<Vec<Recipe>> {
+    Ok(db
+        .get_all_recipes()?
+        .into_iter()
+        .filter(|r| match filter.user_id {
+            Some(ref user_id) => r.user_id.as_deref() == Some(user_id.as_str()),
+            None => true,
+        })
+        .filter(|r| filter.includes_date(r.created_at.date_naive()))
+        .collect())
+}
+
+pub fn select_history(db: &Database, filter: &ExportFilter) -> AppResult<Vec<HistoryExportRow>> {
+    let history =
+        db.get_diet_history_between(filter.user_id.as_deref(), filter.start_date, filter.end_date)?;
+
+    history
+        .into_iter()
+        .map(|entry| {
+            let title = resolve_title(db, &entry)?;
+            Ok(HistoryExportRow { entry, title })
+        })
+        .collect()
+}
+
+/// History entries point at a recommendation or, for custom entries, directly at a recipe.
+fn resolve_title(db: &Database, entry: &DietHistory) -> AppResult<Option<String>> {
+    let id = entry.diet_item_id.to_string();
+    if let Some(recommendation) = db.get_recommendation_by_id(&id)? {
+        return Ok(Some(recommendation.title));
+    }
+    Ok(db.get_recipe_by_id(&id)?.map(|recipe| recipe.title))
+}
+
+/// A JSON-LD document with one `Recipe` node per recipe in its `@graph`.
+pub fn recipes_to_json_ld(recipes: &[Recipe]) -> AppResult<String> {
+    let graph: Vec<Value> = recipes
+        .iter()
+        .map(|recipe| {
+            let mut node = recipe_to_json_ld(recipe);
+            if let Some(map) = node.as_object_mut() {
+                map.remove("@context");
+            }
+            node
+        })
+        .collect();
+
+    Ok(serde_json::to_string_pretty(&serde_json::json!({
+        "@context": "https://schema.org",
+        "@graph": graph,
+    }))?)
+}
+
+pub fn recipes_to_markdown(recipes: &[Recipe]) -> String {
+    recipes
+        .iter()
+        .map(recipe_to_markdown)
+        .collect::<Vec<_>>()
+        .join("\n---\n\n")
+}
+
+fn recipe_to_markdown(recipe: &Recipe) -> String {
+    let mut out = format!("# {}\n\n", recipe.title);
+    if !recipe.description.is_empty() {
+        out.push_str(&format!("{}\n\n", recipe.description));
+    }
+
+    out.push_str(&format!(
+        "- 餐次：{}\n- 难度：{}\n- 准备时间：{} 分钟\n",
+        recipe.meal_type, recipe.difficulty_level, recipe.preparation_time
+    ));
+    if let Some(ref cuisine) = recipe.cuisine_type {
+        out.push_str(&format!("- 菜系：{}\n", cuisine));
+    }
+    if !recipe.tags.is_empty() {
+        out.push_str(&format!("- 标签：{}\n", recipe.tags.join(", ")));
+    }
+
+    out.push_str("\n## 食材\n\n");
+    for ingredient in &recipe.ingredients {
+        let quantity = if ingredient.unit == TO_TASTE_UNIT {
+            TO_TASTE_UNIT.to_string()
+        } else {
+            format!("{} {}", format_amount(ingredient.amount), ingredient.unit)
+        };
+        let optional = if ingredient.optional { "（可选）" } else { "" };
+        out.push_str(&format!("- {} {}{}\n", ingredient.name, quantity, optional));
+    }
+
+    out.push_str(&format!("\n## 做法\n\n{}\n", recipe.recipe_instructions));
+
+    let n = &recipe.nutritional_info_per_serving;
+    out.push_str(&format!(
+        "\n## 营养（每份）\n\n| 热量 | 蛋白质 | 碳水 | 脂肪 | 膳食纤维 |\n| --- | --- | --- | --- | --- |\n| {} kcal | {} g | {} g | {} g | {} g |\n",
+        format_amount(n.calories),
+        format_amount(n.protein),
+        format_amount(n.carbs),
+        format_amount(n.fat),
+        format_amount(n.fiber),
+    ));
+
+    out
+}
+
+const CSV_HEADER: &[&str] = &[
+    "id",
+    "user_id",
+    "date_attempted",
+    "meal_type",
+    "diet_item_id",
+    "title",
+    "rating",
+    "was_prepared",
+    "notes",
+    "created_at",
+    "updated_at",
+];
+
+pub fn history_to_csv(rows: &[HistoryExportRow]) -> String {
+    let mut out = CSV_HEADER.join(",");
+    out.push('\n');
+
+    for row in rows {
+        let entry = &row.entry;
+        let fields = [
+            entry.id.to_string(),
+            entry.user_id.clone(),
+            entry.date_attempted.format("%Y-%m-%d").to_string(),
+            entry.meal_type.clone(),
+            entry.diet_item_id.to_string(),
+            row.title.clone().unwrap_or_default(),
+            entry.rating.map(|r| r.to_string()).unwrap_or_default(),
+            entry.was_prepared.to_string(),
+            entry.notes.clone().unwrap_or_default(),
+            entry.created_at.to_rfc3339(),
+            entry.updated_at.to_rfc3339(),
+        ];
+        out.push_str(
+            &fields
+                .iter()
+                .map(|f| csv_field(f))
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Quotes a field when it contains a separator, quote or line break (RFC 4180).
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub fn history_to_jsonl(rows: &[HistoryExportRow]) -> AppResult<String> {
+    let mut out = String::new();
+    for row in rows {
+        out.push_str(&serde_json::to_string(row)?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Collects the user's profile, history, meal plans, owned recipes and the recommendations
+/// their history refers to.
+pub fn build_account_archive(db: &Database, user_id: &str) -> AppResult<AccountArchive> {
+    let diet_history = db.get_diet_history_between(Some(user_id), None, None)?;
+
+    let mut seen = HashSet::new();
+    let mut recommendations = Vec::new();
+    for entry in &diet_history {
+        let id = entry.diet_item_id.to_string();
+        if seen.insert(id.clone()) {
+            if let Some(recommendation) = db.get_recommendation_by_id(&id)? {
+                recommendations.push(recommendation);
+            }
+        }
+    }
+
+    Ok(AccountArchive {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        exported_at: Utc::now(),
+        user_id: user_id.to_string(),
+        health_profile: db.get_health_profile(user_id)?,
+        recipes: select_recipes(
+            db,
+            &ExportFilter {
+                user_id: Some(user_id.to_string()),
+                ..Default::default()
+            },
+        )?,
+        recommendations,
+        diet_history,
+        meal_plans: db.list_meal_plans(user_id)?,
+        shopping_lists: db.list_shopping_lists(user_id)?,
+        pantry: db.get_pantry(user_id)?,
+    })
+}
+
+/// Writes an export, creating the parent directory if needed.
+pub fn write_export(path: &Path, contents: &str) -> AppResult<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, contents)?;
+    log::info!("Wrote export to {}", path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::models::{NutritionalInfo, RecipeIngredient, RecipeOrigin};
+    use tempfile::TempDir;
+    use uuid::Uuid;
+
+    fn entry(user_id: &str, date: &str, notes: Option<&str>) -> DietHistory {
+        DietHistory {
+            id: Uuid::new_v4(),
+            user_id: user_id.to_string(),
+            diet_item_id: Uuid::new_v4(),
+            date_attempted: NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap(),
+            rating: Some(4),
+            notes: notes.map(str::to_string),
+            was_prepared: true,
+            meal_type: "lunch".to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn recipe(title: &str, user_id: Option<&str>) -> Recipe {
+        Recipe {
+            id: Uuid::new_v4(),
+            title: title.to_string(),
+            description: String::new(),
+            ingredients: vec![
+                RecipeIngredient {
+                    name: "鸡蛋".to_string(),
+                    amount: 2.0,
+                    unit: "个".to_string(),
+                    optional: false,
+                },
+                RecipeIngredient {
+                    name: "葱".to_string(),
+                    amount: 1.0,
+                    unit: TO_TASTE_UNIT.to_string(),
+                    optional: true,
+                },
+            ],
+            nutritional_info_per_serving: NutritionalInfo {
+                calories: 180.0,
+                protein: 12.5,
+                carbs: 2.0,
+                fat: 13.0,
+                fiber: 0.0,
+            },
+            servings: 1,
+            preparation_time: 10,
+            difficulty_level: "easy".to_string(),
+            meal_type: "breakfast".to_string(),
+            recipe_instructions: "打散后煎熟。".to_string(),
+            cuisine_type: None,
+            seasonal: false,
+            tags: vec!["quick".to_string()],
+            user_id: user_id.map(str::to_string),
+            origin: if user_id.is_some() {
+                RecipeOrigin::User
+            } else {
+                RecipeOrigin::Bundled
+            },
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_history_filters_and_csv_quoting() {
+        let dir = TempDir::new().unwrap();
+        let db = Database::new(dir.path().join("data.db")).unwrap();
+        db.log_diet_entry(&entry("user_1", "2024-03-01", Some("好吃, \"再做\"")))
+            .unwrap();
+        db.log_diet_entry(&entry("user_1", "2024-04-01", None)).unwrap();
+        db.log_diet_entry(&entry("user_2", "2024-03-02", None)).unwrap();
+
+        let rows = select_history(
+            &db,
+            &ExportFilter {
+                user_id: Some("user_1".to_string()),
+                start_date: NaiveDate::from_ymd_opt(2024, 3, 1),
+                end_date: NaiveDate::from_ymd_opt(2024, 3, 31),
+            },
+        )
+        .unwrap();
+        assert_eq!(rows.len(), 1);
+
+        let csv = history_to_csv(&rows);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("id,user_id,date_attempted"));
+        assert!(lines[1].contains(",\"好吃, \"\"再做\"\"\","));
+
+        let all = select_history(&db, &ExportFilter::default()).unwrap();
+        let jsonl = history_to_jsonl(&all).unwrap();
+        assert_eq!(jsonl.lines().count(), 3);
+        let first: HistoryExportRow = serde_json::from_str(jsonl.lines().next().unwrap()).unwrap();
+        assert_eq!(first.entry.date_attempted.to_string(), "2024-03-01");
+    }
+
+    #[test]
+    fn test_recipe_exports() {
+        let recipes = vec![recipe("葱花蛋饼", None)];
+
+        let markdown = recipes_to_markdown(&recipes);
+        assert!(markdown.starts_with("# 葱花蛋饼\n"));
+        assert!(markdown.contains("- 鸡蛋 2 个\n"));
+        assert!(markdown.contains("- 葱 适量（可选）\n"));
+        assert!(markdown.contains("| 180 kcal | 12.5 g |"));
+
+        let document: Value = serde_json::from_str(&recipes_to_json_ld(&recipes).unwrap()).unwrap();
+        assert_eq!(document["@graph"].as_array().unwrap().len(), 1);
+        assert_eq!(document["@graph"][0]["totalTime"], "PT10M");
+    }
+
+    #[test]
+    fn test_account_archive_contents() {
+        let dir = TempDir::new().unwrap();
+        let db = Database::new(dir.path().join("data.db")).unwrap();
+        db.save_recipe(&recipe("葱花蛋饼", Some("user_1"))).unwrap();
+        db.save_recipe(&recipe("别人的蛋饼", Some("user_2"))).unwrap();
+        db.save_recipe(&recipe("内置蛋饼", None)).unwrap();
+        db.log_diet_entry(&entry("user_1", "2024-03-01", None)).unwrap();
+
+        let archive = build_account_archive(&db, "user_1").unwrap();
+        assert_eq!(archive.format, ARCHIVE_FORMAT);
+        assert_eq!(archive.version, ARCHIVE_VERSION);
+        assert!(archive.health_profile.is_none());
+        assert_eq!(archive.recipes.len(), 1);
+        assert_eq!(archive.recipes[0].title, "葱花蛋饼");
+        assert_eq!(archive.diet_history.len(), 1);
+    }
+}
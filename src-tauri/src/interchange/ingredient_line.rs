@@ -0,0 +1,408 @@
+use crate::storage::models::RecipeIngredient;
+
+/// Unit used when an ingredient line has no quantity, e.g. "盐 适量" or "salt to taste".
+pub const TO_TASTE_UNIT: &str = "适量";
+
+/// Result of parsing one free-text ingredient line.
+#[derive(Debug, Clone)]
+pub struct ParsedIngredient {
+    pub ingredient: RecipeIngredient,
+    pub warning: Option<String>,
+}
+
+/// Spellings accepted for each unit, mapped to the unit stored on the recipe.
+/// Longer spellings come first so "tablespoons" is not read as "t".
+const UNITS: &[(&str, &str)] = &[
+    ("tablespoons", "tbsp"),
+    ("tablespoon", "tbsp"),
+    ("teaspoons", "tsp"),
+    ("teaspoon", "tsp"),
+    ("kilograms", "kg"),
+    ("kilogram", "kg"),
+    ("milliliters", "ml"),
+    ("milliliter", "ml"),
+    ("millilitres", "ml"),
+    ("millilitre", "ml"),
+    ("grams", "g"),
+    ("gram", "g"),
+    ("liters", "l"),
+    ("liter", "l"),
+    ("litres", "l"),
+    ("litre", "l"),
+    ("ounces", "oz"),
+    ("ounce", "oz"),
+    ("pounds", "lb"),
+    ("pound", "lb"),
+    ("cloves", "clove"),
+    ("clove", "clove"),
+    ("pieces", "piece"),
+    ("piece", "piece"),
+    ("slices", "slice"),
+    ("slice", "slice"),
+    ("pinches", "pinch"),
+    ("pinch", "pinch"),
+    ("cups", "cup"),
+    ("cup", "cup"),
+    ("cans", "can"),
+    ("can", "can"),
+    ("tbsp", "tbsp"),
+    ("tsp", "tsp"),
+    ("lbs", "lb"),
+    ("lb", "lb"),
+    ("oz", "oz"),
+    ("kg", "kg"),
+    ("mg", "mg"),
+    ("ml", "ml"),
+    ("g", "g"),
+    ("l", "l"),
+    ("千克", "kg"),
+    ("公斤", "kg"),
+    ("毫升", "ml"),
+    ("克", "g"),
+    ("升", "l"),
+    ("大勺", "汤匙"),
+    ("小勺", "茶匙"),
+    ("汤匙", "汤匙"),
+    ("茶匙", "茶匙"),
+    ("勺", "勺"),
+    ("杯", "杯"),
+    ("个", "个"),
+    ("只", "只"),
+    ("根", "根"),
+    ("片", "片"),
+    ("瓣", "瓣"),
+    ("块", "块"),
+    ("颗", "颗"),
+    ("把", "把"),
+    ("斤", "斤"),
+    ("两", "两"),
+    ("碗", "碗"),
+    ("条", "条"),
+];
+
+/// Parses lines such as "2 cups flour", "1 1/2 tbsp sugar", "200g 豆腐", "鸡蛋 2个",
+/// "两个鸡蛋" or "盐 适量". A range like "1-2" uses its lower bound. Lines without a
+/// quantity get an amount of 1 in `TO_TASTE_UNIT` and a warning.
+pub fn parse_ingredient_line(line: &str) -> Option<ParsedIngredient> {
+    let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.is_empty() {
+        return None;
+    }
+
+    let lower = line.to_lowercase();
+    let optional = ["optional", "可选", "可不加"]
+        .iter()
+        .any(|marker| lower.contains(marker));
+    let text = strip_notes(&line);
+    if text.is_empty() {
+        return None;
+    }
+
+    let parsed = parse_leading_quantity(&text)
+        .or_else(|| parse_trailing_quantity(&text))
+        .filter(|(_, _, name)| !name.is_empty());
+
+    let (amount, unit, name, warning) = match parsed {
+        Some((amount, unit, name)) => (
+            amount,
+            unit.unwrap_or_else(|| "个".to_string()),
+            name,
+            None,
+        ),
+        None => (
+            1.0,
+            TO_TASTE_UNIT.to_string(),
+            strip_to_taste(&text),
+            Some(format!("No quantity in ingredient '{}', stored as {}", line, TO_TASTE_UNIT)),
+        ),
+    };
+
+    if name.is_empty() {
+        return None;
+    }
+
+    Some(ParsedIngredient {
+        ingredient: RecipeIngredient {
+            name,
+            amount,
+            unit,
+            optional,
+        },
+        warning,
+    })
+}
+
+/// Drops parenthesised notes and anything after the first comma ("onion, finely chopped").
+fn strip_notes(line: &str) -> String {
+    let mut result = String::new();
+    let mut depth = 0;
+    for c in line.chars() {
+        match c {
+            '(' | '（' => depth += 1,
+            ')' | '）' => depth = (depth - 1).max(0),
+            ',' | '，' if depth == 0 => break,
+            _ if depth == 0 => result.push(c),
+            _ => {}
+        }
+    }
+    result.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn strip_to_taste(text: &str) -> String {
+    let mut name = text.to_string();
+    for marker in ["to taste", "as needed", "适量", "少许", "少量"] {
+        name = name.replace(marker, "");
+    }
+    name.trim().to_string()
+}
+
+/// "2 cups of flour" -> (2.0, Some("cup"), "flour")
+fn parse_leading_quantity(text: &str) -> Option<(f64, Option<String>, String)> {
+    let (amount, consumed) = parse_number(text)?;
+    let rest = text[consumed..].trim_start();
+    let rest = skip_range(rest);
+    let (unit, rest) = match parse_unit(rest) {
+        Some((unit, len)) => (Some(unit), rest[len..].trim_start()),
+        None => (None, rest),
+    };
+    // A Chinese numeral only counts with a unit: "两个鸡蛋", but not "三文鱼"
+    if unit.is_none() && text.chars().next().and_then(chinese_digit).is_some() {
+        return None;
+    }
+    let name = rest.strip_prefix("of ").unwrap_or(rest).trim().to_string();
+    Some((amount, unit, name))
+}
+
+/// "鸡蛋 2个" or "豆腐200克" -> quantity at the end of the line
+fn parse_trailing_quantity(text: &str) -> Option<(f64, Option<String>, String)> {
+    // Try every position where a number could start, leftmost first, and accept the
+    // first one whose remainder is exactly a number followed by an optional unit
+    for (start, _) in text.char_indices() {
+        let Some((amount, consumed)) = parse_number(&text[start..]) else {
+            continue;
+        };
+        if text[..start].chars().last().is_some_and(is_number_char) {
+            continue;
+        }
+        let rest = text[start + consumed..].trim_start();
+        let unit = if rest.is_empty() {
+            None
+        } else {
+            match parse_unit(rest) {
+                Some((unit, len)) if rest[len..].trim().is_empty() => Some(unit),
+                _ => continue,
+            }
+        };
+        let name = text[..start].trim().to_string();
+        return Some((amount, unit, name));
+    }
+    None
+}
+
+fn is_number_char(c: char) -> bool {
+    c.is_ascii_digit() || vulgar_fraction(c).is_some() || chinese_digit(c).is_some()
+}
+
+/// Skips the upper bound of a range such as "1-2" or "1 to 2".
+fn skip_range(text: &str) -> &str {
+    for separator in ["-", "–", "~", "～", "to ", "至"] {
+        if let Some(rest) = text.strip_prefix(separator) {
+            if let Some((_, len)) = parse_number(rest.trim_start()) {
+                let rest = rest.trim_start();
+                return rest[len..].trim_start();
+            }
+        }
+    }
+    text
+}
+
+fn parse_unit(text: &str) -> Option<(String, usize)> {
+    for (spelling, unit) in UNITS {
+        // Compared in place: lowercasing can change byte lengths ("İ"), so offsets into a
+        // lowercased copy would not line up with `text`
+        let matches = text
+            .get(..spelling.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(spelling));
+        if matches {
+            let rest = &text[spelling.len()..];
+            // Latin units must end at a word boundary: "g" in "garlic" is not grams
+            let ascii = spelling.is_ascii();
+            let boundary = rest
+                .chars()
+                .next()
+                .is_none_or(|c| !c.is_ascii_alphabetic() || !ascii);
+            let rest = rest.strip_prefix('.').unwrap_or(rest);
+            if boundary {
+                return Some((unit.to_string(), text.len() - rest.len()));
+            }
+        }
+    }
+    None
+}
+
+/// Parses a leading quantity: "2", "1.5", "1/2", "1 1/2", "½", "1½", "两", "半".
+/// Returns the value and the number of bytes consumed.
+fn parse_number(text: &str) -> Option<(f64, usize)> {
+    let first = text.chars().next()?;
+
+    if let Some(value) = chinese_digit(first) {
+        return Some((value, first.len_utf8()));
+    }
+    if let Some(value) = vulgar_fraction(first) {
+        return Some((value, first.len_utf8()));
+    }
+    if !first.is_ascii_digit() {
+        return None;
+    }
+
+    let mut end = 0;
+    for (i, c) in text.char_indices() {
+        if c.is_ascii_digit() || c == '.' {
+            end = i + 1;
+        } else {
+            break;
+        }
+    }
+    let whole: f64 = text[..end].trim_end_matches('.').parse().ok()?;
+    let rest = &text[end..];
+
+    // "1/2"
+    if let Some(after_slash) = rest.strip_prefix('/') {
+        let denom_len = after_slash
+            .char_indices()
+            .take_while(|(_, c)| c.is_ascii_digit())
+            .count();
+        if denom_len > 0 {
+            let denom: f64 = after_slash[..denom_len].parse().ok()?;
+            if denom > 0.0 {
+                return Some((whole / denom, end + 1 + denom_len));
+            }
+        }
+    }
+
+    // "1½"
+    if let Some(c) = rest.chars().next() {
+        if let Some(fraction) = vulgar_fraction(c) {
+            return Some((whole + fraction, end + c.len_utf8()));
+        }
+    }
+
+    // "1 1/2" or "1 ½"
+    if let Some(after_space) = rest.strip_prefix(' ') {
+        if let Some(c) = after_space.chars().next() {
+            if let Some(fraction) = vulgar_fraction(c) {
+                return Some((whole + fraction, end + 1 + c.len_utf8()));
+            }
+        }
+        let num_len = after_space
+            .char_indices()
+            .take_while(|(_, c)| c.is_ascii_digit())
+            .count();
+        if num_len > 0 && after_space[num_len..].starts_with('/') {
+            if let Some((fraction, len)) = parse_number(after_space) {
+                if fraction < 1.0 {
+                    return Some((whole + fraction, end + 1 + len));
+                }
+            }
+        }
+    }
+
+    Some((whole, end))
+}
+
+fn vulgar_fraction(c: char) -> Option<f64> {
+    match c {
+        '½' => Some(0.5),
+        '⅓' => Some(1.0 / 3.0),
+        '⅔' => Some(2.0 / 3.0),
+        '¼' => Some(0.25),
+        '¾' => Some(0.75),
+        '⅛' => Some(0.125),
+        _ => None,
+    }
+}
+
+fn chinese_digit(c: char) -> Option<f64> {
+    match c {
+        '半' => Some(0.5),
+        '一' => Some(1.0),
+        '二' | '两' => Some(2.0),
+        '三' => Some(3.0),
+        '四' => Some(4.0),
+        '五' => Some(5.0),
+        '六' => Some(6.0),
+        '七' => Some(7.0),
+        '八' => Some(8.0),
+        '九' => Some(9.0),
+        '十' => Some(10.0),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> (f64, String, String) {
+        let parsed = parse_ingredient_line(line).unwrap();
+        (
+            parsed.ingredient.amount,
+            parsed.ingredient.unit,
+            parsed.ingredient.name,
+        )
+    }
+
+    #[test]
+    fn test_leading_quantities() {
+        assert_eq!(parse("2 cups flour"), (2.0, "cup".into(), "flour".into()));
+        assert_eq!(parse("1 1/2 tbsp sugar"), (1.5, "tbsp".into(), "sugar".into()));
+        assert_eq!(parse("½ cup of milk"), (0.5, "cup".into(), "milk".into()));
+        assert_eq!(parse("1½ Tablespoons olive oil"), (1.5, "tbsp".into(), "olive oil".into()));
+        assert_eq!(parse("200g 豆腐"), (200.0, "g".into(), "豆腐".into()));
+        assert_eq!(parse("3 garlic cloves"), (3.0, "个".into(), "garlic cloves".into()));
+        assert_eq!(parse("1-2 cloves garlic"), (1.0, "clove".into(), "garlic".into()));
+        assert_eq!(parse("两个鸡蛋"), (2.0, "个".into(), "鸡蛋".into()));
+        assert_eq!(parse("半根胡萝卜"), (0.5, "根".into(), "胡萝卜".into()));
+    }
+
+    #[test]
+    fn test_trailing_quantities() {
+        assert_eq!(parse("豆腐 200克"), (200.0, "g".into(), "豆腐".into()));
+        assert_eq!(parse("鸡蛋2个"), (2.0, "个".into(), "鸡蛋".into()));
+        assert_eq!(parse("生抽 1汤匙"), (1.0, "汤匙".into(), "生抽".into()));
+        assert_eq!(parse("三文鱼 200g"), (200.0, "g".into(), "三文鱼".into()));
+    }
+
+    #[test]
+    fn test_units_next_to_non_ascii_text() {
+        // "İ" lowercases to three bytes instead of two
+        assert_eq!(parse("200 g İİ peynir"), (200.0, "g".into(), "İİ peynir".into()));
+        assert_eq!(parse("1 Kg İnegöl köfte"), (1.0, "kg".into(), "İnegöl köfte".into()));
+        assert_eq!(parse("2 İİ"), (2.0, "个".into(), "İİ".into()));
+    }
+
+    #[test]
+    fn test_notes_and_optional_markers() {
+        let parsed = parse_ingredient_line("1 onion, finely chopped (optional)").unwrap();
+        assert_eq!(parsed.ingredient.name, "onion");
+        assert!(parsed.ingredient.optional);
+        assert!(parsed.warning.is_none());
+
+        let parsed = parse_ingredient_line("1 (14 oz) can tomatoes").unwrap();
+        assert_eq!(parsed.ingredient.unit, "can");
+        assert_eq!(parsed.ingredient.name, "tomatoes");
+    }
+
+    #[test]
+    fn test_lines_without_quantity() {
+        let parsed = parse_ingredient_line("盐 适量").unwrap();
+        assert_eq!(parsed.ingredient.name, "盐");
+        assert_eq!(parsed.ingredient.unit, TO_TASTE_UNIT);
+        assert!(parsed.warning.is_some());
+
+        let parsed = parse_ingredient_line("salt to taste").unwrap();
+        assert_eq!(parsed.ingredient.name, "salt");
+
+        assert!(parse_ingredient_line("   ").is_none());
+    }
+}
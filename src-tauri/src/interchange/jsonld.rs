@@ -0,0 +1,670 @@
+use chrono::Utc;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::interchange::ingredient_line::{parse_ingredient_line, TO_TASTE_UNIT};
+use crate::storage::models::{normalize_name, NutritionalInfo, Recipe, RecipeOrigin};
+use crate::{AppError, AppResult};
+
+/// Preparation time assumed when a record has no prepTime, cookTime or totalTime.
+const DEFAULT_PREPARATION_MINUTES: u32 = 30;
+
+/// Meal type assumed when recipeCategory and keywords do not name one.
+const DEFAULT_MEAL_TYPE: &str = "lunch";
+
+/// A recipe mapped from a schema.org record, with the problems that were worked around.
+#[derive(Debug, Clone)]
+pub struct MappedRecipe {
+    pub recipe: Recipe,
+    pub warnings: Vec<String>,
+}
+
+/// Stable id for an imported recipe, derived from its `@id`/`url` or, failing that, its
+/// title. Importing the same record twice yields the same id.
+pub fn imported_recipe_id(key: &str) -> Uuid {
+    Uuid::new_v5(
+        &Uuid::NAMESPACE_OID,
+        format!("scx-recipes/imported-recipe/{}", key).as_bytes(),
+    )
+}
+
+/// Returns the contents of every `<script type="application/ld+json">` block in a saved page.
+pub fn extract_json_ld_scripts(html: &str) -> Vec<String> {
+    let lower = html.to_lowercase();
+    let mut scripts = Vec::new();
+    let mut position = 0;
+
+    while let Some(offset) = lower[position..].find("<script") {
+        let tag_start = position + offset;
+        let Some(tag_len) = lower[tag_start..].find('>') else {
+            break;
+        };
+        let tag_end = tag_start + tag_len + 1;
+        let Some(close) = lower[tag_end..].find("</script") else {
+            break;
+        };
+        let body_end = tag_end + close;
+
+        if lower[tag_start..tag_end].contains("application/ld+json") {
+            scripts.push(html[tag_end..body_end].trim().to_string());
+        }
+        position = body_end;
+    }
+
+    scripts
+}
+
+/// Collects every node typed `Recipe` in a JSON-LD document, looking through top-level
+/// arrays, `@graph` and nested objects.
+pub fn find_recipe_nodes(value: &Value) -> Vec<&Value> {
+    let mut nodes = Vec::new();
+    collect_recipe_nodes(value, &mut nodes);
+    nodes
+}
+
+fn collect_recipe_nodes<'a>(value: &'a Value, nodes: &mut Vec<&'a Value>) {
+    match value {
+        Value::Array(items) => {
+            for item in items {
+                collect_recipe_nodes(item, nodes);
+            }
+        }
+        Value::Object(map) => {
+            if is_recipe_type(map.get("@type")) {
+                nodes.push(value);
+                return;
+            }
+            for child in map.values() {
+                collect_recipe_nodes(child, nodes);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_recipe_type(value: Option<&Value>) -> bool {
+    let is_recipe = |t: &str| {
+        t == "Recipe" || t.ends_with(":Recipe") || t.ends_with("/Recipe")
+    };
+    match value {
+        Some(Value::String(t)) => is_recipe(t),
+        Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).any(is_recipe),
+        _ => false,
+    }
+}
+
+/// Maps a schema.org `Recipe` node onto a stored recipe with origin `Imported`.
+/// Fails only when the record has no name or no usable ingredients; everything else
+/// falls back to a default and adds a warning.
+pub fn recipe_from_json_ld(node: &Value, user_id: Option<&str>) -> AppResult<MappedRecipe> {
+    let mut warnings = Vec::new();
+
+    let title = text_field(node, "name")
+        .filter(|t| !t.is_empty())
+        .ok_or_else(|| AppError::Validation("Recipe has no name".to_string()))?;
+
+    let mut ingredients = Vec::new();
+    let lines = string_list(node.get("recipeIngredient").or_else(|| node.get("ingredients")));
+    for line in &lines {
+        match parse_ingredient_line(line) {
+            Some(parsed) => {
+                if let Some(warning) = parsed.warning {
+                    warnings.push(warning);
+                }
+                ingredients.push(parsed.ingredient);
+            }
+            None => warnings.push(format!("Skipped unreadable ingredient '{}'", line)),
+        }
+    }
+    if ingredients.is_empty() {
+        return Err(AppError::Validation(format!(
+            "Recipe '{}' has no ingredients",
+            title
+        )));
+    }
+
+    let preparation_time = match preparation_minutes(node) {
+        Some(minutes) if minutes > 0 => minutes,
+        _ => {
+            warnings.push(format!(
+                "No preparation time, assuming {} minutes",
+                DEFAULT_PREPARATION_MINUTES
+            ));
+            DEFAULT_PREPARATION_MINUTES
+        }
+    };
+
+    let nutritional_info_per_serving = nutrition(node.get("nutrition"), &mut warnings);
+    let servings = servings(node.get("recipeYield"), &mut warnings);
+
+    let categories = string_list(node.get("recipeCategory"));
+    let keywords = keywords(node.get("keywords"));
+    let meal_type = categories
+        .iter()
+        .chain(keywords.iter())
+        .find_map(|c| meal_type_for(c))
+        .unwrap_or_else(|| {
+            warnings.push(format!(
+                "No meal type in recipeCategory, assuming {}",
+                DEFAULT_MEAL_TYPE
+            ));
+            DEFAULT_MEAL_TYPE
+        })
+        .to_string();
+
+    let mut tags: Vec<String> = Vec::new();
+    for tag in categories.iter().chain(keywords.iter()) {
+        let tag = normalize_name(tag);
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+
+    let recipe_instructions = instructions(node.get("recipeInstructions"));
+    if recipe_instructions.is_empty() {
+        warnings.push("No recipeInstructions".to_string());
+    }
+
+    let key = text_field(node, "@id")
+        .or_else(|| text_field(node, "url"))
+        .unwrap_or_else(|| normalize_name(&title));
+    // Our own exports carry the original id as urn:uuid
+    let id = key
+        .strip_prefix("urn:uuid:")
+        .and_then(|id| Uuid::parse_str(id).ok())
+        .unwrap_or_else(|| imported_recipe_id(&key));
+
+    let now = Utc::now();
+    let recipe = Recipe {
+        id,
+        title,
+        description: text_field(node, "description").unwrap_or_default(),
+        ingredients,
+        nutritional_info_per_serving,
+        servings,
+        preparation_time,
+        difficulty_level: difficulty_for(preparation_time).to_string(),
+        meal_type,
+        recipe_instructions,
+        cuisine_type: string_list(node.get("recipeCuisine")).into_iter().next(),
+        seasonal: false,
+        tags,
+        user_id: user_id.map(str::to_string),
+        origin: RecipeOrigin::Imported,
+        created_at: now,
+        updated_at: now,
+    };
+
+    Ok(MappedRecipe { recipe, warnings })
+}
+
+/// Maps a stored recipe onto a schema.org `Recipe` node. `@id` is the recipe's urn:uuid,
+/// so importing the export again is recognised as a duplicate.
+pub fn recipe_to_json_ld(recipe: &Recipe) -> Value {
+    let nutrition = &recipe.nutritional_info_per_serving;
+    let mut node = serde_json::json!({
+        "@context": "https://schema.org",
+        "@type": "Recipe",
+        "@id": format!("urn:uuid:{}", recipe.id),
+        "name": recipe.title,
+        "description": recipe.description,
+        "totalTime": format_iso8601_duration(recipe.preparation_time),
+        "recipeCategory": recipe.meal_type,
+        "keywords": recipe.tags.join(", "),
+        "recipeIngredient": recipe
+            .ingredients
+            .iter()
+            .map(|i| {
+                let line = if i.unit == TO_TASTE_UNIT {
+                    format!("{} {}", i.name, TO_TASTE_UNIT)
+                } else {
+                    format!("{} {} {}", format_amount(i.amount), i.unit, i.name)
+                };
+                if i.optional {
+                    format!("{} (optional)", line)
+                } else {
+                    line
+                }
+            })
+            .collect::<Vec<_>>(),
+        "recipeInstructions": recipe.recipe_instructions,
+        "recipeYield": recipe.servings.to_string(),
+        "nutrition": {
+            "@type": "NutritionInformation",
+            "servingSize": "1",
+            "calories": format!("{} kcal", format_amount(nutrition.calories)),
+            "proteinContent": format!("{} g", format_amount(nutrition.protein)),
+            "carbohydrateContent": format!("{} g", format_amount(nutrition.carbs)),
+            "fatContent": format!("{} g", format_amount(nutrition.fat)),
+            "fiberContent": format!("{} g", format_amount(nutrition.fiber)),
+        },
+        "dateCreated": recipe.created_at.to_rfc3339(),
+        "dateModified": recipe.updated_at.to_rfc3339(),
+    });
+    if let Some(ref cuisine) = recipe.cuisine_type {
+        node["recipeCuisine"] = Value::String(cuisine.clone());
+    }
+    node
+}
+
+/// Formats minutes as an ISO-8601 duration, e.g. 90 -> "PT1H30M".
+pub fn format_iso8601_duration(minutes: u32) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("PT{}M", m),
+        (h, 0) => format!("PT{}H", h),
+        (h, m) => format!("PT{}H{}M", h, m),
+    }
+}
+
+/// Drops a trailing ".0" so amounts read naturally: 2.0 -> "2", 0.5 -> "0.5".
+pub(crate) fn format_amount(amount: f64) -> String {
+    let rounded = (amount * 100.0).round() / 100.0;
+    if rounded.fract() == 0.0 {
+        format!("{}", rounded as i64)
+    } else {
+        format!("{}", rounded)
+    }
+}
+
+/// Parses an ISO-8601 duration such as "PT1H30M" or "P1DT2H" into whole minutes,
+/// rounding seconds up. Year and month components are rejected as ambiguous.
+pub fn parse_iso8601_duration(value: &str) -> Option<u32> {
+    let value = value.trim().to_uppercase();
+    let rest = value.strip_prefix('P')?;
+    if rest.is_empty() {
+        return None;
+    }
+
+    let mut seconds = 0.0;
+    let mut in_time = false;
+    let mut number = String::new();
+    for c in rest.chars() {
+        match c {
+            'T' if !in_time && number.is_empty() => in_time = true,
+            '0'..='9' | '.' | ',' => number.push(if c == ',' { '.' } else { c }),
+            designator => {
+                let amount: f64 = number.parse().ok()?;
+                number.clear();
+                seconds += amount
+                    * match (in_time, designator) {
+                        (false, 'W') => 7.0 * 86400.0,
+                        (false, 'D') => 86400.0,
+                        (true, 'H') => 3600.0,
+                        (true, 'M') => 60.0,
+                        (true, 'S') => 1.0,
+                        _ => return None,
+                    };
+            }
+        }
+    }
+    if !number.is_empty() {
+        return None;
+    }
+
+    Some((seconds / 60.0).ceil() as u32)
+}
+
+/// totalTime when present, otherwise prepTime + cookTime.
+fn preparation_minutes(node: &Value) -> Option<u32> {
+    let duration = |field: &str| text_field(node, field).and_then(|d| parse_iso8601_duration(&d));
+    duration("totalTime").or_else(|| match (duration("prepTime"), duration("cookTime")) {
+        (None, None) => None,
+        (prep, cook) => Some(prep.unwrap_or(0) + cook.unwrap_or(0)),
+    })
+}
+
+fn nutrition(value: Option<&Value>, warnings: &mut Vec<String>) -> NutritionalInfo {
+    let field = |name: &str| value.and_then(|v| v.get(name)).and_then(quantity);
+
+    let protein = field("proteinContent");
+    let carbs = field("carbohydrateContent");
+    let fat = field("fatContent");
+    let fiber = field("fiberContent");
+
+    for (name, amount) in [
+        ("proteinContent", protein),
+        ("carbohydrateContent", carbs),
+        ("fatContent", fat),
+    ] {
+        if amount.is_none() {
+            warnings.push(format!("No {} in nutrition, assuming 0", name));
+        }
+    }
+
+    let calories = match value.and_then(|v| v.get("calories")).and_then(quantity) {
+        Some(calories) => calories,
+        None => {
+            let estimate =
+                protein.unwrap_or(0.0) * 4.0 + carbs.unwrap_or(0.0) * 4.0 + fat.unwrap_or(0.0) * 9.0;
+            warnings.push(format!(
+                "No calories in nutrition, estimated {:.0} kcal from macronutrients",
+                estimate
+            ));
+            estimate
+        }
+    };
+
+    NutritionalInfo {
+        calories,
+        protein: protein.unwrap_or(0.0),
+        carbs: carbs.unwrap_or(0.0),
+        fat: fat.unwrap_or(0.0),
+        fiber: fiber.unwrap_or(0.0),
+    }
+}
+
+/// Reads recipeYield ("4", "4 servings", ["4", "4 portions"] or a bare number). A missing
+/// yield means one serving; one that does not start with a number is ignored with a warning.
+fn servings(value: Option<&Value>, warnings: &mut Vec<String>) -> u32 {
+    let Some(value) = value else {
+        return 1;
+    };
+    let candidates = match value {
+        Value::Array(items) => items.iter().collect(),
+        other => vec![other],
+    };
+    match candidates.into_iter().find_map(quantity) {
+        Some(count) if count >= 1.0 => (count.round() as u32).min(Recipe::MAX_SERVINGS),
+        _ => {
+            warnings.push(format!("Unreadable recipeYield {}, assuming 1 serving", value));
+            1
+        }
+    }
+}
+
+/// Reads "250 kcal", "1046 kJ", "12 g", "350 mg" or a bare number. Energy is returned in
+/// kcal and masses in grams.
+fn quantity(value: &Value) -> Option<f64> {
+    if let Some(number) = value.as_f64() {
+        return Some(number);
+    }
+    let text = value.as_str()?.trim().to_lowercase();
+    let end = text
+        .char_indices()
+        .find(|(_, c)| !(c.is_ascii_digit() || *c == '.' || *c == ','))
+        .map(|(i, _)| i)
+        .unwrap_or(text.len());
+    let number: f64 = text[..end].replace(',', ".").parse().ok()?;
+    let unit = text[end..].trim();
+
+    Some(if unit.starts_with("kj") {
+        number / 4.184
+    } else if unit.starts_with("mg") {
+        number / 1000.0
+    } else {
+        number
+    })
+}
+
+fn meal_type_for(category: &str) -> Option<&'static str> {
+    let category = category.to_lowercase();
+    let matches = |words: &[&str]| words.iter().any(|w| category.contains(w));
+
+    if matches(&["breakfast", "brunch", "早餐", "早饭", "早点"]) {
+        Some("breakfast")
+    } else if matches(&["lunch", "午餐", "午饭"]) {
+        Some("lunch")
+    } else if matches(&["dinner", "supper", "main course", "main dish", "entree", "晚餐", "晚饭", "主菜"]) {
+        Some("dinner")
+    } else if matches(&["snack", "dessert", "appetizer", "小吃", "点心", "甜点", "零食"]) {
+        Some("snack")
+    } else {
+        None
+    }
+}
+
+/// Same bands as the bundled recipes: up to 25 minutes is easy, up to 45 medium.
+fn difficulty_for(minutes: u32) -> &'static str {
+    match minutes {
+        0..=25 => "easy",
+        26..=45 => "medium",
+        _ => "hard",
+    }
+}
+
+/// Flattens recipeInstructions, which may be text, a list of strings, HowToStep
+/// objects or HowToSection objects, into numbered lines.
+fn instructions(value: Option<&Value>) -> String {
+    let mut steps = Vec::new();
+    collect_steps(value, &mut steps);
+
+    if steps.len() == 1 {
+        return steps.remove(0);
+    }
+    steps
+        .iter()
+        .enumerate()
+        .map(|(i, step)| format!("{}. {}", i + 1, step))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn collect_steps(value: Option<&Value>, steps: &mut Vec<String>) {
+    match value {
+        Some(Value::String(text)) => {
+            let text = clean_text(text);
+            if !text.is_empty() {
+                steps.push(text);
+            }
+        }
+        Some(Value::Array(items)) => {
+            for item in items {
+                collect_steps(Some(item), steps);
+            }
+        }
+        Some(Value::Object(map)) => {
+            if let Some(items) = map.get("itemListElement") {
+                collect_steps(Some(items), steps);
+            } else {
+                collect_steps(map.get("text").or_else(|| map.get("name")), steps);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// keywords is either a comma-separated string or a list.
+fn keywords(value: Option<&Value>) -> Vec<String> {
+    string_list(value)
+        .iter()
+        .flat_map(|k| k.split([',', '，']))
+        .map(|k| k.trim().to_string())
+        .filter(|k| !k.is_empty())
+        .collect()
+}
+
+fn string_list(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::String(text)) => vec![clean_text(text)],
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(Value::as_str)
+            .map(clean_text)
+            .filter(|t| !t.is_empty())
+            .collect(),
+        _ => vec![],
+    }
+}
+
+fn text_field(node: &Value, field: &str) -> Option<String> {
+    match node.get(field)? {
+        Value::String(text) => Some(clean_text(text)),
+        Value::Array(items) => items.iter().find_map(Value::as_str).map(clean_text),
+        _ => None,
+    }
+}
+
+/// Removes HTML tags and decodes the entities that commonly appear in scraped recipes.
+fn clean_text(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => stripped.push(c),
+            _ => {}
+        }
+    }
+
+    stripped
+        .replace("&nbsp;", " ")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_iso8601_durations() {
+        assert_eq!(parse_iso8601_duration("PT45M"), Some(45));
+        assert_eq!(parse_iso8601_duration("PT1H30M"), Some(90));
+        assert_eq!(parse_iso8601_duration("P0DT1H"), Some(60));
+        assert_eq!(parse_iso8601_duration("PT90S"), Some(2));
+        assert_eq!(parse_iso8601_duration("PT0.5H"), Some(30));
+        assert_eq!(parse_iso8601_duration("P1D"), Some(1440));
+        assert_eq!(parse_iso8601_duration("P1M"), None);
+        assert_eq!(parse_iso8601_duration("45 minutes"), None);
+        assert_eq!(parse_iso8601_duration("P"), None);
+    }
+
+    #[test]
+    fn test_exported_recipe_maps_back() {
+        let node = json!({
+            "@type": "Recipe",
+            "name": "清蒸鲈鱼",
+            "totalTime": "PT1H5M",
+            "recipeCategory": "dinner",
+            "keywords": "high_protein",
+            "recipeIngredient": ["鲈鱼 1条", "1.5 tbsp 蒸鱼豉油", "葱 适量"],
+            "recipeInstructions": "蒸八分钟。",
+            "nutrition": {"calories": "220 kcal", "proteinContent": "30 g", "carbohydrateContent": "2 g", "fatContent": "9 g"}
+        });
+        let original = recipe_from_json_ld(&node, None).unwrap().recipe;
+
+        let exported = recipe_to_json_ld(&original);
+        assert_eq!(exported["totalTime"], "PT1H5M");
+        let round_trip = recipe_from_json_ld(&exported, None).unwrap().recipe;
+
+        assert_eq!(round_trip.id, original.id);
+        assert_eq!(round_trip.title, original.title);
+        assert_eq!(round_trip.preparation_time, 65);
+        assert_eq!(round_trip.meal_type, "dinner");
+        assert_eq!(round_trip.tags, vec!["dinner", "high_protein"]);
+        let ingredients: Vec<_> = round_trip
+            .ingredients
+            .iter()
+            .map(|i| (i.name.as_str(), i.amount, i.unit.as_str()))
+            .collect();
+        assert_eq!(
+            ingredients,
+            vec![("鲈鱼", 1.0, "条"), ("蒸鱼豉油", 1.5, "tbsp"), ("葱", 1.0, "适量")]
+        );
+        assert_eq!(round_trip.nutritional_info_per_serving.protein, 30.0);
+        assert_eq!(round_trip.servings, original.servings);
+    }
+
+    #[test]
+    fn test_finds_recipes_in_graphs_and_pages() {
+        let html = r#"<html><head>
+            <script type="application/ld+json">{"@context":"https://schema.org","@graph":[
+                {"@type":"WebPage","name":"page"},
+                {"@type":["Recipe","NewsArticle"],"name":"番茄炒蛋"}
+            ]}</script>
+            <script>var x = 1;</script>
+        </head></html>"#;
+
+        let scripts = extract_json_ld_scripts(html);
+        assert_eq!(scripts.len(), 1);
+        let document: Value = serde_json::from_str(&scripts[0]).unwrap();
+        let nodes = find_recipe_nodes(&document);
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0]["name"], "番茄炒蛋");
+    }
+
+    #[test]
+    fn test_maps_schema_org_recipe() {
+        let node = json!({
+            "@type": "Recipe",
+            "name": "Tomato &amp; Egg Stir-fry",
+            "url": "https://example.com/tomato-egg",
+            "description": "<p>A quick weeknight dish.</p>",
+            "prepTime": "PT10M",
+            "cookTime": "PT10M",
+            "recipeCategory": "Dinner",
+            "recipeCuisine": ["Chinese"],
+            "keywords": "quick, Vegetarian",
+            "recipeYield": ["4", "4 servings"],
+            "recipeIngredient": ["3 eggs", "2 tomatoes", "1 tsp salt"],
+            "recipeInstructions": [
+                {"@type": "HowToStep", "text": "Beat the eggs."},
+                {"@type": "HowToSection", "itemListElement": [
+                    {"@type": "HowToStep", "text": "Fry the tomatoes."}
+                ]}
+            ],
+            "nutrition": {
+                "@type": "NutritionInformation",
+                "calories": "1046 kJ",
+                "proteinContent": "14 g",
+                "carbohydrateContent": "8 g",
+                "fatContent": "16 g",
+                "fiberContent": "1500 mg"
+            }
+        });
+
+        let mapped = recipe_from_json_ld(&node, Some("user_1")).unwrap();
+        let recipe = mapped.recipe;
+        assert!(mapped.warnings.is_empty(), "{:?}", mapped.warnings);
+        assert_eq!(recipe.title, "Tomato & Egg Stir-fry");
+        assert_eq!(recipe.description, "A quick weeknight dish.");
+        assert_eq!(recipe.preparation_time, 20);
+        assert_eq!(recipe.difficulty_level, "easy");
+        assert_eq!(recipe.meal_type, "dinner");
+        assert_eq!(recipe.cuisine_type.as_deref(), Some("Chinese"));
+        assert_eq!(recipe.tags, vec!["dinner", "quick", "vegetarian"]);
+        assert_eq!(recipe.ingredients.len(), 3);
+        assert_eq!(recipe.ingredients[2].unit, "tsp");
+        assert_eq!(recipe.servings, 4);
+        assert_eq!(
+            recipe.recipe_instructions,
+            "1. Beat the eggs.\n2. Fry the tomatoes."
+        );
+        assert!((recipe.nutritional_info_per_serving.calories - 250.0).abs() < 0.1);
+        assert_eq!(recipe.nutritional_info_per_serving.fiber, 1.5);
+        assert_eq!(recipe.origin, RecipeOrigin::Imported);
+        assert_eq!(recipe.id, imported_recipe_id("https://example.com/tomato-egg"));
+        assert!(recipe.validate().is_ok());
+    }
+
+    #[test]
+    fn test_missing_fields_become_warnings() {
+        let node = json!({
+            "@type": "Recipe",
+            "name": "盐水毛豆",
+            "recipeIngredient": ["毛豆 500克", "盐 适量"],
+            "recipeInstructions": "煮熟后加盐。",
+            "nutrition": {"proteinContent": "11 g", "carbohydrateContent": "9 g", "fatContent": "5 g"}
+        });
+
+        let mapped = recipe_from_json_ld(&node, None).unwrap();
+        assert_eq!(mapped.recipe.preparation_time, DEFAULT_PREPARATION_MINUTES);
+        assert_eq!(mapped.recipe.meal_type, DEFAULT_MEAL_TYPE);
+        assert_eq!(mapped.recipe.nutritional_info_per_serving.calories, 125.0);
+        assert_eq!(mapped.recipe.servings, 1);
+        // quantity-less salt, time, meal type and estimated calories
+        assert_eq!(mapped.warnings.len(), 4, "{:?}", mapped.warnings);
+        assert!(mapped.recipe.validate().is_ok());
+
+        assert!(recipe_from_json_ld(&json!({"@type": "Recipe", "name": "空"}), None).is_err());
+        assert!(recipe_from_json_ld(&json!({"@type": "Recipe"}), None).is_err());
+    }
+}
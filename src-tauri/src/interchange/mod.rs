@@ -0,0 +1,676 @@
+pub mod export;
+pub mod ingredient_line;
+pub mod jsonld;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::storage::models::{normalize_name, Recipe};
+use crate::storage::Database;
+use crate::AppResult;
+use export::{AccountArchive, ARCHIVE_FORMAT, ARCHIVE_VERSION};
+
+/// File extensions picked up when importing a directory.
+const IMPORT_EXTENSIONS: &[&str] = &["json", "jsonld", "html", "htm"];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportStatus {
+    Imported,
+    Duplicate,
+    Failed,
+}
+
+/// Outcome for one recipe record (or one unreadable file).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportRecord {
+    pub source: String, // file path, with "#n" when a file holds several recipes
+    pub title: Option<String>,
+    pub status: ImportStatus,
+    pub recipe_id: Option<String>,
+    pub warnings: Vec<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub duplicates: usize,
+    pub failed: usize,
+    #[serde(default)]
+    pub profiles_restored: usize,
+    #[serde(default)]
+    pub history_restored: usize,
+    #[serde(default)]
+    pub meal_plans_restored: usize,
+    #[serde(default)]
+    pub shopping_lists_restored: usize,
+    #[serde(default)]
+    pub pantry_restored: usize,
+    pub records: Vec<ImportRecord>,
+}
+
+impl ImportReport {
+    fn push(&mut self, record: ImportRecord) {
+        match record.status {
+            ImportStatus::Imported => self.imported += 1,
+            ImportStatus::Duplicate => self.duplicates += 1,
+            ImportStatus::Failed => self.failed += 1,
+        }
+        self.records.push(record);
+    }
+
+    fn failure(&mut self, source: String, title: Option<String>, error: String) {
+        self.push(ImportRecord {
+            source,
+            title,
+            status: ImportStatus::Failed,
+            recipe_id: None,
+            warnings: vec![],
+            error: Some(error),
+        });
+    }
+}
+
+/// Imports schema.org `Recipe` JSON-LD and account archives from files and directories
+/// (searched recursively for .json, .jsonld and saved .html pages). Recipes that already
+/// exist, by id or by title among the recipes `user_id` can see, are skipped. Problems are
+/// reported per record; only database failures abort the import.
+pub fn import_paths(
+    db: &Database,
+    paths: &[PathBuf],
+    user_id: Option<&str>,
+) -> AppResult<ImportReport> {
+    let mut report = ImportReport::default();
+
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            collect_files(path, &mut files);
+        } else if path.is_file() {
+            files.push(path.clone());
+        } else {
+            report.failure(display(path), None, "File not found".to_string());
+        }
+    }
+
+    for file in files {
+        let documents = match read_documents(&file) {
+            Ok(documents) => documents,
+            Err(error) => {
+                report.failure(display(&file), None, error);
+                continue;
+            }
+        };
+
+        let (archives, documents): (Vec<Value>, Vec<Value>) = documents
+            .into_iter()
+            .partition(|document| document.get("format").and_then(Value::as_str) == Some(ARCHIVE_FORMAT));
+        let has_archive = !archives.is_empty();
+        for archive in archives {
+            import_archive(db, archive, display(&file), user_id, &mut report)?;
+        }
+
+        let nodes: Vec<&Value> = documents.iter().flat_map(jsonld::find_recipe_nodes).collect();
+        if nodes.is_empty() && !has_archive {
+            report.failure(
+                display(&file),
+                None,
+                "No schema.org Recipe found".to_string(),
+            );
+            continue;
+        }
+
+        for (index, node) in nodes.iter().enumerate() {
+            let source = if nodes.len() == 1 {
+                display(&file)
+            } else {
+                format!("{}#{}", display(&file), index + 1)
+            };
+            import_node(db, node, source, user_id, &mut report)?;
+        }
+    }
+
+    log::info!(
+        "Imported {} recipes ({} duplicates, {} failed), restored {} profiles, {} history entries, {} meal plans, {} shopping lists and {} pantry items",
+        report.imported,
+        report.duplicates,
+        report.failed,
+        report.profiles_restored,
+        report.history_restored,
+        report.meal_plans_restored,
+        report.shopping_lists_restored,
+        report.pantry_restored
+    );
+    Ok(report)
+}
+
+fn import_node(
+    db: &Database,
+    node: &Value,
+    source: String,
+    user_id: Option<&str>,
+    report: &mut ImportReport,
+) -> AppResult<()> {
+    let title = node.get("name").and_then(Value::as_str).map(str::to_string);
+    let mapped = match jsonld::recipe_from_json_ld(node, user_id) {
+        Ok(mapped) => mapped,
+        Err(e) => {
+            report.failure(source, title, e.to_string());
+            return Ok(());
+        }
+    };
+    import_recipe(db, mapped.recipe, mapped.warnings, source, user_id, report)?;
+    Ok(())
+}
+
+/// Stores `recipe` unless it is a duplicate. Returns the id of the stored recipe or of
+/// the one it duplicates; `None` if it failed validation.
+fn import_recipe(
+    db: &Database,
+    recipe: Recipe,
+    warnings: Vec<String>,
+    source: String,
+    user_id: Option<&str>,
+    report: &mut ImportReport,
+) -> AppResult<Option<Uuid>> {
+    let duplicate_of = match db.get_recipe_by_id(&recipe.id.to_string())? {
+        Some(existing) => Some(existing.id),
+        None => db.find_recipe_by_title(&recipe.title, user_id)?,
+    };
+    if let Some(existing) = duplicate_of {
+        report.push(ImportRecord {
+            source,
+            title: Some(recipe.title),
+            status: ImportStatus::Duplicate,
+            recipe_id: Some(existing.to_string()),
+            warnings,
+            error: None,
+        });
+        return Ok(Some(existing));
+    }
+
+    if let Err(e) = recipe.validate() {
+        report.failure(source, Some(recipe.title), e.to_string());
+        return Ok(None);
+    }
+
+    db.save_recipe(&recipe)?;
+    report.push(ImportRecord {
+        source,
+        title: Some(recipe.title),
+        status: ImportStatus::Imported,
+        recipe_id: Some(recipe.id.to_string()),
+        warnings,
+        error: None,
+    });
+    Ok(Some(recipe.id))
+}
+
+/// Restores an account archive written by `export::build_account_archive`. Everything is
+/// restored for `user_id`, or for the archive's own user when none is given. Existing data
+/// wins: a stored health profile is kept, recipes, recommendations, history entries, meal
+/// plans and shopping lists already present are skipped, and so are pantry items the user
+/// already has an amount for.
+fn import_archive(
+    db: &Database,
+    document: Value,
+    source: String,
+    user_id: Option<&str>,
+    report: &mut ImportReport,
+) -> AppResult<()> {
+    // Check the version first: a newer layout may not deserialize at all
+    let version = document.get("version").and_then(Value::as_u64).unwrap_or(0);
+    if version > ARCHIVE_VERSION as u64 {
+        report.failure(
+            source,
+            None,
+            format!(
+                "Account archive version {} is newer than the supported version {}",
+                version, ARCHIVE_VERSION
+            ),
+        );
+        return Ok(());
+    }
+    let archive: AccountArchive = match serde_json::from_value(document) {
+        Ok(archive) => archive,
+        Err(e) => {
+            report.failure(source, None, format!("Invalid account archive: {}", e));
+            return Ok(());
+        }
+    };
+
+    let owner = user_id.unwrap_or(&archive.user_id).to_string();
+
+    if let Some(mut profile) = archive.health_profile {
+        if db.get_health_profile(&owner)?.is_none() {
+            // The archived id may already belong to another user's profile here
+            profile.id = uuid::Uuid::new_v4();
+            profile.user_id = owner.clone();
+            db.save_health_profile(&profile)?;
+            report.profiles_restored += 1;
+        }
+    }
+
+    // Archived recipe id -> the id it is stored under here
+    let mut recipe_ids = HashMap::new();
+    for mut recipe in archive.recipes {
+        recipe.user_id = Some(owner.clone());
+        let archived_id = recipe.id;
+        let source = format!("{}#{}", source, recipe.id);
+        if let Some(stored_id) = import_recipe(db, recipe, vec![], source, Some(&owner), report)? {
+            recipe_ids.insert(archived_id, stored_id);
+        }
+    }
+
+    for mut recommendation in archive.recommendations {
+        recommendation.user_id = owner.clone();
+        db.insert_recommendation(&recommendation)?;
+    }
+
+    for mut entry in archive.diet_history {
+        if db.diet_entry_exists(&entry.id.to_string())? {
+            continue;
+        }
+        entry.user_id = owner.clone();
+        if let Some(stored_id) = recipe_ids.get(&entry.diet_item_id) {
+            entry.diet_item_id = *stored_id;
+        }
+        db.log_diet_entry(&entry)?;
+        report.history_restored += 1;
+    }
+
+    for mut plan in archive.meal_plans {
+        if db.get_meal_plan(&plan.id.to_string())?.is_some() {
+            continue;
+        }
+        plan.user_id = owner.clone();
+        let mut entries = Vec::with_capacity(plan.entries.len());
+        for mut entry in plan.entries {
+            if let Some(stored_id) = recipe_ids.get(&entry.recipe_id) {
+                entry.recipe_id = *stored_id;
+            }
+            // Meals whose recipe did not come along (another user's, or one that failed to
+            // import) are left out of the plan
+            if db.get_recipe_by_id(&entry.recipe_id.to_string())?.is_none() {
+                continue;
+            }
+            if let Some(history_id) = entry.diet_history_id {
+                if !db.diet_entry_exists(&history_id.to_string())? {
+                    entry.diet_history_id = None;
+                }
+            }
+            entries.push(entry);
+        }
+        plan.entries = entries;
+        db.save_meal_plan(&plan)?;
+        report.meal_plans_restored += 1;
+    }
+
+    for mut list in archive.shopping_lists {
+        if db.get_shopping_list(&list.id.to_string())?.is_some() {
+            continue;
+        }
+        list.user_id = owner.clone();
+        if let Some(plan_id) = list.meal_plan_id {
+            if db.get_meal_plan(&plan_id.to_string())?.is_none() {
+                list.meal_plan_id = None;
+            }
+        }
+        db.save_shopping_list(&list)?;
+        report.shopping_lists_restored += 1;
+    }
+
+    let stocked: HashSet<String> = db
+        .get_pantry(&owner)?
+        .iter()
+        .map(|item| normalize_name(&item.name))
+        .collect();
+    for mut item in archive.pantry {
+        if stocked.contains(&normalize_name(&item.name)) {
+            continue;
+        }
+        // Nothing refers to pantry ids, and the archived one may be taken here
+        item.id = Uuid::new_v4();
+        item.user_id = owner.clone();
+        db.save_pantry_item(&item)?;
+        report.pantry_restored += 1;
+    }
+
+    Ok(())
+}
+
+/// Parses a file into JSON documents: every ld+json script of an HTML page, or the
+/// whole file otherwise.
+fn read_documents(path: &Path) -> Result<Vec<Value>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+
+    let is_html = matches!(extension(path).as_deref(), Some("html" | "htm"))
+        || content.trim_start().starts_with('<');
+    let sources = if is_html {
+        jsonld::extract_json_ld_scripts(&content)
+    } else {
+        vec![content]
+    };
+
+    sources
+        .iter()
+        .map(|source| {
+            serde_json::from_str(source).map_err(|e| format!("Invalid JSON-LD: {}", e))
+        })
+        .collect()
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut paths: Vec<PathBuf> = entries.filter_map(|e| e.ok().map(|e| e.path())).collect();
+    paths.sort();
+
+    for path in paths {
+        if path.is_dir() {
+            collect_files(&path, files);
+        } else if extension(&path).is_some_and(|ext| IMPORT_EXTENSIONS.contains(&ext.as_str())) {
+            files.push(path);
+        }
+    }
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+}
+
+fn display(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const RECIPE: &str = r#"{
+        "@context": "https://schema.org",
+        "@type": "Recipe",
+        "name": "凉拌黄瓜",
+        "totalTime": "PT10M",
+        "recipeCategory": "小吃",
+        "recipeIngredient": ["黄瓜 2根", "蒜 3瓣", "醋 1汤匙"],
+        "recipeInstructions": "拍碎黄瓜，加调料拌匀。",
+        "nutrition": {"calories": "80 kcal", "proteinContent": "2 g", "carbohydrateContent": "10 g", "fatContent": "3 g"}
+    }"#;
+
+    #[test]
+    fn test_imports_directory_and_skips_duplicates() {
+        let dir = TempDir::new().unwrap();
+        let db = Database::new(dir.path().join("data.db")).unwrap();
+        let import_dir = dir.path().join("recipes");
+        fs::create_dir_all(import_dir.join("saved")).unwrap();
+        fs::write(import_dir.join("cucumber.json"), RECIPE).unwrap();
+        fs::write(
+            import_dir.join("saved").join("page.html"),
+            format!(
+                "<html><script type=\"application/ld+json\">{}</script></html>",
+                RECIPE
+            ),
+        )
+        .unwrap();
+        fs::write(import_dir.join("broken.json"), "{ not json").unwrap();
+        fs::write(import_dir.join("notes.txt"), "ignored").unwrap();
+
+        let report = import_paths(&db, std::slice::from_ref(&import_dir), Some("user_1")).unwrap();
+
+        assert_eq!(report.imported, 1);
+        assert_eq!(report.duplicates, 1);
+        assert_eq!(report.failed, 1);
+        let imported = report
+            .records
+            .iter()
+            .find(|r| r.status == ImportStatus::Imported)
+            .unwrap();
+        let stored = db
+            .get_recipe_by_id(imported.recipe_id.as_ref().unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.meal_type, "snack");
+        assert_eq!(stored.user_id.as_deref(), Some("user_1"));
+        assert_eq!(stored.ingredients[1].unit, "瓣");
+
+        // Re-importing the same files imports nothing new
+        let again = import_paths(&db, &[import_dir], Some("user_1")).unwrap();
+        assert_eq!(again.imported, 0);
+        assert_eq!(again.duplicates, 2);
+    }
+
+    #[test]
+    fn test_missing_path_is_reported() {
+        let dir = TempDir::new().unwrap();
+        let db = Database::new(dir.path().join("data.db")).unwrap();
+        let report =
+            import_paths(&db, &[dir.path().join("missing.json")], None).unwrap();
+        assert_eq!(report.failed, 1);
+        assert_eq!(report.records[0].error.as_deref(), Some("File not found"));
+    }
+
+    #[test]
+    fn test_account_archive_round_trip() {
+        use crate::storage::models::{DietHistory, HealthProfile};
+
+        let dir = TempDir::new().unwrap();
+        let source_db = Database::new(dir.path().join("source.db")).unwrap();
+        let recipe_file = dir.path().join("cucumber.json");
+        fs::write(&recipe_file, RECIPE).unwrap();
+        let imported = import_paths(&source_db, &[recipe_file], Some("user_1")).unwrap();
+        let recipe_id = imported.records[0].recipe_id.clone().unwrap();
+
+        let mut profile = HealthProfile::new("user_1".to_string());
+        profile.allergies = vec!["花生".to_string()];
+        source_db.save_health_profile(&profile).unwrap();
+        let now = chrono::Utc::now();
+        source_db
+            .log_diet_entry(&DietHistory {
+                id: uuid::Uuid::new_v4(),
+                user_id: "user_1".to_string(),
+                diet_item_id: uuid::Uuid::parse_str(&recipe_id).unwrap(),
+                date_attempted: now.date_naive(),
+                rating: Some(5),
+                notes: None,
+                was_prepared: true,
+                meal_type: "snack".to_string(),
+                created_at: now,
+                updated_at: now,
+            })
+            .unwrap();
+
+        let archive = export::build_account_archive(&source_db, "user_1").unwrap();
+        let archive_file = dir.path().join("account.json");
+        export::write_export(&archive_file, &serde_json::to_string_pretty(&archive).unwrap())
+            .unwrap();
+
+        let target_db = Database::new(dir.path().join("target.db")).unwrap();
+        let report = import_paths(&target_db, std::slice::from_ref(&archive_file), None).unwrap();
+        assert_eq!(report.imported, 1);
+        assert_eq!(report.profiles_restored, 1);
+        assert_eq!(report.history_restored, 1);
+
+        let restored = target_db.get_recipe_by_id(&recipe_id).unwrap().unwrap();
+        assert_eq!(restored.user_id.as_deref(), Some("user_1"));
+        assert_eq!(restored.origin, crate::storage::models::RecipeOrigin::Imported);
+        let restored_profile = target_db.get_health_profile("user_1").unwrap().unwrap();
+        assert_eq!(restored_profile.allergies, vec!["花生".to_string()]);
+
+        // Restoring twice changes nothing
+        let again = import_paths(&target_db, std::slice::from_ref(&archive_file), None).unwrap();
+        assert_eq!(again.imported, 0);
+        assert_eq!(again.duplicates, 1);
+        assert_eq!(again.profiles_restored, 0);
+        assert_eq!(again.history_restored, 0);
+
+        // Restoring into the source database for another user does not clash with the
+        // original profile's id
+        let copy = import_paths(&source_db, std::slice::from_ref(&archive_file), Some("user_2"))
+            .unwrap();
+        assert_eq!(copy.profiles_restored, 1);
+        let copied = source_db.get_health_profile("user_2").unwrap().unwrap();
+        assert_ne!(copied.id, profile.id);
+        assert_eq!(copied.allergies, vec!["花生".to_string()]);
+    }
+
+    #[test]
+    fn test_account_archive_restores_plans_lists_and_pantry() {
+        use crate::storage::models::{
+            MealPlan, MealPlanEntry, PantryItem, ShoppingList, ShoppingListItem, StoreSection,
+        };
+
+        let dir = TempDir::new().unwrap();
+        let source_db = Database::new(dir.path().join("source.db")).unwrap();
+        let recipe_file = dir.path().join("cucumber.json");
+        fs::write(&recipe_file, RECIPE).unwrap();
+        let imported = import_paths(&source_db, &[recipe_file], Some("user_1")).unwrap();
+        let cucumber = source_db
+            .get_recipe_by_id(imported.records[0].recipe_id.as_ref().unwrap())
+            .unwrap()
+            .unwrap();
+        // Another user's recipe is not part of user_1's archive
+        let foreign = Recipe {
+            id: Uuid::new_v4(),
+            title: "拍黄瓜".to_string(),
+            user_id: Some("user_9".to_string()),
+            ..cucumber.clone()
+        };
+        source_db.save_recipe(&foreign).unwrap();
+
+        let today = chrono::Utc::now().date_naive();
+        let plan_id = Uuid::new_v4();
+        let entry = |meal_type: &str, recipe_id: Uuid| MealPlanEntry {
+            id: Uuid::new_v4(),
+            plan_id,
+            date: today,
+            meal_type: meal_type.to_string(),
+            recipe_id,
+            recipe_title: String::new(),
+            diet_history_id: None,
+        };
+        let plan = MealPlan {
+            id: plan_id,
+            user_id: "user_1".to_string(),
+            name: "本周食谱".to_string(),
+            start_date: today,
+            end_date: today,
+            entries: vec![entry("lunch", foreign.id), entry("snack", cucumber.id)],
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        };
+        source_db.save_meal_plan(&plan).unwrap();
+        let snack_id = plan.entries[1].id.to_string();
+        let eaten = source_db.mark_meal_plan_entry_eaten(&snack_id, Some(4), None).unwrap();
+        let list_id = Uuid::new_v4();
+        source_db
+            .save_shopping_list(&ShoppingList {
+                id: list_id,
+                user_id: "user_1".to_string(),
+                name: "周末采购".to_string(),
+                meal_plan_id: Some(plan_id),
+                items: vec![ShoppingListItem {
+                    id: Uuid::new_v4(),
+                    list_id,
+                    name: "黄瓜".to_string(),
+                    amount: 2.0,
+                    unit: "根".to_string(),
+                    section: StoreSection::Produce,
+                    recipes: vec![cucumber.title.clone()],
+                    optional: false,
+                    checked: true,
+                }],
+                created_at: chrono::Utc::now(),
+                updated_at: chrono::Utc::now(),
+            })
+            .unwrap();
+        let pantry_item = |user_id: &str, name: &str, amount: f64| PantryItem {
+            id: Uuid::new_v4(),
+            user_id: user_id.to_string(),
+            name: name.to_string(),
+            amount,
+            unit: "瓣".to_string(),
+            updated_at: chrono::Utc::now(),
+        };
+        source_db.save_pantry_item(&pantry_item("user_1", "蒜", 3.0)).unwrap();
+        source_db.save_pantry_item(&pantry_item("user_1", "醋", 1.0)).unwrap();
+
+        let archive = export::build_account_archive(&source_db, "user_1").unwrap();
+        let archive_file = dir.path().join("account.json");
+        export::write_export(&archive_file, &serde_json::to_string_pretty(&archive).unwrap())
+            .unwrap();
+
+        // The target already has the recipe under its own id
+        let target_db = Database::new(dir.path().join("target.db")).unwrap();
+        let existing = Recipe {
+            id: Uuid::new_v4(),
+            user_id: Some("user_2".to_string()),
+            ..cucumber
+        };
+        target_db.save_recipe(&existing).unwrap();
+        target_db.save_pantry_item(&pantry_item("user_2", "蒜", 10.0)).unwrap();
+
+        let report =
+            import_paths(&target_db, std::slice::from_ref(&archive_file), Some("user_2")).unwrap();
+        assert_eq!(report.duplicates, 1);
+        assert_eq!(report.meal_plans_restored, 1);
+        let restored = target_db.get_meal_plan(&plan_id.to_string()).unwrap().unwrap();
+        assert_eq!(restored.user_id, "user_2");
+        assert_eq!(restored.entries.len(), 1, "the other user's recipe is left out");
+        assert_eq!(restored.entries[0].recipe_id, existing.id);
+        assert_eq!(restored.entries[0].diet_history_id, Some(eaten.id));
+        let history = target_db.get_diet_history("user_2", None, None, None, None, None).unwrap();
+        assert_eq!(history[0].diet_item_id, existing.id);
+
+        assert_eq!(report.shopping_lists_restored, 1);
+        let list = target_db.get_shopping_list(&list_id.to_string()).unwrap().unwrap();
+        assert_eq!(list.user_id, "user_2");
+        assert_eq!(list.meal_plan_id, Some(plan_id));
+        assert!(list.items[0].checked);
+
+        assert_eq!(report.pantry_restored, 1, "the garlic already in the pantry is kept");
+        let pantry: Vec<(String, f64)> = target_db
+            .get_pantry("user_2")
+            .unwrap()
+            .into_iter()
+            .map(|item| (item.name, item.amount))
+            .collect();
+        assert_eq!(pantry, vec![("蒜".to_string(), 10.0), ("醋".to_string(), 1.0)]);
+
+        // Plans, lists and pantry items that already exist are skipped
+        let again = import_paths(&target_db, &[archive_file], Some("user_2")).unwrap();
+        assert_eq!(again.meal_plans_restored, 0);
+        assert_eq!(again.shopping_lists_restored, 0);
+        assert_eq!(again.pantry_restored, 0);
+        assert_eq!(target_db.list_meal_plans("user_2").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_newer_archive_version_is_rejected() {
+        let dir = TempDir::new().unwrap();
+        let db = Database::new(dir.path().join("data.db")).unwrap();
+        let file = dir.path().join("account.json");
+        fs::write(
+            &file,
+            serde_json::json!({
+                "format": ARCHIVE_FORMAT,
+                "version": ARCHIVE_VERSION + 1,
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let report = import_paths(&db, &[file], None).unwrap();
+        assert_eq!(report.failed, 1);
+        assert!(report.records[0].error.as_ref().unwrap().contains("newer"));
+    }
+}
@@ -5,8 +5,11 @@ use tauri::Manager;
 
 mod commands;
 mod config;
+mod ingredients;
+mod interchange;
 mod location;
 mod recommendation;
+mod shopping;
 mod storage;
 mod utils;
 
@@ -19,6 +22,8 @@ pub enum AppError {
     NotFound(String),
     Network(String),
     Location(String),
+    Io(String),
+    Encryption(String),
 }
 
 impl std::fmt::Display for AppError {
@@ -29,6 +34,8 @@ impl std::fmt::Display for AppError {
             AppError::NotFound(msg) => write!(f, "Not found: {}", msg),
             AppError::Network(msg) => write!(f, "Network error: {}", msg),
             AppError::Location(msg) => write!(f, "Location error: {}", msg),
+            AppError::Io(msg) => write!(f, "IO error: {}", msg),
+            AppError::Encryption(msg) => write!(f, "Encryption error: {}", msg),
         }
     }
 }
@@ -41,6 +48,12 @@ impl From<rusqlite::Error> for AppError {
     }
 }
 
+impl From<std::io::Error> for AppError {
+    fn from(error: std::io::Error) -> Self {
+        AppError::Io(error.to_string())
+    }
+}
+
 impl From<serde_json::Error> for AppError {
     fn from(error: serde_json::Error) -> Self {
         AppError::Database(error.to_string())
@@ -65,8 +78,61 @@ impl From<AppError> for String {
     }
 }
 
+use crate::commands::SharedEngine;
+use crate::recommendation::config::{ConfigWatcher, RecommendationConfig};
+use crate::recommendation::{engine::RecommendationEngine, rules::RuleSet};
 use crate::storage::Database;
-use std::sync::Arc;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+/// How often the scheduler checks whether a backup is due. The interval itself comes
+/// from the config on every check, so changes apply without a restart.
+const BACKUP_SCHEDULE_TICK: std::time::Duration = std::time::Duration::from_secs(15 * 60);
+
+fn spawn_backup_scheduler(db: Arc<Database>) {
+    std::thread::spawn(move || loop {
+        match config::get_app_config() {
+            Ok(config) => {
+                if let Err(e) = storage::backup::run_scheduled_backup(
+                    &db,
+                    &config.get_backup_dir(),
+                    &config.backup,
+                ) {
+                    log::error!("Scheduled backup failed: {}", e);
+                }
+            }
+            Err(e) => log::error!("Failed to read config for scheduled backup: {}", e),
+        }
+        std::thread::sleep(BACKUP_SCHEDULE_TICK);
+    });
+}
+
+/// How often `recommendation.toml` is checked for edits.
+const RECOMMENDATION_CONFIG_POLL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Applies edits to `recommendation.toml` to the running engine. An invalid edit is logged
+/// and the weights in use stay in effect.
+fn spawn_recommendation_config_watcher(engine: SharedEngine, path: PathBuf) {
+    let mut watcher = ConfigWatcher::new(path);
+    std::thread::spawn(move || loop {
+        std::thread::sleep(RECOMMENDATION_CONFIG_POLL);
+        match watcher.poll() {
+            Some(Ok(config)) => match engine.write() {
+                Ok(mut engine) => {
+                    engine.apply_config(&config);
+                    log::info!("Reloaded {}", watcher.path().display());
+                }
+                Err(_) => log::error!("Recommendation engine lock poisoned"),
+            },
+            Some(Err(e)) => log::error!(
+                "Ignoring invalid {}, keeping the current weights: {}",
+                watcher.path().display(),
+                e
+            ),
+            None => {}
+        }
+    });
+}
 
 #[cfg_attr(not(debug_assertions), tauri::mobile_entry_point)]
 pub fn run() {
@@ -79,16 +145,64 @@ pub fn run() {
             commands::save_health_profile,
             commands::get_health_profile,
             commands::delete_health_profile,
+            commands::list_profiles,
+            commands::create_profile,
+            commands::rename_profile,
+            commands::delete_profile,
+            commands::set_active_profile,
             commands::get_recommendations,
+            commands::get_group_recommendations,
+            commands::get_excluded_recipes,
+            commands::get_nutrition_targets,
+            commands::generate_daily_plan,
+            commands::regenerate_plan_slot,
+            commands::create_meal_plan,
+            commands::get_meal_plan,
+            commands::list_meal_plans,
+            commands::rename_meal_plan,
+            commands::update_meal_plan_entry,
+            commands::delete_meal_plan,
+            commands::mark_meal_plan_entry_eaten,
+            commands::create_shopping_list,
+            commands::get_shopping_list,
+            commands::list_shopping_lists,
+            commands::set_shopping_list_item_checked,
+            commands::delete_shopping_list,
+            commands::get_pantry,
+            commands::save_pantry_item,
+            commands::delete_pantry_item,
+            commands::get_ingredient_catalog,
+            commands::normalize_ingredients,
+            commands::convert_ingredient_amount,
+            commands::calculate_recipe_nutrition,
+            commands::scale_recipe,
+            commands::suggest_portion,
+            commands::lint_recipe_nutrition,
             commands::get_recommendation_by_id,
+            commands::get_recommendation_batch,
             commands::log_diet_entry,
             commands::get_diet_history,
             commands::update_diet_entry,
             commands::delete_diet_entry,
             commands::get_recipe_by_id,
             commands::search_recipes,
+            commands::get_recipe_tag_counts,
+            commands::create_recipe,
+            commands::update_recipe,
+            commands::delete_recipe,
+            commands::duplicate_recipe,
+            commands::import_recipes,
+            commands::export_recipes,
+            commands::export_diet_history,
+            commands::export_account,
             commands::get_config,
             commands::set_config,
+            commands::reload_recommendation_config,
+            commands::set_privacy_mode,
+            commands::unlock_database,
+            commands::backup_data,
+            commands::list_backups,
+            commands::restore_data,
             commands::get_user_location,
             commands::search_nearby_restaurants,
             commands::search_delivery_options,
@@ -114,8 +228,53 @@ pub fn run() {
                 ))
             })?;
 
+            // Import the bundled recipes on first run so the recipes table is the
+            // single source of truth for recommendations, search and detail lookups
+            storage::seed::seed_bundled_recipes(&db).map_err(|e| {
+                eprintln!("Failed to seed bundled recipes: {}", e);
+                tauri::Error::Io(std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    "database error",
+                ))
+            })?;
+
+            // The database is the source of truth for encryption; a config file that
+            // disagrees (e.g. restored from elsewhere) is corrected to match it
+            if db.is_encrypted() != config.privacy_mode {
+                log::warn!(
+                    "privacy_mode in config ({}) does not match the database, updating config",
+                    config.privacy_mode
+                );
+                if let Err(e) = config::update_app_config(Some(db.is_encrypted()), None, None, None, None) {
+                    log::error!("Failed to update privacy mode in config: {}", e);
+                }
+            }
+
+            let recommendation_config_path = config.get_recommendation_config_path();
+            let recommendation_config =
+                recommendation::config::load_config(&recommendation_config_path).unwrap_or_else(
+                    |e| {
+                        log::error!("Invalid recommendation config, using defaults: {}", e);
+                        RecommendationConfig::default()
+                    },
+                );
+
+            let mut engine = RecommendationEngine::from_database(&db, RuleSet::builtin()).map_err(|e| {
+                eprintln!("Failed to load recipes for recommendations: {}", e);
+                tauri::Error::Io(std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    "database error",
+                ))
+            })?;
+            engine.apply_config(&recommendation_config);
+            let engine: SharedEngine = Arc::new(RwLock::new(engine));
+
             // Store the database in the app state so it can be used by commands
-            app.manage(Arc::new(db));
+            let db = Arc::new(db);
+            spawn_backup_scheduler(db.clone());
+            spawn_recommendation_config_watcher(engine.clone(), recommendation_config_path);
+            app.manage(db);
+            app.manage(engine);
 
             Ok(())
         })
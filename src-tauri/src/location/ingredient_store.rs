@@ -1,3 +1,4 @@
+use crate::ingredients::catalog::catalog;
 use crate::location::{Location, LocationService};
 use crate::storage::models::{IngredientStore, PriceLevel, StoreType};
 use serde::{Deserialize, Serialize};
@@ -76,19 +77,12 @@ impl IngredientStoreFinder {
             nearby_stores.retain(|store| price_levels.contains(&store.price_level));
         }
 
-        // 按所需食材筛选
+        // 按所需食材筛选（按食材目录比较，“油”不会匹配“酱油”）
         if let Some(ref required_ingredients) = params.required_ingredients {
             nearby_stores.retain(|store| {
-                required_ingredients.iter().any(|ingredient| {
-                    store.available_ingredients.iter().any(|available| {
-                        available
-                            .to_lowercase()
-                            .contains(&ingredient.to_lowercase())
-                            || ingredient
-                                .to_lowercase()
-                                .contains(&available.to_lowercase())
-                    })
-                })
+                required_ingredients
+                    .iter()
+                    .any(|ingredient| store_sells(store, ingredient))
             });
         }
 
@@ -136,15 +130,7 @@ impl IngredientStoreFinder {
             let mut available_store_ids = Vec::new();
 
             for store in &available_stores {
-                if store.available_ingredients.iter().any(|available| {
-                    available
-                        .to_lowercase()
-                        .contains(&ingredient.name.to_lowercase())
-                        || ingredient
-                            .name
-                            .to_lowercase()
-                            .contains(&available.to_lowercase())
-                }) {
+                if store_sells(store, &ingredient.name) {
                     available_store_ids.push(store.id.clone());
                 }
             }
@@ -338,6 +324,14 @@ impl IngredientStoreFinder {
     }
 }
 
+/// 商店是否有售该食材：在售品类（如“新鲜蔬菜”）涵盖其下食材，在售食材涵盖其细分品种
+fn store_sells(store: &IngredientStore, ingredient: &str) -> bool {
+    store
+        .available_ingredients
+        .iter()
+        .any(|available| catalog().covers(available, ingredient))
+}
+
 impl Default for IngredientStoreFinder {
     fn default() -> Self {
         Self::new(LocationService::new())
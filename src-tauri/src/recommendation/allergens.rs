@@ -0,0 +1,478 @@
+use serde::{Deserialize, Serialize};
+
+use crate::ingredients::catalog::{catalog, Resolved};
+use crate::recommendation::engine::Recipe;
+use crate::storage::models::{normalize_name, HealthProfile};
+
+/// One of the major allergen groups.
+#[derive(Debug)]
+pub struct AllergenGroup {
+    pub id: &'static str,
+    pub name: &'static str, // shown in exclusion reasons
+    /// What people write for the allergy itself; an allergy or restriction equal to one of
+    /// these stands for the whole group.
+    pub terms: &'static [&'static str],
+    /// Ingredients that are this allergen (besides names containing one of `terms`).
+    pub ingredients: &'static [&'static str],
+    /// Look-alikes cut out of an ingredient name before matching, e.g. 椰奶 for dairy.
+    pub except: &'static [&'static str],
+}
+
+#[rustfmt::skip]
+pub const ALLERGEN_GROUPS: &[AllergenGroup] = &[
+    AllergenGroup {
+        id: "peanut",
+        name: "花生",
+        terms: &["花生", "peanut", "peanuts", "groundnut"],
+        ingredients: &["satay", "沙爹"],
+        except: &[],
+    },
+    AllergenGroup {
+        id: "tree_nut",
+        name: "坚果",
+        terms: &["树坚果", "tree nut", "tree nuts"],
+        ingredients: &[
+            "杏仁", "核桃", "腰果", "榛子", "开心果", "松子", "夏威夷果", "碧根果", "巴旦木", "almond",
+            "walnut", "cashew", "hazelnut", "pistachio", "pecan", "macadamia", "pine nut",
+            "marzipan", "praline",
+        ],
+        except: &["nutmeg", "coconut", "butternut", "doughnut"],
+    },
+    AllergenGroup {
+        id: "dairy",
+        name: "乳制品",
+        terms: &["乳制品", "奶制品", "乳", "奶", "牛奶", "乳糖", "dairy", "milk", "lactose"],
+        ingredients: &[
+            "奶酪", "芝士", "黄油", "奶油", "酸奶", "乳清", "炼乳", "奶粉", "cheese", "butter", "cream",
+            "yogurt", "yoghurt", "whey", "ghee",
+        ],
+        except: &[
+            "椰奶", "椰浆", "豆奶", "杏仁奶", "燕麦奶", "腐乳", "乳鸽", "coconut milk", "soy milk",
+            "almond milk", "oat milk", "peanut butter", "cocoa butter", "cream of tartar",
+        ],
+    },
+    AllergenGroup {
+        id: "egg",
+        name: "蛋类",
+        terms: &["蛋", "鸡蛋", "蛋类", "egg", "eggs"],
+        ingredients: &["mayonnaise", "meringue"],
+        except: &["eggplant"],
+    },
+    AllergenGroup {
+        id: "fish",
+        name: "鱼类",
+        terms: &["鱼", "鱼类", "fish"],
+        ingredients: &[
+            "salmon", "tuna", "cod", "anchovy", "sardine", "mackerel", "tilapia", "trout", "halibut",
+        ],
+        // Molluscs and turtle, not fish
+        except: &["鱿鱼", "墨鱼", "章鱼", "鲍鱼", "甲鱼", "shellfish"],
+    },
+    AllergenGroup {
+        id: "shellfish",
+        name: "甲壳类和贝类",
+        terms: &["甲壳类", "贝类", "shellfish"],
+        ingredients: &[
+            "虾", "蟹", "贝", "蛤", "蚝", "牡蛎", "蛏", "螺", "青口", "鱿鱼", "墨鱼", "章鱼", "鲍鱼",
+            "shrimp", "prawn", "crab", "lobster", "clam", "oyster", "mussel", "scallop", "squid",
+            "octopus",
+        ],
+        except: &[],
+    },
+    AllergenGroup {
+        id: "soy",
+        name: "大豆",
+        terms: &["大豆", "黄豆", "soy", "soya", "soybean"],
+        ingredients: &[
+            "豆腐", "豆浆", "豆奶", "豆干", "腐竹", "豆皮", "毛豆", "味噌", "纳豆", "腐乳", "tofu",
+            "edamame", "miso", "tempeh", "natto",
+        ],
+        except: &[],
+    },
+    AllergenGroup {
+        id: "wheat",
+        name: "小麦（麸质）",
+        terms: &["小麦", "麸质", "面筋", "wheat", "gluten"],
+        ingredients: &[
+            "面", "大麦", "黑麦", "馒头", "饺子皮", "馄饨皮", "flour", "bread", "pasta", "noodle",
+            "spaghetti", "couscous", "barley", "rye", "seitan",
+        ],
+        except: &[],
+    },
+    AllergenGroup {
+        id: "sesame",
+        name: "芝麻",
+        terms: &["芝麻", "sesame"],
+        ingredients: &["麻油", "香油", "麻酱", "tahini"],
+        except: &[],
+    },
+];
+
+/// Words covering several groups at once.
+#[rustfmt::skip]
+const UMBRELLA_TERMS: &[(&str, &[&str])] = &[
+    ("坚果", &["tree_nut", "peanut"]),
+    ("nuts", &["tree_nut", "peanut"]),
+    ("nut", &["tree_nut", "peanut"]),
+    ("海鲜", &["fish", "shellfish"]),
+    ("海产品", &["fish", "shellfish"]),
+    ("seafood", &["fish", "shellfish"]),
+];
+
+/// Prepared ingredients made from allergens their name does not mention.
+#[rustfmt::skip]
+const DERIVED_INGREDIENTS: &[(&str, &[&str])] = &[
+    ("酱油", &["soy", "wheat"]),
+    ("生抽", &["soy", "wheat"]),
+    ("老抽", &["soy", "wheat"]),
+    ("soy sauce", &["wheat"]),
+    ("豆瓣酱", &["soy", "wheat"]),
+    ("黄豆酱", &["wheat"]),
+    ("豆豉", &["soy"]),
+    ("照烧", &["soy", "wheat"]),
+    ("teriyaki", &["soy", "wheat"]),
+    ("海鲜酱", &["soy", "wheat", "sesame"]),
+    ("hoisin", &["soy", "wheat", "sesame"]),
+    ("蚝油", &["wheat"]),
+    ("oyster sauce", &["wheat"]),
+    ("xo酱", &["shellfish", "fish"]),
+    ("鱼露", &["fish"]),
+    ("worcestershire", &["fish"]),
+    ("pesto", &["tree_nut", "dairy"]),
+    ("啤酒", &["wheat"]),
+    ("beer", &["wheat"]),
+    ("蛋黄酱", &["egg"]),
+    ("沙拉酱", &["egg"]),
+];
+
+pub fn group(id: &str) -> Option<&'static AllergenGroup> {
+    ALLERGEN_GROUPS.iter().find(|g| g.id == id)
+}
+
+/// Groups an allergy or restriction entry stands for; empty for anything that is not
+/// an allergen, which is then only matched by name.
+pub fn groups_for_term(term: &str) -> Vec<&'static AllergenGroup> {
+    let term = normalize_name(term);
+    if let Some((_, ids)) = UMBRELLA_TERMS.iter().find(|(word, _)| *word == term) {
+        return ids.iter().filter_map(|id| group(id)).collect();
+    }
+    ALLERGEN_GROUPS
+        .iter()
+        .filter(|g| g.id == term || g.name == term || g.terms.contains(&term.as_str()))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConstraintKind {
+    Allergy,
+    DietaryRestriction,
+}
+
+/// How an ingredient was found to clash with an entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchRule {
+    /// The ingredient is the entry or a kind of it in the ingredient catalog (鸡肉 covers
+    /// 鸡胸肉, not 鸡蛋); names the catalog does not know must contain the entry as written.
+    Name,
+    /// The entry names a catalog category and the ingredient is in it, e.g. 肉 covers
+    /// 鸡胸肉 but not 肉桂, and 油 covers 橄榄油 but not 酱油.
+    Category,
+    /// The ingredient belongs to the allergen group the entry stands for.
+    AllergenGroup,
+    /// The ingredient is usually made from that allergen, e.g. 酱油 from soy and wheat.
+    Derived,
+}
+
+/// Why a recipe was left out: one entry of one profile and the ingredient that breaks it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Exclusion {
+    pub user_id: String,
+    pub kind: ConstraintKind,
+    pub term: String, // the allergy or restriction as the user entered it
+    pub ingredient: String,
+    pub rule: MatchRule,
+    pub allergen: Option<String>, // group id, unless matched by name
+    pub reason: String,
+}
+
+struct Entry {
+    user_id: String,
+    kind: ConstraintKind,
+    term: String,
+    needle: String, // normalized `term`
+    resolved: Resolved<'static>,
+    groups: Vec<&'static AllergenGroup>,
+}
+
+/// The allergies and dietary restrictions of one or more profiles, resolved against the
+/// allergen groups once so many recipes can be checked against them.
+#[derive(Default)]
+pub struct Avoidances {
+    entries: Vec<Entry>,
+}
+
+impl Avoidances {
+    pub fn for_profile(profile: &HealthProfile) -> Self {
+        let mut avoidances = Avoidances::default();
+        avoidances.add_profile(profile);
+        avoidances
+    }
+
+    pub fn add_profile(&mut self, profile: &HealthProfile) {
+        let allergies = profile
+            .allergies
+            .iter()
+            .map(|t| (ConstraintKind::Allergy, t));
+        let restrictions = profile
+            .dietary_restrictions
+            .iter()
+            .map(|t| (ConstraintKind::DietaryRestriction, t));
+        for (kind, term) in allergies.chain(restrictions) {
+            let needle = normalize_name(term);
+            if needle.is_empty() {
+                continue;
+            }
+            self.entries.push(Entry {
+                user_id: profile.user_id.clone(),
+                kind,
+                term: term.trim().to_string(),
+                resolved: catalog().resolve(&needle),
+                groups: groups_for_term(&needle),
+                needle,
+            });
+        }
+    }
+
+    pub fn allows(&self, recipe: &Recipe) -> bool {
+        recipe.ingredients.iter().all(|ingredient| {
+            let resolved = catalog().resolve(&ingredient.name);
+            self.entries
+                .iter()
+                .all(|entry| entry.matches(&ingredient.name, resolved).is_none())
+        })
+    }
+
+    /// Every entry and ingredient of `recipe` that clash; empty when the recipe is fine.
+    pub fn exclusions(&self, recipe: &Recipe) -> Vec<Exclusion> {
+        let resolved: Vec<Resolved> = recipe
+            .ingredients
+            .iter()
+            .map(|ingredient| catalog().resolve(&ingredient.name))
+            .collect();
+        let mut exclusions = Vec::new();
+        for entry in &self.entries {
+            for (ingredient, resolved) in recipe.ingredients.iter().zip(&resolved) {
+                if let Some((rule, group)) = entry.matches(&ingredient.name, *resolved) {
+                    exclusions.push(entry.exclusion(&ingredient.name, rule, group));
+                }
+            }
+        }
+        exclusions
+    }
+}
+
+impl Entry {
+    fn matches(
+        &self,
+        ingredient: &str,
+        resolved: Resolved,
+    ) -> Option<(MatchRule, Option<&'static AllergenGroup>)> {
+        let name = normalize_name(ingredient);
+        // "egg" must not catch eggplant, nor 奶 catch 椰奶
+        let mut rest = name.clone();
+        for look_alike in self.groups.iter().flat_map(|g| g.except) {
+            rest = rest.replace(look_alike, " ");
+        }
+        match (self.resolved, resolved) {
+            (Resolved::Ingredient(avoided), Resolved::Ingredient(found)) => {
+                if catalog().is_a(found, &avoided.id) {
+                    return Some((MatchRule::Name, None));
+                }
+            }
+            // Allergen groups decide for allergens, so 坚果 does not take in sesame
+            (Resolved::Category(avoided), Resolved::Ingredient(found))
+                if self.groups.is_empty() =>
+            {
+                if found.category == avoided {
+                    return Some((MatchRule::Category, None));
+                }
+            }
+            _ => {
+                if rest.contains(&self.needle) {
+                    return Some((MatchRule::Name, None));
+                }
+            }
+        }
+        if let Some(group) = self.groups.iter().find(|g| {
+            g.terms
+                .iter()
+                .chain(g.ingredients)
+                .any(|word| rest.contains(word))
+        }) {
+            return Some((MatchRule::AllergenGroup, Some(*group)));
+        }
+        DERIVED_INGREDIENTS
+            .iter()
+            .filter(|(prepared, _)| name.contains(prepared))
+            .flat_map(|(_, ids)| ids.iter())
+            .find_map(|id| self.groups.iter().find(|g| g.id == *id))
+            .map(|group| (MatchRule::Derived, Some(*group)))
+    }
+
+    fn exclusion(
+        &self,
+        ingredient: &str,
+        rule: MatchRule,
+        group: Option<&AllergenGroup>,
+    ) -> Exclusion {
+        let entry = match self.kind {
+            ConstraintKind::Allergy => format!("过敏“{}”", self.term),
+            ConstraintKind::DietaryRestriction => format!("忌口“{}”", self.term),
+        };
+        let reason = match (rule, group) {
+            (MatchRule::AllergenGroup, Some(group)) => {
+                format!("{}属于{}，与{}冲突", ingredient, group.name, entry)
+            }
+            (MatchRule::Derived, Some(group)) => {
+                format!("{}通常含有{}，与{}冲突", ingredient, group.name, entry)
+            }
+            (MatchRule::Category, _) => match self.resolved {
+                Resolved::Category(category) => {
+                    format!("{}属于{}，与{}冲突", ingredient, category.label(), entry)
+                }
+                _ => format!("{}与{}冲突", ingredient, entry),
+            },
+            _ => format!("{}与{}冲突", ingredient, entry),
+        };
+        Exclusion {
+            user_id: self.user_id.clone(),
+            kind: self.kind,
+            term: self.term.clone(),
+            ingredient: ingredient.to_string(),
+            rule,
+            allergen: group.map(|g| g.id.to_string()),
+            reason,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recommendation::engine::{Ingredient, NutritionalInfo};
+
+    fn recipe(ingredients: &[&str]) -> Recipe {
+        Recipe {
+            id: "r".to_string(),
+            title: "r".to_string(),
+            description: String::new(),
+            ingredients: ingredients
+                .iter()
+                .map(|name| Ingredient {
+                    name: name.to_string(),
+                    amount: 1.0,
+                    unit: "个".to_string(),
+                    optional: false,
+                })
+                .collect(),
+            nutritional_info_per_serving: NutritionalInfo {
+                calories: 300.0,
+                protein: 10.0,
+                carbs: 30.0,
+                fat: 10.0,
+                fiber: 3.0,
+            },
+            servings: 1,
+            preparation_time: 10,
+            difficulty_level: "easy".to_string(),
+            meal_type: "lunch".to_string(),
+            recipe_instructions: String::new(),
+            cuisine_type: None,
+            seasonal: false,
+            tags: vec![],
+            user_id: None,
+        }
+    }
+
+    fn avoiding(allergies: &[&str], restrictions: &[&str]) -> Avoidances {
+        let mut profile = HealthProfile::new("user_1".to_string());
+        profile.allergies = allergies.iter().map(|a| a.to_string()).collect();
+        profile.dietary_restrictions = restrictions.iter().map(|r| r.to_string()).collect();
+        Avoidances::for_profile(&profile)
+    }
+
+    #[test]
+    fn test_groups_catch_synonyms_and_members() {
+        let nuts = avoiding(&["nuts"], &[]);
+        assert!(!nuts.allows(&recipe(&["花生", "米饭"])));
+        assert!(!nuts.allows(&recipe(&["杏仁片"])));
+        assert!(nuts.allows(&recipe(&["nutmeg", "椰子"])));
+
+        let dairy = avoiding(&["乳制品"], &[]);
+        assert!(!dairy.allows(&recipe(&["牛奶"])));
+        assert!(!dairy.allows(&recipe(&["马苏里拉奶酪"])));
+        assert!(dairy.allows(&recipe(&["椰奶", "豆奶"])));
+
+        let egg = avoiding(&["egg"], &[]);
+        assert!(egg.allows(&recipe(&["eggplant"])));
+        assert!(!egg.allows(&recipe(&["鸡蛋"])));
+
+        // Entries that are no allergen still match by name
+        let pork = avoiding(&[], &["猪肉"]);
+        assert!(!pork.allows(&recipe(&["猪肉末"])));
+        assert!(pork.allows(&recipe(&["牛肉"])));
+    }
+
+    #[test]
+    fn test_exclusions_name_rule_and_ingredient() {
+        let avoid = avoiding(&["大豆", "wheat"], &["香菜"]);
+        let exclusions = avoid.exclusions(&recipe(&["酱油", "豆腐", "香菜", "米饭"]));
+
+        let by_soy: Vec<(&str, MatchRule)> = exclusions
+            .iter()
+            .filter(|e| e.term == "大豆")
+            .map(|e| (e.ingredient.as_str(), e.rule))
+            .collect();
+        assert_eq!(
+            by_soy,
+            vec![
+                ("酱油", MatchRule::Derived),
+                ("豆腐", MatchRule::AllergenGroup)
+            ]
+        );
+
+        let wheat: Vec<&Exclusion> = exclusions.iter().filter(|e| e.term == "wheat").collect();
+        assert_eq!(wheat.len(), 1);
+        assert_eq!(wheat[0].ingredient, "酱油");
+        assert_eq!(wheat[0].allergen.as_deref(), Some("wheat"));
+        assert_eq!(
+            wheat[0].reason,
+            "酱油通常含有小麦（麸质），与过敏“wheat”冲突"
+        );
+
+        let coriander = exclusions.iter().find(|e| e.term == "香菜").unwrap();
+        assert_eq!(coriander.kind, ConstraintKind::DietaryRestriction);
+        assert_eq!(coriander.rule, MatchRule::Name);
+        assert_eq!(coriander.allergen, None);
+        assert_eq!(exclusions.len(), 4);
+    }
+
+    #[test]
+    fn test_restrictions_compare_catalog_ids() {
+        let oil = avoiding(&[], &["油"]);
+        assert!(oil.allows(&recipe(&["酱油", "蚝油"])));
+        assert!(!oil.allows(&recipe(&["橄榄油"])));
+
+        let meat = avoiding(&[], &["肉"]);
+        assert!(meat.allows(&recipe(&["肉桂", "豆腐"])));
+        let exclusions = meat.exclusions(&recipe(&["鸡胸肉"]));
+        assert_eq!(exclusions[0].rule, MatchRule::Category);
+        assert_eq!(exclusions[0].reason, "鸡胸肉属于肉类，与忌口“肉”冲突");
+
+        let chicken = avoiding(&[], &["鸡肉"]);
+        assert!(!chicken.allows(&recipe(&["去骨鸡腿"])));
+        assert!(chicken.allows(&recipe(&["鸡蛋", "鸡精"])));
+    }
+}
@@ -0,0 +1,626 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::recommendation::rules::RuleSet;
+use crate::recommendation::targets::MealShares;
+use crate::{AppError, AppResult};
+
+pub const CONFIG_FILE_NAME: &str = "recommendation.toml";
+
+/// The defaults shipped with the app. They reproduce the scores of the rules as they were
+/// before thresholds and weights became configurable.
+const BUNDLED_CONFIG: &str = include_str!("../../recommendation.toml");
+
+static DEFAULT_CONFIG: Lazy<RecommendationConfig> = Lazy::new(|| {
+    let config = parse_config(BUNDLED_CONFIG).expect("bundled recommendation.toml is invalid");
+    validate_config(&config).expect("bundled recommendation.toml is invalid");
+    config
+});
+
+/// Thresholds and weights of the built-in scoring rules, read from `recommendation.toml`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RecommendationConfig {
+    /// Recipes scoring at or below this are not recommended.
+    pub min_relevance: f64,
+    #[serde(default)]
+    pub disabled_rules: Vec<String>,
+    pub balanced_meal: BalancedMealConfig,
+    pub weight_loss: WeightLossConfig,
+    pub muscle_gain: MuscleGainConfig,
+    pub preferences: PreferenceConfig,
+    pub age: AgeConfig,
+    pub activity: ActivityConfig,
+    /// Optional so configs written before per-meal targets existed still load.
+    #[serde(default)]
+    pub meal_targets: MealTargetConfig,
+    #[serde(default)]
+    pub meal_plan: MealPlanConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BalancedMealConfig {
+    pub min_calories: f64,
+    pub max_calories: f64,
+    pub min_protein: f64,
+    pub weight: f64,
+    pub maintain_weight: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WeightLossConfig {
+    pub max_calories: f64,
+    pub low_calorie_weight: f64,
+    pub min_fiber: f64,
+    pub high_fiber_weight: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MuscleGainConfig {
+    pub min_protein: f64,
+    pub high_protein_weight: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PreferenceConfig {
+    pub tag_match_weight: f64,
+    pub vegetarian_weight: f64,
+    pub low_carb_weight: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AgeConfig {
+    pub weight: f64,
+    pub young_below: u32,
+    pub young_min_calories: f64,
+    pub young_max_calories: f64,
+    pub older_above: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ActivityConfig {
+    pub weight: f64,
+    pub sedentary_min_calories: f64,
+    pub sedentary_max_calories: f64,
+    pub very_active_min_calories: f64,
+    pub quick_meal_minutes: u32,
+}
+
+/// How recipes are compared with the profile's daily targets when its body measurements
+/// are known.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MealTargetConfig {
+    /// How far a balanced meal may stray from its share of the daily targets, e.g. 0.25
+    /// for ±25% of the calories.
+    pub tolerance: f64,
+    /// How far a day's meal plan may stray from the daily targets, per macro.
+    pub daily_tolerance: f64,
+    pub shares: MealShares,
+}
+
+impl Default for MealTargetConfig {
+    fn default() -> Self {
+        MealTargetConfig {
+            tolerance: 0.25,
+            daily_tolerance: 0.1,
+            shares: MealShares::default(),
+        }
+    }
+}
+
+/// Variety rules for plans spanning several days.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MealPlanConfig {
+    /// A recipe is not planned again within this many days.
+    pub no_repeat_days: u32,
+    /// Avoid giving a meal the same cuisine as the day before.
+    pub rotate_cuisines: bool,
+    /// Difficulty levels allowed Monday to Friday; weekends allow any.
+    pub weekday_difficulties: Vec<String>,
+}
+
+impl Default for MealPlanConfig {
+    fn default() -> Self {
+        MealPlanConfig {
+            no_repeat_days: 3,
+            rotate_cuisines: true,
+            weekday_difficulties: vec!["easy".to_string(), "medium".to_string()],
+        }
+    }
+}
+
+impl Default for RecommendationConfig {
+    fn default() -> Self {
+        DEFAULT_CONFIG.clone()
+    }
+}
+
+/// Loads `path`, or the bundled defaults when the file does not exist.
+pub fn load_config(path: &Path) -> AppResult<RecommendationConfig> {
+    if !path.exists() {
+        return Ok(RecommendationConfig::default());
+    }
+
+    let content = fs::read_to_string(path)
+        .map_err(|e| AppError::Io(format!("Failed to read {}: {}", path.display(), e)))?;
+    let config = parse_config(&content)?;
+    validate_config(&config)?;
+
+    log::info!("Recommendation config loaded from {}", path.display());
+    Ok(config)
+}
+
+fn parse_config(content: &str) -> AppResult<RecommendationConfig> {
+    toml::from_str(content)
+        .map_err(|e| AppError::Validation(format!("Failed to parse {}: {}", CONFIG_FILE_NAME, e)))
+}
+
+pub fn validate_config(config: &RecommendationConfig) -> AppResult<()> {
+    if !(0.0..1.0).contains(&config.min_relevance) {
+        return Err(AppError::Validation(
+            "min_relevance must be at least 0 and below 1".to_string(),
+        ));
+    }
+
+    let weights = [
+        ("balanced_meal.weight", config.balanced_meal.weight),
+        (
+            "balanced_meal.maintain_weight",
+            config.balanced_meal.maintain_weight,
+        ),
+        (
+            "weight_loss.low_calorie_weight",
+            config.weight_loss.low_calorie_weight,
+        ),
+        (
+            "weight_loss.high_fiber_weight",
+            config.weight_loss.high_fiber_weight,
+        ),
+        (
+            "muscle_gain.high_protein_weight",
+            config.muscle_gain.high_protein_weight,
+        ),
+        (
+            "preferences.tag_match_weight",
+            config.preferences.tag_match_weight,
+        ),
+        (
+            "preferences.vegetarian_weight",
+            config.preferences.vegetarian_weight,
+        ),
+        (
+            "preferences.low_carb_weight",
+            config.preferences.low_carb_weight,
+        ),
+        ("age.weight", config.age.weight),
+        ("activity.weight", config.activity.weight),
+    ];
+    for (name, weight) in weights {
+        if !(-1.0..=1.0).contains(&weight) {
+            return Err(AppError::Validation(format!(
+                "{} must be between -1 and 1",
+                name
+            )));
+        }
+    }
+
+    let amounts = [
+        (
+            "balanced_meal.min_calories",
+            config.balanced_meal.min_calories,
+        ),
+        (
+            "balanced_meal.max_calories",
+            config.balanced_meal.max_calories,
+        ),
+        (
+            "balanced_meal.min_protein",
+            config.balanced_meal.min_protein,
+        ),
+        ("weight_loss.max_calories", config.weight_loss.max_calories),
+        ("weight_loss.min_fiber", config.weight_loss.min_fiber),
+        ("muscle_gain.min_protein", config.muscle_gain.min_protein),
+        ("age.young_min_calories", config.age.young_min_calories),
+        ("age.young_max_calories", config.age.young_max_calories),
+        (
+            "activity.sedentary_min_calories",
+            config.activity.sedentary_min_calories,
+        ),
+        (
+            "activity.sedentary_max_calories",
+            config.activity.sedentary_max_calories,
+        ),
+        (
+            "activity.very_active_min_calories",
+            config.activity.very_active_min_calories,
+        ),
+    ];
+    for (name, amount) in amounts {
+        if !amount.is_finite() || amount < 0.0 {
+            return Err(AppError::Validation(format!(
+                "{} must be a non-negative number",
+                name
+            )));
+        }
+    }
+
+    let ranges = [
+        (
+            "balanced_meal",
+            config.balanced_meal.min_calories,
+            config.balanced_meal.max_calories,
+        ),
+        (
+            "age.young",
+            config.age.young_min_calories,
+            config.age.young_max_calories,
+        ),
+        (
+            "activity.sedentary",
+            config.activity.sedentary_min_calories,
+            config.activity.sedentary_max_calories,
+        ),
+    ];
+    for (name, min, max) in ranges {
+        if min > max {
+            return Err(AppError::Validation(format!(
+                "{} calorie range is empty: minimum {} is above maximum {}",
+                name, min, max
+            )));
+        }
+    }
+
+    let tolerances = [
+        ("meal_targets.tolerance", config.meal_targets.tolerance),
+        (
+            "meal_targets.daily_tolerance",
+            config.meal_targets.daily_tolerance,
+        ),
+    ];
+    for (name, tolerance) in tolerances {
+        if !(0.0..1.0).contains(&tolerance) {
+            return Err(AppError::Validation(format!(
+                "{} must be at least 0 and below 1",
+                name
+            )));
+        }
+    }
+    let shares = &config.meal_targets.shares;
+    for meal_type in MealShares::MEAL_TYPES {
+        let share = shares.share(meal_type).unwrap_or_default();
+        if !(share > 0.0 && share <= 1.0) {
+            return Err(AppError::Validation(format!(
+                "meal_targets.shares.{} must be above 0 and at most 1",
+                meal_type
+            )));
+        }
+    }
+    if (shares.total() - 1.0).abs() > 0.01 {
+        return Err(AppError::Validation(format!(
+            "meal_targets.shares must add up to 1, not {}",
+            shares.total()
+        )));
+    }
+
+    if config.meal_plan.weekday_difficulties.is_empty() {
+        return Err(AppError::Validation(
+            "meal_plan.weekday_difficulties must list at least one difficulty".to_string(),
+        ));
+    }
+    if let Some(unknown) = config
+        .meal_plan
+        .weekday_difficulties
+        .iter()
+        .find(|d| !["easy", "medium", "hard"].contains(&d.as_str()))
+    {
+        return Err(AppError::Validation(format!(
+            "Unknown difficulty in meal_plan.weekday_difficulties: {} (expected easy, medium or hard)",
+            unknown
+        )));
+    }
+
+    if config.age.young_below > config.age.older_above {
+        return Err(AppError::Validation(
+            "age.young_below must not be above age.older_above".to_string(),
+        ));
+    }
+
+    let known = RuleSet::builtin_rule_names();
+    if let Some(unknown) = config
+        .disabled_rules
+        .iter()
+        .find(|name| !known.contains(&name.as_str()))
+    {
+        return Err(AppError::Validation(format!(
+            "Unknown rule in disabled_rules: {} (known rules: {})",
+            unknown,
+            known.join(", ")
+        )));
+    }
+
+    Ok(())
+}
+
+/// Detects edits to `recommendation.toml` by its modification time, so the engine can pick
+/// up new weights without a restart.
+pub struct ConfigWatcher {
+    path: PathBuf,
+    last_modified: Option<SystemTime>,
+}
+
+impl ConfigWatcher {
+    /// Starts watching `path`. The file as it is now counts as already loaded.
+    pub fn new(path: PathBuf) -> Self {
+        let last_modified = modified_time(&path);
+        ConfigWatcher {
+            path,
+            last_modified,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the freshly loaded config if the file was created, changed or removed since
+    /// the last poll. Removing the file brings back the bundled defaults.
+    pub fn poll(&mut self) -> Option<AppResult<RecommendationConfig>> {
+        let modified = modified_time(&self.path);
+        if modified == self.last_modified {
+            return None;
+        }
+        self.last_modified = modified;
+        Some(load_config(&self.path))
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recommendation::engine::{Ingredient, NutritionalInfo, Recipe};
+    use crate::recommendation::rules::ScoringContext;
+    use crate::storage::models::HealthProfile;
+    use tempfile::TempDir;
+
+    /// The scoring rules as they were hardcoded before `recommendation.toml` existed. They
+    /// still apply whenever the profile's daily targets are unknown.
+    fn legacy_score(recipe: &Recipe, profile: &HealthProfile) -> f64 {
+        let n = &recipe.nutritional_info_per_serving;
+        let balanced = n.calories >= 300.0 && n.calories <= 600.0 && n.protein >= 15.0;
+
+        let mut goals = 0.0;
+        for goal in &profile.health_goals {
+            match goal.as_str() {
+                "weight_loss" => {
+                    if n.calories < 400.0 {
+                        goals += 0.15;
+                    }
+                    if n.fiber > 5.0 {
+                        goals += 0.1;
+                    }
+                }
+                "muscle_gain" if n.protein > 25.0 => goals += 0.2,
+                "maintain" if balanced => goals += 0.1,
+                _ => {}
+            }
+        }
+
+        let nutrition = if balanced { 0.1 } else { 0.0 };
+
+        let mut preferences = 0.0;
+        for preference in &profile.dietary_preferences {
+            if recipe.tags.contains(preference) {
+                preferences += 0.15;
+            }
+        }
+        for emphasized in ["vegetarian", "low_carb"] {
+            if profile.dietary_preferences.iter().any(|p| p == emphasized)
+                && recipe.tags.iter().any(|t| t == emphasized)
+            {
+                preferences += 0.1;
+            }
+        }
+
+        let mut characteristics = 0.0;
+        if profile.age < 30 {
+            if n.calories > 300.0 && n.calories < 600.0 {
+                characteristics += 0.05;
+            }
+        } else if profile.age > 50
+            && (recipe.difficulty_level == "easy" || recipe.difficulty_level == "medium")
+        {
+            characteristics += 0.05;
+        }
+        match profile.activity_level.as_str() {
+            "sedentary" if n.calories > 250.0 && n.calories < 500.0 => characteristics += 0.05,
+            "very_active" => {
+                if n.calories > 400.0 {
+                    characteristics += 0.05;
+                }
+                if recipe.preparation_time < 45 {
+                    characteristics += 0.05;
+                }
+            }
+            _ => {}
+        }
+
+        let total: f64 = goals + nutrition + preferences + characteristics;
+        total.clamp(0.0, 1.0)
+    }
+
+    fn recipe(calories: f64, protein: f64, fiber: f64, tags: &[&str], difficulty: &str) -> Recipe {
+        Recipe {
+            id: "r".to_string(),
+            title: "r".to_string(),
+            description: String::new(),
+            ingredients: vec![Ingredient {
+                name: "鸡蛋".to_string(),
+                amount: 2.0,
+                unit: "个".to_string(),
+                optional: false,
+            }],
+            nutritional_info_per_serving: NutritionalInfo {
+                calories,
+                protein,
+                carbs: 40.0,
+                fat: 12.0,
+                fiber,
+            },
+            servings: 1,
+            preparation_time: if difficulty == "hard" { 60 } else { 30 },
+            difficulty_level: difficulty.to_string(),
+            meal_type: "lunch".to_string(),
+            recipe_instructions: String::new(),
+            cuisine_type: None,
+            seasonal: false,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            user_id: None,
+        }
+    }
+
+    #[test]
+    fn test_bundled_defaults_reproduce_legacy_scores() {
+        let config = RecommendationConfig::default();
+        let rules = RuleSet::from_config(&config);
+        let context = ScoringContext::default();
+
+        let goal_sets: [&[&str]; 5] = [
+            &[],
+            &["weight_loss"],
+            &["muscle_gain"],
+            &["maintain"],
+            &["weight_loss", "muscle_gain", "maintain"],
+        ];
+        let preference_sets: [&[&str]; 3] = [&[], &["vegetarian"], &["low_carb", "high_protein"]];
+        let tag_sets: [&[&str]; 3] = [&[], &["vegetarian"], &["low_carb", "high_protein"]];
+
+        let mut compared = 0;
+        for calories in [
+            200.0, 250.0, 300.0, 350.0, 400.0, 450.0, 500.0, 600.0, 650.0,
+        ] {
+            for protein in [10.0, 15.0, 25.0, 30.0] {
+                for fiber in [2.0, 5.0, 8.0] {
+                    for tags in tag_sets {
+                        for difficulty in ["easy", "hard"] {
+                            let recipe = recipe(calories, protein, fiber, tags, difficulty);
+                            for age in [20, 30, 40, 51] {
+                                for activity in ["sedentary", "moderately_active", "very_active"] {
+                                    for goals in goal_sets {
+                                        for preferences in preference_sets {
+                                            let mut profile = HealthProfile::new("u".to_string());
+                                            profile.age = age;
+                                            profile.activity_level = activity.to_string();
+                                            profile.health_goals =
+                                                goals.iter().map(|g| g.to_string()).collect();
+                                            profile.dietary_preferences =
+                                                preferences.iter().map(|p| p.to_string()).collect();
+
+                                            let expected = legacy_score(&recipe, &profile);
+                                            let actual = RuleSet::total_score(
+                                                &rules.score_breakdown(&recipe, &profile, &context),
+                                            );
+                                            assert!(
+                                                (actual - expected).abs() < 1e-12,
+                                                "{} vs legacy {} for {:?} / {:?}",
+                                                actual,
+                                                expected,
+                                                recipe.nutritional_info_per_serving,
+                                                profile
+                                            );
+                                            assert_eq!(
+                                                actual > config.min_relevance,
+                                                expected > 0.1,
+                                                "cutoff differs for {:?}",
+                                                profile
+                                            );
+                                            compared += 1;
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        assert!(compared > 10_000);
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let mut config = RecommendationConfig::default();
+        assert!(validate_config(&config).is_ok());
+
+        config.min_relevance = 1.5;
+        assert!(validate_config(&config).is_err());
+
+        config = RecommendationConfig::default();
+        config.balanced_meal.min_calories = 700.0;
+        assert!(validate_config(&config).is_err());
+
+        config = RecommendationConfig::default();
+        config.muscle_gain.high_protein_weight = f64::NAN;
+        assert!(validate_config(&config).is_err());
+
+        config = RecommendationConfig::default();
+        config.meal_targets.shares.lunch = 0.5;
+        assert!(validate_config(&config).is_err(), "shares must add up to 1");
+
+        config = RecommendationConfig::default();
+        config.meal_plan.weekday_difficulties = vec!["trivial".to_string()];
+        assert!(validate_config(&config).is_err());
+
+        config = RecommendationConfig::default();
+        config.disabled_rules = vec!["no_such_rule".to_string()];
+        assert!(validate_config(&config).is_err());
+        config.disabled_rules = vec!["age".to_string()];
+        assert!(validate_config(&config).is_ok());
+
+        let typo = BUNDLED_CONFIG.replace("min_relevance", "min_relevence");
+        assert!(parse_config(&typo).is_err(), "unknown fields are rejected");
+
+        let before_targets = BUNDLED_CONFIG.split("[meal_targets]").next().unwrap();
+        let older = parse_config(before_targets).unwrap();
+        assert_eq!(older.meal_targets, MealTargetConfig::default());
+        assert_eq!(older.meal_plan, MealPlanConfig::default());
+    }
+
+    #[test]
+    fn test_watcher_reloads_edits() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(CONFIG_FILE_NAME);
+        let mut watcher = ConfigWatcher::new(path.clone());
+        assert!(watcher.poll().is_none());
+
+        let edited =
+            BUNDLED_CONFIG.replace("high_protein_weight = 0.2", "high_protein_weight = 0.3");
+        fs::write(&path, edited).unwrap();
+        let config = watcher.poll().unwrap().unwrap();
+        assert_eq!(config.muscle_gain.high_protein_weight, 0.3);
+        assert!(watcher.poll().is_none());
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            watcher.poll().unwrap().unwrap(),
+            RecommendationConfig::default()
+        );
+
+        fs::write(&path, "min_relevance = \"high\"").unwrap();
+        assert!(watcher.poll().unwrap().is_err());
+    }
+}
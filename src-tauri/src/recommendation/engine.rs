@@ -1,7 +1,14 @@
 use serde::{Deserialize, Serialize};
 
-use crate::recommendation::rules::RecommendationRules;
-use crate::storage::models::{DietRecommendation, HealthProfile};
+use crate::recommendation::allergens::{Avoidances, Exclusion};
+use crate::recommendation::config::{MealPlanConfig, MealTargetConfig, RecommendationConfig};
+use crate::recommendation::rules::{RuleSet, ScoringContext};
+use crate::recommendation::targets;
+use crate::storage::models::{
+    self, CheckedConstraints, DietRecommendation, HealthProfile, ScoreComponent,
+};
+use crate::storage::Database;
+use crate::AppResult;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Recipe {
@@ -10,6 +17,8 @@ pub struct Recipe {
     pub description: String,
     pub ingredients: Vec<Ingredient>,
     pub nutritional_info_per_serving: NutritionalInfo,
+    #[serde(default = "models::default_servings")]
+    pub servings: u32,
     pub preparation_time: u32,    // in minutes
     pub difficulty_level: String, // "easy", "medium", "hard"
     pub meal_type: String,        // "breakfast", "lunch", "dinner", "snack"
@@ -17,6 +26,15 @@ pub struct Recipe {
     pub cuisine_type: Option<String>,
     pub seasonal: bool,
     pub tags: Vec<String>,
+    #[serde(default)]
+    pub user_id: Option<String>, // owner of a user-authored recipe, None if shared
+}
+
+impl Recipe {
+    /// Shared recipes are recommended to everyone, user recipes only to their owner.
+    pub fn is_visible_to(&self, user_id: &str) -> bool {
+        self.user_id.as_deref().is_none_or(|owner| owner == user_id)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,13 +54,131 @@ pub struct NutritionalInfo {
     pub fiber: f64,   // in grams
 }
 
+impl From<&models::Recipe> for Recipe {
+    fn from(recipe: &models::Recipe) -> Self {
+        Recipe {
+            id: recipe.id.to_string(),
+            title: recipe.title.clone(),
+            description: recipe.description.clone(),
+            ingredients: recipe
+                .ingredients
+                .iter()
+                .map(|i| Ingredient {
+                    name: i.name.clone(),
+                    amount: i.amount,
+                    unit: i.unit.clone(),
+                    optional: i.optional,
+                })
+                .collect(),
+            nutritional_info_per_serving: NutritionalInfo {
+                calories: recipe.nutritional_info_per_serving.calories,
+                protein: recipe.nutritional_info_per_serving.protein,
+                carbs: recipe.nutritional_info_per_serving.carbs,
+                fat: recipe.nutritional_info_per_serving.fat,
+                fiber: recipe.nutritional_info_per_serving.fiber,
+            },
+            servings: recipe.servings,
+            preparation_time: recipe.preparation_time,
+            difficulty_level: recipe.difficulty_level.clone(),
+            meal_type: recipe.meal_type.clone(),
+            recipe_instructions: recipe.recipe_instructions.clone(),
+            cuisine_type: recipe.cuisine_type.clone(),
+            seasonal: recipe.seasonal,
+            tags: recipe.tags.clone(),
+            user_id: recipe.user_id.clone(),
+        }
+    }
+}
+
+/// A recipe left out for a profile, and why.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExcludedRecipe {
+    pub recipe_id: String,
+    pub title: String,
+    pub exclusions: Vec<Exclusion>,
+}
+
+/// How much of a recipe to serve so a meal lands near the user's calorie target for it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortionSuggestion {
+    pub recipe_id: String,
+    pub meal_type: String,
+    /// Servings to eat, in quarters between 0.5 and 2.
+    pub multiplier: f64,
+    pub serving_calories: f64,
+    pub target_calories: f64, // the meal's share of the daily calorie target
+    pub portion_calories: f64,
+}
+
 pub struct RecommendationEngine {
     pub recipes: Vec<Recipe>,
+    rules: RuleSet,
+    min_relevance: f64,
+    meal_targets: MealTargetConfig,
+    meal_plan: MealPlanConfig,
 }
 
 impl RecommendationEngine {
+    /// An empty engine scoring with the built-in rules.
     pub fn new() -> Self {
-        RecommendationEngine { recipes: vec![] }
+        Self::with_rules(RuleSet::builtin())
+    }
+
+    pub fn with_rules(rules: RuleSet) -> Self {
+        RecommendationEngine {
+            recipes: vec![],
+            rules,
+            min_relevance: RecommendationConfig::default().min_relevance,
+            meal_targets: RecommendationConfig::default().meal_targets,
+            meal_plan: RecommendationConfig::default().meal_plan,
+        }
+    }
+
+    /// Builds an engine over every recipe in the database. Recipe ids are the
+    /// database ids, so recommendations can be resolved with `get_recipe_by_id`.
+    pub fn from_database(db: &Database, rules: RuleSet) -> AppResult<Self> {
+        let mut engine = Self::with_rules(rules);
+        engine.reload(db)?;
+        Ok(engine)
+    }
+
+    pub fn rules(&self) -> &RuleSet {
+        &self.rules
+    }
+
+    /// Recipes scoring at or below this are not recommended.
+    pub fn min_relevance(&self) -> f64 {
+        self.min_relevance
+    }
+
+    /// How meals share the daily nutrition targets.
+    pub fn meal_targets(&self) -> &MealTargetConfig {
+        &self.meal_targets
+    }
+
+    /// Variety rules for multi-day plans.
+    pub fn meal_plan_config(&self) -> &MealPlanConfig {
+        &self.meal_plan
+    }
+
+    /// Scoring context for `profile`, carrying its daily targets when they can be computed.
+    pub fn scoring_context(&self, profile: &HealthProfile) -> ScoringContext {
+        ScoringContext::for_profile(profile, &self.meal_targets)
+    }
+
+    /// Rebuilds the built-in rules from `config`. Rules added with `with_rules` are
+    /// replaced, so callers with custom rules should add them again afterwards.
+    pub fn apply_config(&mut self, config: &RecommendationConfig) {
+        self.rules = RuleSet::from_config(config);
+        self.min_relevance = config.min_relevance;
+        self.meal_targets = config.meal_targets.clone();
+        self.meal_plan = config.meal_plan.clone();
+    }
+
+    /// Replaces the corpus with the current contents of the recipes table.
+    pub fn reload(&mut self, db: &Database) -> AppResult<()> {
+        self.recipes = db.get_all_recipes()?.iter().map(Recipe::from).collect();
+        Ok(())
     }
 
     pub fn add_recipe(&mut self, recipe: Recipe) {
@@ -51,17 +187,24 @@ impl RecommendationEngine {
 
     pub fn get_recommendations(&self, profile: &HealthProfile) -> Vec<DietRecommendation> {
         let mut recommendations = Vec::new();
+        let context = self.scoring_context(profile);
+        let avoidances = Avoidances::for_profile(profile);
 
-        for recipe in &self.recipes {
+        for recipe in self.recipes.iter().filter(|r| r.is_visible_to(&profile.user_id)) {
             // Check if the recipe satisfies dietary restrictions and allergies
-            if self.recipe_satisfies_restrictions(recipe, profile) {
+            if avoidances.allows(recipe) {
                 // Calculate the relevance score based on profile preferences and goals
-                let relevance_score = self.calculate_relevance_score(recipe, profile);
+                let (relevance_score, score_breakdown) =
+                    self.calculate_relevance_score(recipe, profile, &context);
 
-                // Only add recommendations with a relevance score > 0.1
-                if relevance_score > 0.1 {
-                    let recommendation =
-                        self.create_recommendation_from_recipe(recipe, profile, relevance_score);
+                // Only add recommendations scoring above the configured cutoff
+                if relevance_score > self.min_relevance {
+                    let recommendation = self.create_recommendation_from_recipe(
+                        recipe,
+                        profile,
+                        relevance_score,
+                        score_breakdown,
+                    );
                     recommendations.push(recommendation);
                 }
             }
@@ -86,7 +229,7 @@ impl RecommendationEngine {
         // Limit each meal type to avoid overwhelming the user
         let max_per_meal_type = 3;
 
-        for recipe in &self.recipes {
+        for recipe in self.recipes.iter().filter(|r| r.is_visible_to(user_id)) {
             let should_include = match recipe.meal_type.as_str() {
                 "breakfast" if breakfast_count < max_per_meal_type => {
                     breakfast_count += 1;
@@ -143,39 +286,71 @@ impl RecommendationEngine {
         recommendations
     }
 
-    fn recipe_satisfies_restrictions(&self, recipe: &Recipe, profile: &HealthProfile) -> bool {
-        // Check dietary restrictions
-        for restriction in &profile.dietary_restrictions {
-            for ingredient in &recipe.ingredients {
-                if ingredient
-                    .name
-                    .to_lowercase()
-                    .contains(&restriction.to_lowercase())
-                {
-                    return false;
-                }
-            }
+    /// Recipes visible to `profiles` that their allergies and restrictions rule out, each
+    /// with every clash that caused it. Recipes owned by one of the profiles count as
+    /// visible to all of them, as for group recommendations.
+    pub fn get_excluded_recipes(&self, profiles: &[HealthProfile]) -> Vec<ExcludedRecipe> {
+        let mut avoidances = Avoidances::default();
+        for profile in profiles {
+            avoidances.add_profile(profile);
         }
 
-        // Check allergies
-        for allergy in &profile.allergies {
-            for ingredient in &recipe.ingredients {
-                if ingredient
-                    .name
-                    .to_lowercase()
-                    .contains(&allergy.to_lowercase())
-                {
-                    return false;
+        self.recipes
+            .iter()
+            .filter(|r| {
+                r.user_id
+                    .as_deref()
+                    .is_none_or(|owner| profiles.iter().any(|p| p.user_id == owner))
+            })
+            .filter_map(|recipe| {
+                let exclusions = avoidances.exclusions(recipe);
+                if exclusions.is_empty() {
+                    return None;
                 }
-            }
-        }
+                Some(ExcludedRecipe {
+                    recipe_id: recipe.id.clone(),
+                    title: recipe.title.clone(),
+                    exclusions,
+                })
+            })
+            .collect()
+    }
+
+    /// Portion of `recipe` that brings one meal closest to `profile`'s calorie target for
+    /// `meal_type` (the recipe's own meal type when `None`). `None` when the profile has no
+    /// body measurements to derive targets from or the meal type has no share.
+    pub fn suggest_portion(
+        &self,
+        recipe: &Recipe,
+        profile: &HealthProfile,
+        meal_type: Option<&str>,
+    ) -> Option<PortionSuggestion> {
+        let mut context = self.scoring_context(profile);
+        context.meal_type = meal_type.map(str::to_string);
+        let target = context.meal_target(recipe)?;
 
-        true
+        let serving_calories = recipe.nutritional_info_per_serving.calories;
+        let multiplier = targets::portion_multiplier(serving_calories, target.calories);
+        Some(PortionSuggestion {
+            recipe_id: recipe.id.clone(),
+            meal_type: meal_type.unwrap_or(&recipe.meal_type).to_string(),
+            multiplier,
+            serving_calories,
+            target_calories: target.calories,
+            portion_calories: serving_calories * multiplier,
+        })
     }
 
-    fn calculate_relevance_score(&self, recipe: &Recipe, profile: &HealthProfile) -> f64 {
-        // Use the rules-based system to calculate the score
-        RecommendationRules::apply_rules(recipe, profile)
+    fn calculate_relevance_score(
+        &self,
+        recipe: &Recipe,
+        profile: &HealthProfile,
+        context: &ScoringContext,
+    ) -> (f64, Vec<ScoreComponent>) {
+        // Use the rule set to calculate the score; restrictions were already checked
+        // by the caller
+        let breakdown = self.rules.score_breakdown(recipe, profile, context);
+        (RuleSet::total_score(&breakdown), breakdown)
     }
 
     fn create_recommendation_from_recipe(
@@ -183,9 +358,10 @@ impl RecommendationEngine {
         recipe: &Recipe,
         profile: &HealthProfile,
         relevance_score: f64,
+        score_breakdown: Vec<ScoreComponent>,
     ) -> DietRecommendation {
         DietRecommendation {
-            id: uuid::Uuid::new_v4(),
+            id: DietRecommendation::stable_id(&profile.user_id, &recipe.id),
             user_id: profile.user_id.clone(),
             title: recipe.title.clone(),
             description: recipe.description.clone(),
@@ -212,6 +388,10 @@ impl RecommendationEngine {
             created_at: chrono::Utc::now(),
             is_personalized: true,
             relevance_score,
+            recipe_id: Some(recipe.id.clone()),
+            batch_id: None,
+            score_breakdown,
+            checked_constraints: CheckedConstraints::from_profile(profile),
         }
     }
 
@@ -238,18 +418,26 @@ impl RecommendationEngine {
 
         let relevance_score = (base_score + nutrition_bonus).min(1.0);
 
+        let mut score_breakdown = vec![ScoreComponent {
+            rule: "difficulty".to_string(),
+            delta: base_score,
+            reason: format!("难度{}", difficulty_label(&recipe.difficulty_level)),
+        }];
+        if nutrition_bonus > 0.0 {
+            score_breakdown.push(ScoreComponent {
+                rule: "balanced_nutrition".to_string(),
+                delta: nutrition_bonus,
+                reason: "蛋白质超过10克且膳食纤维超过2克".to_string(),
+            });
+        }
+
         DietRecommendation {
-            id: uuid::Uuid::new_v4(),
+            id: DietRecommendation::stable_id(user_id, &recipe.id),
             user_id: user_id.to_string(),
             title: recipe.title.clone(),
             description: format!(
                 "推荐理由：这是一道{}难度的{}，营养均衡，适合日常制作。",
-                match recipe.difficulty_level.as_str() {
-                    "easy" => "简单",
-                    "medium" => "中等", 
-                    "hard" => "较高",
-                    _ => "适中",
-                },
+                difficulty_label(&recipe.difficulty_level),
                 match recipe.meal_type.as_str() {
                     "breakfast" => "早餐",
                     "lunch" => "午餐",
@@ -281,6 +469,19 @@ impl RecommendationEngine {
             created_at: chrono::Utc::now(),
             is_personalized: false, // Mark as not personalized since no profile was used
             relevance_score,
+            recipe_id: Some(recipe.id.clone()),
+            batch_id: None,
+            score_breakdown,
+            checked_constraints: CheckedConstraints::default(), // no profile to check against
         }
     }
 }
+
+fn difficulty_label(difficulty_level: &str) -> &'static str {
+    match difficulty_level {
+        "easy" => "简单",
+        "medium" => "中等",
+        "hard" => "较高",
+        _ => "适中",
+    }
+}
@@ -0,0 +1,322 @@
+use serde::{Deserialize, Serialize};
+
+use crate::recommendation::allergens::Avoidances;
+use crate::recommendation::engine::{Recipe, RecommendationEngine};
+use crate::recommendation::rules::{RuleSet, ScoringContext};
+use crate::storage::models::{CheckedConstraints, HealthProfile, ScoreComponent};
+
+/// How per-member scores are combined into one group score.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FairnessStrategy {
+    /// The least satisfied member decides; nobody is left with a poor match.
+    #[default]
+    Min,
+    /// Plain average over all members.
+    Mean,
+    /// Average weighted by each member's `GroupMember::weight`.
+    Weighted,
+}
+
+/// One person eating with the group.
+#[derive(Debug, Clone)]
+pub struct GroupMember {
+    pub profile: HealthProfile,
+    /// Only used by `FairnessStrategy::Weighted`; negative weights count as zero.
+    pub weight: f64,
+}
+
+/// How well a recipe suits one member of the group.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemberScore {
+    pub user_id: String,
+    pub score: f64,
+    pub score_breakdown: Vec<ScoreComponent>,
+}
+
+/// A recipe every member can eat, with the combined score and each member's own score.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupRecommendation {
+    pub recipe: Recipe,
+    pub relevance_score: f64,
+    pub strategy: FairnessStrategy,
+    /// Members in the order they were given.
+    pub member_scores: Vec<MemberScore>,
+    /// The member the recipe scores highest for; the first one listed on a tie.
+    pub best_suited_for: String,
+    /// Every member's allergies and restrictions, each listed once.
+    pub checked_constraints: CheckedConstraints,
+}
+
+impl GroupRecommendation {
+    pub fn best_member(&self) -> Option<&MemberScore> {
+        self.member_scores
+            .iter()
+            .find(|m| m.user_id == self.best_suited_for)
+    }
+}
+
+impl FairnessStrategy {
+    pub fn combine(self, scores: &[(f64, f64)]) -> f64 {
+        if scores.is_empty() {
+            return 0.0;
+        }
+        let mean = scores.iter().map(|(score, _)| score).sum::<f64>() / scores.len() as f64;
+        match self {
+            FairnessStrategy::Min => scores
+                .iter()
+                .map(|(score, _)| *score)
+                .fold(f64::INFINITY, f64::min),
+            FairnessStrategy::Mean => mean,
+            FairnessStrategy::Weighted => {
+                let total_weight: f64 = scores.iter().map(|(_, weight)| weight.max(0.0)).sum();
+                if total_weight <= 0.0 {
+                    // Nobody carries any weight; treat everyone equally
+                    return mean;
+                }
+                scores
+                    .iter()
+                    .map(|(score, weight)| score * weight.max(0.0))
+                    .sum::<f64>()
+                    / total_weight
+            }
+        }
+    }
+}
+
+impl RecommendationEngine {
+    /// Recommends recipes for several people eating together. Every member's allergies and
+    /// dietary restrictions are hard filters, and each member's rule score is combined with
+    /// `strategy`. Recipes owned by a member are visible to the whole group. As for single
+    /// profiles, recipes at or below the relevance cutoff are left out.
+    pub fn get_group_recommendations(
+        &self,
+        members: &[GroupMember],
+        strategy: FairnessStrategy,
+    ) -> Vec<GroupRecommendation> {
+        if members.is_empty() {
+            return vec![];
+        }
+
+        let mut avoidances = Avoidances::default();
+        for member in members {
+            avoidances.add_profile(&member.profile);
+        }
+
+        let mut checked = CheckedConstraints::default();
+        for member in members {
+            let member_checks = CheckedConstraints::from_profile(&member.profile);
+            for allergy in member_checks.allergies {
+                if !checked.allergies.contains(&allergy) {
+                    checked.allergies.push(allergy);
+                }
+            }
+            for restriction in member_checks.dietary_restrictions {
+                if !checked.dietary_restrictions.contains(&restriction) {
+                    checked.dietary_restrictions.push(restriction);
+                }
+            }
+        }
+
+        // Each member is scored against their own daily targets
+        let contexts: Vec<ScoringContext> = members
+            .iter()
+            .map(|m| self.scoring_context(&m.profile))
+            .collect();
+        let mut recommendations: Vec<GroupRecommendation> = self
+            .recipes
+            .iter()
+            .filter(|r| {
+                r.user_id
+                    .as_deref()
+                    .is_none_or(|owner| members.iter().any(|m| m.profile.user_id == owner))
+            })
+            .filter(|r| avoidances.allows(r))
+            .filter_map(|recipe| {
+                let member_scores: Vec<MemberScore> = members
+                    .iter()
+                    .zip(&contexts)
+                    .map(|(m, context)| {
+                        let breakdown = self.rules().score_breakdown(recipe, &m.profile, context);
+                        MemberScore {
+                            user_id: m.profile.user_id.clone(),
+                            score: RuleSet::total_score(&breakdown),
+                            score_breakdown: breakdown,
+                        }
+                    })
+                    .collect();
+
+                let weighted: Vec<(f64, f64)> = member_scores
+                    .iter()
+                    .zip(members)
+                    .map(|(score, member)| (score.score, member.weight))
+                    .collect();
+                let relevance_score = strategy.combine(&weighted);
+                if relevance_score <= self.min_relevance() {
+                    return None;
+                }
+
+                let best = member_scores.iter().fold(&member_scores[0], |best, m| {
+                    if m.score > best.score {
+                        m
+                    } else {
+                        best
+                    }
+                });
+                Some(GroupRecommendation {
+                    recipe: recipe.clone(),
+                    relevance_score,
+                    strategy,
+                    best_suited_for: best.user_id.clone(),
+                    member_scores,
+                    checked_constraints: checked.clone(),
+                })
+            })
+            .collect();
+
+        recommendations.sort_by(|a, b| b.relevance_score.total_cmp(&a.relevance_score));
+        recommendations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recommendation::allergens::MatchRule;
+    use crate::recommendation::engine::{Ingredient, NutritionalInfo};
+
+    fn recipe(id: &str, ingredients: &[&str], calories: f64, protein: f64) -> Recipe {
+        Recipe {
+            id: id.to_string(),
+            title: id.to_string(),
+            description: String::new(),
+            ingredients: ingredients
+                .iter()
+                .map(|name| Ingredient {
+                    name: name.to_string(),
+                    amount: 100.0,
+                    unit: "g".to_string(),
+                    optional: false,
+                })
+                .collect(),
+            nutritional_info_per_serving: NutritionalInfo {
+                calories,
+                protein,
+                carbs: 30.0,
+                fat: 10.0,
+                fiber: 5.0,
+            },
+            servings: 1,
+            preparation_time: 20,
+            difficulty_level: "easy".to_string(),
+            meal_type: "dinner".to_string(),
+            recipe_instructions: String::new(),
+            cuisine_type: None,
+            seasonal: false,
+            tags: vec![],
+            user_id: None,
+        }
+    }
+
+    fn member(user_id: &str, goals: &[&str], allergies: &[&str]) -> GroupMember {
+        let mut profile = HealthProfile::new(user_id.to_string());
+        profile.health_goals = goals.iter().map(|g| g.to_string()).collect();
+        profile.allergies = allergies.iter().map(|a| a.to_string()).collect();
+        GroupMember {
+            profile,
+            weight: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_group_filters_union_and_combines_scores() {
+        let mut engine = RecommendationEngine::new();
+        engine.add_recipe(recipe("shrimp", &["虾仁", "西兰花"], 400.0, 30.0));
+        engine.add_recipe(recipe("chicken", &["鸡胸肉", "西兰花"], 380.0, 35.0));
+        engine.add_recipe(recipe("noodles", &["面条", "猪肉"], 750.0, 20.0));
+
+        let mut private = recipe("dad_only", &["豆腐"], 350.0, 20.0);
+        private.user_id = Some("someone_else".to_string());
+        engine.add_recipe(private);
+
+        let members = vec![
+            member("dad", &["weight_loss"], &[]),
+            member("kid", &["muscle_gain"], &["虾"]),
+        ];
+
+        let mean = engine.get_group_recommendations(&members, FairnessStrategy::Mean);
+        let ids: Vec<&str> = mean.iter().map(|r| r.recipe.id.as_str()).collect();
+        assert!(
+            !ids.contains(&"shrimp"),
+            "one member's allergy excludes it for everyone"
+        );
+        assert!(
+            !ids.contains(&"dad_only"),
+            "recipes owned outside the group stay hidden"
+        );
+        assert!(ids.contains(&"chicken"));
+        assert_eq!(
+            mean[0].checked_constraints.allergies,
+            vec!["虾".to_string()]
+        );
+
+        for rec in &mean {
+            let scores: Vec<f64> = rec.member_scores.iter().map(|m| m.score).collect();
+            let expected = scores.iter().sum::<f64>() / scores.len() as f64;
+            assert!((rec.relevance_score - expected).abs() < 1e-9);
+            let best = rec.best_member().unwrap();
+            assert!(scores.iter().all(|s| *s <= best.score));
+        }
+
+        let min = engine.get_group_recommendations(&members, FairnessStrategy::Min);
+        for rec in &min {
+            let lowest = rec
+                .member_scores
+                .iter()
+                .map(|m| m.score)
+                .fold(f64::INFINITY, f64::min);
+            assert_eq!(rec.relevance_score, lowest);
+        }
+    }
+
+    #[test]
+    fn test_group_filter_uses_allergen_groups() {
+        let mut engine = RecommendationEngine::new();
+        engine.add_recipe(recipe("satay", &["鸡胸肉", "花生酱"], 400.0, 30.0));
+        engine.add_recipe(recipe("stir_fry", &["牛肉", "生抽"], 400.0, 30.0));
+        engine.add_recipe(recipe("salad", &["生菜", "鸡胸肉"], 400.0, 30.0));
+
+        let members = vec![
+            member("dad", &["weight_loss"], &["nuts"]),
+            member("kid", &["muscle_gain"], &["麸质"]),
+        ];
+        let ids: Vec<String> = engine
+            .get_group_recommendations(&members, FairnessStrategy::Mean)
+            .into_iter()
+            .map(|r| r.recipe.id)
+            .collect();
+        assert_eq!(ids, vec!["salad".to_string()]);
+
+        let profiles: Vec<HealthProfile> = members.into_iter().map(|m| m.profile).collect();
+        let excluded = engine.get_excluded_recipes(&profiles);
+        assert_eq!(excluded.len(), 2);
+        let soy_sauce = &excluded[1].exclusions[0];
+        assert_eq!(soy_sauce.user_id, "kid");
+        assert_eq!(soy_sauce.ingredient, "生抽");
+        assert_eq!(soy_sauce.rule, MatchRule::Derived);
+        assert_eq!(
+            excluded[0].exclusions[0].allergen.as_deref(),
+            Some("peanut")
+        );
+    }
+
+    #[test]
+    fn test_weighted_strategy() {
+        let scores = [(0.2, 1.0), (0.8, 3.0)];
+        assert!((FairnessStrategy::Weighted.combine(&scores) - 0.65).abs() < 1e-9);
+        assert!((FairnessStrategy::Mean.combine(&scores) - 0.5).abs() < 1e-9);
+        assert_eq!(FairnessStrategy::Min.combine(&scores), 0.2);
+        // All-zero weights fall back to the plain mean
+        assert!((FairnessStrategy::Weighted.combine(&[(0.2, 0.0), (0.8, 0.0)]) - 0.5).abs() < 1e-9);
+    }
+}
@@ -1,2 +1,7 @@
+pub mod allergens;
+pub mod config;
 pub mod engine;
+pub mod group;
+pub mod planner;
 pub mod rules;
+pub mod targets;
@@ -0,0 +1,674 @@
+use chrono::{Datelike, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+use crate::recommendation::allergens::Avoidances;
+use crate::recommendation::engine::{NutritionalInfo, Recipe, RecommendationEngine};
+use crate::recommendation::rules::{RuleSet, ScoringContext};
+use crate::recommendation::targets::{MealShares, NutritionTargets};
+use crate::storage::models::{HealthProfile, ScoreComponent};
+use crate::{AppError, AppResult};
+
+/// Longest plan `generate_weekly_plan` builds in one go.
+pub const MAX_PLAN_DAYS: u32 = 14;
+
+/// Recipes considered per open slot: this many of the best scoring ones, plus this many of
+/// those closest to the slot's calorie target. Keeps the search over whole days small.
+const CANDIDATES_PER_SLOT: usize = 12;
+
+/// What to keep or avoid when generating a daily plan.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PlanRequest {
+    /// Recipe ids of slots that are already decided, by meal type.
+    #[serde(default)]
+    pub fixed: BTreeMap<String, String>,
+    /// Recipe ids not to pick for any open slot.
+    #[serde(default)]
+    pub excluded: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedMeal {
+    pub meal_type: String,
+    pub recipe: Recipe,
+    pub relevance_score: f64,
+    pub score_breakdown: Vec<ScoreComponent>,
+}
+
+/// One recipe per meal slot, with how the day adds up against the profile's targets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyPlan {
+    pub user_id: String,
+    /// In breakfast, lunch, dinner, snack order.
+    pub meals: Vec<PlannedMeal>,
+    /// Slots no suitable recipe could fill. Their share is left out of `targets`.
+    pub unfilled_slots: Vec<String>,
+    /// Slots where every recipe broke a multi-day variety rule, so the fewest were broken.
+    #[serde(default)]
+    pub relaxed_slots: Vec<String>,
+    pub totals: NutritionalInfo,
+    pub targets: NutritionalInfo,
+    /// `totals - targets` for every nutrient.
+    pub deviation: NutritionalInfo,
+    pub tolerance: f64,
+    /// Whether calories, protein, carbs and fat are all within `tolerance` of the targets.
+    pub within_tolerance: bool,
+    pub total_relevance: f64,
+}
+
+/// One day of a multi-day plan.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedDay {
+    pub date: NaiveDate,
+    pub plan: DailyPlan,
+}
+
+impl DailyPlan {
+    pub fn meal(&self, meal_type: &str) -> Option<&PlannedMeal> {
+        self.meals.iter().find(|m| m.meal_type == meal_type)
+    }
+}
+
+struct Candidate<'a> {
+    recipe: &'a Recipe,
+    relevance_score: f64,
+    score_breakdown: Vec<ScoreComponent>,
+}
+
+/// How good a complete day is. Days within tolerance beat days outside it; among the
+/// former the higher summed relevance wins, among the latter the smaller worst deviation.
+#[derive(Debug, Clone, Copy)]
+struct Fit {
+    within_tolerance: bool,
+    relevance: f64,
+    worst_deviation: f64,
+}
+
+impl Fit {
+    fn is_better_than(&self, other: &Fit) -> bool {
+        if self.within_tolerance != other.within_tolerance {
+            return self.within_tolerance;
+        }
+        if self.within_tolerance || self.worst_deviation == other.worst_deviation {
+            self.relevance > other.relevance
+        } else {
+            self.worst_deviation < other.worst_deviation
+        }
+    }
+}
+
+impl RecommendationEngine {
+    /// Picks one recipe per meal slot so the day's calories and macros land within the
+    /// configured daily tolerance of the profile's targets, maximizing the summed relevance.
+    /// When no combination fits, the closest one is returned with `within_tolerance` unset.
+    /// Fails when the profile's targets cannot be computed or a fixed recipe is unusable.
+    pub fn generate_daily_plan(
+        &self,
+        profile: &HealthProfile,
+        request: &PlanRequest,
+    ) -> AppResult<DailyPlan> {
+        self.plan_day(profile, request, &|_, _| 0)
+    }
+
+    /// Plans `days` consecutive days from `start_date`, each as `generate_daily_plan` would,
+    /// under the configured variety rules: no recipe repeats within `no_repeat_days`, a meal
+    /// does not keep yesterday's cuisine, and weekdays stick to the easier difficulties.
+    /// When a slot cannot satisfy every rule, the recipes breaking the fewest are used.
+    pub fn generate_weekly_plan(
+        &self,
+        profile: &HealthProfile,
+        start_date: NaiveDate,
+        days: u32,
+    ) -> AppResult<Vec<PlannedDay>> {
+        if days == 0 || days > MAX_PLAN_DAYS {
+            return Err(AppError::Validation(format!(
+                "A meal plan covers 1 to {} days",
+                MAX_PLAN_DAYS
+            )));
+        }
+
+        let rules = self.meal_plan_config();
+        let mut planned: Vec<PlannedDay> = Vec::with_capacity(days as usize);
+        for date in start_date.iter_days().take(days as usize) {
+            let plan = {
+                let recent: HashSet<&str> = planned
+                    .iter()
+                    .rev()
+                    .take(rules.no_repeat_days as usize)
+                    .flat_map(|day| day.plan.meals.iter().map(|m| m.recipe.id.as_str()))
+                    .collect();
+                let yesterday = planned.last();
+                let weekend = matches!(date.weekday(), Weekday::Sat | Weekday::Sun);
+
+                let violations = |meal_type: &str, recipe: &Recipe| {
+                    let repeated = recent.contains(recipe.id.as_str());
+                    let too_hard = !weekend
+                        && !rules
+                            .weekday_difficulties
+                            .contains(&recipe.difficulty_level);
+                    let same_cuisine = rules.rotate_cuisines
+                        && recipe.cuisine_type.is_some()
+                        && yesterday
+                            .and_then(|day| day.plan.meal(meal_type))
+                            .is_some_and(|m| m.recipe.cuisine_type == recipe.cuisine_type);
+                    [repeated, too_hard, same_cuisine]
+                        .iter()
+                        .filter(|broken| **broken)
+                        .count()
+                };
+                self.plan_day(profile, &PlanRequest::default(), &violations)?
+            };
+            planned.push(PlannedDay { date, plan });
+        }
+        Ok(planned)
+    }
+
+    /// `violations` counts the variety rules a recipe would break in a slot; each open slot
+    /// only considers the recipes breaking the fewest.
+    fn plan_day(
+        &self,
+        profile: &HealthProfile,
+        request: &PlanRequest,
+        violations: &dyn Fn(&str, &Recipe) -> usize,
+    ) -> AppResult<DailyPlan> {
+        let daily = NutritionTargets::for_profile(profile).ok_or_else(|| {
+            AppError::Validation(
+                "Age, weight and height are needed to plan meals against nutrition targets"
+                    .to_string(),
+            )
+        })?;
+        let shares = &self.meal_targets().shares;
+        if let Some(unknown) = request.fixed.keys().find(|m| shares.share(m).is_none()) {
+            return Err(AppError::Validation(format!(
+                "Unknown meal slot: {}",
+                unknown
+            )));
+        }
+        let excluded: HashSet<&str> = request.excluded.iter().map(String::as_str).collect();
+
+        let mut slots: Vec<(&str, Vec<Candidate>)> = Vec::new();
+        let mut unfilled_slots = Vec::new();
+        let mut relaxed_slots = Vec::new();
+        for meal_type in MealShares::MEAL_TYPES {
+            let mut context = self.scoring_context(profile);
+            context.meal_type = Some(meal_type.to_string());
+
+            let candidates = match request.fixed.get(meal_type) {
+                Some(recipe_id) => vec![self.fixed_candidate(recipe_id, profile, &context)?],
+                None => {
+                    let slot_calories = daily.calories * shares.share(meal_type).unwrap_or(0.0);
+                    let (candidates, relaxed) = self.slot_candidates(
+                        meal_type,
+                        profile,
+                        &context,
+                        &excluded,
+                        violations,
+                        slot_calories,
+                    );
+                    if relaxed {
+                        relaxed_slots.push(meal_type.to_string());
+                    }
+                    candidates
+                }
+            };
+            if candidates.is_empty() {
+                unfilled_slots.push(meal_type.to_string());
+            } else {
+                slots.push((meal_type, candidates));
+            }
+        }
+
+        let planned_share: f64 = slots
+            .iter()
+            .filter_map(|(meal_type, _)| shares.share(meal_type))
+            .sum();
+        let targets = daily.portion(planned_share);
+        let tolerance = self.meal_targets().daily_tolerance;
+
+        let mut best: Option<(Fit, Vec<usize>)> = None;
+        search(
+            &slots,
+            &mut Vec::with_capacity(slots.len()),
+            &targets,
+            tolerance,
+            &mut best,
+        );
+
+        let chosen = best.map(|(_, chosen)| chosen).unwrap_or_default();
+        let meals: Vec<PlannedMeal> = slots
+            .into_iter()
+            .zip(chosen)
+            .map(|((meal_type, mut candidates), index)| {
+                let candidate = candidates.swap_remove(index);
+                PlannedMeal {
+                    meal_type: meal_type.to_string(),
+                    recipe: candidate.recipe.clone(),
+                    relevance_score: candidate.relevance_score,
+                    score_breakdown: candidate.score_breakdown,
+                }
+            })
+            .collect();
+
+        let totals = meals.iter().fold(zero(), |sum, m| {
+            add(&sum, &m.recipe.nutritional_info_per_serving)
+        });
+        let worst = worst_deviation(&totals, &targets);
+        Ok(DailyPlan {
+            user_id: profile.user_id.clone(),
+            total_relevance: meals.iter().map(|m| m.relevance_score).sum(),
+            meals,
+            unfilled_slots,
+            relaxed_slots,
+            deviation: subtract(&totals, &targets),
+            within_tolerance: worst <= tolerance,
+            totals,
+            targets,
+            tolerance,
+        })
+    }
+
+    /// Replaces the recipe in `meal_type` and keeps the other slots of `current` as they
+    /// are. The current recipe and `excluded` are not picked again.
+    pub fn regenerate_slot(
+        &self,
+        profile: &HealthProfile,
+        current: &BTreeMap<String, String>,
+        meal_type: &str,
+        excluded: &[String],
+    ) -> AppResult<DailyPlan> {
+        if self.meal_targets().shares.share(meal_type).is_none() {
+            return Err(AppError::Validation(format!(
+                "Unknown meal slot: {}",
+                meal_type
+            )));
+        }
+
+        let mut request = PlanRequest {
+            fixed: current.clone(),
+            excluded: excluded.to_vec(),
+        };
+        if let Some(replaced) = request.fixed.remove(meal_type) {
+            request.excluded.push(replaced);
+        }
+
+        let plan = self.generate_daily_plan(profile, &request)?;
+        if plan.meal(meal_type).is_none() {
+            return Err(AppError::NotFound(format!(
+                "No other {} recipe suits this profile",
+                meal_type
+            )));
+        }
+        Ok(plan)
+    }
+
+    fn fixed_candidate(
+        &self,
+        recipe_id: &str,
+        profile: &HealthProfile,
+        context: &ScoringContext,
+    ) -> AppResult<Candidate<'_>> {
+        let recipe = self
+            .recipes
+            .iter()
+            .find(|r| r.id == recipe_id && r.is_visible_to(&profile.user_id))
+            .ok_or_else(|| AppError::NotFound(format!("Recipe {} not found", recipe_id)))?;
+        let exclusions = Avoidances::for_profile(profile).exclusions(recipe);
+        if !exclusions.is_empty() {
+            let reasons: Vec<&str> = exclusions.iter().map(|e| e.reason.as_str()).collect();
+            return Err(AppError::Validation(format!(
+                "Recipe {} conflicts with the profile's allergies or dietary restrictions: {}",
+                recipe_id,
+                reasons.join("；")
+            )));
+        }
+        Ok(self.candidate(recipe, profile, context))
+    }
+
+    fn slot_candidates<'a>(
+        &'a self,
+        meal_type: &str,
+        profile: &HealthProfile,
+        context: &ScoringContext,
+        excluded: &HashSet<&str>,
+        violations: &dyn Fn(&str, &Recipe) -> usize,
+        slot_calories: f64,
+    ) -> (Vec<Candidate<'a>>, bool) {
+        let avoidances = Avoidances::for_profile(profile);
+        let suitable: Vec<(&Recipe, usize)> = self
+            .recipes
+            .iter()
+            .filter(|r| r.meal_type == meal_type && r.is_visible_to(&profile.user_id))
+            .filter(|r| !excluded.contains(r.id.as_str()))
+            .filter(|r| avoidances.allows(r))
+            .map(|r| (r, violations(meal_type, r)))
+            .collect();
+        let fewest = suitable
+            .iter()
+            .map(|(_, broken)| *broken)
+            .min()
+            .unwrap_or(0);
+        let mut eligible: Vec<Candidate> = suitable
+            .into_iter()
+            .filter(|(_, broken)| *broken == fewest)
+            .map(|(r, _)| self.candidate(r, profile, context))
+            .collect();
+        if eligible.len() <= CANDIDATES_PER_SLOT * 2 {
+            return (eligible, fewest > 0);
+        }
+
+        let distance =
+            |c: &Candidate| (c.recipe.nutritional_info_per_serving.calories - slot_calories).abs();
+        eligible.sort_by(|a, b| distance(a).total_cmp(&distance(b)));
+        let closest: HashSet<String> = eligible
+            .iter()
+            .take(CANDIDATES_PER_SLOT)
+            .map(|c| c.recipe.id.clone())
+            .collect();
+
+        eligible.sort_by(|a, b| b.relevance_score.total_cmp(&a.relevance_score));
+        let mut kept = 0;
+        eligible.retain(|c| {
+            let keep = kept < CANDIDATES_PER_SLOT || closest.contains(&c.recipe.id);
+            kept += 1;
+            keep
+        });
+        (eligible, fewest > 0)
+    }
+
+    fn candidate<'a>(
+        &self,
+        recipe: &'a Recipe,
+        profile: &HealthProfile,
+        context: &ScoringContext,
+    ) -> Candidate<'a> {
+        let score_breakdown = self.rules().score_breakdown(recipe, profile, context);
+        Candidate {
+            recipe,
+            relevance_score: RuleSet::total_score(&score_breakdown),
+            score_breakdown,
+        }
+    }
+}
+
+/// Tries every combination of one candidate per slot and keeps the best in `best`.
+fn search(
+    slots: &[(&str, Vec<Candidate>)],
+    chosen: &mut Vec<usize>,
+    targets: &NutritionalInfo,
+    tolerance: f64,
+    best: &mut Option<(Fit, Vec<usize>)>,
+) {
+    let depth = chosen.len();
+    if depth == slots.len() {
+        let mut totals = zero();
+        let mut relevance = 0.0;
+        for ((_, candidates), &index) in slots.iter().zip(chosen.iter()) {
+            totals = add(
+                &totals,
+                &candidates[index].recipe.nutritional_info_per_serving,
+            );
+            relevance += candidates[index].relevance_score;
+        }
+        let worst_deviation = worst_deviation(&totals, targets);
+        let fit = Fit {
+            within_tolerance: worst_deviation <= tolerance,
+            relevance,
+            worst_deviation,
+        };
+        if best.as_ref().is_none_or(|(b, _)| fit.is_better_than(b)) {
+            *best = Some((fit, chosen.clone()));
+        }
+        return;
+    }
+
+    for index in 0..slots[depth].1.len() {
+        chosen.push(index);
+        search(slots, chosen, targets, tolerance, best);
+        chosen.pop();
+    }
+}
+
+/// Largest relative miss among calories, protein, carbs and fat.
+fn worst_deviation(totals: &NutritionalInfo, targets: &NutritionalInfo) -> f64 {
+    [
+        (totals.calories, targets.calories),
+        (totals.protein, targets.protein),
+        (totals.carbs, targets.carbs),
+        (totals.fat, targets.fat),
+    ]
+    .into_iter()
+    .filter(|(_, target)| *target > 0.0)
+    .map(|(total, target)| (total - target).abs() / target)
+    .fold(0.0, f64::max)
+}
+
+fn zero() -> NutritionalInfo {
+    NutritionalInfo {
+        calories: 0.0,
+        protein: 0.0,
+        carbs: 0.0,
+        fat: 0.0,
+        fiber: 0.0,
+    }
+}
+
+fn add(a: &NutritionalInfo, b: &NutritionalInfo) -> NutritionalInfo {
+    NutritionalInfo {
+        calories: a.calories + b.calories,
+        protein: a.protein + b.protein,
+        carbs: a.carbs + b.carbs,
+        fat: a.fat + b.fat,
+        fiber: a.fiber + b.fiber,
+    }
+}
+
+fn subtract(a: &NutritionalInfo, b: &NutritionalInfo) -> NutritionalInfo {
+    NutritionalInfo {
+        calories: a.calories - b.calories,
+        protein: a.protein - b.protein,
+        carbs: a.carbs - b.carbs,
+        fat: a.fat - b.fat,
+        fiber: a.fiber - b.fiber,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recommendation::engine::Ingredient;
+
+    fn profile() -> HealthProfile {
+        let mut profile = HealthProfile::new("user_1".to_string());
+        profile.age = 30;
+        profile.gender = "male".to_string();
+        profile.weight = 70.0;
+        profile.height = 175.0;
+        profile.activity_level = "moderately_active".to_string();
+        profile.health_goals = vec!["maintain".to_string()];
+        profile.dietary_preferences = vec!["vegetarian".to_string()];
+        profile.allergies = vec!["花生".to_string()];
+        profile
+    }
+
+    /// The chosen recipe ids by meal type, as `regenerate_slot` takes them.
+    fn recipe_ids(plan: &DailyPlan) -> BTreeMap<String, String> {
+        plan.meals
+            .iter()
+            .map(|m| (m.meal_type.clone(), m.recipe.id.clone()))
+            .collect()
+    }
+
+    /// A recipe with `factor` times the slot's share of the test profile's daily targets.
+    fn recipe(id: &str, meal_type: &str, factor: f64, ingredient: &str, tags: &[&str]) -> Recipe {
+        let targets = NutritionTargets::for_profile(&profile()).unwrap();
+        let share = MealShares::default().share(meal_type).unwrap();
+        let mut nutrition = targets.portion(share * factor);
+        nutrition.fiber = 0.0;
+        Recipe {
+            id: id.to_string(),
+            title: id.to_string(),
+            description: String::new(),
+            ingredients: vec![Ingredient {
+                name: ingredient.to_string(),
+                amount: 100.0,
+                unit: "g".to_string(),
+                optional: false,
+            }],
+            nutritional_info_per_serving: nutrition,
+            servings: 1,
+            preparation_time: 20,
+            difficulty_level: "easy".to_string(),
+            meal_type: meal_type.to_string(),
+            recipe_instructions: String::new(),
+            cuisine_type: None,
+            seasonal: false,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            user_id: None,
+        }
+    }
+
+    fn engine() -> RecommendationEngine {
+        let mut engine = RecommendationEngine::new();
+        engine.add_recipe(recipe("oats", "breakfast", 1.0, "燕麦", &[]));
+        engine.add_recipe(recipe("big_breakfast", "breakfast", 2.0, "培根", &[]));
+        engine.add_recipe(recipe("satay", "lunch", 1.0, "花生酱", &["vegetarian"]));
+        engine.add_recipe(recipe("rice_bowl", "lunch", 1.04, "米饭", &[]));
+        engine.add_recipe(recipe("tofu", "dinner", 0.98, "豆腐", &["vegetarian"]));
+        engine.add_recipe(recipe("fish", "dinner", 1.02, "鲈鱼", &[]));
+        engine.add_recipe(recipe("fruit", "snack", 1.0, "苹果", &[]));
+        engine
+    }
+
+    #[test]
+    fn test_plan_fits_targets_and_respects_allergies() {
+        let engine = engine();
+        let plan = engine
+            .generate_daily_plan(&profile(), &PlanRequest::default())
+            .unwrap();
+
+        let ids = recipe_ids(&plan);
+        assert_eq!(ids["breakfast"], "oats");
+        assert_eq!(ids["lunch"], "rice_bowl", "the peanut lunch is excluded");
+        assert_eq!(
+            ids["dinner"], "tofu",
+            "the preferred dinner wins on relevance"
+        );
+        assert_eq!(ids["snack"], "fruit");
+        assert!(plan.unfilled_slots.is_empty());
+        assert!(plan.within_tolerance);
+        assert!(plan.deviation.calories.abs() / plan.targets.calories < plan.tolerance);
+        assert!(
+            (plan.totals.calories - plan.targets.calories - plan.deviation.calories).abs() < 1e-9
+        );
+
+        let mut no_snacks = RecommendationEngine::new();
+        no_snacks.recipes = engine
+            .recipes
+            .iter()
+            .filter(|r| r.meal_type != "snack")
+            .cloned()
+            .collect();
+        let plan = no_snacks
+            .generate_daily_plan(&profile(), &PlanRequest::default())
+            .unwrap();
+        assert_eq!(plan.unfilled_slots, vec!["snack".to_string()]);
+        assert!(plan.within_tolerance, "targets cover the filled slots only");
+
+        assert!(engine
+            .generate_daily_plan(
+                &HealthProfile::new("user_1".to_string()),
+                &PlanRequest::default()
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_weekly_plan_varies_recipes() {
+        let mut engine = RecommendationEngine::new();
+        for (day, cuisine) in ["川菜", "粤菜", "川菜", "鲁菜", "粤菜"].iter().enumerate()
+        {
+            let mut lunch = recipe(&format!("lunch_{}", day), "lunch", 1.0, "米饭", &[]);
+            lunch.cuisine_type = Some(cuisine.to_string());
+            engine.add_recipe(lunch);
+        }
+        let mut feast = recipe("feast", "lunch", 1.0, "羊肉", &["vegetarian"]);
+        feast.difficulty_level = "hard".to_string();
+        engine.add_recipe(feast);
+
+        // 2024-01-01 is a Monday
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let week = engine.generate_weekly_plan(&profile(), start, 7).unwrap();
+        assert_eq!(week.len(), 7);
+        assert_eq!(week[6].date, NaiveDate::from_ymd_opt(2024, 1, 7).unwrap());
+
+        let lunches: Vec<&Recipe> = week
+            .iter()
+            .map(|day| &day.plan.meal("lunch").unwrap().recipe)
+            .collect();
+        for (index, lunch) in lunches.iter().enumerate() {
+            let weekend = index >= 5;
+            assert!(
+                weekend || lunch.difficulty_level != "hard",
+                "{:?}",
+                lunch.id
+            );
+            for previous in lunches[index.saturating_sub(3)..index].iter() {
+                assert_ne!(previous.id, lunch.id, "repeated within 3 days");
+            }
+            if index > 0 {
+                assert_ne!(lunches[index - 1].cuisine_type, lunch.cuisine_type);
+            }
+            assert!(week[index].plan.relaxed_slots.is_empty());
+        }
+        // The preferred hard recipe waits for the weekend
+        assert_eq!(lunches[5].id, "feast");
+
+        // A single breakfast cannot avoid repeating; the rule is relaxed instead
+        let mut tiny = RecommendationEngine::new();
+        tiny.add_recipe(recipe("oats", "breakfast", 1.0, "燕麦", &[]));
+        let days = tiny.generate_weekly_plan(&profile(), start, 2).unwrap();
+        assert!(days[0].plan.relaxed_slots.is_empty());
+        assert_eq!(days[1].plan.relaxed_slots, vec!["breakfast".to_string()]);
+        assert!(tiny.generate_weekly_plan(&profile(), start, 0).is_err());
+    }
+
+    #[test]
+    fn test_regenerate_slot_keeps_other_slots() {
+        let engine = engine();
+        let plan = engine
+            .generate_daily_plan(&profile(), &PlanRequest::default())
+            .unwrap();
+
+        let regenerated = engine
+            .regenerate_slot(&profile(), &recipe_ids(&plan), "dinner", &[])
+            .unwrap();
+        assert_eq!(regenerated.meal("dinner").unwrap().recipe.id, "fish");
+        for meal_type in ["breakfast", "lunch", "snack"] {
+            assert_eq!(
+                regenerated.meal(meal_type).unwrap().recipe.id,
+                plan.meal(meal_type).unwrap().recipe.id
+            );
+        }
+
+        // Nothing else is left for dinner
+        assert!(engine
+            .regenerate_slot(
+                &profile(),
+                &recipe_ids(&regenerated),
+                "dinner",
+                &["tofu".to_string()]
+            )
+            .is_err());
+
+        let mut fixed = recipe_ids(&plan);
+        fixed.insert("lunch".to_string(), "satay".to_string());
+        let request = PlanRequest {
+            fixed,
+            excluded: vec![],
+        };
+        assert!(
+            engine.generate_daily_plan(&profile(), &request).is_err(),
+            "a fixed recipe must still respect allergies"
+        );
+    }
+}
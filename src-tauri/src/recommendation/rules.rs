@@ -1,199 +1,836 @@
+use std::sync::Arc;
+
+use crate::recommendation::config::{MealTargetConfig, RecommendationConfig};
 use crate::recommendation::engine::{NutritionalInfo, Recipe};
-use crate::storage::models::HealthProfile;
-
-/// Contains rule-based logic for diet recommendations
-pub struct RecommendationRules;
-
-impl RecommendationRules {
-    /// Generates rules-based scores for recipes based on health profile
-    pub fn apply_rules(recipe: &Recipe, profile: &HealthProfile) -> f64 {
-        let mut score = 0.0;
-
-        // Check if recipe passes dietary restrictions and allergies first
-        if !Self::passes_dietary_restrictions(recipe, profile) {
-            return 0.0; // Recipe is not suitable at all
-        }
-
-        // Apply various rules with different weights
-        score += Self::apply_goal_based_rules(recipe, profile);
-        score += Self::apply_nutrition_based_rules(recipe, profile);
-        score += Self::apply_preference_rules(recipe, profile);
-        score += Self::apply_profile_characteristic_rules(recipe, profile);
-
-        // Ensure score is between 0 and 1
-        score.min(1.0).max(0.0)
-    }
-
-    /// Checks if recipe passes dietary restrictions and allergies
-    fn passes_dietary_restrictions(recipe: &Recipe, profile: &HealthProfile) -> bool {
-        // Check dietary restrictions
-        for restriction in &profile.dietary_restrictions {
-            for ingredient in &recipe.ingredients {
-                if ingredient
-                    .name
-                    .to_lowercase()
-                    .contains(&restriction.to_lowercase())
-                {
-                    return false;
-                }
-            }
+use crate::recommendation::targets::NutritionTargets;
+use crate::storage::models::{HealthProfile, ScoreComponent};
+
+/// What the engine knows about the request beyond the recipe and the profile.
+#[derive(Debug, Clone, Default)]
+pub struct ScoringContext {
+    /// Meal slot being filled, when the caller knows it (e.g. a meal planner).
+    pub meal_type: Option<String>,
+    /// Daily targets of the profile being scored; `None` when its body measurements are
+    /// missing, in which case rules fall back to their fixed per-serving cutoffs.
+    pub daily_targets: Option<NutritionTargets>,
+    pub meal_targets: MealTargetConfig,
+}
+
+impl ScoringContext {
+    pub fn for_profile(profile: &HealthProfile, meal_targets: &MealTargetConfig) -> Self {
+        ScoringContext {
+            meal_type: None,
+            daily_targets: NutritionTargets::for_profile(profile),
+            meal_targets: meal_targets.clone(),
         }
+    }
 
-        // Check allergies
-        for allergy in &profile.allergies {
-            for ingredient in &recipe.ingredients {
-                if ingredient
-                    .name
-                    .to_lowercase()
-                    .contains(&allergy.to_lowercase())
-                {
-                    return false;
-                }
-            }
+    /// The share of the daily targets one serving of `recipe` should cover, based on the
+    /// meal slot being filled or else the recipe's own meal type.
+    pub fn meal_target(&self, recipe: &Recipe) -> Option<NutritionalInfo> {
+        let targets = self.daily_targets.as_ref()?;
+        let meal_type = self.meal_type.as_deref().unwrap_or(&recipe.meal_type);
+        let share = self.meal_targets.shares.share(meal_type)?;
+        Some(targets.portion(share))
+    }
+}
+
+/// One scoring rule. A rule contributes `weight() * score(...)` to a recipe's relevance;
+/// dietary restrictions and allergies are hard filters applied before any rule runs.
+pub trait ScoringRule: Send + Sync {
+    /// Stable identifier, used in score breakdowns and to disable the rule.
+    fn name(&self) -> &str;
+
+    fn weight(&self) -> f64;
+
+    /// Unweighted score: 1.0 when the rule applies, 0.0 when it does not. Rules that
+    /// reward several matches may return the number of matches.
+    fn score(&self, recipe: &Recipe, profile: &HealthProfile, context: &ScoringContext) -> f64;
+
+    /// What the rule rewards, in words the user can read.
+    fn explanation(&self) -> String;
+
+    /// Why the rule scored for this particular recipe; only asked when `score` is non-zero.
+    /// Rules whose explanation already says everything can keep the default.
+    fn reason(
+        &self,
+        _recipe: &Recipe,
+        _profile: &HealthProfile,
+        _context: &ScoringContext,
+    ) -> String {
+        self.explanation()
+    }
+}
+
+/// The ordered set of rules the engine scores with.
+#[derive(Clone)]
+pub struct RuleSet {
+    rules: Vec<Arc<dyn ScoringRule>>,
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl RuleSet {
+    pub fn empty() -> Self {
+        RuleSet { rules: vec![] }
+    }
+
+    /// The rules SmartDiet ships with, using the bundled `recommendation.toml`.
+    pub fn builtin() -> Self {
+        Self::from_config(&RecommendationConfig::default())
+    }
+
+    /// Names of the rules `from_config` builds, in scoring order.
+    pub fn builtin_rule_names() -> Vec<&'static str> {
+        vec![
+            "weight_loss_low_calorie",
+            "weight_loss_high_fiber",
+            "muscle_gain_high_protein",
+            "maintain_balanced",
+            "balanced_meal",
+            "preference_tags",
+            "vegetarian_preference",
+            "low_carb_preference",
+            "age",
+            "activity_level",
+        ]
+    }
+
+    /// The built-in rules with thresholds and weights from `config`, minus the rules it
+    /// disables.
+    pub fn from_config(config: &RecommendationConfig) -> Self {
+        let balanced = BalancedMealCriteria {
+            min_calories: config.balanced_meal.min_calories,
+            max_calories: config.balanced_meal.max_calories,
+            min_protein: config.balanced_meal.min_protein,
+        };
+
+        let mut rules = Self::empty();
+        rules.add(GoalThresholdRule {
+            name: "weight_loss_low_calorie".to_string(),
+            goal: "weight_loss".to_string(),
+            nutrient: Nutrient::Calories,
+            bound: Bound::Below(config.weight_loss.max_calories),
+            weight: config.weight_loss.low_calorie_weight,
+        });
+        rules.add(GoalThresholdRule {
+            name: "weight_loss_high_fiber".to_string(),
+            goal: "weight_loss".to_string(),
+            nutrient: Nutrient::Fiber,
+            bound: Bound::Above(config.weight_loss.min_fiber),
+            weight: config.weight_loss.high_fiber_weight,
+        });
+        rules.add(GoalThresholdRule {
+            name: "muscle_gain_high_protein".to_string(),
+            goal: "muscle_gain".to_string(),
+            nutrient: Nutrient::Protein,
+            bound: Bound::Above(config.muscle_gain.min_protein),
+            weight: config.muscle_gain.high_protein_weight,
+        });
+        rules.add(BalancedMealRule {
+            name: "maintain_balanced".to_string(),
+            goal: Some("maintain".to_string()),
+            criteria: balanced.clone(),
+            weight: config.balanced_meal.maintain_weight,
+        });
+        rules.add(BalancedMealRule {
+            name: "balanced_meal".to_string(),
+            goal: None,
+            criteria: balanced,
+            weight: config.balanced_meal.weight,
+        });
+        rules.add(PreferenceTagRule {
+            weight: config.preferences.tag_match_weight,
+        });
+        rules.add(EmphasizedPreferenceRule {
+            name: "vegetarian_preference".to_string(),
+            tag: "vegetarian".to_string(),
+            weight: config.preferences.vegetarian_weight,
+        });
+        rules.add(EmphasizedPreferenceRule {
+            name: "low_carb_preference".to_string(),
+            tag: "low_carb".to_string(),
+            weight: config.preferences.low_carb_weight,
+        });
+        rules.add(AgeRule {
+            weight: config.age.weight,
+            young_below: config.age.young_below,
+            young_min_calories: config.age.young_min_calories,
+            young_max_calories: config.age.young_max_calories,
+            older_above: config.age.older_above,
+        });
+        rules.add(ActivityLevelRule {
+            weight: config.activity.weight,
+            sedentary_min_calories: config.activity.sedentary_min_calories,
+            sedentary_max_calories: config.activity.sedentary_max_calories,
+            very_active_min_calories: config.activity.very_active_min_calories,
+            quick_meal_minutes: config.activity.quick_meal_minutes,
+        });
+
+        for name in &config.disabled_rules {
+            rules.disable(name);
         }
+        rules
+    }
 
-        true
+    /// Appends a rule. A rule with the same name replaces the existing one in place.
+    pub fn add(&mut self, rule: impl ScoringRule + 'static) -> &mut Self {
+        let rule: Arc<dyn ScoringRule> = Arc::new(rule);
+        match self.rules.iter().position(|r| r.name() == rule.name()) {
+            Some(index) => self.rules[index] = rule,
+            None => self.rules.push(rule),
+        }
+        self
     }
 
-    /// Applies rules based on user's health goals
-    fn apply_goal_based_rules(recipe: &Recipe, profile: &HealthProfile) -> f64 {
-        let mut score = 0.0;
+    /// Removes the rule called `name`. Returns whether it was present.
+    pub fn disable(&mut self, name: &str) -> bool {
+        let before = self.rules.len();
+        self.rules.retain(|r| r.name() != name);
+        self.rules.len() != before
+    }
 
-        for goal in &profile.health_goals {
-            match goal.as_str() {
-                "weight_loss" => {
-                    // For weight loss, prefer lower calorie, higher fiber foods
-                    if recipe.nutritional_info_per_serving.calories < 400.0 {
-                        score += 0.15; // Bonus for lower calorie meals
-                    }
-                    if recipe.nutritional_info_per_serving.fiber > 5.0 {
-                        score += 0.1; // Bonus for high fiber
-                    }
-                }
-                "muscle_gain" => {
-                    // For muscle gain, prefer high protein foods
-                    if recipe.nutritional_info_per_serving.protein > 25.0 {
-                        score += 0.2; // Significant bonus for high protein
-                    }
-                }
-                "maintain" => {
-                    // For maintenance, look for balanced nutrition
-                    if Self::is_balanced_meal(&recipe.nutritional_info_per_serving) {
-                        score += 0.1;
-                    }
-                }
-                _ => {} // Other goals not currently handled
-            }
+    #[cfg(test)]
+    pub fn rules(&self) -> impl Iterator<Item = &dyn ScoringRule> {
+        self.rules.iter().map(|r| r.as_ref())
+    }
+
+    /// The weighted contribution and reason of every rule that scored, in rule order.
+    /// Rules contributing nothing are left out. Does not check dietary restrictions.
+    pub fn score_breakdown(
+        &self,
+        recipe: &Recipe,
+        profile: &HealthProfile,
+        context: &ScoringContext,
+    ) -> Vec<ScoreComponent> {
+        self.rules
+            .iter()
+            .filter_map(|rule| {
+                let delta = rule.weight() * rule.score(recipe, profile, context);
+                (delta != 0.0).then(|| ScoreComponent {
+                    rule: rule.name().to_string(),
+                    delta,
+                    reason: rule.reason(recipe, profile, context),
+                })
+            })
+            .collect()
+    }
+
+    /// Sums a breakdown into a relevance score between 0 and 1.
+    pub fn total_score(breakdown: &[ScoreComponent]) -> f64 {
+        let score: f64 = breakdown.iter().map(|c| c.delta).sum();
+        score.clamp(0.0, 1.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Nutrient {
+    Calories,
+    Protein,
+    Fiber,
+}
+
+impl Nutrient {
+    pub fn amount(self, nutrition: &NutritionalInfo) -> f64 {
+        match self {
+            Nutrient::Calories => nutrition.calories,
+            Nutrient::Protein => nutrition.protein,
+            Nutrient::Fiber => nutrition.fiber,
         }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Nutrient::Calories => "热量",
+            Nutrient::Protein => "蛋白质",
+            Nutrient::Fiber => "膳食纤维",
+        }
+    }
 
-        score
+    fn unit(self) -> &'static str {
+        match self {
+            Nutrient::Calories => "千卡",
+            _ => "克",
+        }
     }
+}
 
-    /// Applies rules based on nutritional content
-    fn apply_nutrition_based_rules(recipe: &Recipe, _profile: &HealthProfile) -> f64 {
-        let mut score = 0.0;
-        let nutrition = &recipe.nutritional_info_per_serving;
+/// Strict bound on a per-serving amount.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bound {
+    Below(f64),
+    Above(f64),
+}
 
-        // Balanced nutrition bonus
-        if Self::is_balanced_meal(nutrition) {
-            score += 0.1;
+impl Bound {
+    pub fn contains(self, value: f64) -> bool {
+        match self {
+            Bound::Below(limit) => value < limit,
+            Bound::Above(limit) => value > limit,
         }
+    }
+}
+
+/// Rewards recipes whose per-serving nutrient is within `bound`, for profiles with `goal`.
+/// When the profile's daily targets are known, the nutrient is compared with the meal's
+/// share of its target instead of the fixed limit.
+pub struct GoalThresholdRule {
+    pub name: String,
+    pub goal: String,
+    pub nutrient: Nutrient,
+    pub bound: Bound,
+    pub weight: f64,
+}
+
+impl ScoringRule for GoalThresholdRule {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn weight(&self) -> f64 {
+        self.weight
+    }
 
-        // Low sugar bonus (if we had sugar data)
-        // score += if Self::is_low_sugar(nutrition) { 0.05 } else { 0.0 };
+    fn score(&self, recipe: &Recipe, profile: &HealthProfile, context: &ScoringContext) -> f64 {
+        let amount = self.nutrient.amount(&recipe.nutritional_info_per_serving);
+        if self.bound_for(recipe, context).contains(amount) {
+            goal_count(profile, &self.goal)
+        } else {
+            0.0
+        }
+    }
 
-        score
+    fn explanation(&self) -> String {
+        format!(
+            "{}目标：每份{}{}（已填写身高体重时与本餐目标比较）",
+            goal_label(&self.goal),
+            self.nutrient.label(),
+            self.describe_bound(self.bound)
+        )
     }
 
-    /// Applies rules based on dietary preferences
-    fn apply_preference_rules(recipe: &Recipe, profile: &HealthProfile) -> f64 {
-        let mut score = 0.0;
+    fn reason(
+        &self,
+        recipe: &Recipe,
+        _profile: &HealthProfile,
+        context: &ScoringContext,
+    ) -> String {
+        match context.meal_target(recipe) {
+            Some(_) => format!(
+                "{}目标：{}{}本餐目标",
+                goal_label(&self.goal),
+                self.nutrient.label(),
+                self.describe_bound(self.bound_for(recipe, context))
+            ),
+            None => format!(
+                "{}目标：每份{}{}",
+                goal_label(&self.goal),
+                self.nutrient.label(),
+                self.describe_bound(self.bound)
+            ),
+        }
+    }
+}
 
-        // Check for matching tags with dietary preferences
-        for preference in &profile.dietary_preferences {
-            if recipe.tags.contains(preference) {
-                score += 0.15; // Significant bonus for matching preferences
+impl GoalThresholdRule {
+    /// The fixed bound, or the same kind of bound at the meal's share of the daily target.
+    fn bound_for(&self, recipe: &Recipe, context: &ScoringContext) -> Bound {
+        match context.meal_target(recipe) {
+            Some(target) => {
+                let limit = self.nutrient.amount(&target);
+                match self.bound {
+                    Bound::Below(_) => Bound::Below(limit),
+                    Bound::Above(_) => Bound::Above(limit),
+                }
             }
+            None => self.bound,
         }
+    }
 
-        // Special consideration for meal type preferences
-        if profile
-            .dietary_preferences
-            .contains(&"vegetarian".to_string())
-            && recipe.tags.contains(&"vegetarian".to_string())
+    fn describe_bound(&self, bound: Bound) -> String {
+        let (relation, limit) = match bound {
+            Bound::Below(limit) => ("低于", limit),
+            Bound::Above(limit) => ("高于", limit),
+        };
+        format!("{}{:.0}{}", relation, limit, self.nutrient.unit())
+    }
+}
+
+/// Rewards nutritionally balanced meals, optionally only for profiles with `goal`. When
+/// the profile's daily targets are known, a meal is balanced if its calories are within
+/// the configured tolerance of the meal target and its protein nearly reaches it.
+pub struct BalancedMealRule {
+    pub name: String,
+    pub goal: Option<String>,
+    pub criteria: BalancedMealCriteria,
+    pub weight: f64,
+}
+
+impl ScoringRule for BalancedMealRule {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn weight(&self) -> f64 {
+        self.weight
+    }
+
+    fn score(&self, recipe: &Recipe, profile: &HealthProfile, context: &ScoringContext) -> f64 {
+        if !self
+            .criteria_for(recipe, context)
+            .is_balanced(&recipe.nutritional_info_per_serving)
         {
-            score += 0.1;
+            return 0.0;
+        }
+        match &self.goal {
+            Some(goal) => goal_count(profile, goal),
+            None => 1.0,
         }
+    }
+
+    fn explanation(&self) -> String {
+        self.describe(&format!(
+            "营养均衡（每份{}–{}千卡，蛋白质不少于{}克；已填写身高体重时按本餐目标）",
+            self.criteria.min_calories, self.criteria.max_calories, self.criteria.min_protein
+        ))
+    }
 
-        if profile
+    fn reason(
+        &self,
+        recipe: &Recipe,
+        _profile: &HealthProfile,
+        context: &ScoringContext,
+    ) -> String {
+        let criteria = self.criteria_for(recipe, context);
+        let per_meal = if context.meal_target(recipe).is_some() {
+            "符合本餐目标"
+        } else {
+            "每份"
+        };
+        self.describe(&format!(
+            "营养均衡（{}{:.0}–{:.0}千卡，蛋白质不少于{:.0}克）",
+            per_meal, criteria.min_calories, criteria.max_calories, criteria.min_protein
+        ))
+    }
+}
+
+impl BalancedMealRule {
+    fn criteria_for(&self, recipe: &Recipe, context: &ScoringContext) -> BalancedMealCriteria {
+        match context.meal_target(recipe) {
+            Some(target) => {
+                let tolerance = context.meal_targets.tolerance;
+                BalancedMealCriteria {
+                    min_calories: target.calories * (1.0 - tolerance),
+                    max_calories: target.calories * (1.0 + tolerance),
+                    min_protein: target.protein * (1.0 - tolerance),
+                }
+            }
+            None => self.criteria.clone(),
+        }
+    }
+
+    fn describe(&self, balanced: &str) -> String {
+        match &self.goal {
+            Some(goal) => format!("{}目标：{}", goal_label(goal), balanced),
+            None => balanced.to_string(),
+        }
+    }
+}
+
+/// Rewards each recipe tag that matches one of the profile's dietary preferences.
+pub struct PreferenceTagRule {
+    pub weight: f64,
+}
+
+impl ScoringRule for PreferenceTagRule {
+    fn name(&self) -> &str {
+        "preference_tags"
+    }
+
+    fn weight(&self) -> f64 {
+        self.weight
+    }
+
+    fn score(&self, recipe: &Recipe, profile: &HealthProfile, _context: &ScoringContext) -> f64 {
+        profile
             .dietary_preferences
-            .contains(&"low_carb".to_string())
-            && recipe.tags.contains(&"low_carb".to_string())
-        {
-            score += 0.1;
+            .iter()
+            .filter(|preference| recipe.tags.contains(preference))
+            .count() as f64
+    }
+
+    fn explanation(&self) -> String {
+        "标签符合饮食偏好（每个匹配的偏好计一次）".to_string()
+    }
+
+    fn reason(
+        &self,
+        recipe: &Recipe,
+        profile: &HealthProfile,
+        _context: &ScoringContext,
+    ) -> String {
+        let matched: Vec<&str> = profile
+            .dietary_preferences
+            .iter()
+            .filter(|preference| recipe.tags.contains(preference))
+            .map(String::as_str)
+            .collect();
+        format!("符合饮食偏好：{}", matched.join("、"))
+    }
+}
+
+/// Extra reward for a preference that matters more than an ordinary tag match.
+pub struct EmphasizedPreferenceRule {
+    pub name: String,
+    pub tag: String,
+    pub weight: f64,
+}
+
+impl ScoringRule for EmphasizedPreferenceRule {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn weight(&self) -> f64 {
+        self.weight
+    }
+
+    fn score(&self, recipe: &Recipe, profile: &HealthProfile, _context: &ScoringContext) -> f64 {
+        let wanted = profile.dietary_preferences.contains(&self.tag);
+        if wanted && recipe.tags.contains(&self.tag) {
+            1.0
+        } else {
+            0.0
         }
+    }
 
-        score
+    fn explanation(&self) -> String {
+        format!("偏好“{}”且菜谱带有该标签", self.tag)
     }
+}
 
-    /// Applies rules based on user profile characteristics
-    fn apply_profile_characteristic_rules(recipe: &Recipe, profile: &HealthProfile) -> f64 {
-        let mut score = 0.0;
+/// Younger people: moderate-to-high energy meals. Older people: recipes that are not hard
+/// to make. Calorie bounds are exclusive.
+pub struct AgeRule {
+    pub weight: f64,
+    pub young_below: u32,
+    pub young_min_calories: f64,
+    pub young_max_calories: f64,
+    pub older_above: u32,
+}
 
-        // Age-based recommendations
-        if profile.age < 30 {
-            // Younger people might prefer higher energy foods
-            if recipe.nutritional_info_per_serving.calories > 300.0
-                && recipe.nutritional_info_per_serving.calories < 600.0
-            {
-                score += 0.05;
-            }
-        } else if profile.age > 50 {
-            // Older people might need easier to digest foods
-            if recipe.difficulty_level == "easy" || recipe.difficulty_level == "medium" {
-                score += 0.05;
-            }
+impl ScoringRule for AgeRule {
+    fn name(&self) -> &str {
+        "age"
+    }
+
+    fn weight(&self) -> f64 {
+        self.weight
+    }
+
+    fn score(&self, recipe: &Recipe, profile: &HealthProfile, _context: &ScoringContext) -> f64 {
+        let calories = recipe.nutritional_info_per_serving.calories;
+        let applies = if profile.age < self.young_below {
+            calories > self.young_min_calories && calories < self.young_max_calories
+        } else if profile.age > self.older_above {
+            recipe.difficulty_level == "easy" || recipe.difficulty_level == "medium"
+        } else {
+            false
+        };
+        if applies {
+            1.0
+        } else {
+            0.0
         }
+    }
 
-        // Activity level considerations
+    fn explanation(&self) -> String {
+        format!(
+            "适合年龄：{}岁以下偏好{}–{}千卡，{}岁以上偏好做法不难的菜",
+            self.young_below, self.young_min_calories, self.young_max_calories, self.older_above
+        )
+    }
+
+    fn reason(
+        &self,
+        _recipe: &Recipe,
+        profile: &HealthProfile,
+        _context: &ScoringContext,
+    ) -> String {
+        if profile.age < self.young_below {
+            format!(
+                "{}岁以下：热量在{}–{}千卡之间",
+                self.young_below, self.young_min_calories, self.young_max_calories
+            )
+        } else {
+            format!("{}岁以上：做法不难", self.older_above)
+        }
+    }
+}
+
+/// Sedentary: moderate calories. Very active: higher calories, and quick meals. Bounds
+/// are exclusive.
+pub struct ActivityLevelRule {
+    pub weight: f64,
+    pub sedentary_min_calories: f64,
+    pub sedentary_max_calories: f64,
+    pub very_active_min_calories: f64,
+    pub quick_meal_minutes: u32,
+}
+
+impl ScoringRule for ActivityLevelRule {
+    fn name(&self) -> &str {
+        "activity_level"
+    }
+
+    fn weight(&self) -> f64 {
+        self.weight
+    }
+
+    fn score(&self, recipe: &Recipe, profile: &HealthProfile, _context: &ScoringContext) -> f64 {
+        let calories = recipe.nutritional_info_per_serving.calories;
         match profile.activity_level.as_str() {
-            "sedentary" => {
-                // For sedentary people, prefer moderate calorie meals
-                if recipe.nutritional_info_per_serving.calories > 250.0
-                    && recipe.nutritional_info_per_serving.calories < 500.0
-                {
-                    score += 0.05;
-                }
+            "sedentary"
+                if calories > self.sedentary_min_calories
+                    && calories < self.sedentary_max_calories =>
+            {
+                1.0
             }
             "very_active" => {
-                // For very active people, higher calories and protein might be preferred
-                if recipe.nutritional_info_per_serving.calories > 400.0 {
-                    score += 0.05;
-                }
-                if recipe.preparation_time < 45 {
-                    // Quick meals for busy people
-                    score += 0.05;
-                }
+                let high_energy = if calories > self.very_active_min_calories {
+                    1.0
+                } else {
+                    0.0
+                };
+                let quick = if recipe.preparation_time < self.quick_meal_minutes {
+                    1.0
+                } else {
+                    0.0
+                };
+                high_energy + quick
             }
-            _ => {} // Other activity levels
+            _ => 0.0,
+        }
+    }
+
+    fn explanation(&self) -> String {
+        format!(
+            "符合活动水平：久坐偏好{}–{}千卡，高强度运动偏好{}千卡以上和{}分钟内做好的菜",
+            self.sedentary_min_calories,
+            self.sedentary_max_calories,
+            self.very_active_min_calories,
+            self.quick_meal_minutes
+        )
+    }
+
+    fn reason(
+        &self,
+        recipe: &Recipe,
+        profile: &HealthProfile,
+        _context: &ScoringContext,
+    ) -> String {
+        if profile.activity_level != "very_active" {
+            return format!(
+                "久坐：热量在{}–{}千卡之间",
+                self.sedentary_min_calories, self.sedentary_max_calories
+            );
+        }
+        let mut reasons = Vec::new();
+        if recipe.nutritional_info_per_serving.calories > self.very_active_min_calories {
+            reasons.push(format!("热量高于{}千卡", self.very_active_min_calories));
+        }
+        if recipe.preparation_time < self.quick_meal_minutes {
+            reasons.push(format!("{}分钟内做好", self.quick_meal_minutes));
         }
+        format!("高强度运动：{}", reasons.join("，"))
+    }
+}
+
+/// What counts as a nutritionally balanced meal: calories within an inclusive range and
+/// adequate protein.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BalancedMealCriteria {
+    pub min_calories: f64,
+    pub max_calories: f64,
+    pub min_protein: f64,
+}
+
+impl BalancedMealCriteria {
+    pub fn is_balanced(&self, nutrition: &NutritionalInfo) -> bool {
+        nutrition.calories >= self.min_calories
+            && nutrition.calories <= self.max_calories
+            && nutrition.protein >= self.min_protein
+    }
+}
+
+/// How many times the profile lists `goal`; each listing used to count once.
+fn goal_count(profile: &HealthProfile, goal: &str) -> f64 {
+    profile.health_goals.iter().filter(|g| *g == goal).count() as f64
+}
 
-        score
+fn goal_label(goal: &str) -> &str {
+    match goal {
+        "weight_loss" => "减脂",
+        "muscle_gain" => "增肌",
+        "maintain" => "保持体重",
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recommendation::engine::Ingredient;
+
+    fn recipe(calories: f64, protein: f64, fiber: f64, tags: &[&str]) -> Recipe {
+        Recipe {
+            id: "r".to_string(),
+            title: "r".to_string(),
+            description: String::new(),
+            ingredients: vec![Ingredient {
+                name: "豆腐".to_string(),
+                amount: 200.0,
+                unit: "g".to_string(),
+                optional: false,
+            }],
+            nutritional_info_per_serving: NutritionalInfo {
+                calories,
+                protein,
+                carbs: 30.0,
+                fat: 10.0,
+                fiber,
+            },
+            servings: 1,
+            preparation_time: 30,
+            difficulty_level: "easy".to_string(),
+            meal_type: "lunch".to_string(),
+            recipe_instructions: String::new(),
+            cuisine_type: None,
+            seasonal: false,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            user_id: None,
+        }
     }
 
-    /// Determines if a meal is nutritionally balanced
-    fn is_balanced_meal(nutrition: &NutritionalInfo) -> bool {
-        // A balanced meal typically has:
-        // - Moderate calories (between 300-600)
-        // - Adequate protein (at least 15g)
-        // - Reasonable carb-to-protein ratio
-        nutrition.calories >= 300.0 && nutrition.calories <= 600.0 && nutrition.protein >= 15.0
+    fn score(rules: &RuleSet, recipe: &Recipe, profile: &HealthProfile) -> f64 {
+        RuleSet::total_score(&rules.score_breakdown(recipe, profile, &ScoringContext::default()))
     }
 
-    /// Placeholder for low sugar check (when sugar data is available)
-    #[allow(dead_code)]
-    fn is_low_sugar(_nutrition: &NutritionalInfo) -> bool {
-        // Implementation would check sugar content when available
-        true
+    #[test]
+    fn test_builtin_rules_score() {
+        let rules = RuleSet::builtin();
+        let mut profile = HealthProfile::new("user_1".to_string());
+        profile.age = 40;
+        profile.health_goals = vec!["weight_loss".to_string()];
+        profile.dietary_preferences = vec!["vegetarian".to_string()];
+
+        // Low calorie 0.15 + high fiber 0.1 + balanced 0.1 + tag match 0.15 + vegetarian 0.1
+        let tofu = recipe(350.0, 20.0, 6.0, &["vegetarian"]);
+        assert!((score(&rules, &tofu, &profile) - 0.6).abs() < 1e-9);
+
+        let breakdown = rules.score_breakdown(&tofu, &profile, &ScoringContext::default());
+        assert!(breakdown
+            .iter()
+            .all(|c| c.rule != "muscle_gain_high_protein"));
+        let tags = breakdown
+            .iter()
+            .find(|c| c.rule == "preference_tags")
+            .unwrap();
+        assert_eq!(tags.to_string(), "符合饮食偏好：vegetarian +0.15");
+        assert!(rules.rules().all(|r| !r.explanation().is_empty()));
+    }
+
+    #[test]
+    fn test_rules_use_meal_targets_when_known() {
+        let rules = RuleSet::builtin();
+        let mut profile = HealthProfile::new("user_1".to_string());
+        profile.age = 40;
+        profile.gender = "male".to_string();
+        profile.weight = 90.0;
+        profile.height = 185.0;
+        profile.activity_level = "moderately_active".to_string();
+        profile.health_goals = vec!["muscle_gain".to_string()];
+        let context = ScoringContext::for_profile(&profile, &MealTargetConfig::default());
+
+        // 10 * 90 + 6.25 * 185 - 5 * 40 + 5 = 1861.25 kcal BMR, * 1.55 * 1.1 ≈ 3173 kcal
+        // and 180 g protein a day; lunch is 35%: ≈ 1111 kcal and 63 g protein
+        let lunch = context.meal_target(&recipe(0.0, 0.0, 0.0, &[])).unwrap();
+        assert!((lunch.calories - 1861.25 * 1.55 * 1.1 * 0.35).abs() < 1e-6);
+        assert!((lunch.protein - 63.0).abs() < 1e-9);
+
+        // Fixed cutoffs call this high-protein and balanced; against the targets it is
+        // a small lunch for a large, active person
+        let small = recipe(450.0, 30.0, 2.0, &[]);
+        assert!(score(&rules, &small, &profile) > 0.25);
+        assert_eq!(
+            RuleSet::total_score(&rules.score_breakdown(&small, &profile, &context)),
+            0.0
+        );
+
+        let hearty = recipe(1200.0, 70.0, 8.0, &[]);
+        let breakdown = rules.score_breakdown(&hearty, &profile, &context);
+        let names: Vec<&str> = breakdown.iter().map(|c| c.rule.as_str()).collect();
+        assert_eq!(names, ["muscle_gain_high_protein", "balanced_meal"]);
+        assert!(breakdown[0].reason.contains("本餐目标"));
+
+        // Without body measurements the fixed cutoffs still apply
+        profile.weight = 0.0;
+        let context = ScoringContext::for_profile(&profile, &MealTargetConfig::default());
+        assert!(context.meal_target(&small).is_none());
+        assert_eq!(
+            rules.score_breakdown(&small, &profile, &context),
+            rules.score_breakdown(&small, &profile, &ScoringContext::default())
+        );
+    }
+
+    struct QuickMealRule;
+
+    impl ScoringRule for QuickMealRule {
+        fn name(&self) -> &str {
+            "quick_meal"
+        }
+
+        fn weight(&self) -> f64 {
+            0.3
+        }
+
+        fn score(
+            &self,
+            recipe: &Recipe,
+            _profile: &HealthProfile,
+            _context: &ScoringContext,
+        ) -> f64 {
+            if recipe.preparation_time <= 30 {
+                1.0
+            } else {
+                0.0
+            }
+        }
+
+        fn explanation(&self) -> String {
+            "30分钟内做好".to_string()
+        }
+    }
+
+    #[test]
+    fn test_custom_and_disabled_rules() {
+        let mut profile = HealthProfile::new("user_1".to_string());
+        profile.age = 40;
+        let plain = recipe(450.0, 20.0, 2.0, &[]);
+
+        let mut rules = RuleSet::builtin();
+        assert!((score(&rules, &plain, &profile) - 0.1).abs() < 1e-9);
+
+        rules.add(QuickMealRule);
+        assert!((score(&rules, &plain, &profile) - 0.4).abs() < 1e-9);
+
+        assert!(rules.disable("balanced_meal"));
+        assert!(!rules.disable("balanced_meal"));
+        assert!((score(&rules, &plain, &profile) - 0.3).abs() < 1e-9);
+        assert!(rules.rules().all(|r| r.name() != "balanced_meal"));
     }
 }
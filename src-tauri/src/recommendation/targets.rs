@@ -0,0 +1,271 @@
+use serde::{Deserialize, Serialize};
+
+use crate::recommendation::engine::NutritionalInfo;
+use crate::storage::models::HealthProfile;
+
+/// Daily calories are never set below this, whatever the goal.
+pub const MIN_DAILY_CALORIES: f64 = 1200.0;
+
+/// Recommended fiber per 1000 kcal eaten.
+const FIBER_PER_1000_KCAL: f64 = 14.0;
+
+const KCAL_PER_GRAM_PROTEIN: f64 = 4.0;
+const KCAL_PER_GRAM_CARBS: f64 = 4.0;
+const KCAL_PER_GRAM_FAT: f64 = 9.0;
+
+/// Smallest and largest portion suggested, as multiples of one serving.
+pub const MIN_PORTION: f64 = 0.5;
+pub const MAX_PORTION: f64 = 2.0;
+
+/// Suggested portions are multiples of this, so they can be served as "1¼ servings".
+const PORTION_STEP: f64 = 0.25;
+
+/// The goal daily targets are computed for. A profile with several goals uses the first
+/// one it lists that is known here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetGoal {
+    WeightLoss,
+    MuscleGain,
+    Maintain,
+}
+
+impl TargetGoal {
+    pub fn from_profile(profile: &HealthProfile) -> Self {
+        profile
+            .health_goals
+            .iter()
+            .find_map(|goal| match goal.as_str() {
+                "weight_loss" => Some(TargetGoal::WeightLoss),
+                "muscle_gain" => Some(TargetGoal::MuscleGain),
+                "maintain" => Some(TargetGoal::Maintain),
+                _ => None,
+            })
+            .unwrap_or(TargetGoal::Maintain)
+    }
+
+    /// Multiplier applied to TDEE: a 20% deficit to lose weight, a 10% surplus to gain.
+    pub fn calorie_factor(self) -> f64 {
+        match self {
+            TargetGoal::WeightLoss => 0.8,
+            TargetGoal::MuscleGain => 1.1,
+            TargetGoal::Maintain => 1.0,
+        }
+    }
+
+    /// Protein in grams per kg of body weight.
+    pub fn protein_per_kg(self) -> f64 {
+        match self {
+            TargetGoal::WeightLoss => 1.6,
+            TargetGoal::MuscleGain => 2.0,
+            TargetGoal::Maintain => 1.2,
+        }
+    }
+
+    /// Share of calories from fat; carbohydrates make up the rest.
+    pub fn fat_share(self) -> f64 {
+        match self {
+            TargetGoal::WeightLoss | TargetGoal::MuscleGain => 0.25,
+            TargetGoal::Maintain => 0.3,
+        }
+    }
+}
+
+/// Daily energy and macro targets derived from a health profile.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NutritionTargets {
+    pub bmr: f64,                 // kcal/day at rest (Mifflin-St Jeor)
+    pub activity_multiplier: f64, // TDEE = BMR * multiplier
+    pub tdee: f64,                // kcal/day
+    pub goal: TargetGoal,
+    pub calories: f64, // goal-adjusted kcal/day
+    pub protein: f64,  // g/day
+    pub carbs: f64,    // g/day
+    pub fat: f64,      // g/day
+    pub fiber: f64,    // g/day
+}
+
+impl NutritionTargets {
+    /// Targets for `profile`, or `None` when its age, weight or height has not been filled
+    /// in (a fresh `HealthProfile::new` has all three at zero).
+    pub fn for_profile(profile: &HealthProfile) -> Option<Self> {
+        if profile.age == 0 || profile.weight <= 0.0 || profile.height <= 0.0 {
+            return None;
+        }
+
+        let goal = TargetGoal::from_profile(profile);
+        let bmr = mifflin_st_jeor(profile.weight, profile.height, profile.age, &profile.gender);
+        let activity_multiplier = activity_multiplier(&profile.activity_level);
+        let tdee = bmr * activity_multiplier;
+        let calories = (tdee * goal.calorie_factor()).max(MIN_DAILY_CALORIES);
+
+        let protein = profile.weight * goal.protein_per_kg();
+        let fat = calories * goal.fat_share() / KCAL_PER_GRAM_FAT;
+        let carbs = ((calories - protein * KCAL_PER_GRAM_PROTEIN - fat * KCAL_PER_GRAM_FAT)
+            / KCAL_PER_GRAM_CARBS)
+            .max(0.0);
+        let fiber = calories / 1000.0 * FIBER_PER_1000_KCAL;
+
+        Some(NutritionTargets {
+            bmr,
+            activity_multiplier,
+            tdee,
+            goal,
+            calories,
+            protein,
+            carbs,
+            fat,
+            fiber,
+        })
+    }
+
+    /// `share` of the daily targets, e.g. 0.35 for lunch.
+    pub fn portion(&self, share: f64) -> NutritionalInfo {
+        NutritionalInfo {
+            calories: self.calories * share,
+            protein: self.protein * share,
+            carbs: self.carbs * share,
+            fat: self.fat * share,
+            fiber: self.fiber * share,
+        }
+    }
+}
+
+/// How the daily targets are split across meals, from the `[meal_targets.shares]` section
+/// of `recommendation.toml`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MealShares {
+    pub breakfast: f64,
+    pub lunch: f64,
+    pub dinner: f64,
+    pub snack: f64,
+}
+
+impl Default for MealShares {
+    fn default() -> Self {
+        MealShares {
+            breakfast: 0.25,
+            lunch: 0.35,
+            dinner: 0.3,
+            snack: 0.1,
+        }
+    }
+}
+
+impl MealShares {
+    pub const MEAL_TYPES: [&'static str; 4] = ["breakfast", "lunch", "dinner", "snack"];
+
+    pub fn share(&self, meal_type: &str) -> Option<f64> {
+        match meal_type {
+            "breakfast" => Some(self.breakfast),
+            "lunch" => Some(self.lunch),
+            "dinner" => Some(self.dinner),
+            "snack" => Some(self.snack),
+            _ => None,
+        }
+    }
+
+    pub fn total(&self) -> f64 {
+        self.breakfast + self.lunch + self.dinner + self.snack
+    }
+}
+
+/// How many servings bring `serving_calories` closest to `target_calories`, in quarter
+/// servings between `MIN_PORTION` and `MAX_PORTION`. One serving when either is not a
+/// positive number.
+pub fn portion_multiplier(serving_calories: f64, target_calories: f64) -> f64 {
+    if !(serving_calories > 0.0 && target_calories > 0.0) {
+        return 1.0;
+    }
+    let steps = (target_calories / serving_calories / PORTION_STEP).round();
+    (steps * PORTION_STEP).clamp(MIN_PORTION, MAX_PORTION)
+}
+
+/// Basal metabolic rate in kcal/day. Genders other than male and female use the midpoint
+/// of the two constants.
+pub fn mifflin_st_jeor(weight_kg: f64, height_cm: f64, age: u32, gender: &str) -> f64 {
+    let offset = match gender {
+        "male" => 5.0,
+        "female" => -161.0,
+        _ => -78.0,
+    };
+    10.0 * weight_kg + 6.25 * height_cm - 5.0 * age as f64 + offset
+}
+
+/// TDEE multiplier for an `activity_level`; unknown levels count as moderate.
+pub fn activity_multiplier(activity_level: &str) -> f64 {
+    match activity_level {
+        "sedentary" => 1.2,
+        "lightly_active" => 1.375,
+        "moderately_active" => 1.55,
+        "very_active" => 1.725,
+        "extremely_active" => 1.9,
+        _ => 1.55,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(gender: &str, goal: &str) -> HealthProfile {
+        let mut profile = HealthProfile::new("user_1".to_string());
+        profile.age = 30;
+        profile.gender = gender.to_string();
+        profile.weight = 70.0;
+        profile.height = 175.0;
+        profile.activity_level = "moderately_active".to_string();
+        profile.health_goals = vec![goal.to_string()];
+        profile
+    }
+
+    #[test]
+    fn test_targets_follow_mifflin_st_jeor() {
+        let maintain = NutritionTargets::for_profile(&profile("male", "maintain")).unwrap();
+        // 10 * 70 + 6.25 * 175 - 5 * 30 + 5
+        assert!((maintain.bmr - 1648.75).abs() < 1e-9);
+        assert!((maintain.tdee - 1648.75 * 1.55).abs() < 1e-9);
+        assert_eq!(maintain.calories, maintain.tdee);
+        assert!((maintain.protein - 84.0).abs() < 1e-9);
+
+        // Macros add back up to the calorie target
+        let kcal = maintain.protein * 4.0 + maintain.carbs * 4.0 + maintain.fat * 9.0;
+        assert!((kcal - maintain.calories).abs() < 1e-6);
+
+        let female = NutritionTargets::for_profile(&profile("female", "maintain")).unwrap();
+        assert!((maintain.bmr - female.bmr - 166.0).abs() < 1e-9);
+
+        let loss = NutritionTargets::for_profile(&profile("male", "weight_loss")).unwrap();
+        let gain = NutritionTargets::for_profile(&profile("male", "muscle_gain")).unwrap();
+        assert!(loss.calories < maintain.calories && maintain.calories < gain.calories);
+        assert!(gain.protein > loss.protein && loss.protein > maintain.protein);
+    }
+
+    #[test]
+    fn test_incomplete_profiles_and_floor() {
+        assert!(NutritionTargets::for_profile(&HealthProfile::new("u".to_string())).is_none());
+
+        let mut small = profile("female", "weight_loss");
+        small.weight = 40.0;
+        small.height = 145.0;
+        small.age = 80;
+        small.activity_level = "sedentary".to_string();
+        let targets = NutritionTargets::for_profile(&small).unwrap();
+        assert_eq!(targets.calories, MIN_DAILY_CALORIES);
+
+        let lunch = targets.portion(MealShares::default().share("lunch").unwrap());
+        assert!((lunch.calories - 420.0).abs() < 1e-9);
+        assert!((MealShares::default().total() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_portion_multiplier() {
+        assert_eq!(portion_multiplier(400.0, 420.0), 1.0);
+        assert_eq!(portion_multiplier(300.0, 420.0), 1.5);
+        assert_eq!(portion_multiplier(700.0, 420.0), 0.5);
+        assert_eq!(portion_multiplier(100.0, 800.0), MAX_PORTION);
+        assert_eq!(portion_multiplier(1200.0, 420.0), MIN_PORTION);
+        assert_eq!(portion_multiplier(0.0, 420.0), 1.0);
+    }
+}
@@ -0,0 +1,441 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use uuid::Uuid;
+
+use crate::ingredients::catalog::{catalog, CatalogEntry};
+use crate::ingredients::units::{self, BaseUnit, UnitSystem};
+use crate::interchange::ingredient_line::TO_TASTE_UNIT;
+use crate::storage::models::{
+    normalize_name, PantryItem, Recipe, RecipeIngredient, ShoppingListItem, StoreSection,
+};
+
+/// Amounts at or below this are treated as covered by the pantry.
+const EPSILON: f64 = 1e-9;
+
+/// Name keywords for each section, checked in this order. Seasonings come first so
+/// 酱油 is not read as oil and 鸡精 not as chicken, eggs and milk before meat so 鸡蛋
+/// and 牛奶 stay out of it.
+#[rustfmt::skip]
+const SECTION_KEYWORDS: &[(StoreSection, &[&str])] = &[
+    (StoreSection::Condiments, &[
+        "盐", "酱", "醋", "糖", "蜂蜜", "料酒", "胡椒", "花椒", "八角", "桂皮", "孜然", "辣椒粉",
+        "咖喱", "鸡精", "味精", "高汤", "淀粉", "salt", "sauce", "vinegar", "sugar", "honey",
+        "pepper", "stock", "spice",
+    ]),
+    (StoreSection::DairyAndEggs, &[
+        "蛋", "奶", "芝士", "奶酪", "黄油", "egg", "milk", "cheese", "butter", "yogurt", "cream",
+    ]),
+    (StoreSection::Seafood, &[
+        "鱼", "虾", "蟹", "贝", "鱿", "蛤", "海带", "紫菜", "fish", "salmon", "tuna", "shrimp",
+        "prawn", "crab", "clam",
+    ]),
+    (StoreSection::Meat, &[
+        "肉", "鸡", "鸭", "牛", "猪", "羊", "排骨", "火腿", "培根", "chicken", "beef", "pork",
+        "lamb", "bacon", "ham", "turkey",
+    ]),
+    (StoreSection::Soy, &["豆腐", "豆浆", "豆干", "腐竹", "豆皮", "tofu", "soy milk"]),
+    (StoreSection::Staples, &[
+        "米", "面", "粉", "燕麦", "藜麦", "扁豆", "油", "rice", "flour", "noodle", "pasta", "oat",
+        "quinoa", "bread", "lentil", "oil",
+    ]),
+    (StoreSection::Produce, &[
+        "菜", "瓜", "茄", "椒", "葱", "姜", "蒜", "萝卜", "芹", "菇", "笋", "薯", "西兰花", "生菜",
+        "果", "莓", "柠檬", "橙", "梨", "桃", "蕉", "tomato", "onion", "garlic", "carrot",
+        "celery", "lettuce", "broccoli", "potato", "mushroom", "cucumber", "apple", "banana",
+        "lemon", "berry", "berries",
+    ]),
+];
+
+/// Store section an ingredient is shelved in: from its catalog category, or guessed from
+/// the name for ingredients the catalog does not know.
+pub fn section_for(name: &str) -> StoreSection {
+    if let Some(entry) = catalog().normalize(name) {
+        return entry.category.store_section();
+    }
+    let name = normalize_name(name);
+    SECTION_KEYWORDS
+        .iter()
+        .find(|(_, keywords)| keywords.iter().any(|keyword| name.contains(keyword)))
+        .map(|(section, _)| *section)
+        .unwrap_or(StoreSection::Other)
+}
+
+/// Running total for one ingredient in one base unit.
+struct Tally {
+    name: String,
+    entry: Option<&'static CatalogEntry>,
+    base: BaseUnit,
+    amount: f64,             // in `base`
+    units: BTreeSet<String>, // units the recipes wrote it in
+    recipes: Vec<String>,
+    optional: bool,
+}
+
+/// Sums the ingredients of `recipes` (a recipe listed twice counts twice), takes off what
+/// `pantry` already covers and returns the remaining items by section, then name, with
+/// amounts shown in `system`.
+///
+/// Names are compared by catalog id, so 西红柿 and 番茄 become one line under the catalog
+/// name. Amounts in units that measure the same thing are added up (g with kg, ml with
+/// 汤匙, 个 with 只); an ingredient written in different kinds of units is weighed where
+/// its density or piece weight allows, so 2 个 and 100 g of 鸡蛋 make 200 g. What cannot
+/// be compared stays on separate lines, and "适量" ingredients are listed once without an
+/// amount to add up.
+pub fn consolidate(
+    recipes: &[Recipe],
+    pantry: &[PantryItem],
+    list_id: Uuid,
+    system: UnitSystem,
+) -> Vec<ShoppingListItem> {
+    let mut kinds: HashMap<String, BTreeSet<String>> = HashMap::new();
+    for ingredient in recipes.iter().flat_map(|recipe| &recipe.ingredients) {
+        let (base, _) = units::to_base(&ingredient.unit);
+        if !base.is_to_taste() {
+            kinds
+                .entry(catalog().canonical_key(&ingredient.name))
+                .or_default()
+                .insert(base.name().to_string());
+        }
+    }
+
+    let mut tallies: BTreeMap<(String, String), Tally> = BTreeMap::new();
+    for recipe in recipes {
+        for ingredient in &recipe.ingredients {
+            let key_name = catalog().canonical_key(&ingredient.name);
+            if key_name.is_empty() {
+                continue;
+            }
+            let entry = catalog().normalize(&ingredient.name);
+            let (mut base, factor) = units::to_base(&ingredient.unit);
+            let mut amount = ingredient.amount * factor;
+            let mut unit = units::canonical(&ingredient.unit);
+            let mixed = kinds.get(&key_name).is_some_and(|kinds| kinds.len() > 1);
+            if mixed && base != BaseUnit::Mass {
+                if let Some(grams) = units::to_grams(entry, ingredient.amount, &ingredient.unit) {
+                    base = BaseUnit::Mass;
+                    amount = grams;
+                    unit = base.name().to_string();
+                }
+            }
+
+            let tally = tallies
+                .entry((key_name, base.name().to_string()))
+                .or_insert_with(|| Tally {
+                    name: match entry {
+                        Some(entry) => entry.name.clone(),
+                        None => ingredient.name.trim().to_string(),
+                    },
+                    entry,
+                    base: base.clone(),
+                    amount: 0.0,
+                    units: BTreeSet::new(),
+                    recipes: Vec::new(),
+                    optional: true,
+                });
+            if !base.is_to_taste() {
+                tally.amount += amount;
+            }
+            tally.units.insert(unit);
+            if !tally.recipes.contains(&recipe.title) {
+                tally.recipes.push(recipe.title.clone());
+            }
+            tally.optional &= ingredient.optional;
+        }
+    }
+
+    let mut stock: HashMap<String, Vec<&PantryItem>> = HashMap::new();
+    for item in pantry {
+        stock
+            .entry(catalog().canonical_key(&item.name))
+            .or_default()
+            .push(item);
+    }
+
+    let mut items: Vec<ShoppingListItem> = tallies
+        .into_iter()
+        .filter_map(|((key_name, _), mut tally)| {
+            let in_stock = stock.get(&key_name).map(Vec::as_slice).unwrap_or(&[]);
+            if tally.base.is_to_taste() {
+                // Any amount at home is enough of something added to taste
+                if in_stock.iter().any(|item| item.amount > EPSILON) {
+                    return None;
+                }
+            } else {
+                // Pantry amounts that cannot be converted to the tally's unit are ignored
+                tally.amount -= in_stock
+                    .iter()
+                    .filter_map(|item| {
+                        units::convert(tally.entry, item.amount, &item.unit, tally.base.name())
+                    })
+                    .sum::<f64>();
+                if tally.amount <= EPSILON {
+                    return None;
+                }
+            }
+
+            let (amount, unit) = if tally.base.is_to_taste() {
+                (1.0, TO_TASTE_UNIT.to_string())
+            } else {
+                units::display(&tally.base, tally.amount, &tally.units, system)
+            };
+            Some(ShoppingListItem {
+                id: Uuid::new_v4(),
+                list_id,
+                section: section_for(&tally.name),
+                name: tally.name,
+                amount,
+                unit,
+                recipes: tally.recipes,
+                optional: tally.optional,
+                checked: false,
+            })
+        })
+        .collect();
+
+    items.sort_by(|a, b| a.section.cmp(&b.section).then_with(|| a.name.cmp(&b.name)));
+    items
+}
+
+/// `recipe` cut down to one serving, the portion a planned meal stands for. Amounts are
+/// left unrounded so that portions add back up to the recipe; `consolidate` rounds the
+/// totals.
+pub fn single_serving(recipe: &Recipe) -> Recipe {
+    let servings = recipe.servings.max(1) as f64;
+    let ingredients = recipe
+        .ingredients
+        .iter()
+        .map(|ingredient| {
+            // 适量 is to taste however many are eating
+            let amount = if units::to_base(&ingredient.unit).0.is_to_taste() {
+                ingredient.amount
+            } else {
+                ingredient.amount / servings
+            };
+            RecipeIngredient {
+                amount,
+                ..ingredient.clone()
+            }
+        })
+        .collect();
+    Recipe {
+        ingredients,
+        servings: 1,
+        ..recipe.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::models::{NutritionalInfo, RecipeIngredient, RecipeOrigin};
+
+    fn recipe(title: &str, ingredients: &[(&str, f64, &str)]) -> Recipe {
+        Recipe {
+            id: Uuid::new_v4(),
+            title: title.to_string(),
+            description: String::new(),
+            ingredients: ingredients
+                .iter()
+                .map(|(name, amount, unit)| RecipeIngredient {
+                    name: name.to_string(),
+                    amount: *amount,
+                    unit: unit.to_string(),
+                    optional: false,
+                })
+                .collect(),
+            nutritional_info_per_serving: NutritionalInfo {
+                calories: 400.0,
+                protein: 20.0,
+                carbs: 40.0,
+                fat: 10.0,
+                fiber: 5.0,
+            },
+            servings: 1,
+            preparation_time: 20,
+            difficulty_level: "easy".to_string(),
+            meal_type: "lunch".to_string(),
+            recipe_instructions: "做。".to_string(),
+            cuisine_type: None,
+            seasonal: false,
+            tags: vec![],
+            user_id: None,
+            origin: RecipeOrigin::Bundled,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    fn pantry(name: &str, amount: f64, unit: &str) -> PantryItem {
+        PantryItem {
+            id: Uuid::new_v4(),
+            user_id: "user_1".to_string(),
+            name: name.to_string(),
+            amount,
+            unit: unit.to_string(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    fn line(items: &[ShoppingListItem], name: &str) -> (f64, String) {
+        let item = items.iter().find(|item| item.name == name).unwrap();
+        (item.amount, item.unit.clone())
+    }
+
+    #[test]
+    fn test_same_ingredients_are_summed_across_units() {
+        let recipes = vec![
+            recipe(
+                "番茄炒蛋",
+                &[
+                    ("鸡蛋", 2.0, "个"),
+                    ("番茄", 300.0, "g"),
+                    ("酱油", 1.0, "汤匙"),
+                ],
+            ),
+            recipe(
+                "蒸蛋",
+                &[
+                    ("鸡蛋", 1.0, "只"),
+                    ("酱油", 1.0, "茶匙"),
+                    ("盐", 1.0, "适量"),
+                ],
+            ),
+            recipe(
+                "番茄汤",
+                &[
+                    ("西红柿", 0.8, "kg"),
+                    ("盐", 1.0, "适量"),
+                    ("葱", 2.0, "根"),
+                ],
+            ),
+        ];
+        let items = consolidate(&recipes, &[], Uuid::nil(), UnitSystem::Metric);
+
+        assert_eq!(line(&items, "鸡蛋"), (3.0, "个".to_string()));
+        assert_eq!(line(&items, "番茄"), (1.1, "kg".to_string()));
+        // 15 ml + 5 ml, still shown in spoons
+        let (amount, unit) = line(&items, "酱油");
+        assert!((amount - 4.0 / 3.0).abs() < 0.01);
+        assert_eq!(unit, "汤匙");
+        assert_eq!(line(&items, "盐"), (1.0, TO_TASTE_UNIT.to_string()));
+        assert_eq!(line(&items, "葱"), (2.0, "根".to_string()));
+
+        let eggs = items.iter().find(|item| item.name == "鸡蛋").unwrap();
+        assert_eq!(eggs.recipes, vec!["番茄炒蛋", "蒸蛋"]);
+        assert_eq!(eggs.section, StoreSection::DairyAndEggs);
+
+        // Grouped by section in store order
+        let sections: Vec<StoreSection> = items.iter().map(|item| item.section).collect();
+        let mut sorted = sections.clone();
+        sorted.sort();
+        assert_eq!(sections, sorted);
+    }
+
+    #[test]
+    fn test_pantry_is_subtracted() {
+        let recipes = vec![
+            recipe(
+                "番茄炒蛋",
+                &[
+                    ("鸡蛋", 4.0, "个"),
+                    ("番茄", 300.0, "g"),
+                    ("盐", 1.0, "适量"),
+                ],
+            ),
+            recipe("米饭", &[("大米", 200.0, "g"), ("葱", 1.0, "根")]),
+        ];
+        let pantry = vec![
+            pantry("鸡蛋", 1.0, "个"),
+            pantry("西红柿", 0.5, "kg"),
+            pantry("盐", 1.0, "适量"),
+            pantry("大米", 2.0, "个"), // not comparable with grams, ignored
+        ];
+        let items = consolidate(&recipes, &pantry, Uuid::nil(), UnitSystem::Metric);
+
+        assert_eq!(line(&items, "鸡蛋"), (3.0, "个".to_string()));
+        assert!(items
+            .iter()
+            .all(|item| item.name != "番茄" && item.name != "盐"));
+        assert_eq!(line(&items, "大米"), (200.0, "g".to_string()));
+    }
+
+    #[test]
+    fn test_mixed_units_are_weighed() {
+        let recipes = vec![
+            recipe("蒸蛋", &[("鸡蛋", 2.0, "个"), ("胡萝卜", 1.0, "根")]),
+            recipe("蛋饼", &[("鸡蛋", 100.0, "g"), ("胡萝卜", 1.0, "把")]),
+        ];
+        let items = consolidate(
+            &recipes,
+            &[pantry("鸡蛋", 1.0, "枚")],
+            Uuid::nil(),
+            UnitSystem::Metric,
+        );
+        assert_eq!(line(&items, "鸡蛋"), (150.0, "g".to_string()));
+        // 把 says nothing about weight, so that line stays apart
+        let carrots: Vec<(f64, &str)> = items
+            .iter()
+            .filter(|item| item.name == "胡萝卜")
+            .map(|item| (item.amount, item.unit.as_str()))
+            .collect();
+        assert_eq!(carrots, vec![(80.0, "g"), (1.0, "把")]);
+
+        let items = consolidate(&recipes, &[], Uuid::nil(), UnitSystem::Imperial);
+        assert_eq!(line(&items, "鸡蛋"), (7.05, "oz".to_string()));
+    }
+
+    #[test]
+    fn test_planned_meals_are_single_servings() {
+        let mut soup = recipe(
+            "扁豆汤",
+            &[
+                ("红扁豆", 300.0, "g"),
+                ("胡萝卜", 3.0, "根"),
+                ("盐", 1.0, "适量"),
+            ],
+        );
+        soup.servings = 3;
+        // The soup is planned for two meals
+        let portion = single_serving(&soup);
+        assert_eq!(portion.servings, 1);
+        let items = consolidate(
+            &[portion.clone(), portion],
+            &[],
+            Uuid::nil(),
+            UnitSystem::Metric,
+        );
+
+        assert_eq!(line(&items, "红扁豆"), (200.0, "g".to_string()));
+        assert_eq!(line(&items, "胡萝卜"), (2.0, "根".to_string()));
+        assert_eq!(line(&items, "盐"), (1.0, TO_TASTE_UNIT.to_string()));
+
+        // Portions are not rounded one by one: a quarter egg each adds up to one egg,
+        // and thirds of 200 g add back up to 200 g
+        let mut omelette = recipe("蛋饼", &[("鸡蛋", 1.0, "个"), ("面粉", 200.0, "g")]);
+        omelette.servings = 4;
+        let portions = vec![single_serving(&omelette); 4];
+        let items = consolidate(&portions, &[], Uuid::nil(), UnitSystem::Metric);
+        assert_eq!(line(&items, "鸡蛋"), (1.0, "个".to_string()));
+        assert_eq!(line(&items, "面粉"), (200.0, "g".to_string()));
+
+        let mut stew = recipe("炖菜", &[("面粉", 200.0, "g")]);
+        stew.servings = 3;
+        let portions = vec![single_serving(&stew); 3];
+        let items = consolidate(&portions, &[], Uuid::nil(), UnitSystem::Metric);
+        assert_eq!(line(&items, "面粉"), (200.0, "g".to_string()));
+    }
+
+    #[test]
+    fn test_sections_from_names() {
+        assert_eq!(section_for("鸡胸肉"), StoreSection::Meat);
+        assert_eq!(section_for("鸡精"), StoreSection::Condiments);
+        assert_eq!(section_for("牛奶"), StoreSection::DairyAndEggs);
+        assert_eq!(section_for("三文鱼"), StoreSection::Seafood);
+        assert_eq!(section_for("橄榄油"), StoreSection::Staples);
+        assert_eq!(section_for("酱油"), StoreSection::Condiments);
+        assert_eq!(section_for("豆腐"), StoreSection::Soy);
+        assert_eq!(section_for("西兰花"), StoreSection::Produce);
+        assert_eq!(section_for("Cherry Tomatoes"), StoreSection::Produce);
+        assert_eq!(section_for("牛油果"), StoreSection::Produce);
+        assert_eq!(section_for("神秘食材"), StoreSection::Other);
+    }
+}
@@ -0,0 +1,274 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::BackupPolicy;
+use crate::storage::migrations;
+use crate::storage::Database;
+use crate::{AppError, AppResult};
+
+const SNAPSHOT_PREFIX: &str = "data-";
+const SNAPSHOT_EXTENSION: &str = "db";
+const SNAPSHOT_TIME_FORMAT: &str = "%Y%m%dT%H%M%S%3fZ";
+
+/// A database snapshot under the backup directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    pub file_name: String,
+    pub path: PathBuf,
+    pub created_at: DateTime<Utc>,
+    pub size_bytes: u64,
+}
+
+/// Takes a timestamped snapshot of the live database into `backup_dir` and prunes old
+/// snapshots down to `policy.keep_snapshots`.
+pub fn create_snapshot(
+    db: &Database,
+    backup_dir: &Path,
+    policy: &BackupPolicy,
+) -> AppResult<SnapshotInfo> {
+    let snapshot = take_snapshot(db, backup_dir)?;
+    let removed = prune_snapshots(backup_dir, policy.keep_snapshots)?;
+    log::info!(
+        "Created database snapshot {} ({} old snapshots removed)",
+        snapshot.file_name,
+        removed
+    );
+    Ok(snapshot)
+}
+
+fn take_snapshot(db: &Database, backup_dir: &Path) -> AppResult<SnapshotInfo> {
+    fs::create_dir_all(backup_dir)?;
+
+    // Names have millisecond resolution; step past a snapshot taken in the same instant
+    let mut created_at = Utc::now();
+    let (file_name, path) = loop {
+        let file_name = format!(
+            "{}{}.{}",
+            SNAPSHOT_PREFIX,
+            created_at.format(SNAPSHOT_TIME_FORMAT),
+            SNAPSHOT_EXTENSION
+        );
+        let path = backup_dir.join(&file_name);
+        if !path.exists() {
+            break (file_name, path);
+        }
+        created_at += chrono::Duration::milliseconds(1);
+    };
+
+    // Back up to a temporary name so a crash never leaves a truncated snapshot behind
+    let partial = path.with_extension("partial");
+    if let Err(e) = db.backup_to(&partial) {
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+    fs::rename(&partial, &path)?;
+
+    Ok(SnapshotInfo {
+        size_bytes: fs::metadata(&path)?.len(),
+        file_name,
+        path,
+        created_at,
+    })
+}
+
+/// Snapshots in `backup_dir`, newest first. Files that do not follow the snapshot naming
+/// scheme are ignored.
+pub fn list_snapshots(backup_dir: &Path) -> AppResult<Vec<SnapshotInfo>> {
+    if !backup_dir.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut snapshots = Vec::new();
+    for entry in fs::read_dir(backup_dir)? {
+        let path = entry?.path();
+        let Some(file_name) = path.file_name().and_then(|n| n.to_str()).map(str::to_string)
+        else {
+            continue;
+        };
+        let Some(created_at) = parse_snapshot_time(&file_name) else {
+            continue;
+        };
+        snapshots.push(SnapshotInfo {
+            size_bytes: fs::metadata(&path)?.len(),
+            file_name,
+            path,
+            created_at,
+        });
+    }
+
+    snapshots.sort_by_key(|snapshot| std::cmp::Reverse(snapshot.created_at));
+    Ok(snapshots)
+}
+
+/// Deletes all but the newest `keep` snapshots. Returns how many were deleted.
+pub fn prune_snapshots(backup_dir: &Path, keep: u32) -> AppResult<usize> {
+    let snapshots = list_snapshots(backup_dir)?;
+    let mut removed = 0;
+    for snapshot in snapshots.iter().skip(keep.max(1) as usize) {
+        fs::remove_file(&snapshot.path)?;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+/// Takes a snapshot if scheduled backups are on and the newest snapshot is older than the
+/// configured interval. Returns the new snapshot, if one was taken.
+pub fn run_scheduled_backup(
+    db: &Database,
+    backup_dir: &Path,
+    policy: &BackupPolicy,
+) -> AppResult<Option<SnapshotInfo>> {
+    let Some(interval) = policy.interval() else {
+        return Ok(None);
+    };
+
+    let latest = list_snapshots(backup_dir)?.into_iter().next();
+    if latest.is_some_and(|s| Utc::now() - s.created_at < interval) {
+        return Ok(None);
+    }
+    create_snapshot(db, backup_dir, policy).map(Some)
+}
+
+/// Checks that `path` is an intact SmartDiet database this build can open, and returns its
+/// schema version. Older schemas are accepted and upgraded after the restore; newer ones
+/// are rejected because this build cannot know what they contain.
+pub fn inspect_snapshot(path: &Path) -> AppResult<u32> {
+    if !path.is_file() {
+        return Err(AppError::NotFound(format!("Backup {} not found", path.display())));
+    }
+
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| AppError::Validation(format!("Not a readable database: {}", e)))?;
+
+    let integrity: String = conn
+        .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+        .map_err(|e| AppError::Validation(format!("Not a readable database: {}", e)))?;
+    if integrity != "ok" {
+        return Err(AppError::Validation(format!(
+            "Backup failed the integrity check: {}",
+            integrity
+        )));
+    }
+
+    let has_profiles: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'health_profiles'",
+        [],
+        |row| row.get(0),
+    )?;
+    if has_profiles == 0 {
+        return Err(AppError::Validation(
+            "Backup is not a SmartDiet database".to_string(),
+        ));
+    }
+
+    let version = migrations::current_version(&conn)?;
+    if version > migrations::latest_version() {
+        return Err(AppError::Validation(format!(
+            "Backup uses schema version {}, but this version of SmartDiet supports up to {}",
+            version,
+            migrations::latest_version()
+        )));
+    }
+    Ok(version)
+}
+
+/// Restores the snapshot at `path` over the live database after checking it. The live
+/// data is snapshotted first so a mistaken restore can be undone.
+pub fn restore_snapshot(
+    db: &Database,
+    path: &Path,
+    backup_dir: &Path,
+    policy: &BackupPolicy,
+) -> AppResult<SnapshotInfo> {
+    let version = inspect_snapshot(path)?;
+
+    // Prune only after restoring: the snapshot being restored may be the oldest one
+    let safety = take_snapshot(db, backup_dir)?;
+    db.restore_from(path)?;
+    prune_snapshots(backup_dir, policy.keep_snapshots)?;
+    log::info!(
+        "Restored database from {} (schema version {}); previous data saved as {}",
+        path.display(),
+        version,
+        safety.file_name
+    );
+    Ok(safety)
+}
+
+fn parse_snapshot_time(file_name: &str) -> Option<DateTime<Utc>> {
+    let stamp = file_name
+        .strip_prefix(SNAPSHOT_PREFIX)?
+        .strip_suffix(SNAPSHOT_EXTENSION)?
+        .strip_suffix('.')?;
+    NaiveDateTime::parse_from_str(stamp, SNAPSHOT_TIME_FORMAT)
+        .ok()
+        .map(|time| time.and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::models::HealthProfile;
+    use tempfile::TempDir;
+
+    fn policy(keep_snapshots: u32) -> BackupPolicy {
+        BackupPolicy {
+            keep_snapshots,
+            interval_hours: Some(24),
+        }
+    }
+
+    #[test]
+    fn test_snapshots_rotate_and_restore() {
+        let dir = TempDir::new().unwrap();
+        let backups = dir.path().join("backups");
+        let db = Database::new(dir.path().join("data.db")).unwrap();
+        db.save_health_profile(&HealthProfile::new("user_1".to_string()))
+            .unwrap();
+
+        let snapshot = create_snapshot(&db, &backups, &policy(5)).unwrap();
+        assert_eq!(inspect_snapshot(&snapshot.path).unwrap(), migrations::latest_version());
+
+        // The newest snapshot is recent, so the schedule does not take another one
+        assert!(run_scheduled_backup(&db, &backups, &policy(5)).unwrap().is_none());
+
+        db.delete_health_profile("user_1").unwrap();
+        let safety = restore_snapshot(&db, &snapshot.path, &backups, &policy(1)).unwrap();
+        assert!(db.get_health_profile("user_1").unwrap().is_some());
+        let snapshots = list_snapshots(&backups).unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].file_name, safety.file_name);
+
+        create_snapshot(&db, &backups, &policy(3)).unwrap();
+        create_snapshot(&db, &backups, &policy(3)).unwrap();
+        create_snapshot(&db, &backups, &policy(3)).unwrap();
+        let snapshots = list_snapshots(&backups).unwrap();
+        assert_eq!(snapshots.len(), 3);
+        assert!(snapshots.iter().all(|s| s.file_name != safety.file_name));
+    }
+
+    #[test]
+    fn test_newer_schema_is_rejected() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("future.db");
+        {
+            let db = Database::new(&path).unwrap();
+            let conn = db.conn().unwrap();
+            conn.pragma_update(None, "user_version", migrations::latest_version() + 1)
+                .unwrap();
+        }
+
+        let error = inspect_snapshot(&path).unwrap_err();
+        assert!(error.to_string().contains("supports up to"));
+
+        let not_smartdiet = dir.path().join("other.db");
+        Connection::open(&not_smartdiet)
+            .unwrap()
+            .execute_batch("CREATE TABLE t (x INTEGER);")
+            .unwrap();
+        assert!(inspect_snapshot(&not_smartdiet).is_err());
+    }
+}
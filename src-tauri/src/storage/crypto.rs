@@ -0,0 +1,129 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+
+use crate::{AppError, AppResult};
+
+/// Prefix of every sealed value, so encrypted and plaintext values can be told apart
+/// and the format can change later.
+pub const SEALED_PREFIX: &str = "enc:v1:";
+
+pub const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Known plaintext sealed with the key when encryption is enabled; opening it checks
+/// the passphrase before any data is touched.
+const VERIFIER_PLAINTEXT: &str = "scx-recipes/privacy-mode";
+
+/// AES-256-GCM with a key derived from the user's passphrase (Argon2id). Used to seal
+/// individual sensitive columns; the rest of the database stays queryable.
+pub struct FieldCipher {
+    cipher: Aes256Gcm,
+}
+
+impl FieldCipher {
+    pub fn derive(passphrase: &str, salt: &[u8]) -> AppResult<Self> {
+        if passphrase.is_empty() {
+            return Err(AppError::Validation("Passphrase must not be empty".to_string()));
+        }
+
+        let mut key = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| AppError::Encryption(format!("Key derivation failed: {}", e)))?;
+
+        let cipher = Aes256Gcm::new_from_slice(&key)
+            .map_err(|e| AppError::Encryption(e.to_string()))?;
+        key.fill(0);
+        Ok(FieldCipher { cipher })
+    }
+
+    pub fn generate_salt() -> Vec<u8> {
+        let mut salt = vec![0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        salt
+    }
+
+    /// Encrypts `plaintext` with a fresh nonce: `enc:v1:` + base64(nonce || ciphertext).
+    pub fn seal(&self, plaintext: &str) -> AppResult<String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+
+        let ciphertext = self
+            .cipher
+            .encrypt(&Nonce::from(nonce), plaintext.as_bytes())
+            .map_err(|_| AppError::Encryption("Failed to encrypt value".to_string()))?;
+
+        let mut payload = nonce.to_vec();
+        payload.extend_from_slice(&ciphertext);
+        Ok(format!("{}{}", SEALED_PREFIX, BASE64.encode(payload)))
+    }
+
+    pub fn open(&self, sealed: &str) -> AppResult<String> {
+        let encoded = sealed
+            .strip_prefix(SEALED_PREFIX)
+            .ok_or_else(|| AppError::Encryption("Value is not encrypted".to_string()))?;
+        let payload = BASE64
+            .decode(encoded)
+            .map_err(|e| AppError::Encryption(format!("Corrupt encrypted value: {}", e)))?;
+        let Some((nonce, ciphertext)) = payload.split_first_chunk::<NONCE_LEN>() else {
+            return Err(AppError::Encryption("Corrupt encrypted value".to_string()));
+        };
+
+        let plaintext = self
+            .cipher
+            .decrypt(&Nonce::from(*nonce), ciphertext)
+            .map_err(|_| AppError::Encryption("Wrong passphrase or corrupt data".to_string()))?;
+        String::from_utf8(plaintext).map_err(|e| AppError::Encryption(e.to_string()))
+    }
+
+    pub fn verifier(&self) -> AppResult<String> {
+        self.seal(VERIFIER_PLAINTEXT)
+    }
+
+    pub fn check_verifier(&self, verifier: &str) -> AppResult<()> {
+        match self.open(verifier) {
+            Ok(plaintext) if plaintext == VERIFIER_PLAINTEXT => Ok(()),
+            _ => Err(AppError::Encryption("Wrong passphrase".to_string())),
+        }
+    }
+}
+
+pub fn is_sealed(value: &str) -> bool {
+    value.starts_with(SEALED_PREFIX)
+}
+
+pub fn encode_salt(salt: &[u8]) -> String {
+    BASE64.encode(salt)
+}
+
+pub fn decode_salt(salt: &str) -> AppResult<Vec<u8>> {
+    BASE64
+        .decode(salt)
+        .map_err(|e| AppError::Encryption(format!("Corrupt key salt: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        let salt = FieldCipher::generate_salt();
+        let cipher = FieldCipher::derive("correct horse", &salt).unwrap();
+
+        let sealed = cipher.seal("花生, 虾").unwrap();
+        assert!(is_sealed(&sealed));
+        assert!(!sealed.contains("花生"));
+        assert_ne!(sealed, cipher.seal("花生, 虾").unwrap(), "nonces must differ");
+        assert_eq!(cipher.open(&sealed).unwrap(), "花生, 虾");
+
+        let wrong = FieldCipher::derive("wrong horse", &salt).unwrap();
+        assert!(wrong.open(&sealed).is_err());
+        assert!(wrong.check_verifier(&cipher.verifier().unwrap()).is_err());
+        assert!(cipher.check_verifier(&cipher.verifier().unwrap()).is_ok());
+    }
+}
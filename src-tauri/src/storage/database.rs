@@ -1,259 +1,330 @@
-use chrono::{DateTime};
+use chrono::{DateTime, Utc};
+use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::{Connection, OptionalExtension};
-use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::RwLock;
+use std::time::Duration;
 use uuid::Uuid;
 
+use crate::storage::crypto::{self, FieldCipher};
+use crate::storage::migrations;
 use crate::storage::models::*;
-use crate::AppResult;
+use crate::{AppError, AppResult};
+
+pub type ConnectionPool = r2d2::Pool<SqliteConnectionManager>;
+pub type PooledConnection = r2d2::PooledConnection<SqliteConnectionManager>;
+
+const MAX_POOL_SIZE: u32 = 8;
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+const STATEMENT_CACHE_CAPACITY: usize = 64;
 
 pub struct Database {
-    path: PathBuf,
+    pool: ConnectionPool,
+    key: RwLock<KeyState>,
+}
+
+/// Privacy mode state. When it is on, health profiles and diet history notes are sealed
+/// with a key derived from the user's passphrase, and they cannot be read or written
+/// until `unlock` has been called with that passphrase.
+enum KeyState {
+    Disabled,
+    Locked,
+    Unlocked(Box<FieldCipher>),
+}
+
+/// Health profile fields that are sealed together when privacy mode is on.
+#[derive(Serialize, Deserialize)]
+struct SensitiveProfileFields {
+    age: u32,
+    gender: String,
+    weight: f64,
+    height: f64,
+    activity_level: String,
+    health_goals: Vec<String>,
+    dietary_preferences: Vec<String>,
+    dietary_restrictions: Vec<String>,
+    allergies: Vec<String>,
+}
+
+/// Per-connection setup, run once when the pool opens a new connection.
+/// Pragmas like `foreign_keys` are connection-scoped, so they must be applied here
+/// rather than on a single connection at startup.
+fn configure_connection(conn: &mut Connection) -> rusqlite::Result<()> {
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    conn.execute_batch(
+        "PRAGMA journal_mode = WAL;
+         PRAGMA synchronous = NORMAL;
+         PRAGMA foreign_keys = ON;",
+    )?;
+    conn.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
+    Ok(())
 }
 
 impl Database {
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
         let path_ref = path.as_ref();
-        let conn = Connection::open(path_ref)?;
-        
-        // Enable foreign key support
-        conn.execute("PRAGMA foreign_keys = ON", [])?;
-        
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS health_profiles (
-                id TEXT PRIMARY KEY,
-                user_id TEXT NOT NULL UNIQUE,
-                age INTEGER NOT NULL,
-                gender TEXT NOT NULL,
-                weight REAL NOT NULL,
-                height REAL NOT NULL,
-                activity_level TEXT NOT NULL,
-                health_goals TEXT NOT NULL,
-                dietary_preferences TEXT NOT NULL,
-                dietary_restrictions TEXT NOT NULL,
-                allergies TEXT NOT NULL,
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL
-            )",
-            [],
-        )?;
 
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS diet_recommendations (
-                id TEXT PRIMARY KEY,
-                user_id TEXT NOT NULL,
-                title TEXT NOT NULL,
-                description TEXT,
-                ingredients TEXT NOT NULL,
-                nutritional_info TEXT NOT NULL,
-                preparation_time INTEGER NOT NULL,
-                difficulty_level TEXT NOT NULL,
-                meal_type TEXT NOT NULL,
-                recipe_instructions TEXT NOT NULL,
-                created_at TEXT NOT NULL,
-                is_personalized BOOLEAN NOT NULL,
-                relevance_score REAL NOT NULL,
-                FOREIGN KEY (user_id) REFERENCES health_profiles (user_id)
-            )",
-            [],
-        )?;
-
-        // Check if diet_history table exists and needs migration
-        let mut table_exists = false;
-        let mut has_foreign_key = false;
-        
-        // Check if table exists
-        let table_check = conn.prepare("SELECT name FROM sqlite_master WHERE type='table' AND name='diet_history'");
-        if let Ok(mut stmt) = table_check {
-            if let Ok(rows) = stmt.query_map([], |_row| Ok(())) {
-                table_exists = rows.count() > 0;
-            }
-        }
-        
-        if table_exists {
-            // Check if foreign key constraint exists
-            let pragma_check = conn.prepare("PRAGMA foreign_key_list(diet_history)");
-            if let Ok(mut stmt) = pragma_check {
-                if let Ok(rows) = stmt.query_map([], |_row| Ok(())) {
-                    has_foreign_key = rows.count() > 0;
-                }
+        // Ensure the database directory exists before the pool opens the file
+        if let Some(parent) = path_ref.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)?;
             }
-            
-            if has_foreign_key {
-                // Need to migrate: recreate table without foreign key
-                println!("Migrating diet_history table to remove foreign key constraint...");
-                
-                // Start transaction
-                let tx = conn.unchecked_transaction()?;
-                
-                // Create new table without foreign key
-                tx.execute(
-                    "CREATE TABLE diet_history_new (
-                        id TEXT PRIMARY KEY,
-                        user_id TEXT NOT NULL,
-                        diet_item_id TEXT NOT NULL,
-                        date_attempted TEXT NOT NULL,
-                        rating INTEGER,
-                        notes TEXT,
-                        was_prepared BOOLEAN NOT NULL,
-                        meal_type TEXT NOT NULL,
-                        created_at TEXT NOT NULL,
-                        updated_at TEXT NOT NULL
-                    )",
-                    [],
-                )?;
-                
-                // Copy data from old table to new table
-                tx.execute(
-                    "INSERT INTO diet_history_new SELECT * FROM diet_history",
-                    [],
-                )?;
-                
-                // Drop old table
-                tx.execute("DROP TABLE diet_history", [])?;
-                
-                // Rename new table
-                tx.execute("ALTER TABLE diet_history_new RENAME TO diet_history", [])?;
-                
-                // Commit transaction
-                tx.commit()?;
-                
-                println!("Successfully migrated diet_history table");
-            }
-        } else {
-            // Create new table without foreign key
-            conn.execute(
-                "CREATE TABLE diet_history (
-                    id TEXT PRIMARY KEY,
-                    user_id TEXT NOT NULL,
-                    diet_item_id TEXT NOT NULL,
-                    date_attempted TEXT NOT NULL,
-                    rating INTEGER,
-                    notes TEXT,
-                    was_prepared BOOLEAN NOT NULL,
-                    meal_type TEXT NOT NULL,
-                    created_at TEXT NOT NULL,
-                    updated_at TEXT NOT NULL
-                )",
-                [],
-            )?;
         }
 
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS recipes (
-                id TEXT PRIMARY KEY,
-                title TEXT NOT NULL,
-                description TEXT,
-                ingredients TEXT NOT NULL,
-                nutritional_info_per_serving TEXT NOT NULL,
-                preparation_time INTEGER NOT NULL,
-                difficulty_level TEXT NOT NULL,
-                meal_type TEXT NOT NULL,
-                recipe_instructions TEXT NOT NULL,
-                cuisine_type TEXT,
-                seasonal BOOLEAN NOT NULL,
-                tags TEXT NOT NULL,
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL
-            )",
-            [],
-        )?;
+        let manager = SqliteConnectionManager::file(path_ref).with_init(configure_connection);
+        let pool = r2d2::Pool::builder()
+            .max_size(MAX_POOL_SIZE)
+            .build(manager)?;
+
+        // Create or upgrade the schema
+        let mut conn = pool.get()?;
+        migrations::run_migrations(&mut conn)?;
+
+        let key = if read_key_row(&conn)?.is_some() {
+            KeyState::Locked
+        } else {
+            KeyState::Disabled
+        };
 
         Ok(Database {
-            path: path_ref.to_path_buf(),
+            pool,
+            key: RwLock::new(key),
         })
     }
 
-    // Health Profile operations
-    pub fn save_health_profile(&self, profile: &HealthProfile) -> AppResult<()> {
-        // Ensure the database directory exists before attempting to save
-        if let Some(parent) = self.path.parent() {
-            std::fs::create_dir_all(parent)
-                .map_err(|e| crate::AppError::Database(format!("Failed to create database directory: {}", e)))?;
+    /// Checks out a configured connection from the pool.
+    pub fn conn(&self) -> AppResult<PooledConnection> {
+        self.pool
+            .get()
+            .map_err(|e| crate::AppError::Database(format!("Failed to connect to database: {}", e)))
+    }
+
+    // Privacy mode
+    pub fn is_encrypted(&self) -> bool {
+        !matches!(*self.key.read().unwrap_or_else(|e| e.into_inner()), KeyState::Disabled)
+    }
+
+    pub fn is_locked(&self) -> bool {
+        matches!(*self.key.read().unwrap_or_else(|e| e.into_inner()), KeyState::Locked)
+    }
+
+    /// Loads the key for an encrypted database. Fails without changing anything if the
+    /// passphrase is wrong.
+    pub fn unlock(&self, passphrase: &str) -> AppResult<()> {
+        let conn = self.conn()?;
+        let cipher = verified_cipher(&conn, passphrase)?;
+        *self.key.write().unwrap_or_else(|e| e.into_inner()) = KeyState::Unlocked(Box::new(cipher));
+        Ok(())
+    }
+
+    /// Turns privacy mode on: derives a key from `passphrase` and seals every stored
+    /// health profile and diet history note in one transaction.
+    pub fn enable_encryption(&self, passphrase: &str) -> AppResult<()> {
+        let mut key = self.key.write().unwrap_or_else(|e| e.into_inner());
+        if !matches!(*key, KeyState::Disabled) {
+            return Err(AppError::Validation("Privacy mode encryption is already enabled".to_string()));
         }
 
-        let conn = Connection::open(&self.path)
-            .map_err(|e| crate::AppError::Database(format!("Failed to connect to database: {}", e)))?;
-        
-        conn.execute(
-            "INSERT INTO health_profiles (id, user_id, age, gender, weight, height, activity_level, health_goals, dietary_preferences, dietary_restrictions, allergies, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
-             ON CONFLICT(user_id) DO UPDATE SET
-                age=excluded.age,
-                gender=excluded.gender,
-                weight=excluded.weight,
-                height=excluded.height,
-                activity_level=excluded.activity_level,
-                health_goals=excluded.health_goals,
-                dietary_preferences=excluded.dietary_preferences,
-                dietary_restrictions=excluded.dietary_restrictions,
-                allergies=excluded.allergies,
-                updated_at=excluded.updated_at",
-            (
-                profile.id.to_string(),
-                &profile.user_id,
-                profile.age,
-                &profile.gender,
-                profile.weight,
-                profile.height,
-                &profile.activity_level,
-                serde_json::to_string(&profile.health_goals).map_err(|e| crate::AppError::Database(format!("Failed to serialize health goals: {}", e)))?,
-                serde_json::to_string(&profile.dietary_preferences).map_err(|e| crate::AppError::Database(format!("Failed to serialize dietary preferences: {}", e)))?,
-                serde_json::to_string(&profile.dietary_restrictions).map_err(|e| crate::AppError::Database(format!("Failed to serialize dietary restrictions: {}", e)))?,
-                serde_json::to_string(&profile.allergies).map_err(|e| crate::AppError::Database(format!("Failed to serialize allergies: {}", e)))?,
-                profile.created_at.to_rfc3339(),
-                profile.updated_at.to_rfc3339(),
-            ),
-        ).map_err(|e| crate::AppError::Database(format!("Database execution failed: {}", e)))?;
+        let salt = FieldCipher::generate_salt();
+        let cipher = FieldCipher::derive(passphrase, &salt)?;
+
+        let conn = self.conn()?;
+        let tx = conn.unchecked_transaction()
+            .map_err(|e| AppError::Database(format!("Failed to start transaction: {}", e)))?;
+        tx.execute(
+            "INSERT INTO encryption_keys (id, salt, verifier, created_at) VALUES (1, ?1, ?2, ?3)",
+            (crypto::encode_salt(&salt), cipher.verifier()?, Utc::now().to_rfc3339()),
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+        reseal_sensitive_data(&tx, None, Some(&cipher))?;
+        tx.commit()
+            .map_err(|e| AppError::Database(format!("Failed to commit transaction: {}", e)))?;
+
+        *key = KeyState::Unlocked(Box::new(cipher));
+        compact(&conn)?;
+        log::info!("Privacy mode encryption enabled");
         Ok(())
     }
 
-    pub fn get_health_profile(&self, user_id: &str) -> AppResult<Option<HealthProfile>> {
-        let conn = Connection::open(&self.path)?;
-        let mut stmt = conn.prepare(
-            "SELECT id, user_id, age, gender, weight, height, activity_level, health_goals, dietary_preferences, dietary_restrictions, allergies, created_at, updated_at
-             FROM health_profiles WHERE user_id = ?1"
-        ).map_err(|e| crate::AppError::Database(e.to_string()))?;
+    /// Turns privacy mode off, writing every sealed value back in plaintext.
+    pub fn disable_encryption(&self, passphrase: &str) -> AppResult<()> {
+        let mut key = self.key.write().unwrap_or_else(|e| e.into_inner());
+        let conn = self.conn()?;
+        let cipher = verified_cipher(&conn, passphrase)?;
 
-        let profile = stmt
-            .query_row([user_id], |row| {
-                let health_goals_str: String = row.get(7)?;
-                let dietary_preferences_str: String = row.get(8)?;
-                let dietary_restrictions_str: String = row.get(9)?;
-                let allergies_str: String = row.get(10)?;
-
-                Ok(HealthProfile {
-                    id: Uuid::parse_str(&row.get::<_, String>(0)?)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
-                    user_id: row.get(1)?,
-                    age: row.get(2)?,
-                    gender: row.get(3)?,
-                    weight: row.get(4)?,
-                    height: row.get(5)?,
-                    activity_level: row.get(6)?,
-                    health_goals: serde_json::from_str(&health_goals_str)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
-                    dietary_preferences: serde_json::from_str(&dietary_preferences_str)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
-                    dietary_restrictions: serde_json::from_str(&dietary_restrictions_str)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
-                    allergies: serde_json::from_str(&allergies_str)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
-                    created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(11)?)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?
-                        .into(),
-                    updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(12)?)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?
-                        .into(),
-                })
-            })
-            .optional()
-            .map_err(|e| crate::AppError::Database(e.to_string()))?;
+        let tx = conn.unchecked_transaction()
+            .map_err(|e| AppError::Database(format!("Failed to start transaction: {}", e)))?;
+        reseal_sensitive_data(&tx, Some(&cipher), None)?;
+        tx.execute("DELETE FROM encryption_keys", [])
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        tx.commit()
+            .map_err(|e| AppError::Database(format!("Failed to commit transaction: {}", e)))?;
+
+        *key = KeyState::Disabled;
+        compact(&conn)?;
+        log::info!("Privacy mode encryption disabled");
+        Ok(())
+    }
+
+    /// Runs `f` with the key when privacy mode is on, or with `None` when it is off.
+    /// Fails while the database is locked.
+    fn with_cipher<T>(&self, f: impl FnOnce(Option<&FieldCipher>) -> AppResult<T>) -> AppResult<T> {
+        match &*self.key.read().unwrap_or_else(|e| e.into_inner()) {
+            KeyState::Disabled => f(None),
+            KeyState::Unlocked(cipher) => f(Some(cipher)),
+            KeyState::Locked => Err(locked_error()),
+        }
+    }
+
+    // Backup and restore
+    /// Copies the live database to `path` with SQLite's online backup API, which yields a
+    /// consistent snapshot even while other connections keep writing.
+    pub fn backup_to(&self, path: &Path) -> AppResult<()> {
+        let conn = self.conn()?;
+        conn.backup(rusqlite::DatabaseName::Main, path, None)
+            .map_err(|e| AppError::Database(format!("Backup failed: {}", e)))
+    }
+
+    /// Replaces the live database with the snapshot at `path`, then upgrades it to the
+    /// current schema. The caller checks the snapshot first (see `backup::inspect_snapshot`).
+    /// Privacy mode follows the restored data, so the database may come back locked.
+    pub fn restore_from(&self, path: &Path) -> AppResult<()> {
+        let mut key = self.key.write().unwrap_or_else(|e| e.into_inner());
+        let mut conn = self.conn()?;
+        conn.restore(
+            rusqlite::DatabaseName::Main,
+            path,
+            None::<fn(rusqlite::backup::Progress)>,
+        )
+        .map_err(|e| AppError::Database(format!("Restore failed: {}", e)))?;
+        migrations::run_migrations(&mut conn)?;
+
+        *key = if read_key_row(&conn)?.is_some() {
+            KeyState::Locked
+        } else {
+            KeyState::Disabled
+        };
+        Ok(())
+    }
 
+    // Profile operations
+    /// Household profiles in the order they were created.
+    pub fn list_profiles(&self) -> AppResult<Vec<Profile>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT user_id, display_name, created_at, updated_at FROM profiles ORDER BY created_at, display_name"
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+        let profiles = stmt
+            .query_map([], profile_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(profiles)
+    }
+
+    pub fn get_profile(&self, user_id: &str) -> AppResult<Option<Profile>> {
+        let conn = self.conn()?;
+        conn.query_row(
+            "SELECT user_id, display_name, created_at, updated_at FROM profiles WHERE user_id = ?1",
+            [user_id],
+            profile_from_row,
+        )
+        .optional()
+        .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// Creates a profile with a new random user id. Names must be unique, ignoring case.
+    pub fn create_profile(&self, display_name: &str) -> AppResult<Profile> {
+        Profile::validate_name(display_name)?;
+        let now = Utc::now();
+        let profile = Profile {
+            user_id: Uuid::new_v4().to_string(),
+            display_name: display_name.trim().to_string(),
+            created_at: now,
+            updated_at: now,
+        };
+
+        let conn = self.conn()?;
+        ensure_unique_profile_name(&conn, &profile.display_name, &profile.user_id)?;
+        conn.execute(
+            "INSERT INTO profiles (user_id, display_name, created_at, updated_at) VALUES (?1, ?2, ?3, ?4)",
+            (&profile.user_id, &profile.display_name, now.to_rfc3339(), now.to_rfc3339()),
+        ).map_err(|e| AppError::Database(e.to_string()))?;
         Ok(profile)
     }
 
+    pub fn rename_profile(&self, user_id: &str, display_name: &str) -> AppResult<Profile> {
+        Profile::validate_name(display_name)?;
+        let display_name = display_name.trim();
+
+        let conn = self.conn()?;
+        ensure_unique_profile_name(&conn, display_name, user_id)?;
+        let updated = conn.execute(
+            "UPDATE profiles SET display_name = ?1, updated_at = ?2 WHERE user_id = ?3",
+            (display_name, Utc::now().to_rfc3339(), user_id),
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+        if updated == 0 {
+            return Err(AppError::NotFound(format!("Profile {} not found", user_id)));
+        }
+
+        self.get_profile(user_id)?
+            .ok_or_else(|| AppError::NotFound(format!("Profile {} not found", user_id)))
+    }
+
+    /// Deletes a profile together with everything it owns: health profile, diet history,
+    /// recommendations, meal plans, shopping lists, pantry and user recipes. Returns false if
+    /// the profile did not exist.
+    pub fn delete_profile(&self, user_id: &str) -> AppResult<bool> {
+        let conn = self.conn()?;
+        let tx = conn.unchecked_transaction()
+            .map_err(|e| AppError::Database(format!("Failed to start transaction: {}", e)))?;
+
+        for sql in [
+            "DELETE FROM diet_history WHERE user_id = ?1",
+            "DELETE FROM diet_recommendations WHERE user_id = ?1",
+            "DELETE FROM recommendation_batches WHERE user_id = ?1",
+            // Entries follow via cascade
+            "DELETE FROM meal_plans WHERE user_id = ?1",
+            "DELETE FROM shopping_lists WHERE user_id = ?1",
+            "DELETE FROM pantry_items WHERE user_id = ?1",
+            "DELETE FROM health_profiles WHERE user_id = ?1",
+            // Tags, ingredients and the search index follow via cascades and triggers
+            "DELETE FROM recipes WHERE user_id = ?1",
+        ] {
+            tx.execute(sql, [user_id])
+                .map_err(|e| AppError::Database(format!("Failed to delete profile data: {}", e)))?;
+        }
+        let deleted = tx.execute("DELETE FROM profiles WHERE user_id = ?1", [user_id])
+            .map_err(|e| AppError::Database(format!("Failed to delete profile: {}", e)))?;
+
+        tx.commit()
+            .map_err(|e| AppError::Database(format!("Failed to commit transaction: {}", e)))?;
+        Ok(deleted > 0)
+    }
+
+    // Health Profile operations
+    pub fn save_health_profile(&self, profile: &HealthProfile) -> AppResult<()> {
+        let conn = self.conn()?;
+        register_profile(&conn, &profile.user_id)?;
+        self.with_cipher(|cipher| write_health_profile(&conn, profile, cipher))
+    }
+
+    pub fn get_health_profile(&self, user_id: &str) -> AppResult<Option<HealthProfile>> {
+        let conn = self.conn()?;
+        self.with_cipher(|cipher| {
+            Ok(read_health_profiles(&conn, Some(user_id), cipher)?.into_iter().next())
+        })
+    }
+
+    /// Every stored health profile, oldest first.
+    pub fn get_all_health_profiles(&self) -> AppResult<Vec<HealthProfile>> {
+        let conn = self.conn()?;
+        let mut profiles = self.with_cipher(|cipher| read_health_profiles(&conn, None, cipher))?;
+        profiles.sort_by_key(|p| p.created_at);
+        Ok(profiles)
+    }
+
     pub fn delete_health_profile(&self, user_id: &str) -> AppResult<()> {
-        let conn = Connection::open(&self.path)?;
+        let conn = self.conn()?;
         
         // Start a transaction to ensure data consistency
         let tx = conn.unchecked_transaction()
@@ -266,6 +337,14 @@ impl Database {
         // Then delete all diet recommendations for this user
         tx.execute("DELETE FROM diet_recommendations WHERE user_id = ?1", [user_id])
             .map_err(|e| crate::AppError::Database(format!("Failed to delete diet recommendations: {}", e)))?;
+        tx.execute("DELETE FROM recommendation_batches WHERE user_id = ?1", [user_id])
+            .map_err(|e| crate::AppError::Database(format!("Failed to delete recommendation batches: {}", e)))?;
+        tx.execute("DELETE FROM meal_plans WHERE user_id = ?1", [user_id])
+            .map_err(|e| crate::AppError::Database(format!("Failed to delete meal plans: {}", e)))?;
+        tx.execute("DELETE FROM shopping_lists WHERE user_id = ?1", [user_id])
+            .map_err(|e| crate::AppError::Database(format!("Failed to delete shopping lists: {}", e)))?;
+        tx.execute("DELETE FROM pantry_items WHERE user_id = ?1", [user_id])
+            .map_err(|e| crate::AppError::Database(format!("Failed to delete pantry: {}", e)))?;
         
         // Finally delete the health profile
         tx.execute("DELETE FROM health_profiles WHERE user_id = ?1", [user_id])
@@ -278,13 +357,189 @@ impl Database {
         Ok(())
     }
 
-    // Recipe operations
-    pub fn save_recipe(&self, recipe: &Recipe) -> AppResult<()> {
-        let conn = Connection::open(&self.path)?;
+    // Pantry operations
+    /// The user's pantry, by name.
+    pub fn get_pantry(&self, user_id: &str) -> AppResult<Vec<PantryItem>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, user_id, name, amount, unit, updated_at FROM pantry_items
+             WHERE user_id = ?1 ORDER BY normalized_name"
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+        let items = stmt
+            .query_map([user_id], pantry_item_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(items)
+    }
+
+    /// Stores how much of an ingredient is at home, replacing the amount and unit of an
+    /// existing row with the same normalized name. Returns the stored row.
+    pub fn save_pantry_item(&self, item: &PantryItem) -> AppResult<PantryItem> {
+        item.validate()?;
+        let conn = self.conn()?;
+        register_profile(&conn, &item.user_id)?;
+        let normalized = normalize_name(&item.name);
         conn.execute(
-            "INSERT INTO recipes (id, title, description, ingredients, nutritional_info_per_serving, preparation_time, difficulty_level, meal_type, recipe_instructions, cuisine_type, seasonal, tags, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            "INSERT INTO pantry_items (id, user_id, name, normalized_name, amount, unit, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT (user_id, normalized_name)
+             DO UPDATE SET name = excluded.name, amount = excluded.amount, unit = excluded.unit, updated_at = excluded.updated_at",
+            (
+                item.id.to_string(),
+                &item.user_id,
+                item.name.trim(),
+                &normalized,
+                item.amount,
+                item.unit.trim(),
+                item.updated_at.to_rfc3339(),
+            ),
+        ).map_err(|e| AppError::Database(format!("Failed to save pantry item: {}", e)))?;
+
+        conn.query_row(
+            "SELECT id, user_id, name, amount, unit, updated_at FROM pantry_items
+             WHERE user_id = ?1 AND normalized_name = ?2",
+            (&item.user_id, &normalized),
+            pantry_item_from_row,
+        ).map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    pub fn delete_pantry_item(&self, id: &str) -> AppResult<bool> {
+        let conn = self.conn()?;
+        let deleted = conn.execute("DELETE FROM pantry_items WHERE id = ?1", [id])
+            .map_err(|e| AppError::Database(format!("Failed to delete pantry item: {}", e)))?;
+        Ok(deleted > 0)
+    }
+
+    // Shopping list operations
+    /// Stores a new list together with its items, keeping their order.
+    pub fn save_shopping_list(&self, list: &ShoppingList) -> AppResult<()> {
+        ShoppingList::validate_name(&list.name)?;
+        let conn = self.conn()?;
+        register_profile(&conn, &list.user_id)?;
+        let tx = conn.unchecked_transaction()
+            .map_err(|e| AppError::Database(format!("Failed to start transaction: {}", e)))?;
+
+        tx.execute(
+            "INSERT INTO shopping_lists (id, user_id, name, meal_plan_id, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
             (
+                list.id.to_string(),
+                &list.user_id,
+                list.name.trim(),
+                list.meal_plan_id.map(|id| id.to_string()),
+                list.created_at.to_rfc3339(),
+                list.updated_at.to_rfc3339(),
+            ),
+        ).map_err(|e| AppError::Database(format!("Failed to save shopping list: {}", e)))?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO shopping_list_items (id, list_id, position, name, amount, unit, section, recipes, optional, checked)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)"
+            ).map_err(|e| AppError::Database(e.to_string()))?;
+            for (position, item) in list.items.iter().enumerate() {
+                stmt.execute((
+                    item.id.to_string(),
+                    list.id.to_string(),
+                    position as i64,
+                    &item.name,
+                    item.amount,
+                    &item.unit,
+                    item.section.as_str(),
+                    serde_json::to_string(&item.recipes)?,
+                    item.optional,
+                    item.checked,
+                )).map_err(|e| AppError::Database(format!("Failed to save shopping list item: {}", e)))?;
+            }
+        }
+
+        tx.commit()
+            .map_err(|e| AppError::Database(format!("Failed to commit transaction: {}", e)))?;
+        Ok(())
+    }
+
+    pub fn get_shopping_list(&self, id: &str) -> AppResult<Option<ShoppingList>> {
+        let conn = self.conn()?;
+        let list = conn.query_row(
+            "SELECT id, user_id, name, meal_plan_id, created_at, updated_at FROM shopping_lists WHERE id = ?1",
+            [id],
+            shopping_list_from_row,
+        )
+        .optional()
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        match list {
+            Some(mut list) => {
+                list.items = read_shopping_list_items(&conn, id)?;
+                Ok(Some(list))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// The user's lists with their items, newest first.
+    pub fn list_shopping_lists(&self, user_id: &str) -> AppResult<Vec<ShoppingList>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, user_id, name, meal_plan_id, created_at, updated_at FROM shopping_lists
+             WHERE user_id = ?1 ORDER BY created_at DESC"
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+        let mut lists = stmt
+            .query_map([user_id], shopping_list_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        for list in &mut lists {
+            list.items = read_shopping_list_items(&conn, &list.id.to_string())?;
+        }
+        Ok(lists)
+    }
+
+    /// Checks an item off (or back on) the list.
+    pub fn set_shopping_list_item_checked(&self, item_id: &str, checked: bool) -> AppResult<ShoppingListItem> {
+        let conn = self.conn()?;
+        let tx = conn.unchecked_transaction()
+            .map_err(|e| AppError::Database(format!("Failed to start transaction: {}", e)))?;
+
+        let updated = tx.execute(
+            "UPDATE shopping_list_items SET checked = ?1 WHERE id = ?2",
+            (checked, item_id),
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+        if updated == 0 {
+            return Err(AppError::NotFound(format!("Shopping list item {} not found", item_id)));
+        }
+        tx.execute(
+            "UPDATE shopping_lists SET updated_at = ?1
+             WHERE id = (SELECT list_id FROM shopping_list_items WHERE id = ?2)",
+            (Utc::now().to_rfc3339(), item_id),
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+        let item = tx.query_row(
+            &format!("SELECT {} FROM shopping_list_items WHERE id = ?1", SHOPPING_LIST_ITEM_COLUMNS),
+            [item_id],
+            shopping_list_item_from_row,
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        tx.commit()
+            .map_err(|e| AppError::Database(format!("Failed to commit transaction: {}", e)))?;
+        Ok(item)
+    }
+
+    /// Deletes a list and its items. Returns false if the list did not exist.
+    pub fn delete_shopping_list(&self, id: &str) -> AppResult<bool> {
+        let conn = self.conn()?;
+        let deleted = conn.execute("DELETE FROM shopping_lists WHERE id = ?1", [id])
+            .map_err(|e| AppError::Database(format!("Failed to delete shopping list: {}", e)))?;
+        Ok(deleted > 0)
+    }
+
+    // Recipe operations
+    pub fn save_recipe(&self, recipe: &Recipe) -> AppResult<()> {
+        let conn = self.conn()?;
+        let tx = conn.unchecked_transaction()
+            .map_err(|e| crate::AppError::Database(format!("Failed to start transaction: {}", e)))?;
+        tx.execute(
+            "INSERT INTO recipes (id, title, description, ingredients, nutritional_info_per_serving, preparation_time, difficulty_level, meal_type, recipe_instructions, cuisine_type, seasonal, tags, created_at, updated_at, user_id, origin, servings)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
+            rusqlite::params![
                 recipe.id.to_string(),
                 &recipe.title,
                 &recipe.description,
@@ -299,47 +554,130 @@ impl Database {
                 serde_json::to_string(&recipe.tags).map_err(|e| crate::AppError::Database(e.to_string()))?,
                 recipe.created_at.to_rfc3339(),
                 recipe.updated_at.to_rfc3339(),
-            ),
+                &recipe.user_id,
+                recipe.origin.as_str(),
+                recipe.servings,
+            ],
+        ).map_err(|e| crate::AppError::Database(e.to_string()))?;
+        if let Some(ref owner) = recipe.user_id {
+            register_profile(&tx, owner)?;
+        }
+
+        write_recipe_index(&tx, &recipe.id.to_string(), &recipe.ingredients, &recipe.tags)
+            .map_err(|e| crate::AppError::Database(format!("Failed to index recipe: {}", e)))?;
+
+        tx.commit()
+            .map_err(|e| crate::AppError::Database(format!("Failed to commit transaction: {}", e)))?;
+        Ok(())
+    }
+
+    /// Overwrites an existing recipe. The id, owner, origin and creation time are kept.
+    pub fn update_recipe(&self, recipe: &Recipe) -> AppResult<()> {
+        let conn = self.conn()?;
+        let tx = conn.unchecked_transaction()
+            .map_err(|e| crate::AppError::Database(format!("Failed to start transaction: {}", e)))?;
+        let updated = tx.execute(
+            "UPDATE recipes SET title = ?2, description = ?3, ingredients = ?4, nutritional_info_per_serving = ?5,
+                 preparation_time = ?6, difficulty_level = ?7, meal_type = ?8, recipe_instructions = ?9,
+                 cuisine_type = ?10, seasonal = ?11, tags = ?12, updated_at = ?13, servings = ?14
+             WHERE id = ?1",
+            rusqlite::params![
+                recipe.id.to_string(),
+                &recipe.title,
+                &recipe.description,
+                serde_json::to_string(&recipe.ingredients).map_err(|e| crate::AppError::Database(e.to_string()))?,
+                serde_json::to_string(&recipe.nutritional_info_per_serving).map_err(|e| crate::AppError::Database(e.to_string()))?,
+                recipe.preparation_time,
+                &recipe.difficulty_level,
+                &recipe.meal_type,
+                &recipe.recipe_instructions,
+                &recipe.cuisine_type,
+                recipe.seasonal,
+                serde_json::to_string(&recipe.tags).map_err(|e| crate::AppError::Database(e.to_string()))?,
+                recipe.updated_at.to_rfc3339(),
+                recipe.servings,
+            ],
         ).map_err(|e| crate::AppError::Database(e.to_string()))?;
+
+        if updated == 0 {
+            return Err(crate::AppError::NotFound(format!("Recipe {} not found", recipe.id)));
+        }
+
+        write_recipe_index(&tx, &recipe.id.to_string(), &recipe.ingredients, &recipe.tags)
+            .map_err(|e| crate::AppError::Database(format!("Failed to index recipe: {}", e)))?;
+
+        tx.commit()
+            .map_err(|e| crate::AppError::Database(format!("Failed to commit transaction: {}", e)))?;
         Ok(())
     }
 
+    /// Deletes a recipe together with its tag and ingredient rows. Returns false if it did not exist.
+    pub fn delete_recipe(&self, id: &str) -> AppResult<bool> {
+        let conn = self.conn()?;
+        let deleted = conn
+            .execute("DELETE FROM recipes WHERE id = ?1", [id])
+            .map_err(|e| crate::AppError::Database(e.to_string()))?;
+        Ok(deleted > 0)
+    }
+
+    /// Number of recipes carrying each tag, most common first. Used to build tag filters.
+    pub fn get_tag_counts(&self) -> AppResult<Vec<TagCount>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT tag, COUNT(*) FROM recipe_tags GROUP BY tag ORDER BY COUNT(*) DESC, tag"
+        ).map_err(|e| crate::AppError::Database(e.to_string()))?;
+
+        let counts = stmt
+            .query_map([], |row| {
+                Ok(TagCount {
+                    tag: row.get(0)?,
+                    count: row.get(1)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| crate::AppError::Database(e.to_string()))?;
+
+        Ok(counts)
+    }
+
     // Recommendation operations
+
+    /// Recommendations from the user's most recent batch, in the order they were generated.
     pub fn get_recommendations(&self, user_id: &str) -> AppResult<Vec<DietRecommendation>> {
-        // Get recommendations based on user profile - for now return empty list
-        // In a real implementation, this would join with health profiles to create personalized recommendations
-        let conn = Connection::open(&self.path)?;
-        let mut stmt = conn.prepare(
-            "SELECT id, user_id, title, description, ingredients, nutritional_info, preparation_time, difficulty_level, meal_type, recipe_instructions, created_at, is_personalized, relevance_score
-             FROM diet_recommendations WHERE user_id = ?1"
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, user_id, title, description, ingredients, nutritional_info, preparation_time, difficulty_level, meal_type, recipe_instructions, created_at, is_personalized, relevance_score, recipe_id, batch_id, score_breakdown, checked_constraints
+             FROM diet_recommendations
+             WHERE batch_id = (
+                 SELECT id FROM recommendation_batches WHERE user_id = ?1
+                 ORDER BY generated_at DESC LIMIT 1
+             )
+             ORDER BY position"
         ).map_err(|e| crate::AppError::Database(e.to_string()))?;
 
         let recommendations = stmt
-            .query_map([user_id], |row| {
-                let ingredients_str: String = row.get(5)?;
-                let nutritional_info_str: String = row.get(6)?;
-
-                Ok(DietRecommendation {
-                    id: Uuid::parse_str(&row.get::<_, String>(0)?)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
-                    user_id: row.get(1)?,
-                    title: row.get(2)?,
-                    description: row.get(3)?,
-                    ingredients: serde_json::from_str(&ingredients_str)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
-                    nutritional_info: serde_json::from_str(&nutritional_info_str)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
-                    preparation_time: row.get(7)?,
-                    difficulty_level: row.get(8)?,
-                    meal_type: row.get(9)?,
-                    recipe_instructions: row.get(10)?,
-                    created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(11)?)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?
-                        .into(),
-                    is_personalized: row.get(12)?,
-                    relevance_score: row.get(13)?,
-                })
-            })?
+            .query_map([user_id], recommendation_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| crate::AppError::Database(e.to_string()))?;
+
+        Ok(recommendations)
+    }
+
+    /// The recommendations of one batch in their original order, with the scores and
+    /// generation time of that batch. Recommendations purged since are left out.
+    pub fn get_recommendation_batch(&self, batch_id: &str) -> AppResult<Vec<DietRecommendation>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT r.id, r.user_id, r.title, r.description, r.ingredients, r.nutritional_info, r.preparation_time, r.difficulty_level, r.meal_type, r.recipe_instructions, b.generated_at, r.is_personalized, i.relevance_score, r.recipe_id, i.batch_id, i.score_breakdown, i.checked_constraints
+             FROM recommendation_batch_items i
+             JOIN recommendation_batches b ON b.id = i.batch_id
+             JOIN diet_recommendations r ON r.id = i.recommendation_id
+             WHERE i.batch_id = ?1
+             ORDER BY i.position"
+        ).map_err(|e| crate::AppError::Database(e.to_string()))?;
+
+        let recommendations = stmt
+            .query_map([batch_id], recommendation_from_row)?
             .collect::<std::result::Result<Vec<_>, _>>()
             .map_err(|e| crate::AppError::Database(e.to_string()))?;
 
@@ -347,47 +685,197 @@ impl Database {
     }
 
     pub fn get_recommendation_by_id(&self, id: &str) -> AppResult<Option<DietRecommendation>> {
-        let conn = Connection::open(&self.path)?;
-        let mut stmt = conn.prepare(
-            "SELECT id, user_id, title, description, ingredients, nutritional_info, preparation_time, difficulty_level, meal_type, recipe_instructions, created_at, is_personalized, relevance_score
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, user_id, title, description, ingredients, nutritional_info, preparation_time, difficulty_level, meal_type, recipe_instructions, created_at, is_personalized, relevance_score, recipe_id, batch_id, score_breakdown, checked_constraints
              FROM diet_recommendations WHERE id = ?1"
         ).map_err(|e| crate::AppError::Database(e.to_string()))?;
 
         let recommendation = stmt
-            .query_row([id], |row| {
-                let ingredients_str: String = row.get(5)?;
-                let nutritional_info_str: String = row.get(6)?;
-
-                Ok(DietRecommendation {
-                    id: Uuid::parse_str(&row.get::<_, String>(0)?)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
-                    user_id: row.get(1)?,
-                    title: row.get(2)?,
-                    description: row.get(3)?,
-                    ingredients: serde_json::from_str(&ingredients_str)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
-                    nutritional_info: serde_json::from_str(&nutritional_info_str)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
-                    preparation_time: row.get(7)?,
-                    difficulty_level: row.get(8)?,
-                    meal_type: row.get(9)?,
-                    recipe_instructions: row.get(10)?,
-                    created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(11)?)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?
-                        .into(),
-                    is_personalized: row.get(12)?,
-                    relevance_score: row.get(13)?,
-                })
-            })
+            .query_row([id], recommendation_from_row)
             .optional()
             .map_err(|e| crate::AppError::Database(e.to_string()))?;
 
         Ok(recommendation)
     }
 
+    /// Stores a freshly generated set of recommendations as a new batch and returns its id.
+    ///
+    /// Recommendation ids are stable per user and recipe, so a recipe that was recommended
+    /// before is updated in place and points at the new batch; diet history entries that
+    /// reference it keep resolving. The scores each batch was generated with are kept in
+    /// `recommendation_batch_items`, so `get_recommendation_batch` still returns older
+    /// batches as they were. `batch_id` and `created_at` are filled in on each item.
+    pub fn save_recommendation_batch(
+        &self,
+        user_id: &str,
+        recommendations: &mut [DietRecommendation],
+    ) -> AppResult<Uuid> {
+        let batch_id = Uuid::new_v4();
+        let generated_at = Utc::now();
+
+        let conn = self.conn()?;
+        let tx = conn.unchecked_transaction()
+            .map_err(|e| crate::AppError::Database(format!("Failed to start transaction: {}", e)))?;
+
+        register_profile(&tx, user_id)?;
+        tx.execute(
+            "INSERT INTO recommendation_batches (id, user_id, generated_at) VALUES (?1, ?2, ?3)",
+            (batch_id.to_string(), user_id, generated_at.to_rfc3339()),
+        ).map_err(|e| crate::AppError::Database(e.to_string()))?;
+
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO diet_recommendations (id, user_id, title, description, ingredients, nutritional_info, preparation_time, difficulty_level, meal_type, recipe_instructions, created_at, is_personalized, relevance_score, recipe_id, batch_id, position, score_breakdown, checked_constraints)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)
+                 ON CONFLICT(id) DO UPDATE SET
+                     title = excluded.title,
+                     description = excluded.description,
+                     ingredients = excluded.ingredients,
+                     nutritional_info = excluded.nutritional_info,
+                     preparation_time = excluded.preparation_time,
+                     difficulty_level = excluded.difficulty_level,
+                     meal_type = excluded.meal_type,
+                     recipe_instructions = excluded.recipe_instructions,
+                     created_at = excluded.created_at,
+                     is_personalized = excluded.is_personalized,
+                     relevance_score = excluded.relevance_score,
+                     recipe_id = excluded.recipe_id,
+                     batch_id = excluded.batch_id,
+                     position = excluded.position,
+                     score_breakdown = excluded.score_breakdown,
+                     checked_constraints = excluded.checked_constraints"
+            ).map_err(|e| crate::AppError::Database(e.to_string()))?;
+
+            let mut item_stmt = tx.prepare_cached(
+                "INSERT INTO recommendation_batch_items (batch_id, position, recommendation_id, relevance_score, score_breakdown, checked_constraints)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)"
+            ).map_err(|e| crate::AppError::Database(e.to_string()))?;
+
+            for (position, recommendation) in recommendations.iter_mut().enumerate() {
+                recommendation.batch_id = Some(batch_id);
+                let score_breakdown = serde_json::to_string(&recommendation.score_breakdown).map_err(|e| crate::AppError::Database(e.to_string()))?;
+                let checked_constraints = serde_json::to_string(&recommendation.checked_constraints).map_err(|e| crate::AppError::Database(e.to_string()))?;
+                recommendation.created_at = generated_at;
+
+                stmt.execute(rusqlite::params![
+                    recommendation.id.to_string(),
+                    &recommendation.user_id,
+                    &recommendation.title,
+                    &recommendation.description,
+                    serde_json::to_string(&recommendation.ingredients).map_err(|e| crate::AppError::Database(e.to_string()))?,
+                    serde_json::to_string(&recommendation.nutritional_info).map_err(|e| crate::AppError::Database(e.to_string()))?,
+                    recommendation.preparation_time,
+                    &recommendation.difficulty_level,
+                    &recommendation.meal_type,
+                    &recommendation.recipe_instructions,
+                    generated_at.to_rfc3339(),
+                    recommendation.is_personalized,
+                    recommendation.relevance_score,
+                    &recommendation.recipe_id,
+                    batch_id.to_string(),
+                    position as u32,
+                    &score_breakdown,
+                    &checked_constraints,
+                ]).map_err(|e| crate::AppError::Database(e.to_string()))?;
+
+                item_stmt.execute(rusqlite::params![
+                    batch_id.to_string(),
+                    position as u32,
+                    recommendation.id.to_string(),
+                    recommendation.relevance_score,
+                    &score_breakdown,
+                    &checked_constraints,
+                ]).map_err(|e| crate::AppError::Database(e.to_string()))?;
+            }
+        }
+
+        tx.commit()
+            .map_err(|e| crate::AppError::Database(format!("Failed to commit transaction: {}", e)))?;
+        Ok(batch_id)
+    }
+
+    /// Inserts a single recommendation outside any batch, keeping an existing row with the
+    /// same id. Used when restoring an account archive. Returns false if it already existed.
+    pub fn insert_recommendation(&self, recommendation: &DietRecommendation) -> AppResult<bool> {
+        let conn = self.conn()?;
+        let inserted = conn.execute(
+            "INSERT OR IGNORE INTO diet_recommendations (id, user_id, title, description, ingredients, nutritional_info, preparation_time, difficulty_level, meal_type, recipe_instructions, created_at, is_personalized, relevance_score, recipe_id, batch_id, position, score_breakdown, checked_constraints)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, NULL, 0, ?15, ?16)",
+            rusqlite::params![
+                recommendation.id.to_string(),
+                &recommendation.user_id,
+                &recommendation.title,
+                &recommendation.description,
+                serde_json::to_string(&recommendation.ingredients).map_err(|e| crate::AppError::Database(e.to_string()))?,
+                serde_json::to_string(&recommendation.nutritional_info).map_err(|e| crate::AppError::Database(e.to_string()))?,
+                recommendation.preparation_time,
+                &recommendation.difficulty_level,
+                &recommendation.meal_type,
+                &recommendation.recipe_instructions,
+                recommendation.created_at.to_rfc3339(),
+                recommendation.is_personalized,
+                recommendation.relevance_score,
+                &recommendation.recipe_id,
+                serde_json::to_string(&recommendation.score_breakdown).map_err(|e| crate::AppError::Database(e.to_string()))?,
+                serde_json::to_string(&recommendation.checked_constraints).map_err(|e| crate::AppError::Database(e.to_string()))?,
+            ],
+        ).map_err(|e| crate::AppError::Database(e.to_string()))?;
+        Ok(inserted > 0)
+    }
+
+    /// Drops the user's batches that are older than `max_age` or fall outside the newest
+    /// `keep_batches`; the newest batch is always kept. Recommendations left without a
+    /// batch are deleted unless a diet history entry still references them.
+    /// Returns the number of recommendations deleted.
+    pub fn purge_expired_recommendations(
+        &self,
+        user_id: &str,
+        max_age: chrono::Duration,
+        keep_batches: u32,
+    ) -> AppResult<usize> {
+        let cutoff = (Utc::now() - max_age).to_rfc3339();
+
+        let conn = self.conn()?;
+        let tx = conn.unchecked_transaction()
+            .map_err(|e| crate::AppError::Database(format!("Failed to start transaction: {}", e)))?;
+
+        // Deleting a batch sets batch_id to NULL on its recommendations (ON DELETE SET NULL)
+        tx.execute(
+            "DELETE FROM recommendation_batches
+             WHERE user_id = ?1
+               AND id NOT IN (
+                   SELECT id FROM recommendation_batches WHERE user_id = ?1
+                   ORDER BY generated_at DESC LIMIT 1
+               )
+               AND (
+                   generated_at < ?2
+                   OR id NOT IN (
+                       SELECT id FROM recommendation_batches WHERE user_id = ?1
+                       ORDER BY generated_at DESC LIMIT ?3
+                   )
+               )",
+            (user_id, &cutoff, keep_batches),
+        ).map_err(|e| crate::AppError::Database(format!("Failed to delete expired batches: {}", e)))?;
+
+        let deleted = tx.execute(
+            "DELETE FROM diet_recommendations
+             WHERE user_id = ?1
+               AND batch_id IS NULL
+               AND id NOT IN (SELECT diet_item_id FROM diet_history)",
+            [user_id],
+        ).map_err(|e| crate::AppError::Database(format!("Failed to delete expired recommendations: {}", e)))?;
+
+        tx.commit()
+            .map_err(|e| crate::AppError::Database(format!("Failed to commit transaction: {}", e)))?;
+        Ok(deleted)
+    }
+
     // Diet history operations
     pub fn log_diet_entry(&self, entry: &DietHistory) -> AppResult<()> {
-        let conn = Connection::open(&self.path)?;
+        let notes = self.with_cipher(|cipher| seal_notes(cipher, entry.notes.clone()))?;
+        let conn = self.conn()?;
+        register_profile(&conn, &entry.user_id)?;
         conn.execute(
             "INSERT INTO diet_history (id, user_id, diet_item_id, date_attempted, rating, notes, was_prepared, meal_type, created_at, updated_at)
              VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
@@ -397,7 +885,7 @@ impl Database {
                 &entry.diet_item_id.to_string(),
                 entry.date_attempted.format("%Y-%m-%d").to_string(),
                 &entry.rating,
-                &entry.notes,
+                &notes,
                 entry.was_prepared,
                 &entry.meal_type,
                 entry.created_at.to_rfc3339(),
@@ -407,6 +895,16 @@ impl Database {
         Ok(())
     }
 
+    pub fn diet_entry_exists(&self, id: &str) -> AppResult<bool> {
+        let conn = self.conn()?;
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM diet_history WHERE id = ?1",
+            [id],
+            |row| row.get(0),
+        ).map_err(|e| crate::AppError::Database(e.to_string()))?;
+        Ok(count > 0)
+    }
+
     pub fn get_diet_history(
         &self,
         user_id: &str,
@@ -416,7 +914,7 @@ impl Database {
         offset: Option<u32>,
         meal_type: Option<&str>, // New parameter for filtering by meal type
     ) -> AppResult<Vec<DietHistory>> {
-        let conn = Connection::open(&self.path)?;
+        let conn = self.conn()?;
         // Build the query with optional date filtering
         let mut query = "SELECT id, user_id, diet_item_id, date_attempted, rating, notes, was_prepared, meal_type, created_at, updated_at FROM diet_history WHERE user_id = ?1".to_string();
         let mut param_counter = 2; // Start from 2 since user_id is parameter 1
@@ -444,6 +942,7 @@ impl Database {
             query.push_str(&format!(" OFFSET {}", off));
         }
 
+        // The SQL varies with the filters and paging, so keep it out of the statement cache
         let mut stmt = conn
             .prepare(&query)
             .map_err(|e| crate::AppError::Database(e.to_string()))?;
@@ -466,34 +965,11 @@ impl Database {
         let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|b| b.as_ref()).collect();
 
         let history = stmt
-            .query_map(param_refs.as_slice(), |row| {
-                Ok(DietHistory {
-                    id: Uuid::parse_str(&row.get::<_, String>(0)?)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
-                    user_id: row.get(1)?,
-                    diet_item_id: Uuid::parse_str(&row.get::<_, String>(2)?)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
-                    date_attempted: chrono::NaiveDate::parse_from_str(
-                        &row.get::<_, String>(3)?,
-                        "%Y-%m-%d",
-                    )
-                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
-                    rating: row.get(4)?,
-                    notes: row.get(5)?,
-                    was_prepared: row.get(6)?,
-                    meal_type: row.get(7)?,
-                    created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(8)?)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?
-                        .into(),
-                    updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(9)?)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?
-                        .into(),
-                })
-            })?
+            .query_map(param_refs.as_slice(), diet_history_from_row)?
             .collect::<std::result::Result<Vec<_>, _>>()
             .map_err(|e| crate::AppError::Database(e.to_string()))?;
 
-        Ok(history)
+        self.with_cipher(|cipher| open_history_notes(cipher, history))
     }
 
     pub fn get_diet_history_count(
@@ -503,7 +979,7 @@ impl Database {
         end_date: Option<&str>,
         meal_type: Option<&str>,
     ) -> AppResult<u32> {
-        let conn = Connection::open(&self.path)?;
+        let conn = self.conn()?;
         let mut query = "SELECT COUNT(*) FROM diet_history WHERE user_id = ?1".to_string();
         let mut param_counter = 2; // Start from 2 since user_id is parameter 1
 
@@ -542,6 +1018,39 @@ impl Database {
         Ok(count as u32)
     }
 
+    /// Diet history for export, oldest first. `None` filters are not applied, so
+    /// `user_id: None` returns every user's entries. Dates are inclusive.
+    pub fn get_diet_history_between(
+        &self,
+        user_id: Option<&str>,
+        start_date: Option<chrono::NaiveDate>,
+        end_date: Option<chrono::NaiveDate>,
+    ) -> AppResult<Vec<DietHistory>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, user_id, diet_item_id, date_attempted, rating, notes, was_prepared, meal_type, created_at, updated_at
+             FROM diet_history
+             WHERE (?1 IS NULL OR user_id = ?1)
+               AND (?2 IS NULL OR date_attempted >= ?2)
+               AND (?3 IS NULL OR date_attempted <= ?3)
+             ORDER BY date_attempted, created_at"
+        ).map_err(|e| crate::AppError::Database(e.to_string()))?;
+
+        let history = stmt
+            .query_map(
+                (
+                    user_id,
+                    start_date.map(|d| d.format("%Y-%m-%d").to_string()),
+                    end_date.map(|d| d.format("%Y-%m-%d").to_string()),
+                ),
+                diet_history_from_row,
+            )?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| crate::AppError::Database(e.to_string()))?;
+
+        self.with_cipher(|cipher| open_history_notes(cipher, history))
+    }
+
     pub fn update_diet_entry(
         &self,
         id: &str,
@@ -549,7 +1058,8 @@ impl Database {
         notes: Option<String>,
         was_prepared: Option<bool>,
     ) -> AppResult<()> {
-        let conn = Connection::open(&self.path)?;
+        let notes = self.with_cipher(|cipher| seal_notes(cipher, notes))?;
+        let conn = self.conn()?;
         
         // Build query and parameter vector based on provided values
         let mut query = String::from("UPDATE diet_history SET updated_at = ?");
@@ -580,7 +1090,7 @@ impl Database {
     }
 
     pub fn delete_diet_entry(&self, id: &str) -> AppResult<()> {
-        let conn = Connection::open(&self.path)?;
+        let conn = self.conn()?;
         
         let rows_affected = conn.execute(
             "DELETE FROM diet_history WHERE id = ?1",
@@ -591,159 +1101,443 @@ impl Database {
             return Err(crate::AppError::Database(format!("Diet entry with id {} not found", id)));
         }
 
+        // The planned meal it was logged from counts as not eaten again
+        conn.execute(
+            "UPDATE meal_plan_entries SET diet_history_id = NULL WHERE diet_history_id = ?1",
+            [id],
+        ).map_err(|e| crate::AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    // Meal plan operations
+    /// Stores a new plan together with its entries.
+    pub fn save_meal_plan(&self, plan: &MealPlan) -> AppResult<()> {
+        MealPlan::validate_name(&plan.name)?;
+        let conn = self.conn()?;
+        register_profile(&conn, &plan.user_id)?;
+        let tx = conn.unchecked_transaction()
+            .map_err(|e| AppError::Database(format!("Failed to start transaction: {}", e)))?;
+
+        tx.execute(
+            "INSERT INTO meal_plans (id, user_id, name, start_date, end_date, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            (
+                plan.id.to_string(),
+                &plan.user_id,
+                plan.name.trim(),
+                plan.start_date.format("%Y-%m-%d").to_string(),
+                plan.end_date.format("%Y-%m-%d").to_string(),
+                plan.created_at.to_rfc3339(),
+                plan.updated_at.to_rfc3339(),
+            ),
+        ).map_err(|e| AppError::Database(format!("Failed to save meal plan: {}", e)))?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO meal_plan_entries (id, plan_id, date, meal_type, recipe_id, diet_history_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)"
+            ).map_err(|e| AppError::Database(e.to_string()))?;
+            for entry in &plan.entries {
+                stmt.execute((
+                    entry.id.to_string(),
+                    plan.id.to_string(),
+                    entry.date.format("%Y-%m-%d").to_string(),
+                    &entry.meal_type,
+                    entry.recipe_id.to_string(),
+                    entry.diet_history_id.map(|id| id.to_string()),
+                )).map_err(|e| AppError::Database(format!("Failed to save meal plan entry: {}", e)))?;
+            }
+        }
+
+        tx.commit()
+            .map_err(|e| AppError::Database(format!("Failed to commit transaction: {}", e)))?;
+        Ok(())
+    }
+
+    pub fn get_meal_plan(&self, id: &str) -> AppResult<Option<MealPlan>> {
+        let conn = self.conn()?;
+        let plan = conn.query_row(
+            "SELECT id, user_id, name, start_date, end_date, created_at, updated_at FROM meal_plans WHERE id = ?1",
+            [id],
+            meal_plan_from_row,
+        )
+        .optional()
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        match plan {
+            Some(mut plan) => {
+                plan.entries = read_meal_plan_entries(&conn, id)?;
+                Ok(Some(plan))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// The user's plans with their entries, latest start date first.
+    pub fn list_meal_plans(&self, user_id: &str) -> AppResult<Vec<MealPlan>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, user_id, name, start_date, end_date, created_at, updated_at FROM meal_plans
+             WHERE user_id = ?1 ORDER BY start_date DESC, created_at DESC"
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+        let mut plans = stmt
+            .query_map([user_id], meal_plan_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        for plan in &mut plans {
+            plan.entries = read_meal_plan_entries(&conn, &plan.id.to_string())?;
+        }
+        Ok(plans)
+    }
+
+    pub fn rename_meal_plan(&self, id: &str, name: &str) -> AppResult<MealPlan> {
+        MealPlan::validate_name(name)?;
+        let conn = self.conn()?;
+        let updated = conn.execute(
+            "UPDATE meal_plans SET name = ?1, updated_at = ?2 WHERE id = ?3",
+            (name.trim(), Utc::now().to_rfc3339(), id),
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+        if updated == 0 {
+            return Err(AppError::NotFound(format!("Meal plan {} not found", id)));
+        }
+
+        self.get_meal_plan(id)?
+            .ok_or_else(|| AppError::NotFound(format!("Meal plan {} not found", id)))
+    }
+
+    /// Swaps the recipe of one planned meal. Meals already marked as eaten keep theirs.
+    pub fn update_meal_plan_entry(&self, entry_id: &str, recipe_id: &str) -> AppResult<MealPlanEntry> {
+        let conn = self.conn()?;
+        let tx = conn.unchecked_transaction()
+            .map_err(|e| AppError::Database(format!("Failed to start transaction: {}", e)))?;
+
+        let entry = read_meal_plan_entry(&tx, entry_id)?
+            .ok_or_else(|| AppError::NotFound(format!("Meal plan entry {} not found", entry_id)))?;
+        if entry.is_eaten() {
+            return Err(AppError::Validation(
+                "This meal was already eaten; its recipe can no longer change".to_string(),
+            ));
+        }
+        tx.execute(
+            "UPDATE meal_plan_entries SET recipe_id = ?1 WHERE id = ?2",
+            (recipe_id, entry_id),
+        ).map_err(|e| match e {
+            rusqlite::Error::SqliteFailure(ref err, _)
+                if err.code == rusqlite::ErrorCode::ConstraintViolation =>
+            {
+                AppError::NotFound(format!("Recipe {} not found", recipe_id))
+            }
+            e => AppError::Database(e.to_string()),
+        })?;
+        tx.execute(
+            "UPDATE meal_plans SET updated_at = ?1 WHERE id = ?2",
+            (Utc::now().to_rfc3339(), entry.plan_id.to_string()),
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+        let entry = read_meal_plan_entry(&tx, entry_id)?
+            .ok_or_else(|| AppError::NotFound(format!("Meal plan entry {} not found", entry_id)))?;
+
+        tx.commit()
+            .map_err(|e| AppError::Database(format!("Failed to commit transaction: {}", e)))?;
+        Ok(entry)
+    }
+
+    /// Deletes a plan and its entries. Diet history logged from it is kept. Returns false
+    /// if the plan did not exist.
+    pub fn delete_meal_plan(&self, id: &str) -> AppResult<bool> {
+        let conn = self.conn()?;
+        let deleted = conn.execute("DELETE FROM meal_plans WHERE id = ?1", [id])
+            .map_err(|e| AppError::Database(format!("Failed to delete meal plan: {}", e)))?;
+        Ok(deleted > 0)
+    }
+
+    /// Logs a planned meal to the diet history and links the entry to the new row, in one
+    /// transaction. Fails if the entry was already marked as eaten.
+    pub fn mark_meal_plan_entry_eaten(
+        &self,
+        entry_id: &str,
+        rating: Option<u8>,
+        notes: Option<String>,
+    ) -> AppResult<DietHistory> {
+        let conn = self.conn()?;
+        let tx = conn.unchecked_transaction()
+            .map_err(|e| AppError::Database(format!("Failed to start transaction: {}", e)))?;
+
+        let entry = read_meal_plan_entry(&tx, entry_id)?
+            .ok_or_else(|| AppError::NotFound(format!("Meal plan entry {} not found", entry_id)))?;
+        if entry.is_eaten() {
+            return Err(AppError::Validation("This meal is already marked as eaten".to_string()));
+        }
+        let user_id: String = tx.query_row(
+            "SELECT user_id FROM meal_plans WHERE id = ?1",
+            [entry.plan_id.to_string()],
+            |row| row.get(0),
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        let now = Utc::now();
+        let history = DietHistory {
+            id: Uuid::new_v4(),
+            user_id,
+            diet_item_id: entry.recipe_id,
+            date_attempted: entry.date,
+            rating,
+            notes,
+            was_prepared: true,
+            meal_type: entry.meal_type.clone(),
+            created_at: now,
+            updated_at: now,
+        };
+        history.validate()?;
+
+        let sealed_notes = self.with_cipher(|cipher| seal_notes(cipher, history.notes.clone()))?;
+        tx.execute(
+            "INSERT INTO diet_history (id, user_id, diet_item_id, date_attempted, rating, notes, was_prepared, meal_type, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            (
+                history.id.to_string(),
+                &history.user_id,
+                history.diet_item_id.to_string(),
+                history.date_attempted.format("%Y-%m-%d").to_string(),
+                &history.rating,
+                &sealed_notes,
+                history.was_prepared,
+                &history.meal_type,
+                history.created_at.to_rfc3339(),
+                history.updated_at.to_rfc3339(),
+            ),
+        ).map_err(|e| AppError::Database(format!("Failed to log eaten meal: {}", e)))?;
+        tx.execute(
+            "UPDATE meal_plan_entries SET diet_history_id = ?1 WHERE id = ?2",
+            (history.id.to_string(), entry_id),
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+
+        tx.commit()
+            .map_err(|e| AppError::Database(format!("Failed to commit transaction: {}", e)))?;
+        Ok(history)
+    }
+
+    // App state
+    /// Reads a value from the `app_state` key-value table.
+    pub fn get_app_state(&self, key: &str) -> AppResult<Option<String>> {
+        let conn = self.conn()?;
+        conn.query_row("SELECT value FROM app_state WHERE key = ?1", [key], |row| row.get(0))
+            .optional()
+            .map_err(|e| crate::AppError::Database(e.to_string()))
+    }
+
+    pub fn set_app_state(&self, key: &str, value: &str) -> AppResult<()> {
+        let conn = self.conn()?;
+        conn.execute(
+            "INSERT INTO app_state (key, value) VALUES (?1, ?2)
+             ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+            [key, value],
+        ).map_err(|e| crate::AppError::Database(e.to_string()))?;
         Ok(())
     }
 
     // Recipe operations
+    pub fn count_recipes(&self) -> AppResult<u32> {
+        let conn = self.conn()?;
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM recipes", [], |row| row.get(0))
+            .map_err(|e| crate::AppError::Database(e.to_string()))?;
+        Ok(count as u32)
+    }
+
+    /// Loads every stored recipe. This is the corpus the recommendation engine works from.
+    pub fn get_all_recipes(&self) -> AppResult<Vec<Recipe>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, title, description, ingredients, nutritional_info_per_serving, preparation_time, difficulty_level, meal_type, recipe_instructions, cuisine_type, seasonal, tags, created_at, updated_at, user_id, origin, servings
+             FROM recipes ORDER BY created_at, title"
+        ).map_err(|e| crate::AppError::Database(e.to_string()))?;
+
+        let recipes = stmt
+            .query_map([], recipe_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| crate::AppError::Database(e.to_string()))?;
+
+        Ok(recipes)
+    }
+
     pub fn get_recipe_by_id(&self, id: &str) -> AppResult<Option<Recipe>> {
-        let conn = Connection::open(&self.path)?;
-        let mut stmt = conn.prepare(
-            "SELECT id, title, description, ingredients, nutritional_info_per_serving, preparation_time, difficulty_level, meal_type, recipe_instructions, cuisine_type, seasonal, tags, created_at, updated_at
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, title, description, ingredients, nutritional_info_per_serving, preparation_time, difficulty_level, meal_type, recipe_instructions, cuisine_type, seasonal, tags, created_at, updated_at, user_id, origin, servings
              FROM recipes WHERE id = ?1"
         ).map_err(|e| crate::AppError::Database(e.to_string()))?;
 
         let recipe = stmt
-            .query_row([id], |row| {
-                let ingredients_str: String = row.get(3)?;
-                let nutritional_info_str: String = row.get(4)?;
-                let tags_str: String = row.get(11)?;
-
-                Ok(Recipe {
-                    id: Uuid::parse_str(&row.get::<_, String>(0)?)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
-                    title: row.get(1)?,
-                    description: row.get(2)?,
-                    ingredients: serde_json::from_str(&ingredients_str)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
-                    nutritional_info_per_serving: serde_json::from_str(&nutritional_info_str)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
-                    preparation_time: row.get(5)?,
-                    difficulty_level: row.get(6)?,
-                    meal_type: row.get(7)?,
-                    recipe_instructions: row.get(8)?,
-                    cuisine_type: row.get(9)?,
-                    seasonal: row.get(10)?,
-                    tags: serde_json::from_str(&tags_str)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
-                    created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(12)?)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?
-                        .into(),
-                    updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(13)?)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?
-                        .into(),
-                })
-            })
+            .query_row([id], recipe_from_row)
             .optional()
             .map_err(|e| crate::AppError::Database(e.to_string()))?;
 
         Ok(recipe)
     }
 
-    pub fn search_recipes(
-        &self,
-        query: Option<&str>,
-        tags: Option<Vec<&str>>,
-        exclude_ingredients: Option<Vec<&str>>,
-        max_preparation_time: Option<u32>,
-        difficulty_level: Option<&str>,
-        meal_type: Option<&str>,
-        limit: Option<u32>,
-        offset: Option<u32>,
-    ) -> AppResult<Vec<Recipe>> {
-        let conn = Connection::open(&self.path)?;
-        let mut sql = "SELECT id, title, description, ingredients, nutritional_info_per_serving, preparation_time, difficulty_level, meal_type, recipe_instructions, cuisine_type, seasonal, tags, created_at, updated_at FROM recipes WHERE 1=1".to_string();
+    /// Id of a recipe with this title (ignoring ASCII case) among the recipes `visible_to`
+    /// can see, or among all recipes when no user is given. Used to detect duplicates on import.
+    pub fn find_recipe_by_title(&self, title: &str, visible_to: Option<&str>) -> AppResult<Option<Uuid>> {
+        let conn = self.conn()?;
+        let id: Option<String> = conn
+            .query_row(
+                "SELECT id FROM recipes
+                 WHERE title = ?1 COLLATE NOCASE AND (?2 IS NULL OR user_id IS NULL OR user_id = ?2)
+                 LIMIT 1",
+                (title.trim(), visible_to),
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| crate::AppError::Database(e.to_string()))?;
+
+        Ok(id.map(|id| Uuid::parse_str(&id)).transpose()?)
+    }
+
+    pub fn search_recipes(&self, filter: &RecipeSearchFilter) -> AppResult<Vec<RecipeSearchHit>> {
+        let conn = self.conn()?;
         let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![];
 
-        // Add search query condition
-        if let Some(q) = query {
-            sql.push_str(" AND (title LIKE ? OR description LIKE ?)");
-            let search_pattern = format!("%{}%", q);
-            params.push(Box::new(search_pattern.clone()));
-            params.push(Box::new(search_pattern));
-        }
-
-        // Add tag filtering - this is a simplified approach as full tag matching with JSON is complex in SQLite
-        if let Some(tag_list) = tags {
-            for tag in tag_list {
-                sql.push_str(" AND tags LIKE ?");
-                let tag_pattern = format!("%{}%", tag);
-                params.push(Box::new(tag_pattern));
+        // Split the query into FTS phrases (3+ characters) and short LIKE terms
+        let mut match_terms = Vec::new();
+        let mut like_terms = Vec::new();
+        for term in filter.query.as_deref().unwrap_or("").split_whitespace() {
+            let term = term.replace('"', "");
+            match term.chars().count() {
+                0 => {}
+                1 | 2 => like_terms.push(term),
+                _ => match_terms.push(format!("\"{}\"", term)),
             }
         }
+        let use_fts = !match_terms.is_empty() || !like_terms.is_empty();
+        let ranked = !match_terms.is_empty();
+
+        let mut sql = format!(
+            "SELECT r.id, r.title, r.description, r.ingredients, r.nutritional_info_per_serving, r.preparation_time, r.difficulty_level, r.meal_type, r.recipe_instructions, r.cuisine_type, r.seasonal, r.tags, r.created_at, r.updated_at, r.user_id, r.origin, r.servings, {}
+             FROM recipes r{} WHERE 1=1",
+            if ranked {
+                // bm25 is lower-is-better; negate it so a higher score means a better match.
+                // Column weights favour title over description, ingredients and instructions.
+                "-bm25(recipes_fts, 0.0, 10.0, 4.0, 4.0, 1.0), \
+                 highlight(recipes_fts, 1, '<mark>', '</mark>'), \
+                 snippet(recipes_fts, -1, '<mark>', '</mark>', '…', 16)"
+            } else {
+                "NULL, NULL, NULL"
+            },
+            if use_fts {
+                " JOIN recipes_fts ON recipes_fts.recipe_id = r.id"
+            } else {
+                ""
+            },
+        );
 
-        // Add exclude ingredients filtering
-        if let Some(ingredients) = exclude_ingredients {
-            for ingredient in ingredients {
-                sql.push_str(" AND ingredients NOT LIKE ?");
-                let ingr_pattern = format!("%{}%", ingredient);
-                params.push(Box::new(ingr_pattern));
+        // Add search query condition
+        if ranked {
+            sql.push_str(" AND recipes_fts MATCH ?");
+            params.push(Box::new(match_terms.join(" ")));
+        }
+        for term in like_terms {
+            sql.push_str(
+                " AND (recipes_fts.title LIKE ? ESCAPE '\\' OR recipes_fts.description LIKE ? ESCAPE '\\' \
+                 OR recipes_fts.ingredient_names LIKE ? ESCAPE '\\' OR recipes_fts.recipe_instructions LIKE ? ESCAPE '\\')",
+            );
+            let pattern = format!("%{}%", escape_like(&term));
+            for _ in 0..4 {
+                params.push(Box::new(pattern.clone()));
             }
         }
 
+        // Tags and ingredients are matched exactly against the normalized join tables
+        let all_tags = normalize_names(&filter.all_tags);
+        if !all_tags.is_empty() {
+            sql.push_str(&format!(
+                " AND r.id IN (SELECT recipe_id FROM recipe_tags WHERE tag IN ({}) GROUP BY recipe_id HAVING COUNT(*) = ?)",
+                placeholders(all_tags.len())
+            ));
+            let count = all_tags.len() as i64;
+            params.extend(all_tags.into_iter().map(|t| Box::new(t) as Box<dyn rusqlite::ToSql>));
+            params.push(Box::new(count));
+        }
+
+        let any_tags = normalize_names(&filter.any_tags);
+        if !any_tags.is_empty() {
+            sql.push_str(&format!(
+                " AND EXISTS (SELECT 1 FROM recipe_tags t WHERE t.recipe_id = r.id AND t.tag IN ({}))",
+                placeholders(any_tags.len())
+            ));
+            params.extend(any_tags.into_iter().map(|t| Box::new(t) as Box<dyn rusqlite::ToSql>));
+        }
+
+        for ingredient in normalize_names(&filter.include_ingredients) {
+            sql.push_str(" AND EXISTS (SELECT 1 FROM recipe_ingredients i WHERE i.recipe_id = r.id AND i.normalized_name = ?)");
+            params.push(Box::new(ingredient));
+        }
+
+        let exclude_ingredients = normalize_names(&filter.exclude_ingredients);
+        if !exclude_ingredients.is_empty() {
+            sql.push_str(&format!(
+                " AND NOT EXISTS (SELECT 1 FROM recipe_ingredients i WHERE i.recipe_id = r.id AND i.normalized_name IN ({}))",
+                placeholders(exclude_ingredients.len())
+            ));
+            params.extend(
+                exclude_ingredients
+                    .into_iter()
+                    .map(|i| Box::new(i) as Box<dyn rusqlite::ToSql>),
+            );
+        }
+
+        // Other users' recipes are hidden; bundled and imported recipes without an owner are shared
+        if let Some(ref user_id) = filter.visible_to {
+            sql.push_str(" AND (r.user_id IS NULL OR r.user_id = ?)");
+            params.push(Box::new(user_id.clone()));
+        }
+
         // Add max preparation time
-        if let Some(max_time) = max_preparation_time {
-            sql.push_str(" AND preparation_time <= ?");
+        if let Some(max_time) = filter.max_preparation_time {
+            sql.push_str(" AND r.preparation_time <= ?");
             params.push(Box::new(max_time as i32));
         }
 
         // Add difficulty level
-        if let Some(diff_level) = difficulty_level {
-            sql.push_str(" AND difficulty_level = ?");
-            params.push(Box::new(diff_level));
+        if let Some(ref diff_level) = filter.difficulty_level {
+            sql.push_str(" AND r.difficulty_level = ?");
+            params.push(Box::new(diff_level.clone()));
         }
 
         // Add meal type
-        if let Some(m_type) = meal_type {
-            sql.push_str(" AND meal_type = ?");
-            params.push(Box::new(m_type));
+        if let Some(ref m_type) = filter.meal_type {
+            sql.push_str(" AND r.meal_type = ?");
+            params.push(Box::new(m_type.clone()));
         }
 
-        sql.push_str(" ORDER BY title");
+        if ranked {
+            sql.push_str(" ORDER BY bm25(recipes_fts, 0.0, 10.0, 4.0, 4.0, 1.0), r.title");
+        } else {
+            sql.push_str(" ORDER BY r.title");
+        }
 
         // Add limit and offset
-        if let Some(lim) = limit {
+        if let Some(lim) = filter.limit {
             sql.push_str(&format!(" LIMIT {}", lim));
         }
-        if let Some(off) = offset {
-            let limit_part = if limit.is_some() { "" } else { " LIMIT -1" };
+        if let Some(off) = filter.offset {
+            let limit_part = if filter.limit.is_some() { "" } else { " LIMIT -1" };
             sql.push_str(limit_part);
             sql.push_str(&format!(" OFFSET {}", off));
         }
 
         let params_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|b| b.as_ref()).collect();
+        // The SQL varies with the filters and paging, so keep it out of the statement cache
         let mut stmt = conn
             .prepare(&sql)
             .map_err(|e| crate::AppError::Database(e.to_string()))?;
 
         let recipes = stmt
             .query_map(params_refs.as_slice(), |row| {
-                let ingredients_str: String = row.get(3)?;
-                let nutritional_info_str: String = row.get(4)?;
-                let tags_str: String = row.get(11)?;
-
-                Ok(Recipe {
-                    id: Uuid::parse_str(&row.get::<_, String>(0)?)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
-                    title: row.get(1)?,
-                    description: row.get(2)?,
-                    ingredients: serde_json::from_str(&ingredients_str)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
-                    nutritional_info_per_serving: serde_json::from_str(&nutritional_info_str)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
-                    preparation_time: row.get(5)?,
-                    difficulty_level: row.get(6)?,
-                    meal_type: row.get(7)?,
-                    recipe_instructions: row.get(8)?,
-                    cuisine_type: row.get(9)?,
-                    seasonal: row.get(10)?,
-                    tags: serde_json::from_str(&tags_str)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
-                    created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(12)?)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?
-                        .into(),
-                    updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(13)?)
-                        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?
-                        .into(),
+                Ok(RecipeSearchHit {
+                    recipe: recipe_from_row(row)?,
+                    score: row.get(17)?,
+                    title_highlight: row.get(18)?,
+                    snippet: row.get(19)?,
                 })
             })?
             .collect::<std::result::Result<Vec<_>, _>>()
@@ -752,3 +1546,1240 @@ impl Database {
         Ok(recipes)
     }
 }
+
+/// Rewrites the `recipe_tags` and `recipe_ingredients` rows for one recipe.
+pub(crate) fn write_recipe_index(
+    conn: &Connection,
+    recipe_id: &str,
+    ingredients: &[RecipeIngredient],
+    tags: &[String],
+) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM recipe_tags WHERE recipe_id = ?1", [recipe_id])?;
+    conn.execute("DELETE FROM recipe_ingredients WHERE recipe_id = ?1", [recipe_id])?;
+
+    let mut tag_stmt = conn.prepare_cached(
+        "INSERT OR IGNORE INTO recipe_tags (recipe_id, tag) VALUES (?1, ?2)",
+    )?;
+    for tag in normalize_names(tags) {
+        tag_stmt.execute((recipe_id, tag))?;
+    }
+
+    let mut ingredient_stmt = conn.prepare_cached(
+        "INSERT INTO recipe_ingredients (recipe_id, position, name, normalized_name, amount, unit, optional)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+    )?;
+    for (position, ingredient) in ingredients.iter().enumerate() {
+        ingredient_stmt.execute((
+            recipe_id,
+            position as i64,
+            &ingredient.name,
+            normalize_name(&ingredient.name),
+            ingredient.amount,
+            &ingredient.unit,
+            ingredient.optional,
+        ))?;
+    }
+
+    Ok(())
+}
+
+fn normalize_names(names: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = names
+        .iter()
+        .map(|name| normalize_name(name))
+        .filter(|name| !name.is_empty())
+        .collect();
+    normalized.sort();
+    normalized.dedup();
+    normalized
+}
+
+fn placeholders(count: usize) -> String {
+    vec!["?"; count].join(", ")
+}
+
+/// Maps the first 16 columns of a `recipes` row, in table order, onto a `Recipe`.
+fn recipe_from_row(row: &rusqlite::Row) -> rusqlite::Result<Recipe> {
+    let ingredients_str: String = row.get(3)?;
+    let nutritional_info_str: String = row.get(4)?;
+    let tags_str: String = row.get(11)?;
+
+    Ok(Recipe {
+        id: Uuid::parse_str(&row.get::<_, String>(0)?)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+        title: row.get(1)?,
+        description: row.get(2)?,
+        ingredients: serde_json::from_str(&ingredients_str)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+        nutritional_info_per_serving: serde_json::from_str(&nutritional_info_str)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+        preparation_time: row.get(5)?,
+        difficulty_level: row.get(6)?,
+        meal_type: row.get(7)?,
+        recipe_instructions: row.get(8)?,
+        cuisine_type: row.get(9)?,
+        seasonal: row.get(10)?,
+        tags: serde_json::from_str(&tags_str)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+        created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(12)?)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?
+            .into(),
+        updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(13)?)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?
+            .into(),
+        user_id: row.get(14)?,
+        origin: RecipeOrigin::parse(&row.get::<_, String>(15)?)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+        servings: row.get(16)?,
+    })
+}
+
+/// Makes sure `user_id` is listed as a profile, named after the id until renamed. Data can
+/// be written for any user id, and this keeps the profile list complete.
+fn register_profile(conn: &Connection, user_id: &str) -> AppResult<()> {
+    let now = Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT OR IGNORE INTO profiles (user_id, display_name, created_at, updated_at) VALUES (?1, ?1, ?2, ?2)",
+        (user_id, now),
+    ).map_err(|e| AppError::Database(e.to_string()))?;
+    Ok(())
+}
+
+fn ensure_unique_profile_name(conn: &Connection, display_name: &str, user_id: &str) -> AppResult<()> {
+    let taken: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM profiles WHERE display_name = ?1 COLLATE NOCASE AND user_id != ?2",
+        (display_name, user_id),
+        |row| row.get(0),
+    ).map_err(|e| AppError::Database(e.to_string()))?;
+    if taken > 0 {
+        return Err(AppError::Validation(format!(
+            "A profile named '{}' already exists",
+            display_name
+        )));
+    }
+    Ok(())
+}
+
+fn profile_from_row(row: &rusqlite::Row) -> rusqlite::Result<Profile> {
+    Ok(Profile {
+        user_id: row.get(0)?,
+        display_name: row.get(1)?,
+        created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(2)?)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?
+            .into(),
+        updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(3)?)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?
+            .into(),
+    })
+}
+
+fn meal_plan_from_row(row: &rusqlite::Row) -> rusqlite::Result<MealPlan> {
+    Ok(MealPlan {
+        id: Uuid::parse_str(&row.get::<_, String>(0)?)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+        user_id: row.get(1)?,
+        name: row.get(2)?,
+        start_date: chrono::NaiveDate::parse_from_str(&row.get::<_, String>(3)?, "%Y-%m-%d")
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+        end_date: chrono::NaiveDate::parse_from_str(&row.get::<_, String>(4)?, "%Y-%m-%d")
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+        entries: vec![],
+        created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(5)?)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?
+            .into(),
+        updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(6)?)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?
+            .into(),
+    })
+}
+
+const MEAL_PLAN_ENTRY_COLUMNS: &str =
+    "e.id, e.plan_id, e.date, e.meal_type, e.recipe_id, COALESCE(r.title, ''), e.diet_history_id
+     FROM meal_plan_entries e LEFT JOIN recipes r ON r.id = e.recipe_id";
+
+fn read_meal_plan_entries(conn: &Connection, plan_id: &str) -> AppResult<Vec<MealPlanEntry>> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT {} WHERE e.plan_id = ?1
+         ORDER BY e.date, CASE e.meal_type WHEN 'breakfast' THEN 0 WHEN 'lunch' THEN 1 WHEN 'dinner' THEN 2 WHEN 'snack' THEN 3 ELSE 4 END",
+        MEAL_PLAN_ENTRY_COLUMNS
+    )).map_err(|e| AppError::Database(e.to_string()))?;
+    let entries = stmt
+        .query_map([plan_id], meal_plan_entry_from_row)?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| AppError::Database(e.to_string()))?;
+    Ok(entries)
+}
+
+fn read_meal_plan_entry(conn: &Connection, entry_id: &str) -> AppResult<Option<MealPlanEntry>> {
+    conn.query_row(
+        &format!("SELECT {} WHERE e.id = ?1", MEAL_PLAN_ENTRY_COLUMNS),
+        [entry_id],
+        meal_plan_entry_from_row,
+    )
+    .optional()
+    .map_err(|e| AppError::Database(e.to_string()))
+}
+
+fn meal_plan_entry_from_row(row: &rusqlite::Row) -> rusqlite::Result<MealPlanEntry> {
+    let diet_history_id: Option<String> = row.get(6)?;
+    Ok(MealPlanEntry {
+        id: Uuid::parse_str(&row.get::<_, String>(0)?)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+        plan_id: Uuid::parse_str(&row.get::<_, String>(1)?)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+        date: chrono::NaiveDate::parse_from_str(&row.get::<_, String>(2)?, "%Y-%m-%d")
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+        meal_type: row.get(3)?,
+        recipe_id: Uuid::parse_str(&row.get::<_, String>(4)?)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+        recipe_title: row.get(5)?,
+        diet_history_id: diet_history_id
+            .map(|id| Uuid::parse_str(&id))
+            .transpose()
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+    })
+}
+
+fn pantry_item_from_row(row: &rusqlite::Row) -> rusqlite::Result<PantryItem> {
+    Ok(PantryItem {
+        id: Uuid::parse_str(&row.get::<_, String>(0)?)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+        user_id: row.get(1)?,
+        name: row.get(2)?,
+        amount: row.get(3)?,
+        unit: row.get(4)?,
+        updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(5)?)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?
+            .into(),
+    })
+}
+
+fn shopping_list_from_row(row: &rusqlite::Row) -> rusqlite::Result<ShoppingList> {
+    let meal_plan_id: Option<String> = row.get(3)?;
+    Ok(ShoppingList {
+        id: Uuid::parse_str(&row.get::<_, String>(0)?)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+        user_id: row.get(1)?,
+        name: row.get(2)?,
+        meal_plan_id: meal_plan_id
+            .map(|id| Uuid::parse_str(&id))
+            .transpose()
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+        items: vec![],
+        created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(4)?)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?
+            .into(),
+        updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(5)?)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?
+            .into(),
+    })
+}
+
+const SHOPPING_LIST_ITEM_COLUMNS: &str =
+    "id, list_id, name, amount, unit, section, recipes, optional, checked";
+
+fn read_shopping_list_items(conn: &Connection, list_id: &str) -> AppResult<Vec<ShoppingListItem>> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT {} FROM shopping_list_items WHERE list_id = ?1 ORDER BY position",
+        SHOPPING_LIST_ITEM_COLUMNS
+    )).map_err(|e| AppError::Database(e.to_string()))?;
+    let items = stmt
+        .query_map([list_id], shopping_list_item_from_row)?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| AppError::Database(e.to_string()))?;
+    Ok(items)
+}
+
+fn shopping_list_item_from_row(row: &rusqlite::Row) -> rusqlite::Result<ShoppingListItem> {
+    Ok(ShoppingListItem {
+        id: Uuid::parse_str(&row.get::<_, String>(0)?)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+        list_id: Uuid::parse_str(&row.get::<_, String>(1)?)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+        name: row.get(2)?,
+        amount: row.get(3)?,
+        unit: row.get(4)?,
+        section: StoreSection::parse(&row.get::<_, String>(5)?)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+        recipes: serde_json::from_str(&row.get::<_, String>(6)?)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+        optional: row.get(7)?,
+        checked: row.get(8)?,
+    })
+}
+
+fn locked_error() -> AppError {
+    AppError::Encryption("Privacy mode is on; unlock the database with your passphrase first".to_string())
+}
+
+/// Salt and passphrase verifier, present only while privacy mode is on.
+fn read_key_row(conn: &Connection) -> AppResult<Option<(String, String)>> {
+    conn.query_row(
+        "SELECT salt, verifier FROM encryption_keys WHERE id = 1",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .optional()
+    .map_err(|e| AppError::Database(e.to_string()))
+}
+
+fn verified_cipher(conn: &Connection, passphrase: &str) -> AppResult<FieldCipher> {
+    let (salt, verifier) = read_key_row(conn)?
+        .ok_or_else(|| AppError::Validation("Privacy mode encryption is not enabled".to_string()))?;
+    let cipher = FieldCipher::derive(passphrase, &crypto::decode_salt(&salt)?)?;
+    cipher.check_verifier(&verifier)?;
+    Ok(cipher)
+}
+
+/// Rewrites every health profile and diet history note, opening values with `from` and
+/// sealing them with `to` (`None` meaning plaintext).
+fn reseal_sensitive_data(
+    conn: &Connection,
+    from: Option<&FieldCipher>,
+    to: Option<&FieldCipher>,
+) -> AppResult<()> {
+    for profile in read_health_profiles(conn, None, from)? {
+        write_health_profile(conn, &profile, to)?;
+    }
+
+    let notes: Vec<(String, String)> = {
+        let mut stmt = conn
+            .prepare("SELECT id, notes FROM diet_history WHERE notes IS NOT NULL")
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        rows
+    };
+    for (id, note) in notes {
+        let note = open_note(from, note)?;
+        conn.execute(
+            "UPDATE diet_history SET notes = ?1 WHERE id = ?2",
+            (seal_notes(to, Some(note))?, id),
+        ).map_err(|e| AppError::Database(e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Rewrites the database file so pages that held plaintext before encryption (or
+/// ciphertext before decryption) do not linger in free pages or the WAL.
+fn compact(conn: &Connection) -> AppResult<()> {
+    conn.execute_batch("VACUUM; PRAGMA wal_checkpoint(TRUNCATE);")
+        .map_err(|e| AppError::Database(format!("Failed to compact database: {}", e)))
+}
+
+/// Inserts or updates a profile. With a cipher the sensitive fields are sealed into
+/// `sealed` and the plaintext columns hold placeholders.
+fn write_health_profile(
+    conn: &Connection,
+    profile: &HealthProfile,
+    cipher: Option<&FieldCipher>,
+) -> AppResult<()> {
+    let fields = SensitiveProfileFields {
+        age: profile.age,
+        gender: profile.gender.clone(),
+        weight: profile.weight,
+        height: profile.height,
+        activity_level: profile.activity_level.clone(),
+        health_goals: profile.health_goals.clone(),
+        dietary_preferences: profile.dietary_preferences.clone(),
+        dietary_restrictions: profile.dietary_restrictions.clone(),
+        allergies: profile.allergies.clone(),
+    };
+    let (fields, sealed) = match cipher {
+        Some(cipher) => {
+            let sealed = cipher.seal(&serde_json::to_string(&fields)?)?;
+            let placeholder = SensitiveProfileFields {
+                age: 0,
+                gender: String::new(),
+                weight: 0.0,
+                height: 0.0,
+                activity_level: String::new(),
+                health_goals: vec![],
+                dietary_preferences: vec![],
+                dietary_restrictions: vec![],
+                allergies: vec![],
+            };
+            (placeholder, Some(sealed))
+        }
+        None => (fields, None),
+    };
+
+    conn.execute(
+        "INSERT INTO health_profiles (id, user_id, age, gender, weight, height, activity_level, health_goals, dietary_preferences, dietary_restrictions, allergies, created_at, updated_at, sealed)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+         ON CONFLICT(user_id) DO UPDATE SET
+            age=excluded.age,
+            gender=excluded.gender,
+            weight=excluded.weight,
+            height=excluded.height,
+            activity_level=excluded.activity_level,
+            health_goals=excluded.health_goals,
+            dietary_preferences=excluded.dietary_preferences,
+            dietary_restrictions=excluded.dietary_restrictions,
+            allergies=excluded.allergies,
+            updated_at=excluded.updated_at,
+            sealed=excluded.sealed",
+        (
+            profile.id.to_string(),
+            &profile.user_id,
+            fields.age,
+            &fields.gender,
+            fields.weight,
+            fields.height,
+            &fields.activity_level,
+            serde_json::to_string(&fields.health_goals).map_err(|e| AppError::Database(format!("Failed to serialize health goals: {}", e)))?,
+            serde_json::to_string(&fields.dietary_preferences).map_err(|e| AppError::Database(format!("Failed to serialize dietary preferences: {}", e)))?,
+            serde_json::to_string(&fields.dietary_restrictions).map_err(|e| AppError::Database(format!("Failed to serialize dietary restrictions: {}", e)))?,
+            serde_json::to_string(&fields.allergies).map_err(|e| AppError::Database(format!("Failed to serialize allergies: {}", e)))?,
+            profile.created_at.to_rfc3339(),
+            profile.updated_at.to_rfc3339(),
+            sealed,
+        ),
+    ).map_err(|e| AppError::Database(format!("Database execution failed: {}", e)))?;
+    Ok(())
+}
+
+/// Reads one user's profile, or every profile when `user_id` is `None`, opening sealed
+/// profiles with `cipher`.
+fn read_health_profiles(
+    conn: &Connection,
+    user_id: Option<&str>,
+    cipher: Option<&FieldCipher>,
+) -> AppResult<Vec<HealthProfile>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, user_id, age, gender, weight, height, activity_level, health_goals, dietary_preferences, dietary_restrictions, allergies, created_at, updated_at, sealed
+         FROM health_profiles WHERE (?1 IS NULL OR user_id = ?1)"
+    ).map_err(|e| AppError::Database(e.to_string()))?;
+
+    let rows = stmt
+        .query_map([user_id], |row| {
+            let health_goals_str: String = row.get(7)?;
+            let dietary_preferences_str: String = row.get(8)?;
+            let dietary_restrictions_str: String = row.get(9)?;
+            let allergies_str: String = row.get(10)?;
+
+            let profile = HealthProfile {
+                id: Uuid::parse_str(&row.get::<_, String>(0)?)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+                user_id: row.get(1)?,
+                age: row.get(2)?,
+                gender: row.get(3)?,
+                weight: row.get(4)?,
+                height: row.get(5)?,
+                activity_level: row.get(6)?,
+                health_goals: serde_json::from_str(&health_goals_str)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+                dietary_preferences: serde_json::from_str(&dietary_preferences_str)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+                dietary_restrictions: serde_json::from_str(&dietary_restrictions_str)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+                allergies: serde_json::from_str(&allergies_str)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+                created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(11)?)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?
+                    .into(),
+                updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(12)?)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?
+                    .into(),
+            };
+            Ok((profile, row.get::<_, Option<String>>(13)?))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+    rows.into_iter()
+        .map(|(mut profile, sealed)| {
+            let Some(sealed) = sealed else {
+                return Ok(profile);
+            };
+            let cipher = cipher.ok_or_else(locked_error)?;
+            let fields: SensitiveProfileFields = serde_json::from_str(&cipher.open(&sealed)?)?;
+            profile.age = fields.age;
+            profile.gender = fields.gender;
+            profile.weight = fields.weight;
+            profile.height = fields.height;
+            profile.activity_level = fields.activity_level;
+            profile.health_goals = fields.health_goals;
+            profile.dietary_preferences = fields.dietary_preferences;
+            profile.dietary_restrictions = fields.dietary_restrictions;
+            profile.allergies = fields.allergies;
+            Ok(profile)
+        })
+        .collect()
+}
+
+fn seal_notes(cipher: Option<&FieldCipher>, notes: Option<String>) -> AppResult<Option<String>> {
+    match (cipher, notes) {
+        (Some(cipher), Some(notes)) => Ok(Some(cipher.seal(&notes)?)),
+        (_, notes) => Ok(notes),
+    }
+}
+
+fn open_note(cipher: Option<&FieldCipher>, note: String) -> AppResult<String> {
+    if !crypto::is_sealed(&note) {
+        return Ok(note);
+    }
+    cipher.ok_or_else(locked_error)?.open(&note)
+}
+
+fn open_history_notes(
+    cipher: Option<&FieldCipher>,
+    history: Vec<DietHistory>,
+) -> AppResult<Vec<DietHistory>> {
+    history
+        .into_iter()
+        .map(|mut entry| {
+            entry.notes = entry.notes.map(|note| open_note(cipher, note)).transpose()?;
+            Ok(entry)
+        })
+        .collect()
+}
+
+/// Maps a `diet_history` row selected in table order.
+fn diet_history_from_row(row: &rusqlite::Row) -> rusqlite::Result<DietHistory> {
+    Ok(DietHistory {
+        id: Uuid::parse_str(&row.get::<_, String>(0)?)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+        user_id: row.get(1)?,
+        diet_item_id: Uuid::parse_str(&row.get::<_, String>(2)?)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+        date_attempted: chrono::NaiveDate::parse_from_str(&row.get::<_, String>(3)?, "%Y-%m-%d")
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+        rating: row.get(4)?,
+        notes: row.get(5)?,
+        was_prepared: row.get(6)?,
+        meal_type: row.get(7)?,
+        created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(8)?)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?
+            .into(),
+        updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(9)?)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?
+            .into(),
+    })
+}
+
+/// Maps a `diet_recommendations` row selected with the recommendation column list.
+fn recommendation_from_row(row: &rusqlite::Row) -> rusqlite::Result<DietRecommendation> {
+    let ingredients_str: String = row.get(4)?;
+    let nutritional_info_str: String = row.get(5)?;
+    let batch_id: Option<String> = row.get(14)?;
+    let score_breakdown_str: String = row.get(15)?;
+    let checked_constraints_str: String = row.get(16)?;
+
+    Ok(DietRecommendation {
+        id: Uuid::parse_str(&row.get::<_, String>(0)?)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+        user_id: row.get(1)?,
+        title: row.get(2)?,
+        description: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+        ingredients: serde_json::from_str(&ingredients_str)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+        nutritional_info: serde_json::from_str(&nutritional_info_str)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+        preparation_time: row.get(6)?,
+        difficulty_level: row.get(7)?,
+        meal_type: row.get(8)?,
+        recipe_instructions: row.get(9)?,
+        created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(10)?)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?
+            .into(),
+        is_personalized: row.get(11)?,
+        relevance_score: row.get(12)?,
+        recipe_id: row.get(13)?,
+        batch_id: batch_id
+            .map(|id| Uuid::parse_str(&id))
+            .transpose()
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+        score_breakdown: serde_json::from_str(&score_breakdown_str)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+        checked_constraints: serde_json::from_str(&checked_constraints_str)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+    })
+}
+
+/// Escapes LIKE wildcards so user input is matched literally (used with `ESCAPE '\'`).
+fn escape_like(term: &str) -> String {
+    term.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn test_db() -> (TempDir, Database) {
+        let dir = TempDir::new().unwrap();
+        let db = Database::new(dir.path().join("data.db")).unwrap();
+        (dir, db)
+    }
+
+    fn test_recipe(title: &str, ingredients: &[&str], instructions: &str) -> Recipe {
+        Recipe {
+            id: Uuid::new_v4(),
+            title: title.to_string(),
+            description: format!("{}的做法", title),
+            ingredients: ingredients
+                .iter()
+                .map(|name| RecipeIngredient {
+                    name: name.to_string(),
+                    amount: 100.0,
+                    unit: "g".to_string(),
+                    optional: false,
+                })
+                .collect(),
+            nutritional_info_per_serving: NutritionalInfo {
+                calories: 300.0,
+                protein: 20.0,
+                carbs: 30.0,
+                fat: 10.0,
+                fiber: 5.0,
+            },
+            servings: 1,
+            preparation_time: 20,
+            difficulty_level: "easy".to_string(),
+            meal_type: "lunch".to_string(),
+            recipe_instructions: instructions.to_string(),
+            cuisine_type: None,
+            seasonal: false,
+            tags: vec![],
+            user_id: None,
+            origin: RecipeOrigin::Bundled,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    fn search(db: &Database, query: &str) -> Vec<RecipeSearchHit> {
+        db.search_recipes(&RecipeSearchFilter {
+            query: Some(query.to_string()),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn titles(hits: &[RecipeSearchHit]) -> Vec<String> {
+        let mut titles: Vec<String> = hits.iter().map(|h| h.recipe.title.clone()).collect();
+        titles.sort();
+        titles
+    }
+
+    #[test]
+    fn test_pooled_connections_are_configured() {
+        let (_dir, db) = test_db();
+
+        // Hold two connections at once so the pool has to open a second one
+        let first = db.conn().unwrap();
+        let second = db.conn().unwrap();
+
+        for conn in [&first, &second] {
+            let foreign_keys: i64 = conn
+                .query_row("PRAGMA foreign_keys", [], |row| row.get(0))
+                .unwrap();
+            assert_eq!(foreign_keys, 1);
+
+            let journal_mode: String = conn
+                .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+                .unwrap();
+            assert_eq!(journal_mode, "wal");
+        }
+    }
+
+    #[test]
+    fn test_concurrent_writes_share_the_pool() {
+        let (_dir, db) = test_db();
+        let db = std::sync::Arc::new(db);
+
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let db = db.clone();
+                std::thread::spawn(move || {
+                    let mut profile = HealthProfile::new(format!("user_{}", i));
+                    profile.age = 30;
+                    profile.weight = 70.0;
+                    profile.height = 175.0;
+                    db.save_health_profile(&profile).unwrap();
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        for i in 0..4 {
+            assert!(db.get_health_profile(&format!("user_{}", i)).unwrap().is_some());
+        }
+    }
+
+    #[test]
+    fn test_full_text_search_ranks_title_matches_first() {
+        let (_dir, db) = test_db();
+        db.save_recipe(&test_recipe("蔬菜炒豆腐", &["豆腐", "西兰花"], "将西兰花焯水。"))
+            .unwrap();
+        db.save_recipe(&test_recipe("西兰花炒虾仁", &["虾仁", "西兰花"], "翻炒即可。"))
+            .unwrap();
+        db.save_recipe(&test_recipe("番茄炒蛋", &["番茄", "鸡蛋"], "打散鸡蛋。"))
+            .unwrap();
+
+        let hits = search(&db, "西兰花");
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].recipe.title, "西兰花炒虾仁");
+        assert!(hits[0].score.unwrap() > hits[1].score.unwrap());
+        assert_eq!(
+            hits[0].title_highlight.as_deref(),
+            Some("<mark>西兰花</mark>炒虾仁")
+        );
+        assert!(hits[1].snippet.as_deref().unwrap().contains("<mark>西兰花</mark>"));
+    }
+
+    #[test]
+    fn test_short_terms_match_ingredient_names() {
+        let (_dir, db) = test_db();
+        db.save_recipe(&test_recipe("蔬菜炒豆腐", &["豆腐", "西兰花"], "煎至金黄。"))
+            .unwrap();
+        db.save_recipe(&test_recipe("番茄炒蛋", &["番茄", "鸡蛋"], "打散。"))
+            .unwrap();
+
+        let hits = search(&db, "鸡蛋");
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].recipe.title, "番茄炒蛋");
+        assert!(hits[0].score.is_none());
+
+        // A percent sign is matched literally rather than as a wildcard
+        assert!(search(&db, "%").is_empty());
+    }
+
+    #[test]
+    fn test_search_index_follows_updates_and_deletes() {
+        let (_dir, db) = test_db();
+        let recipe = test_recipe("番茄炒蛋", &["番茄", "鸡蛋"], "打散。");
+        db.save_recipe(&recipe).unwrap();
+
+        let conn = db.conn().unwrap();
+        conn.execute(
+            "UPDATE recipes SET title = '西红柿炒鸡蛋', description = '家常做法' WHERE id = ?1",
+            [recipe.id.to_string()],
+        )
+        .unwrap();
+        assert_eq!(search(&db, "西红柿").len(), 1);
+        assert!(search(&db, "番茄炒蛋").is_empty());
+
+        conn.execute("DELETE FROM recipes WHERE id = ?1", [recipe.id.to_string()])
+            .unwrap();
+        assert!(search(&db, "西红柿").is_empty());
+    }
+
+    #[test]
+    fn test_tag_filters_match_exactly() {
+        let (_dir, db) = test_db();
+        let mut salad = test_recipe("鸡肉沙拉", &["鸡胸肉"], "拌匀。");
+        salad.tags = vec!["low_carb".to_string(), "high_protein".to_string()];
+        let mut tofu = test_recipe("蔬菜炒豆腐", &["豆腐"], "翻炒。");
+        tofu.tags = vec!["vegetarian".to_string(), "low_carb".to_string()];
+        db.save_recipe(&salad).unwrap();
+        db.save_recipe(&tofu).unwrap();
+
+        let by = |all: &[&str], any: &[&str]| {
+            titles(
+                &db.search_recipes(&RecipeSearchFilter {
+                    all_tags: all.iter().map(|t| t.to_string()).collect(),
+                    any_tags: any.iter().map(|t| t.to_string()).collect(),
+                    ..Default::default()
+                })
+                .unwrap(),
+            )
+        };
+
+        assert!(by(&["low"], &[]).is_empty());
+        assert_eq!(by(&["low_carb"], &[]).len(), 2);
+        assert_eq!(by(&["LOW_CARB", "vegetarian"], &[]), vec!["蔬菜炒豆腐"]);
+        assert_eq!(by(&[], &["vegetarian", "high_protein"]).len(), 2);
+
+        let counts = db.get_tag_counts().unwrap();
+        assert_eq!(counts[0].tag, "low_carb");
+        assert_eq!(counts[0].count, 2);
+        assert_eq!(counts.len(), 3);
+    }
+
+    #[test]
+    fn test_ingredient_filters_match_normalized_names() {
+        let (_dir, db) = test_db();
+        db.save_recipe(&test_recipe("Scrambled eggs", &["Egg", "Milk"], "Whisk."))
+            .unwrap();
+        db.save_recipe(&test_recipe("Roast eggplant", &["eggplant", "olive oil"], "Roast."))
+            .unwrap();
+
+        let hits = db
+            .search_recipes(&RecipeSearchFilter {
+                exclude_ingredients: vec!["egg".to_string()],
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(titles(&hits), vec!["Roast eggplant"]);
+
+        let hits = db
+            .search_recipes(&RecipeSearchFilter {
+                include_ingredients: vec!["  Olive   Oil ".to_string()],
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(titles(&hits), vec!["Roast eggplant"]);
+    }
+
+    #[test]
+    fn test_deleting_a_recipe_cascades_to_join_tables() {
+        let (_dir, db) = test_db();
+        let mut recipe = test_recipe("番茄炒蛋", &["番茄", "鸡蛋"], "打散。");
+        recipe.tags = vec!["quick".to_string()];
+        db.save_recipe(&recipe).unwrap();
+
+        let conn = db.conn().unwrap();
+        conn.execute("DELETE FROM recipes WHERE id = ?1", [recipe.id.to_string()])
+            .unwrap();
+        let remaining: i64 = conn
+            .query_row(
+                "SELECT (SELECT COUNT(*) FROM recipe_tags) + (SELECT COUNT(*) FROM recipe_ingredients)",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(remaining, 0);
+    }
+
+    #[test]
+    fn test_update_recipe_reindexes_and_keeps_owner() {
+        let (_dir, db) = test_db();
+        let mut recipe = test_recipe("番茄炒蛋", &["番茄", "鸡蛋"], "打散。");
+        recipe.user_id = Some("user_1".to_string());
+        recipe.origin = RecipeOrigin::User;
+        db.save_recipe(&recipe).unwrap();
+
+        recipe.title = "青椒炒蛋".to_string();
+        recipe.ingredients.remove(0);
+        recipe.tags = vec!["quick".to_string()];
+        db.update_recipe(&recipe).unwrap();
+
+        let stored = db.get_recipe_by_id(&recipe.id.to_string()).unwrap().unwrap();
+        assert_eq!(stored.title, "青椒炒蛋");
+        assert_eq!(stored.user_id.as_deref(), Some("user_1"));
+        assert_eq!(stored.origin, RecipeOrigin::User);
+        assert_eq!(titles(&search(&db, "青椒炒")), vec!["青椒炒蛋"]);
+        let with_tomato = db
+            .search_recipes(&RecipeSearchFilter {
+                include_ingredients: vec!["番茄".to_string()],
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(with_tomato.is_empty());
+
+        let missing = test_recipe("不存在", &["水"], "无。");
+        assert!(matches!(db.update_recipe(&missing), Err(crate::AppError::NotFound(_))));
+
+        assert!(db.delete_recipe(&recipe.id.to_string()).unwrap());
+        assert!(!db.delete_recipe(&recipe.id.to_string()).unwrap());
+    }
+
+    #[test]
+    fn test_search_hides_other_users_recipes() {
+        let (_dir, db) = test_db();
+        db.save_recipe(&test_recipe("蔬菜沙拉", &["生菜"], "拌匀。")).unwrap();
+        for owner in ["user_1", "user_2"] {
+            let mut recipe = test_recipe(&format!("{}的沙拉", owner), &["生菜"], "拌匀。");
+            recipe.user_id = Some(owner.to_string());
+            recipe.origin = RecipeOrigin::User;
+            db.save_recipe(&recipe).unwrap();
+        }
+
+        let hits = db
+            .search_recipes(&RecipeSearchFilter {
+                visible_to: Some("user_1".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(titles(&hits), vec!["user_1的沙拉", "蔬菜沙拉"]);
+    }
+
+    fn test_recommendation(user_id: &str, recipe_id: &str, score: f64) -> DietRecommendation {
+        DietRecommendation {
+            id: DietRecommendation::stable_id(user_id, recipe_id),
+            user_id: user_id.to_string(),
+            title: format!("菜谱 {}", recipe_id),
+            description: String::new(),
+            ingredients: vec![],
+            nutritional_info: NutritionalInfo {
+                calories: 300.0,
+                protein: 20.0,
+                carbs: 30.0,
+                fat: 10.0,
+                fiber: 5.0,
+            },
+            preparation_time: 20,
+            difficulty_level: "easy".to_string(),
+            meal_type: "lunch".to_string(),
+            recipe_instructions: String::new(),
+            created_at: chrono::Utc::now(),
+            is_personalized: false,
+            relevance_score: score,
+            recipe_id: Some(recipe_id.to_string()),
+            batch_id: None,
+            score_breakdown: vec![ScoreComponent {
+                rule: "difficulty".to_string(),
+                delta: score,
+                reason: "做法简单".to_string(),
+            }],
+            checked_constraints: CheckedConstraints {
+                allergies: vec!["花生".to_string()],
+                dietary_restrictions: vec![],
+            },
+        }
+    }
+
+    fn log_history(db: &Database, user_id: &str, diet_item_id: Uuid) {
+        db.log_diet_entry(&DietHistory {
+            id: Uuid::new_v4(),
+            user_id: user_id.to_string(),
+            diet_item_id,
+            date_attempted: chrono::Utc::now().date_naive(),
+            rating: Some(4),
+            notes: None,
+            was_prepared: true,
+            meal_type: "lunch".to_string(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_saved_batches_resolve_by_id() {
+        let (_dir, db) = test_db();
+        let mut batch = vec![
+            test_recommendation("user_1", "a", 0.9),
+            test_recommendation("user_1", "b", 0.5),
+        ];
+        let batch_id = db.save_recommendation_batch("user_1", &mut batch).unwrap();
+        assert!(batch.iter().all(|r| r.batch_id == Some(batch_id)));
+
+        let stored = db
+            .get_recommendation_by_id(&batch[1].id.to_string())
+            .unwrap()
+            .expect("saved recommendation should resolve");
+        assert_eq!(stored.recipe_id.as_deref(), Some("b"));
+        assert_eq!(stored.batch_id, Some(batch_id));
+        assert_eq!(stored.score_breakdown, batch[1].score_breakdown);
+        assert_eq!(stored.checked_constraints, batch[1].checked_constraints);
+        assert_eq!(stored.relevance_score, 0.5);
+
+        let latest = db.get_recommendations("user_1").unwrap();
+        let recipes: Vec<_> = latest.iter().filter_map(|r| r.recipe_id.clone()).collect();
+        assert_eq!(recipes, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_regenerating_keeps_recommendation_ids() {
+        let (_dir, db) = test_db();
+        let mut first = vec![test_recommendation("user_1", "a", 0.9)];
+        db.save_recommendation_batch("user_1", &mut first).unwrap();
+        let mut second = vec![
+            test_recommendation("user_1", "b", 0.8),
+            test_recommendation("user_1", "a", 0.7),
+        ];
+        let second_batch = db.save_recommendation_batch("user_1", &mut second).unwrap();
+
+        assert_eq!(first[0].id, second[1].id);
+        let stored = db
+            .get_recommendation_by_id(&first[0].id.to_string())
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.batch_id, Some(second_batch));
+        assert_eq!(stored.relevance_score, 0.7);
+
+        // The first batch is still returned as it was generated
+        let first_batch = db
+            .get_recommendation_batch(&first[0].batch_id.unwrap().to_string())
+            .unwrap();
+        assert_eq!(first_batch.len(), 1);
+        assert_eq!(first_batch[0].id, first[0].id);
+        assert_eq!(first_batch[0].relevance_score, 0.9);
+        assert_eq!(first_batch[0].batch_id, first[0].batch_id);
+        let second_scores: Vec<f64> = db
+            .get_recommendation_batch(&second_batch.to_string())
+            .unwrap()
+            .iter()
+            .map(|r| r.relevance_score)
+            .collect();
+        assert_eq!(second_scores, vec![0.8, 0.7]);
+    }
+
+    #[test]
+    fn test_purge_keeps_latest_batch_and_history_references() {
+        let (_dir, db) = test_db();
+        let mut old = vec![
+            test_recommendation("user_1", "eaten", 0.9),
+            test_recommendation("user_1", "ignored", 0.8),
+        ];
+        db.save_recommendation_batch("user_1", &mut old).unwrap();
+        log_history(&db, "user_1", old[0].id);
+        let mut latest = vec![test_recommendation("user_1", "new", 0.7)];
+        db.save_recommendation_batch("user_1", &mut latest).unwrap();
+
+        let deleted = db
+            .purge_expired_recommendations("user_1", chrono::Duration::days(7), 1)
+            .unwrap();
+
+        assert_eq!(deleted, 1);
+        let eaten = db.get_recommendation_by_id(&old[0].id.to_string()).unwrap().unwrap();
+        assert_eq!(eaten.batch_id, None);
+        assert!(db.get_recommendation_by_id(&old[1].id.to_string()).unwrap().is_none());
+        assert_eq!(db.get_recommendations("user_1").unwrap().len(), 1);
+
+        // An expired max age never drops the newest batch
+        db.purge_expired_recommendations("user_1", chrono::Duration::zero(), 5)
+            .unwrap();
+        assert!(db.get_recommendation_by_id(&latest[0].id.to_string()).unwrap().is_some());
+    }
+
+    fn raw_profile_and_note(db: &Database) -> (String, String) {
+        let conn = db.conn().unwrap();
+        let allergies: String = conn
+            .query_row("SELECT allergies FROM health_profiles", [], |row| row.get(0))
+            .unwrap();
+        let notes: String = conn
+            .query_row("SELECT notes FROM diet_history", [], |row| row.get(0))
+            .unwrap();
+        (allergies, notes)
+    }
+
+    #[test]
+    fn test_privacy_mode_encrypts_and_decrypts() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("data.db");
+        let db = Database::new(&path).unwrap();
+
+        let mut profile = HealthProfile::new("user_1".to_string());
+        profile.weight = 72.5;
+        profile.allergies = vec!["花生".to_string()];
+        db.save_health_profile(&profile).unwrap();
+        db.log_diet_entry(&DietHistory {
+            id: Uuid::new_v4(),
+            user_id: "user_1".to_string(),
+            diet_item_id: Uuid::new_v4(),
+            date_attempted: chrono::Utc::now().date_naive(),
+            rating: None,
+            notes: Some("胃不舒服".to_string()),
+            was_prepared: true,
+            meal_type: "dinner".to_string(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        })
+        .unwrap();
+
+        db.enable_encryption("correct horse").unwrap();
+        let (allergies, notes) = raw_profile_and_note(&db);
+        assert_eq!(allergies, "[]");
+        assert!(crypto::is_sealed(&notes));
+        assert_eq!(db.get_health_profile("user_1").unwrap().unwrap().weight, 72.5);
+        drop(db);
+
+        // A fresh process starts locked until the passphrase is entered
+        let db = Database::new(&path).unwrap();
+        assert!(db.is_encrypted() && db.is_locked());
+        assert!(db.get_health_profile("user_1").is_err());
+        assert!(db.unlock("wrong horse").is_err());
+        db.unlock("correct horse").unwrap();
+        let restored = db.get_health_profile("user_1").unwrap().unwrap();
+        assert_eq!(restored.allergies, vec!["花生".to_string()]);
+        let history = db.get_diet_history("user_1", None, None, None, None, None).unwrap();
+        assert_eq!(history[0].notes.as_deref(), Some("胃不舒服"));
+
+        assert!(db.disable_encryption("wrong horse").is_err());
+        db.disable_encryption("correct horse").unwrap();
+        assert!(!db.is_encrypted());
+        assert_eq!(
+            raw_profile_and_note(&db),
+            (r#"["花生"]"#.to_string(), "胃不舒服".to_string())
+        );
+    }
+
+    #[test]
+    fn test_profiles_and_cascading_delete() {
+        let (_dir, db) = test_db();
+        let dad = db.create_profile(" 爸爸 ").unwrap();
+        assert_eq!(dad.display_name, "爸爸");
+        assert!(db.create_profile("爸爸").is_err());
+        assert!(db.create_profile("  ").is_err());
+
+        // Writing data for an unknown id registers it as a profile
+        let mut recipe = test_recipe("爸爸的炒饭", &["米饭"], "炒。");
+        recipe.user_id = Some(dad.user_id.clone());
+        recipe.origin = RecipeOrigin::User;
+        db.save_recipe(&recipe).unwrap();
+        db.save_health_profile(&HealthProfile::new(dad.user_id.clone())).unwrap();
+        log_history(&db, &dad.user_id, Uuid::new_v4());
+        log_history(&db, "guest", Uuid::new_v4());
+        let names: Vec<String> = db.list_profiles().unwrap().into_iter().map(|p| p.display_name).collect();
+        assert_eq!(names, vec!["爸爸".to_string(), "guest".to_string()]);
+
+        let renamed = db.rename_profile("guest", "客人").unwrap();
+        assert_eq!(renamed.display_name, "客人");
+        assert!(db.rename_profile("guest", "爸爸").is_err());
+        assert!(matches!(db.rename_profile("nobody", "x"), Err(AppError::NotFound(_))));
+
+        assert!(db.delete_profile(&dad.user_id).unwrap());
+        assert!(db.get_health_profile(&dad.user_id).unwrap().is_none());
+        assert!(db.get_recipe_by_id(&recipe.id.to_string()).unwrap().is_none());
+        assert!(db.get_diet_history(&dad.user_id, None, None, None, None, None).unwrap().is_empty());
+        assert_eq!(db.get_diet_history("guest", None, None, None, None, None).unwrap().len(), 1);
+        assert!(!db.delete_profile(&dad.user_id).unwrap());
+    }
+
+    #[test]
+    fn test_meal_plan_crud_and_eaten_entries() {
+        let (_dir, db) = test_db();
+        let rice = test_recipe("蛋炒饭", &["米饭", "鸡蛋"], "炒。");
+        let noodles = test_recipe("牛肉面", &["面条", "牛肉"], "煮。");
+        db.save_recipe(&rice).unwrap();
+        db.save_recipe(&noodles).unwrap();
+
+        let today = chrono::Utc::now().date_naive();
+        let plan_id = Uuid::new_v4();
+        let entry = |date: chrono::NaiveDate, meal_type: &str, recipe: &Recipe| MealPlanEntry {
+            id: Uuid::new_v4(),
+            plan_id,
+            date,
+            meal_type: meal_type.to_string(),
+            recipe_id: recipe.id,
+            recipe_title: String::new(),
+            diet_history_id: None,
+        };
+        let plan = MealPlan {
+            id: plan_id,
+            user_id: "user_1".to_string(),
+            name: "本周食谱".to_string(),
+            start_date: today,
+            end_date: today.succ_opt().unwrap(),
+            entries: vec![
+                entry(today.succ_opt().unwrap(), "lunch", &noodles),
+                entry(today, "dinner", &rice),
+                entry(today, "lunch", &noodles),
+            ],
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        };
+        db.save_meal_plan(&plan).unwrap();
+
+        let stored = db.get_meal_plan(&plan_id.to_string()).unwrap().unwrap();
+        let order: Vec<(chrono::NaiveDate, &str)> =
+            stored.entries.iter().map(|e| (e.date, e.meal_type.as_str())).collect();
+        assert_eq!(order, vec![(today, "lunch"), (today, "dinner"), (today.succ_opt().unwrap(), "lunch")]);
+        assert_eq!(stored.entries[0].recipe_title, "牛肉面");
+        assert_eq!(db.list_meal_plans("user_1").unwrap().len(), 1);
+        assert_eq!(db.rename_meal_plan(&plan_id.to_string(), " 减脂周 ").unwrap().name, "减脂周");
+
+        let lunch_id = stored.entries[0].id.to_string();
+        let swapped = db.update_meal_plan_entry(&lunch_id, &rice.id.to_string()).unwrap();
+        assert_eq!(swapped.recipe_title, "蛋炒饭");
+        assert!(matches!(
+            db.update_meal_plan_entry(&lunch_id, &Uuid::new_v4().to_string()),
+            Err(AppError::NotFound(_))
+        ));
+
+        let history = db.mark_meal_plan_entry_eaten(&lunch_id, Some(5), Some("好吃".to_string())).unwrap();
+        assert_eq!(history.diet_item_id, rice.id);
+        assert_eq!(history.meal_type, "lunch");
+        assert!(db.mark_meal_plan_entry_eaten(&lunch_id, None, None).is_err());
+        assert!(db.update_meal_plan_entry(&lunch_id, &noodles.id.to_string()).is_err());
+        let logged = db.get_diet_history("user_1", None, None, None, None, None).unwrap();
+        assert_eq!(logged.len(), 1);
+        assert_eq!(logged[0].notes.as_deref(), Some("好吃"));
+
+        // Tomorrow's meal cannot be eaten yet
+        let tomorrow_id = stored.entries[2].id.to_string();
+        assert!(matches!(db.mark_meal_plan_entry_eaten(&tomorrow_id, None, None), Err(AppError::Validation(_))));
+
+        db.delete_diet_entry(&history.id.to_string()).unwrap();
+        let stored = db.get_meal_plan(&plan_id.to_string()).unwrap().unwrap();
+        assert!(!stored.entries[0].is_eaten());
+
+        db.mark_meal_plan_entry_eaten(&lunch_id, None, None).unwrap();
+        assert!(db.delete_meal_plan(&plan_id.to_string()).unwrap());
+        assert!(!db.delete_meal_plan(&plan_id.to_string()).unwrap());
+        assert_eq!(db.get_diet_history("user_1", None, None, None, None, None).unwrap().len(), 1);
+
+        // Plans go with their profile
+        let mut plan = plan;
+        plan.id = Uuid::new_v4();
+        plan.entries.clear();
+        db.save_meal_plan(&plan).unwrap();
+        db.delete_profile("user_1").unwrap();
+        assert!(db.list_meal_plans("user_1").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_shopping_lists_and_pantry() {
+        let (_dir, db) = test_db();
+
+        let rice = PantryItem {
+            id: Uuid::new_v4(),
+            user_id: "user_1".to_string(),
+            name: "大米".to_string(),
+            amount: 2.0,
+            unit: "kg".to_string(),
+            updated_at: chrono::Utc::now(),
+        };
+        let stored = db.save_pantry_item(&rice).unwrap();
+        // Same name again replaces the amount but keeps the row
+        let refill = PantryItem { id: Uuid::new_v4(), amount: 5.0, ..rice.clone() };
+        let refilled = db.save_pantry_item(&refill).unwrap();
+        assert_eq!(refilled.id, stored.id);
+        assert_eq!(refilled.amount, 5.0);
+        assert!(db.save_pantry_item(&PantryItem { amount: -1.0, ..rice.clone() }).is_err());
+        assert_eq!(db.get_pantry("user_1").unwrap().len(), 1);
+
+        let list_id = Uuid::new_v4();
+        let item = |name: &str, section: StoreSection| ShoppingListItem {
+            id: Uuid::new_v4(),
+            list_id,
+            name: name.to_string(),
+            amount: 2.0,
+            unit: "个".to_string(),
+            section,
+            recipes: vec!["番茄炒蛋".to_string()],
+            optional: false,
+            checked: false,
+        };
+        let list = ShoppingList {
+            id: list_id,
+            user_id: "user_1".to_string(),
+            name: "周末采购".to_string(),
+            meal_plan_id: None,
+            items: vec![item("番茄", StoreSection::Produce), item("鸡蛋", StoreSection::DairyAndEggs)],
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        };
+        db.save_shopping_list(&list).unwrap();
+
+        let stored = db.get_shopping_list(&list_id.to_string()).unwrap().unwrap();
+        let names: Vec<&str> = stored.items.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, vec!["番茄", "鸡蛋"]);
+        assert_eq!(stored.items[1].section, StoreSection::DairyAndEggs);
+        assert_eq!(stored.items[1].recipes, vec!["番茄炒蛋"]);
+
+        let checked = db.set_shopping_list_item_checked(&stored.items[0].id.to_string(), true).unwrap();
+        assert!(checked.checked);
+        assert!(db.get_shopping_list(&list_id.to_string()).unwrap().unwrap().items[0].checked);
+        assert!(matches!(
+            db.set_shopping_list_item_checked(&Uuid::new_v4().to_string(), true),
+            Err(AppError::NotFound(_))
+        ));
+        assert_eq!(db.list_shopping_lists("user_1").unwrap().len(), 1);
+
+        assert!(db.delete_shopping_list(&list_id.to_string()).unwrap());
+        assert!(db.get_shopping_list(&list_id.to_string()).unwrap().is_none());
+        assert!(db.delete_pantry_item(&refilled.id.to_string()).unwrap());
+        assert!(db.get_pantry("user_1").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,826 @@
+use rusqlite::{Connection, Transaction};
+
+use crate::{AppError, AppResult};
+
+/// A single numbered schema change.
+///
+/// The schema version is tracked in `PRAGMA user_version`. Each migration runs
+/// inside its own transaction together with the version bump, so a failing
+/// migration leaves the database exactly as it was before it started.
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    pub up: fn(&Transaction) -> rusqlite::Result<()>,
+}
+
+/// All migrations in the order they must be applied. Versions are contiguous
+/// and start at 1; never edit a migration once it has shipped, add a new one.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "initial schema",
+        up: initial_schema,
+    },
+    Migration {
+        version: 2,
+        description: "drop diet_history foreign key",
+        up: drop_diet_history_foreign_key,
+    },
+    Migration {
+        version: 3,
+        description: "recipe full-text search",
+        up: recipe_full_text_search,
+    },
+    Migration {
+        version: 4,
+        description: "recipe tag and ingredient join tables",
+        up: recipe_tag_and_ingredient_tables,
+    },
+    Migration {
+        version: 5,
+        description: "recommendation batches",
+        up: recommendation_batches,
+    },
+    Migration {
+        version: 6,
+        description: "recipe owner and origin",
+        up: recipe_owner_and_origin,
+    },
+    Migration {
+        version: 7,
+        description: "privacy mode encryption",
+        up: privacy_mode_encryption,
+    },
+    Migration {
+        version: 8,
+        description: "household profiles",
+        up: household_profiles,
+    },
+    Migration {
+        version: 9,
+        description: "recommendation constraint checks",
+        up: recommendation_constraint_checks,
+    },
+    Migration {
+        version: 10,
+        description: "meal plans",
+        up: meal_plans,
+    },
+    Migration {
+        version: 11,
+        description: "pantry and shopping lists",
+        up: pantry_and_shopping_lists,
+    },
+    Migration {
+        version: 12,
+        description: "recipe servings",
+        up: recipe_servings,
+    },
+    Migration {
+        version: 13,
+        description: "app state",
+        up: app_state,
+    },
+    Migration {
+        version: 14,
+        description: "recommendation batch snapshots",
+        up: recommendation_batch_snapshots,
+    },
+];
+
+/// The schema version a freshly migrated database ends up at.
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+/// Reads the schema version stored in the database header.
+pub fn current_version(conn: &Connection) -> AppResult<u32> {
+    let version: u32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    Ok(version)
+}
+
+/// Brings the database up to the latest schema version.
+pub fn run_migrations(conn: &mut Connection) -> AppResult<()> {
+    apply_migrations(conn, MIGRATIONS)
+}
+
+/// Applies every migration in `migrations` whose version is newer than the
+/// database's current version.
+pub fn apply_migrations(conn: &mut Connection, migrations: &[Migration]) -> AppResult<()> {
+    let current = current_version(conn)?;
+    let target = migrations.last().map(|m| m.version).unwrap_or(0);
+
+    if current > target {
+        return Err(AppError::Database(format!(
+            "Database schema version {} is newer than the latest supported version {}",
+            current, target
+        )));
+    }
+
+    for migration in migrations.iter().filter(|m| m.version > current) {
+        log::info!(
+            "Applying database migration {}: {}",
+            migration.version,
+            migration.description
+        );
+
+        // Dropping the transaction without committing rolls everything back
+        let tx = conn.transaction()?;
+        (migration.up)(&tx).map_err(|e| {
+            AppError::Database(format!(
+                "Migration {} ({}) failed: {}",
+                migration.version, migration.description, e
+            ))
+        })?;
+        tx.pragma_update(None, "user_version", migration.version)?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+fn initial_schema(tx: &Transaction) -> rusqlite::Result<()> {
+    // Databases created before versioning already have these tables, hence IF NOT EXISTS
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS health_profiles (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL UNIQUE,
+            age INTEGER NOT NULL,
+            gender TEXT NOT NULL,
+            weight REAL NOT NULL,
+            height REAL NOT NULL,
+            activity_level TEXT NOT NULL,
+            health_goals TEXT NOT NULL,
+            dietary_preferences TEXT NOT NULL,
+            dietary_restrictions TEXT NOT NULL,
+            allergies TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS diet_recommendations (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            title TEXT NOT NULL,
+            description TEXT,
+            ingredients TEXT NOT NULL,
+            nutritional_info TEXT NOT NULL,
+            preparation_time INTEGER NOT NULL,
+            difficulty_level TEXT NOT NULL,
+            meal_type TEXT NOT NULL,
+            recipe_instructions TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            is_personalized BOOLEAN NOT NULL,
+            relevance_score REAL NOT NULL,
+            FOREIGN KEY (user_id) REFERENCES health_profiles (user_id)
+        );
+
+        CREATE TABLE IF NOT EXISTS diet_history (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            diet_item_id TEXT NOT NULL,
+            date_attempted TEXT NOT NULL,
+            rating INTEGER,
+            notes TEXT,
+            was_prepared BOOLEAN NOT NULL,
+            meal_type TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS recipes (
+            id TEXT PRIMARY KEY,
+            title TEXT NOT NULL,
+            description TEXT,
+            ingredients TEXT NOT NULL,
+            nutritional_info_per_serving TEXT NOT NULL,
+            preparation_time INTEGER NOT NULL,
+            difficulty_level TEXT NOT NULL,
+            meal_type TEXT NOT NULL,
+            recipe_instructions TEXT NOT NULL,
+            cuisine_type TEXT,
+            seasonal BOOLEAN NOT NULL,
+            tags TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );",
+    )
+}
+
+fn drop_diet_history_foreign_key(tx: &Transaction) -> rusqlite::Result<()> {
+    // Early builds created diet_history with a foreign key to health_profiles,
+    // which rejected history entries logged before a profile was saved
+    let foreign_keys: i64 = tx.query_row(
+        "SELECT COUNT(*) FROM pragma_foreign_key_list('diet_history')",
+        [],
+        |row| row.get(0),
+    )?;
+
+    if foreign_keys == 0 {
+        return Ok(());
+    }
+
+    tx.execute_batch(
+        "CREATE TABLE diet_history_new (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            diet_item_id TEXT NOT NULL,
+            date_attempted TEXT NOT NULL,
+            rating INTEGER,
+            notes TEXT,
+            was_prepared BOOLEAN NOT NULL,
+            meal_type TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+        INSERT INTO diet_history_new
+            SELECT id, user_id, diet_item_id, date_attempted, rating, notes, was_prepared, meal_type, created_at, updated_at
+            FROM diet_history;
+        DROP TABLE diet_history;
+        ALTER TABLE diet_history_new RENAME TO diet_history;",
+    )
+}
+
+fn recipe_full_text_search(tx: &Transaction) -> rusqlite::Result<()> {
+    // The trigram tokenizer indexes every three-character window, which works for
+    // Chinese text without word segmentation. Terms shorter than three characters
+    // cannot be matched that way and are searched with LIKE on the same table.
+    tx.execute_batch(
+        "CREATE VIRTUAL TABLE recipes_fts USING fts5(
+            recipe_id UNINDEXED,
+            title,
+            description,
+            ingredient_names,
+            recipe_instructions,
+            tokenize = 'trigram'
+        );
+
+        CREATE TRIGGER recipes_fts_insert AFTER INSERT ON recipes BEGIN
+            INSERT INTO recipes_fts (recipe_id, title, description, ingredient_names, recipe_instructions)
+            VALUES (
+                new.id,
+                new.title,
+                COALESCE(new.description, ''),
+                (SELECT COALESCE(group_concat(json_extract(value, '$.name'), ' '), '') FROM json_each(new.ingredients)),
+                new.recipe_instructions
+            );
+        END;
+
+        CREATE TRIGGER recipes_fts_delete AFTER DELETE ON recipes BEGIN
+            DELETE FROM recipes_fts WHERE recipe_id = old.id;
+        END;
+
+        CREATE TRIGGER recipes_fts_update AFTER UPDATE ON recipes BEGIN
+            DELETE FROM recipes_fts WHERE recipe_id = old.id;
+            INSERT INTO recipes_fts (recipe_id, title, description, ingredient_names, recipe_instructions)
+            VALUES (
+                new.id,
+                new.title,
+                COALESCE(new.description, ''),
+                (SELECT COALESCE(group_concat(json_extract(value, '$.name'), ' '), '') FROM json_each(new.ingredients)),
+                new.recipe_instructions
+            );
+        END;
+
+        INSERT INTO recipes_fts (recipe_id, title, description, ingredient_names, recipe_instructions)
+        SELECT
+            id,
+            title,
+            COALESCE(description, ''),
+            (SELECT COALESCE(group_concat(json_extract(value, '$.name'), ' '), '') FROM json_each(recipes.ingredients)),
+            recipe_instructions
+        FROM recipes;",
+    )
+}
+
+fn recipe_tag_and_ingredient_tables(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE recipe_tags (
+            recipe_id TEXT NOT NULL REFERENCES recipes (id) ON DELETE CASCADE,
+            tag TEXT NOT NULL,
+            PRIMARY KEY (recipe_id, tag)
+        );
+        CREATE INDEX idx_recipe_tags_tag ON recipe_tags (tag);
+
+        CREATE TABLE recipe_ingredients (
+            recipe_id TEXT NOT NULL REFERENCES recipes (id) ON DELETE CASCADE,
+            position INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            normalized_name TEXT NOT NULL,
+            amount REAL NOT NULL,
+            unit TEXT NOT NULL,
+            optional BOOLEAN NOT NULL,
+            PRIMARY KEY (recipe_id, position)
+        );
+        CREATE INDEX idx_recipe_ingredients_name ON recipe_ingredients (normalized_name);",
+    )?;
+
+    // Backfill from the JSON columns, which stay as a denormalized copy
+    let rows = {
+        let mut stmt = tx.prepare("SELECT id, ingredients, tags FROM recipes")?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        rows
+    };
+
+    // The JSON shape and name normalization as they were when this migration shipped,
+    // kept here so later changes to the models cannot alter it
+    #[derive(serde::Deserialize)]
+    struct Ingredient {
+        name: String,
+        amount: f64,
+        unit: String,
+        optional: bool,
+    }
+    let normalize = |name: &str| {
+        name.split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase()
+    };
+
+    let mut tag_stmt =
+        tx.prepare("INSERT OR IGNORE INTO recipe_tags (recipe_id, tag) VALUES (?1, ?2)")?;
+    let mut ingredient_stmt = tx.prepare(
+        "INSERT INTO recipe_ingredients (recipe_id, position, name, normalized_name, amount, unit, optional)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+    )?;
+    for (id, ingredients_json, tags_json) in rows {
+        let ingredients: Vec<Ingredient> = serde_json::from_str(&ingredients_json)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        let tags: Vec<String> = serde_json::from_str(&tags_json)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+
+        for tag in tags.iter().map(|tag| normalize(tag)).filter(|tag| !tag.is_empty()) {
+            tag_stmt.execute((&id, tag))?;
+        }
+        for (position, ingredient) in ingredients.iter().enumerate() {
+            ingredient_stmt.execute((
+                &id,
+                position as i64,
+                &ingredient.name,
+                normalize(&ingredient.name),
+                ingredient.amount,
+                &ingredient.unit,
+                ingredient.optional,
+            ))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn recommendation_batches(tx: &Transaction) -> rusqlite::Result<()> {
+    // diet_recommendations is rebuilt without its foreign key to health_profiles:
+    // default recommendations are stored for users who have not saved a profile yet
+    tx.execute_batch(
+        "CREATE TABLE recommendation_batches (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            generated_at TEXT NOT NULL
+        );
+        CREATE INDEX idx_recommendation_batches_user ON recommendation_batches (user_id, generated_at);
+
+        CREATE TABLE diet_recommendations_new (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            title TEXT NOT NULL,
+            description TEXT,
+            ingredients TEXT NOT NULL,
+            nutritional_info TEXT NOT NULL,
+            preparation_time INTEGER NOT NULL,
+            difficulty_level TEXT NOT NULL,
+            meal_type TEXT NOT NULL,
+            recipe_instructions TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            is_personalized BOOLEAN NOT NULL,
+            relevance_score REAL NOT NULL,
+            recipe_id TEXT,
+            batch_id TEXT REFERENCES recommendation_batches (id) ON DELETE SET NULL,
+            position INTEGER NOT NULL DEFAULT 0,
+            score_breakdown TEXT NOT NULL DEFAULT '[]'
+        );
+        INSERT INTO diet_recommendations_new
+            (id, user_id, title, description, ingredients, nutritional_info, preparation_time, difficulty_level, meal_type, recipe_instructions, created_at, is_personalized, relevance_score)
+            SELECT id, user_id, title, description, ingredients, nutritional_info, preparation_time, difficulty_level, meal_type, recipe_instructions, created_at, is_personalized, relevance_score
+            FROM diet_recommendations;
+        DROP TABLE diet_recommendations;
+        ALTER TABLE diet_recommendations_new RENAME TO diet_recommendations;
+        CREATE INDEX idx_diet_recommendations_batch ON diet_recommendations (batch_id, position);
+
+        CREATE INDEX idx_diet_history_item ON diet_history (diet_item_id);",
+    )
+}
+
+fn recipe_owner_and_origin(tx: &Transaction) -> rusqlite::Result<()> {
+    // Every recipe stored before this migration was seeded from the bundled set
+    tx.execute_batch(
+        "ALTER TABLE recipes ADD COLUMN user_id TEXT;
+        ALTER TABLE recipes ADD COLUMN origin TEXT NOT NULL DEFAULT 'bundled';
+        CREATE INDEX idx_recipes_user ON recipes (user_id);",
+    )
+}
+
+fn privacy_mode_encryption(tx: &Transaction) -> rusqlite::Result<()> {
+    // A row in encryption_keys means privacy mode is on. The key itself is never stored,
+    // only the salt it is derived with and a sealed known value to check the passphrase.
+    // Encrypted profiles keep placeholders in their plaintext columns and the real values
+    // in `sealed`.
+    tx.execute_batch(
+        "CREATE TABLE encryption_keys (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            salt TEXT NOT NULL,
+            verifier TEXT NOT NULL,
+            created_at TEXT NOT NULL
+        );
+        ALTER TABLE health_profiles ADD COLUMN sealed TEXT;",
+    )
+}
+
+fn household_profiles(tx: &Transaction) -> rusqlite::Result<()> {
+    // Register every user id that already owns data, named after the id itself
+    tx.execute_batch(
+        "CREATE TABLE profiles (
+            user_id TEXT PRIMARY KEY,
+            display_name TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+        INSERT OR IGNORE INTO profiles (user_id, display_name, created_at, updated_at)
+            SELECT user_id, user_id, strftime('%Y-%m-%dT%H:%M:%SZ', 'now'), strftime('%Y-%m-%dT%H:%M:%SZ', 'now')
+            FROM (
+                SELECT user_id FROM health_profiles
+                UNION SELECT user_id FROM diet_history
+                UNION SELECT user_id FROM diet_recommendations
+                UNION SELECT user_id FROM recipes WHERE user_id IS NOT NULL
+            );",
+    )
+}
+
+fn recommendation_constraint_checks(tx: &Transaction) -> rusqlite::Result<()> {
+    // Older rows were never told which constraints they were checked against; '{}' reads
+    // back as empty lists. Their score_breakdown entries keep the old `score` key, which
+    // still deserializes as the delta.
+    tx.execute_batch(
+        "ALTER TABLE diet_recommendations ADD COLUMN checked_constraints TEXT NOT NULL DEFAULT '{}';",
+    )
+}
+
+fn meal_plans(tx: &Transaction) -> rusqlite::Result<()> {
+    // Entries go away with their plan or recipe. diet_history_id links an eaten meal to
+    // the history row it created; deleting that row only unlinks it.
+    tx.execute_batch(
+        "CREATE TABLE meal_plans (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            start_date TEXT NOT NULL,
+            end_date TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+        CREATE INDEX idx_meal_plans_user ON meal_plans (user_id, start_date);
+
+        CREATE TABLE meal_plan_entries (
+            id TEXT PRIMARY KEY,
+            plan_id TEXT NOT NULL REFERENCES meal_plans (id) ON DELETE CASCADE,
+            date TEXT NOT NULL,
+            meal_type TEXT NOT NULL,
+            recipe_id TEXT NOT NULL REFERENCES recipes (id) ON DELETE CASCADE,
+            diet_history_id TEXT,
+            UNIQUE (plan_id, date, meal_type)
+        );
+        CREATE INDEX idx_meal_plan_entries_recipe ON meal_plan_entries (recipe_id);
+        CREATE INDEX idx_meal_plan_entries_history ON meal_plan_entries (diet_history_id);",
+    )
+}
+
+fn pantry_and_shopping_lists(tx: &Transaction) -> rusqlite::Result<()> {
+    // A pantry holds one row per normalized ingredient name. Shopping lists outlive the
+    // plan they were built from; their items keep the recipe titles as a JSON array.
+    tx.execute_batch(
+        "CREATE TABLE pantry_items (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            normalized_name TEXT NOT NULL,
+            amount REAL NOT NULL,
+            unit TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            UNIQUE (user_id, normalized_name)
+        );
+
+        CREATE TABLE shopping_lists (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            meal_plan_id TEXT REFERENCES meal_plans (id) ON DELETE SET NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+        CREATE INDEX idx_shopping_lists_user ON shopping_lists (user_id, created_at);
+        CREATE INDEX idx_shopping_lists_plan ON shopping_lists (meal_plan_id);
+
+        CREATE TABLE shopping_list_items (
+            id TEXT PRIMARY KEY,
+            list_id TEXT NOT NULL REFERENCES shopping_lists (id) ON DELETE CASCADE,
+            position INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            amount REAL NOT NULL,
+            unit TEXT NOT NULL,
+            section TEXT NOT NULL,
+            recipes TEXT NOT NULL DEFAULT '[]',
+            optional INTEGER NOT NULL DEFAULT 0,
+            checked INTEGER NOT NULL DEFAULT 0
+        );
+        CREATE INDEX idx_shopping_list_items_list ON shopping_list_items (list_id, position);",
+    )
+}
+
+fn recipe_servings(tx: &Transaction) -> rusqlite::Result<()> {
+    // Existing recipes make one serving, except the bundled 扁豆汤 (bundled id 4) and
+    // 三文鱼配藜麦 (5) if already seeded. Their ids are spelled out so later edits to
+    // `sample_recipes.json` do not change what this migration does.
+    tx.execute_batch(
+        "ALTER TABLE recipes ADD COLUMN servings INTEGER NOT NULL DEFAULT 1;
+        UPDATE recipes SET servings = 3
+            WHERE id = '14c09214-e210-542f-9a5a-5c657700d5a4' AND origin = 'bundled';
+        UPDATE recipes SET servings = 2
+            WHERE id = 'c8906916-f3b3-579b-82da-dcdad7f8a4b6' AND origin = 'bundled';",
+    )
+}
+
+fn app_state(tx: &Transaction) -> rusqlite::Result<()> {
+    // Databases that already hold recipes were seeded by the old "table is empty" check
+    tx.execute_batch(
+        "CREATE TABLE app_state (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+        INSERT INTO app_state (key, value)
+            SELECT 'bundled_recipes_seeded', strftime('%Y-%m-%dT%H:%M:%SZ', 'now')
+            WHERE EXISTS (SELECT 1 FROM recipes);",
+    )
+}
+
+fn recommendation_batch_snapshots(tx: &Transaction) -> rusqlite::Result<()> {
+    // A recommendation row holds its latest scores; each batch keeps the scores it was
+    // generated with. Older batches only have their surviving rows to start from.
+    tx.execute_batch(
+        "CREATE TABLE recommendation_batch_items (
+            batch_id TEXT NOT NULL REFERENCES recommendation_batches (id) ON DELETE CASCADE,
+            position INTEGER NOT NULL,
+            recommendation_id TEXT NOT NULL REFERENCES diet_recommendations (id) ON DELETE CASCADE,
+            relevance_score REAL NOT NULL,
+            score_breakdown TEXT NOT NULL DEFAULT '[]',
+            checked_constraints TEXT NOT NULL DEFAULT '{}',
+            PRIMARY KEY (batch_id, position)
+        );
+        CREATE INDEX idx_recommendation_batch_items_recommendation ON recommendation_batch_items (recommendation_id);
+        INSERT INTO recommendation_batch_items
+            (batch_id, position, recommendation_id, relevance_score, score_breakdown, checked_constraints)
+            SELECT batch_id, position, id, relevance_score, score_breakdown, checked_constraints
+            FROM diet_recommendations WHERE batch_id IS NOT NULL;",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Schema written by builds that predate versioned migrations (user_version 0).
+    fn legacy_fixture() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            r#"CREATE TABLE health_profiles (
+                id TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL UNIQUE,
+                age INTEGER NOT NULL,
+                gender TEXT NOT NULL,
+                weight REAL NOT NULL,
+                height REAL NOT NULL,
+                activity_level TEXT NOT NULL,
+                health_goals TEXT NOT NULL,
+                dietary_preferences TEXT NOT NULL,
+                dietary_restrictions TEXT NOT NULL,
+                allergies TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            CREATE TABLE diet_history (
+                id TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL,
+                diet_item_id TEXT NOT NULL,
+                date_attempted TEXT NOT NULL,
+                rating INTEGER,
+                notes TEXT,
+                was_prepared BOOLEAN NOT NULL,
+                meal_type TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                FOREIGN KEY (user_id) REFERENCES health_profiles (user_id)
+            );
+            CREATE TABLE recipes (
+                id TEXT PRIMARY KEY,
+                title TEXT NOT NULL,
+                description TEXT,
+                ingredients TEXT NOT NULL,
+                nutritional_info_per_serving TEXT NOT NULL,
+                preparation_time INTEGER NOT NULL,
+                difficulty_level TEXT NOT NULL,
+                meal_type TEXT NOT NULL,
+                recipe_instructions TEXT NOT NULL,
+                cuisine_type TEXT,
+                seasonal BOOLEAN NOT NULL,
+                tags TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            INSERT INTO recipes VALUES ('r1', '蔬菜炒豆腐', '健康快捷', '[{"name":"豆腐","amount":200.0,"unit":"g","optional":false}]',
+                '{"calories":280.0,"protein":18.0,"carbs":22.0,"fat":14.0,"fiber":6.0}', 20, 'easy', 'lunch', '煎至金黄。',
+                'Asian', 0, '["vegetarian","Low_Carb"]', '2024-01-01T00:00:00+00:00', '2024-01-01T00:00:00+00:00');
+            INSERT INTO health_profiles VALUES ('p1', 'user_1', 30, 'male', 70.0, 175.0, 'moderate',
+                '[]', '[]', '[]', '[]', '2024-01-01T00:00:00+00:00', '2024-01-01T00:00:00+00:00');
+            INSERT INTO diet_history VALUES ('h1', 'user_1', 'item_1', '2024-01-02', 5, 'tasty', 1, 'lunch',
+                '2024-01-02T00:00:00+00:00', '2024-01-02T00:00:00+00:00');"#,
+        )
+        .unwrap();
+        conn
+    }
+
+    /// Builds a database that has been migrated up to (and including) `version`.
+    fn fixture_at_version(version: u32) -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let count = MIGRATIONS.iter().take_while(|m| m.version <= version).count();
+        apply_migrations(&mut conn, &MIGRATIONS[..count]).unwrap();
+        assert_eq!(current_version(&conn).unwrap(), version);
+        conn
+    }
+
+    fn history_foreign_keys(conn: &Connection) -> i64 {
+        conn.query_row(
+            "SELECT COUNT(*) FROM pragma_foreign_key_list('diet_history')",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_migrations_are_contiguous() {
+        for (index, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version as usize, index + 1);
+        }
+    }
+
+    #[test]
+    fn test_upgrade_from_every_past_version() {
+        for version in 0..=latest_version() {
+            let mut conn = fixture_at_version(version);
+            run_migrations(&mut conn).unwrap();
+            assert_eq!(current_version(&conn).unwrap(), latest_version());
+            assert_eq!(history_foreign_keys(&conn), 0);
+
+            // Re-running is a no-op
+            run_migrations(&mut conn).unwrap();
+            assert_eq!(current_version(&conn).unwrap(), latest_version());
+        }
+    }
+
+    #[test]
+    fn test_upgrade_legacy_database_preserves_data() {
+        let mut conn = legacy_fixture();
+        assert_eq!(history_foreign_keys(&conn), 1);
+
+        run_migrations(&mut conn).unwrap();
+
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+        assert_eq!(history_foreign_keys(&conn), 0);
+        let notes: String = conn
+            .query_row("SELECT notes FROM diet_history WHERE id = 'h1'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(notes, "tasty");
+        let indexed: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM recipes_fts WHERE recipes_fts MATCH '蔬菜炒'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(indexed, 1);
+        let tags: Vec<String> = conn
+            .prepare("SELECT tag FROM recipe_tags WHERE recipe_id = 'r1' ORDER BY tag")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(tags, vec!["low_carb", "vegetarian"]);
+        let ingredient: String = conn
+            .query_row(
+                "SELECT normalized_name FROM recipe_ingredients WHERE recipe_id = 'r1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(ingredient, "豆腐");
+        let origin: String = conn
+            .query_row("SELECT origin FROM recipes WHERE id = 'r1'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(origin, "bundled");
+        let profile: String = conn
+            .query_row("SELECT display_name FROM profiles WHERE user_id = 'user_1'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(profile, "user_1");
+    }
+
+    #[test]
+    fn test_recommendations_survive_foreign_key_removal() {
+        let mut conn = fixture_at_version(4);
+        conn.execute_batch(
+            r#"INSERT INTO health_profiles VALUES ('p1', 'user_1', 30, 'male', 70.0, 175.0, 'moderate',
+                '[]', '[]', '[]', '[]', '2024-01-01T00:00:00+00:00', '2024-01-01T00:00:00+00:00');
+            INSERT INTO diet_recommendations VALUES ('rec_1', 'user_1', '蔬菜炒豆腐', NULL, '[]',
+                '{"calories":280.0,"protein":18.0,"carbs":22.0,"fat":14.0,"fiber":6.0}', 20, 'easy', 'lunch',
+                '煎至金黄。', '2024-01-01T00:00:00+00:00', 1, 0.8);"#,
+        )
+        .unwrap();
+
+        run_migrations(&mut conn).unwrap();
+
+        let (title, breakdown): (String, String) = conn
+            .query_row(
+                "SELECT title, score_breakdown FROM diet_recommendations WHERE id = 'rec_1'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(title, "蔬菜炒豆腐");
+        assert_eq!(breakdown, "[]");
+        let checked: String = conn
+            .query_row(
+                "SELECT checked_constraints FROM diet_recommendations WHERE id = 'rec_1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(checked, "{}");
+        let profile_keys: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM pragma_foreign_key_list('diet_recommendations') WHERE \"table\" = 'health_profiles'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(profile_keys, 0);
+    }
+
+    #[test]
+    fn test_failed_migration_rolls_back() {
+        fn create_then_fail(tx: &Transaction) -> rusqlite::Result<()> {
+            tx.execute_batch("CREATE TABLE half_done (id TEXT)")?;
+            tx.execute_batch("THIS IS NOT SQL")
+        }
+
+        let failing = [
+            Migration {
+                version: 1,
+                description: "initial schema",
+                up: initial_schema,
+            },
+            Migration {
+                version: 2,
+                description: "broken",
+                up: create_then_fail,
+            },
+        ];
+
+        let mut conn = Connection::open_in_memory().unwrap();
+        assert!(apply_migrations(&mut conn, &failing).is_err());
+
+        assert_eq!(current_version(&conn).unwrap(), 1);
+        let leftovers: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE name = 'half_done'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(leftovers, 0);
+    }
+
+    #[test]
+    fn test_newer_database_is_rejected() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", latest_version() + 1)
+            .unwrap();
+        assert!(run_migrations(&mut conn).is_err());
+    }
+}
@@ -1,5 +1,9 @@
+pub mod backup;
+pub mod crypto;
 pub mod database;
+pub mod migrations;
 pub mod models;
+pub mod seed;
 
 pub use database::Database;
 pub use models::*;
@@ -12,7 +12,7 @@ pub struct HealthProfile {
     pub gender: String, // 'male' | 'female' | 'other' | 'prefer_not_to_say'
     pub weight: f64,    // in kg
     pub height: f64,    // in cm
-    pub activity_level: String, // 'sedentary' | 'light' | 'moderate' | 'active' | 'very_active'
+    pub activity_level: String, // 'sedentary' | 'lightly_active' | 'moderately_active' | 'very_active' | 'extremely_active'
     pub health_goals: Vec<String>, // e.g., ['weight_loss', 'muscle_gain']
     pub dietary_preferences: Vec<String>, // e.g., ['vegetarian', 'low_carb']
     pub dietary_restrictions: Vec<String>, // specific foods/ingredients to avoid
@@ -80,6 +80,36 @@ impl HealthProfile {
     }
 }
 
+/// A household member. `user_id` is the key every other table uses; `display_name`
+/// is what the profile switcher shows and can be changed freely.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Profile {
+    pub user_id: String,
+    pub display_name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Profile {
+    pub const MAX_NAME_CHARS: usize = 40;
+
+    pub fn validate_name(name: &str) -> AppResult<()> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(crate::AppError::Validation(
+                "Profile name must not be empty".to_string(),
+            ));
+        }
+        if name.chars().count() > Self::MAX_NAME_CHARS {
+            return Err(crate::AppError::Validation(format!(
+                "Profile name must be at most {} characters",
+                Self::MAX_NAME_CHARS
+            )));
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DietRecommendation {
     pub id: Uuid,
@@ -92,9 +122,62 @@ pub struct DietRecommendation {
     pub difficulty_level: String, // 'easy' | 'medium' | 'hard'
     pub meal_type: String,        // 'breakfast' | 'lunch' | 'dinner' | 'snack'
     pub recipe_instructions: String,
-    pub created_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>, // when the batch containing this recommendation was generated
     pub is_personalized: bool,
     pub relevance_score: f64, // 0.0 to 1.0
+    pub recipe_id: Option<String>, // recipe this recommendation was generated from
+    pub batch_id: Option<Uuid>,    // generation batch, set once persisted
+    pub score_breakdown: Vec<ScoreComponent>, // rules that contributed to relevance_score
+    #[serde(default)]
+    pub checked_constraints: CheckedConstraints,
+}
+
+impl DietRecommendation {
+    /// Recommendation ids are derived from the user and source recipe, so the same
+    /// recipe recommended again keeps its id and diet history entries stay resolvable.
+    pub fn stable_id(user_id: &str, recipe_id: &str) -> Uuid {
+        Uuid::new_v5(
+            &Uuid::NAMESPACE_OID,
+            format!("scx-recipes/recommendation/{}/{}", user_id, recipe_id).as_bytes(),
+        )
+    }
+}
+
+/// One rule's contribution to a recommendation's relevance score.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ScoreComponent {
+    pub rule: String,
+    #[serde(alias = "score")] // name used by recommendations stored before reasons existed
+    pub delta: f64,
+    #[serde(default)]
+    pub reason: String,
+}
+
+impl std::fmt::Display for ScoreComponent {
+    /// Formats as e.g. `高纤维 +0.10`; falls back to the rule name when there is no reason.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = if self.reason.is_empty() { &self.rule } else { &self.reason };
+        write!(f, "{} {:+.2}", label, self.delta)
+    }
+}
+
+/// The allergies and dietary restrictions a recommendation was checked against. Every
+/// listed term was absent from the recipe's ingredients.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct CheckedConstraints {
+    #[serde(default)]
+    pub allergies: Vec<String>,
+    #[serde(default)]
+    pub dietary_restrictions: Vec<String>,
+}
+
+impl CheckedConstraints {
+    pub fn from_profile(profile: &HealthProfile) -> Self {
+        CheckedConstraints {
+            allergies: profile.allergies.clone(),
+            dietary_restrictions: profile.dietary_restrictions.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -104,7 +187,7 @@ pub struct Ingredient {
     pub unit: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct NutritionalInfo {
     pub calories: f64,
     pub protein: f64, // in grams
@@ -148,6 +231,178 @@ impl DietHistory {
     }
 }
 
+/// A stored multi-day meal plan.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MealPlan {
+    pub id: Uuid,
+    pub user_id: String,
+    pub name: String,
+    pub start_date: chrono::NaiveDate,
+    pub end_date: chrono::NaiveDate, // inclusive
+    pub entries: Vec<MealPlanEntry>, // by date, then breakfast, lunch, dinner, snack
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl MealPlan {
+    pub const MAX_NAME_CHARS: usize = 60;
+
+    pub fn validate_name(name: &str) -> AppResult<()> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(crate::AppError::Validation(
+                "Meal plan name must not be empty".to_string(),
+            ));
+        }
+        if name.chars().count() > Self::MAX_NAME_CHARS {
+            return Err(crate::AppError::Validation(format!(
+                "Meal plan name must be at most {} characters",
+                Self::MAX_NAME_CHARS
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// One recipe planned for one meal of one day.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MealPlanEntry {
+    pub id: Uuid,
+    pub plan_id: Uuid,
+    pub date: chrono::NaiveDate,
+    pub meal_type: String, // 'breakfast' | 'lunch' | 'dinner' | 'snack'
+    pub recipe_id: Uuid,
+    #[serde(default)]
+    pub recipe_title: String, // read from the recipes table, not stored with the entry
+    pub diet_history_id: Option<Uuid>, // set once the meal has been marked as eaten
+}
+
+impl MealPlanEntry {
+    pub fn is_eaten(&self) -> bool {
+        self.diet_history_id.is_some()
+    }
+}
+
+/// Something the user already has at home. Shopping lists only ask for what it does not
+/// cover.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PantryItem {
+    pub id: Uuid,
+    pub user_id: String,
+    pub name: String, // one row per normalized name and user
+    pub amount: f64,
+    pub unit: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl PantryItem {
+    pub fn validate(&self) -> AppResult<()> {
+        if normalize_name(&self.name).is_empty() {
+            return Err(crate::AppError::Validation(
+                "Pantry item name must not be empty".to_string(),
+            ));
+        }
+        if !self.amount.is_finite() || self.amount < 0.0 {
+            return Err(crate::AppError::Validation(
+                "Pantry amount must not be negative".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Aisle a shopping list item is grouped under, in the order a typical store is walked.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum StoreSection {
+    Produce,
+    Meat,
+    Seafood,
+    DairyAndEggs,
+    Soy,
+    Staples,
+    Condiments,
+    Other,
+}
+
+impl StoreSection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StoreSection::Produce => "produce",
+            StoreSection::Meat => "meat",
+            StoreSection::Seafood => "seafood",
+            StoreSection::DairyAndEggs => "dairy_and_eggs",
+            StoreSection::Soy => "soy",
+            StoreSection::Staples => "staples",
+            StoreSection::Condiments => "condiments",
+            StoreSection::Other => "other",
+        }
+    }
+
+    pub fn parse(value: &str) -> AppResult<Self> {
+        match value {
+            "produce" => Ok(StoreSection::Produce),
+            "meat" => Ok(StoreSection::Meat),
+            "seafood" => Ok(StoreSection::Seafood),
+            "dairy_and_eggs" => Ok(StoreSection::DairyAndEggs),
+            "soy" => Ok(StoreSection::Soy),
+            "staples" => Ok(StoreSection::Staples),
+            "condiments" => Ok(StoreSection::Condiments),
+            "other" => Ok(StoreSection::Other),
+            other => Err(crate::AppError::Validation(format!(
+                "Invalid store section '{}'",
+                other
+            ))),
+        }
+    }
+}
+
+/// A saved shopping list, built from a meal plan or a set of recipes.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShoppingList {
+    pub id: Uuid,
+    pub user_id: String,
+    pub name: String,
+    pub meal_plan_id: Option<Uuid>, // the plan it was built from; unset if that plan is deleted
+    pub items: Vec<ShoppingListItem>, // by section, then name
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ShoppingList {
+    pub const MAX_NAME_CHARS: usize = 60;
+
+    pub fn validate_name(name: &str) -> AppResult<()> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(crate::AppError::Validation(
+                "Shopping list name must not be empty".to_string(),
+            ));
+        }
+        if name.chars().count() > Self::MAX_NAME_CHARS {
+            return Err(crate::AppError::Validation(format!(
+                "Shopping list name must be at most {} characters",
+                Self::MAX_NAME_CHARS
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// One ingredient to buy, summed over every recipe that needs it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShoppingListItem {
+    pub id: Uuid,
+    pub list_id: Uuid,
+    pub name: String,
+    pub amount: f64,
+    pub unit: String,
+    pub section: StoreSection,
+    pub recipes: Vec<String>, // titles of the recipes that use it
+    pub optional: bool,       // optional in every one of those recipes
+    pub checked: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Recipe {
     pub id: Uuid,
@@ -155,6 +410,8 @@ pub struct Recipe {
     pub description: String,
     pub ingredients: Vec<RecipeIngredient>,
     pub nutritional_info_per_serving: NutritionalInfo,
+    #[serde(default = "default_servings")]
+    pub servings: u32,            // how many servings the ingredient amounts make
     pub preparation_time: u32,    // in minutes
     pub difficulty_level: String, // 'easy' | 'medium' | 'hard'
     pub meal_type: String,        // 'breakfast' | 'lunch' | 'dinner' | 'snack'
@@ -162,11 +419,44 @@ pub struct Recipe {
     pub cuisine_type: Option<String>,
     pub seasonal: bool,
     pub tags: Vec<String>,
+    pub user_id: Option<String>, // owner, None for bundled recipes
+    pub origin: RecipeOrigin,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// Where a recipe came from. Only `User` recipes can be edited or deleted by their owner.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RecipeOrigin {
+    Bundled,
+    Imported,
+    User,
+}
+
+impl RecipeOrigin {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecipeOrigin::Bundled => "bundled",
+            RecipeOrigin::Imported => "imported",
+            RecipeOrigin::User => "user",
+        }
+    }
+
+    pub fn parse(value: &str) -> AppResult<Self> {
+        match value {
+            "bundled" => Ok(RecipeOrigin::Bundled),
+            "imported" => Ok(RecipeOrigin::Imported),
+            "user" => Ok(RecipeOrigin::User),
+            other => Err(crate::AppError::Validation(format!(
+                "Invalid recipe origin '{}'",
+                other
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct RecipeIngredient {
     pub name: String,
     pub amount: f64,
@@ -174,13 +464,86 @@ pub struct RecipeIngredient {
     pub optional: bool,
 }
 
+/// Recipes written before servings were recorded make one serving.
+pub fn default_servings() -> u32 {
+    1
+}
+
 impl Recipe {
+    pub const MAX_SERVINGS: u32 = 100;
+
     pub fn validate(&self) -> AppResult<()> {
-        if self.nutritional_info_per_serving.calories <= 0.0 {
+        if self.title.trim().is_empty() {
+            return Err(crate::AppError::Validation(
+                "Title must not be empty".to_string(),
+            ));
+        }
+        if self.recipe_instructions.trim().is_empty() {
+            return Err(crate::AppError::Validation(
+                "Instructions must not be empty".to_string(),
+            ));
+        }
+
+        if self.servings == 0 || self.servings > Self::MAX_SERVINGS {
+            return Err(crate::AppError::Validation(format!(
+                "Servings must be between 1 and {}",
+                Self::MAX_SERVINGS
+            )));
+        }
+
+        if self.ingredients.is_empty() {
+            return Err(crate::AppError::Validation(
+                "A recipe needs at least one ingredient".to_string(),
+            ));
+        }
+        for ingredient in &self.ingredients {
+            if ingredient.name.trim().is_empty() {
+                return Err(crate::AppError::Validation(
+                    "Ingredient name must not be empty".to_string(),
+                ));
+            }
+            if !ingredient.amount.is_finite() || ingredient.amount <= 0.0 {
+                return Err(crate::AppError::Validation(format!(
+                    "Amount of '{}' must be positive",
+                    ingredient.name
+                )));
+            }
+            if ingredient.unit.trim().is_empty() {
+                return Err(crate::AppError::Validation(format!(
+                    "Unit of '{}' must not be empty",
+                    ingredient.name
+                )));
+            }
+        }
+
+        let nutrition = &self.nutritional_info_per_serving;
+        if !nutrition.calories.is_finite() || nutrition.calories <= 0.0 {
             return Err(crate::AppError::Validation(
                 "Calories must be positive".to_string(),
             ));
         }
+        for (name, value) in [
+            ("Protein", nutrition.protein),
+            ("Carbs", nutrition.carbs),
+            ("Fat", nutrition.fat),
+            ("Fiber", nutrition.fiber),
+        ] {
+            if !value.is_finite() || value < 0.0 {
+                return Err(crate::AppError::Validation(format!(
+                    "{} must not be negative",
+                    name
+                )));
+            }
+        }
+        // Protein and carbs carry 4 kcal/g and fat 9 kcal/g; allow generous slack for
+        // rounding and alcohol, but reject macros that cannot fit in the calories
+        let macro_calories = nutrition.protein * 4.0 + nutrition.carbs * 4.0 + nutrition.fat * 9.0;
+        if macro_calories > nutrition.calories * 1.5 + 50.0 {
+            return Err(crate::AppError::Validation(format!(
+                "Macronutrients add up to {:.0} kcal, more than the declared {:.0} kcal",
+                macro_calories, nutrition.calories
+            )));
+        }
         if self.preparation_time == 0 {
             return Err(crate::AppError::Validation(
                 "Preparation time must be positive".to_string(),
@@ -199,10 +562,65 @@ impl Recipe {
             return Err(crate::AppError::Validation("Invalid meal type".to_string()));
         }
 
-        Ok(())
+        if self.tags.iter().any(|tag| tag.trim().is_empty()) {
+            return Err(crate::AppError::Validation(
+                "Tags must not be empty".to_string(),
+            ));
+        }
+
+        match (self.origin, &self.user_id) {
+            (RecipeOrigin::User, None) => Err(crate::AppError::Validation(
+                "User recipes must have an owner".to_string(),
+            )),
+            (RecipeOrigin::Bundled, Some(_)) => Err(crate::AppError::Validation(
+                "Bundled recipes cannot have an owner".to_string(),
+            )),
+            _ => Ok(()),
+        }
     }
 }
 
+/// Normalized form of a tag or ingredient name used for exact matching.
+pub fn normalize_name(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Filters accepted by `Database::search_recipes`. Empty lists mean "no filter".
+#[derive(Debug, Clone, Default)]
+pub struct RecipeSearchFilter {
+    pub query: Option<String>,
+    pub all_tags: Vec<String>,            // recipe must carry every one of these tags
+    pub any_tags: Vec<String>,            // recipe must carry at least one of these tags
+    pub include_ingredients: Vec<String>, // recipe must use every one of these ingredients
+    pub exclude_ingredients: Vec<String>, // recipe must use none of these ingredients
+    pub max_preparation_time: Option<u32>,
+    pub difficulty_level: Option<String>,
+    pub meal_type: Option<String>,
+    pub visible_to: Option<String>, // hide recipes owned by other users
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: u32,
+}
+
+/// A recipe returned from full-text search, with its ranking and match highlights.
+/// Ranking and highlights are only available when the query contains a term of at
+/// least three characters.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RecipeSearchHit {
+    pub recipe: Recipe,
+    pub score: Option<f64>,              // negated bm25, higher is better
+    pub title_highlight: Option<String>, // title with matches wrapped in <mark></mark>
+    pub snippet: Option<String>,         // best matching fragment of the recipe text
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -331,6 +749,7 @@ mod tests {
                 fat: 5.0,
                 fiber: 2.0,
             },
+            servings: 1,
             preparation_time: 30,
             difficulty_level: "medium".to_string(),
             meal_type: "lunch".to_string(),
@@ -338,6 +757,8 @@ mod tests {
             cuisine_type: Some("Italian".to_string()),
             seasonal: false,
             tags: vec!["quick".to_string(), "healthy".to_string()],
+            user_id: Some("test_user".to_string()),
+            origin: RecipeOrigin::User,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         };
@@ -365,6 +786,7 @@ mod tests {
                 fat: 5.0,
                 fiber: 2.0,
             },
+            servings: 1,
             preparation_time: 30,
             difficulty_level: "medium".to_string(),
             meal_type: "lunch".to_string(),
@@ -372,6 +794,8 @@ mod tests {
             cuisine_type: Some("Italian".to_string()),
             seasonal: false,
             tags: vec!["quick".to_string(), "healthy".to_string()],
+            user_id: Some("test_user".to_string()),
+            origin: RecipeOrigin::User,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         };
@@ -379,6 +803,83 @@ mod tests {
         // This should fail validation due to zero calories
         assert!(recipe.validate().is_err());
     }
+
+    #[test]
+    fn test_recipe_validation_ingredients_nutrition_and_owner() {
+        let valid = Recipe {
+            id: Uuid::new_v4(),
+            title: "番茄炒蛋".to_string(),
+            description: String::new(),
+            ingredients: vec![RecipeIngredient {
+                name: "鸡蛋".to_string(),
+                amount: 2.0,
+                unit: "个".to_string(),
+                optional: false,
+            }],
+            nutritional_info_per_serving: NutritionalInfo {
+                calories: 250.0,
+                protein: 14.0,
+                carbs: 8.0,
+                fat: 16.0,
+                fiber: 1.0,
+            },
+            servings: 1,
+            preparation_time: 15,
+            difficulty_level: "easy".to_string(),
+            meal_type: "dinner".to_string(),
+            recipe_instructions: "炒熟即可。".to_string(),
+            cuisine_type: None,
+            seasonal: false,
+            tags: vec![],
+            user_id: Some("test_user".to_string()),
+            origin: RecipeOrigin::User,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        assert!(valid.validate().is_ok());
+
+        let mut recipe = valid.clone();
+        recipe.ingredients.clear();
+        assert!(recipe.validate().is_err());
+
+        let mut recipe = valid.clone();
+        recipe.ingredients[0].amount = 0.0;
+        assert!(recipe.validate().is_err());
+
+        let mut recipe = valid.clone();
+        recipe.nutritional_info_per_serving.fat = -1.0;
+        assert!(recipe.validate().is_err());
+
+        // 100 g of protein cannot fit in 250 kcal
+        let mut recipe = valid.clone();
+        recipe.nutritional_info_per_serving.protein = 100.0;
+        assert!(recipe.validate().is_err());
+
+        let mut recipe = valid.clone();
+        recipe.user_id = None;
+        assert!(recipe.validate().is_err());
+
+        let mut recipe = valid.clone();
+        recipe.origin = RecipeOrigin::Bundled;
+        assert!(recipe.validate().is_err());
+        recipe.user_id = None;
+        assert!(recipe.validate().is_ok());
+    }
+
+    #[test]
+    fn test_score_component_reads_legacy_breakdowns() {
+        let legacy: Vec<ScoreComponent> =
+            serde_json::from_str(r#"[{"rule":"health_goals","score":0.25}]"#).unwrap();
+        assert_eq!(legacy[0].delta, 0.25);
+        assert_eq!(legacy[0].to_string(), "health_goals +0.25");
+
+        let factor = ScoreComponent {
+            rule: "weight_loss_high_fiber".to_string(),
+            delta: 0.1,
+            reason: "高纤维".to_string(),
+        };
+        assert_eq!(factor.to_string(), "高纤维 +0.10");
+    }
 }
 
 /// 餐厅信息
@@ -0,0 +1,140 @@
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::recommendation::engine;
+use crate::storage::models::{NutritionalInfo, Recipe, RecipeIngredient, RecipeOrigin};
+use crate::storage::Database;
+use crate::AppResult;
+
+/// Stable database id for a bundled recipe. Bundled recipes use short ids like "1"
+/// in `sample_recipes.json`; deriving a v5 UUID from them keeps the id identical
+/// across installs so history entries and recommendations keep resolving.
+pub fn bundled_recipe_id(bundled_id: &str) -> Uuid {
+    Uuid::new_v5(
+        &Uuid::NAMESPACE_OID,
+        format!("scx-recipes/bundled-recipe/{}", bundled_id).as_bytes(),
+    )
+}
+
+/// `app_state` key recording when the bundled recipes were imported.
+const SEEDED_KEY: &str = "bundled_recipes_seeded";
+
+/// Imports the bundled sample recipes on first run. Runs once per database, so recipes
+/// the user deleted stay deleted. Returns the number of recipes inserted.
+pub fn seed_bundled_recipes(db: &Database) -> AppResult<usize> {
+    if db.get_app_state(SEEDED_KEY)?.is_some() {
+        return Ok(0);
+    }
+
+    let bundled = crate::utils::load_sample_recipes().map_err(crate::AppError::Validation)?;
+    let mut count = 0;
+    for recipe in bundled {
+        // A run interrupted before the flag was written may have saved some already
+        let recipe = to_stored_recipe(recipe);
+        if db.get_recipe_by_id(&recipe.id.to_string())?.is_none() {
+            db.save_recipe(&recipe)?;
+            count += 1;
+        }
+    }
+    db.set_app_state(SEEDED_KEY, &Utc::now().to_rfc3339())?;
+
+    log::info!("Seeded {} bundled recipes", count);
+    Ok(count)
+}
+
+fn to_stored_recipe(recipe: engine::Recipe) -> Recipe {
+    let now = Utc::now();
+    Recipe {
+        id: bundled_recipe_id(&recipe.id),
+        title: recipe.title,
+        description: recipe.description,
+        ingredients: recipe
+            .ingredients
+            .into_iter()
+            .map(|i| RecipeIngredient {
+                name: i.name,
+                amount: i.amount,
+                unit: i.unit,
+                optional: i.optional,
+            })
+            .collect(),
+        nutritional_info_per_serving: NutritionalInfo {
+            calories: recipe.nutritional_info_per_serving.calories,
+            protein: recipe.nutritional_info_per_serving.protein,
+            carbs: recipe.nutritional_info_per_serving.carbs,
+            fat: recipe.nutritional_info_per_serving.fat,
+            fiber: recipe.nutritional_info_per_serving.fiber,
+        },
+        servings: recipe.servings,
+        preparation_time: recipe.preparation_time,
+        difficulty_level: recipe.difficulty_level,
+        meal_type: recipe.meal_type,
+        recipe_instructions: recipe.recipe_instructions,
+        cuisine_type: recipe.cuisine_type,
+        seasonal: recipe.seasonal,
+        tags: recipe.tags,
+        user_id: None,
+        origin: RecipeOrigin::Bundled,
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_seeding_runs_once_with_stable_ids() {
+        let dir = TempDir::new().unwrap();
+        let db = Database::new(dir.path().join("data.db")).unwrap();
+
+        let seeded = seed_bundled_recipes(&db).unwrap();
+        assert!(seeded > 0);
+        assert_eq!(seed_bundled_recipes(&db).unwrap(), 0);
+        assert_eq!(db.count_recipes().unwrap() as usize, seeded);
+
+        let first = db
+            .get_recipe_by_id(&bundled_recipe_id("1").to_string())
+            .unwrap()
+            .expect("bundled recipe 1 should be seeded");
+        assert_eq!(first.title, "蔬菜炒豆腐");
+        assert_eq!(first.origin, RecipeOrigin::Bundled);
+
+        let soup = db
+            .get_recipe_by_id(&bundled_recipe_id("4").to_string())
+            .unwrap()
+            .expect("bundled recipe 4 should be seeded");
+        assert_eq!(soup.servings, 3);
+        // The recipe_servings migration spells these ids out
+        assert_eq!(soup.id.to_string(), "14c09214-e210-542f-9a5a-5c657700d5a4");
+        let salmon = db
+            .get_recipe_by_id(&bundled_recipe_id("5").to_string())
+            .unwrap()
+            .expect("bundled recipe 5 should be seeded");
+        assert_eq!(salmon.title, "三文鱼配藜麦");
+        assert_eq!(salmon.servings, 2);
+        assert_eq!(
+            salmon.id.to_string(),
+            "c8906916-f3b3-579b-82da-dcdad7f8a4b6"
+        );
+
+        // Deleting every recipe does not bring the bundled ones back
+        for recipe in db.get_all_recipes().unwrap() {
+            db.delete_recipe(&recipe.id.to_string()).unwrap();
+        }
+        assert_eq!(seed_bundled_recipes(&db).unwrap(), 0);
+        assert_eq!(db.count_recipes().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_bundled_recipes_pass_validation() {
+        for recipe in crate::utils::load_sample_recipes().unwrap() {
+            let stored = to_stored_recipe(recipe);
+            if let Err(e) = stored.validate() {
+                panic!("bundled recipe '{}' is invalid: {}", stored.title, e);
+            }
+        }
+    }
+}
@@ -1,39 +1,12 @@
-use std::fs;
-use std::path::Path;
-
 use crate::recommendation::engine::Recipe;
 
-pub fn load_sample_recipes() -> Result<Vec<Recipe>, String> {
-    // Try multiple potential paths for the sample recipes file
-    let potential_paths = [
-        "src-tauri/sample_recipes.json",
-        "./sample_recipes.json",
-        "../sample_recipes.json",
-        "sample_recipes.json",
-        &format!("{}/sample_recipes.json", std::env::current_dir()
-            .map_err(|e| format!("Failed to get current directory: {}", e))?
-            .to_string_lossy()),
-    ];
-
-    let mut content = String::new();
-    let mut path_found = false;
-
-    for path_str in &potential_paths {
-        let path = Path::new(path_str);
-        if path.exists() {
-            content = fs::read_to_string(path)
-                .map_err(|e| format!("Failed to read sample recipes file {}: {}", path_str, e))?;
-            path_found = true;
-            break;
-        }
-    }
-
-    if !path_found {
-        return Err("Sample recipes file not found in any of the expected locations".to_string());
-    }
+/// The sample recipes shipped with the app, embedded at compile time so they are
+/// available regardless of the working directory.
+const SAMPLE_RECIPES_JSON: &str = include_str!("../../sample_recipes.json");
 
+pub fn load_sample_recipes() -> Result<Vec<Recipe>, String> {
     // Parse the JSON content
-    let recipes: Vec<Recipe> = serde_json::from_str(&content)
+    let recipes: Vec<Recipe> = serde_json::from_str(SAMPLE_RECIPES_JSON)
         .map_err(|e| format!("Failed to parse sample recipes JSON: {}", e))?;
 
     Ok(recipes)